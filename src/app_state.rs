@@ -2,15 +2,20 @@ use std::{
     collections::{HashMap, HashSet},
     path,
     sync::{Arc, Mutex, mpsc},
-    time::Instant,
+    time::{Duration, Instant, SystemTime},
 };
 
+use crate::compare::{
+    CompareContent, CompareDepth, CompareEvent, CompareRequest, Diff, Difference, describe,
+};
 use crate::core::{
-    ActivePanel, BrowserMode, ContainerKind, DirBatch, DirEntry, EditLoadRequest, EditLoadResult,
-    EntryLocation, IOResult, IOTask, ImageLocation, PreviewContent, PreviewRequest, SearchCase,
-    SearchMode, SearchResult, SortMode, container_display_path, container_kind_from_path,
-    format_preview_info, is_image_name, is_image_path, is_text_name, is_text_path,
+    ActivePanel, BrowserMode, CommandCwd, CommandRequest, ConflictAction, ConflictScope,
+    ContainerKind, DirBatch, DirEntry, EditLoadRequest, EditLoadResult, EntryLocation, IOResult,
+    IOTask, ImageLocation, PreviewContent, PreviewRequest, SearchCase, SearchMode, SearchResult,
+    SortMode, container_display_path, container_kind_from_path, format_preview_info, is_image_name,
+    is_image_path, is_text_name, is_text_path,
 };
+use crate::sync::{Action, Plan, SyncOptions};
 use crate::theme::Theme;
 
 /// Determine the syntax-highlighting extension for a path.
@@ -115,6 +120,75 @@ pub struct FilePropsEdit {
     pub group: String,
 }
 
+/// Batch rename of the marked entries (Shift+F6 with marks).
+pub struct MultiRename {
+    pub items: Vec<EntryLocation>,
+    pub sources: Vec<crate::rename::Source>,
+    /// Every name in the directory, to catch renames onto entries left alone.
+    pub existing: HashSet<String>,
+    pub rule: crate::rename::Rule,
+    /// Photo dates still being read, in `sources` order.
+    taken_rx: Option<mpsc::Receiver<Vec<Option<crate::rename::Stamp>>>>,
+    pub focus: bool,
+}
+
+impl MultiRename {
+    /// Pick up EXIF dates once the background read is done.
+    pub fn poll_taken(&mut self) {
+        let Some(ref rx) = self.taken_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(taken) => {
+                for (source, taken) in self.sources.iter_mut().zip(taken) {
+                    source.taken = taken;
+                }
+                self.taken_rx = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.taken_rx = None,
+        }
+    }
+
+    pub fn reading_taken(&self) -> bool {
+        self.taken_rx.is_some()
+    }
+
+    /// New names and what each rename would do, or why the rule is broken.
+    pub fn preview(&self) -> Result<(Vec<String>, Vec<crate::rename::Status>), String> {
+        let names = crate::rename::new_names(&self.rule, &self.sources)?;
+        let status = crate::rename::check(&self.sources, &names, &self.existing);
+        Ok((names, status))
+    }
+}
+
+/// Sync of the two panels' directories (Ctrl+Shift+S): the options, and
+/// the differences of a recursive comparison the plan is made from.
+pub struct SyncDialog {
+    pub left: EntryLocation,
+    pub right: EntryLocation,
+    pub options: SyncOptions,
+    /// None while comparing, or after changing what is compared.
+    pub diffs: Option<Vec<Diff>>,
+    /// Entries looked at so far by the comparison running.
+    pub scanned: usize,
+    pub error: Option<String>,
+    pub focus: bool,
+}
+
+impl SyncDialog {
+    pub fn plan(&self) -> Option<Plan> {
+        let diffs = self.diffs.as_ref()?;
+        Some(crate::sync::plan(
+            diffs,
+            &self.left,
+            &self.right,
+            self.options.direction,
+            self.options.deletes,
+        ))
+    }
+}
+
 pub struct PropsDialog {
     pub target: path::PathBuf,
     pub original: FileProps,
@@ -256,6 +330,54 @@ impl LoadState {
 /// Cap on the in-memory error log surfaced in the Help screen.
 pub const ERROR_LOG_CAP: usize = 100;
 
+/// Cap on the undo journal; the oldest operations fall off the front.
+pub const UNDO_JOURNAL_CAP: usize = 100;
+
+/// How often temp copies of remote files are checked for local edits.
+pub const REMOTE_EDIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a remote panel's free-space reading is shown before it is
+/// measured again.
+pub const REMOTE_FREE_SPACE_REFRESH: Duration = Duration::from_secs(30);
+
+/// Temp copy of a remote file opened in an external app. Saving the copy
+/// uploads it back to `(host, remote_path)`.
+pub struct RemoteEdit {
+    pub host: String,
+    pub remote_path: String,
+    pub local_path: path::PathBuf,
+    /// Local mtime as of the last upload (or the download).
+    pub local_mtime: Option<SystemTime>,
+    /// Remote mtime the local copy is based on; a mismatch at upload time
+    /// means someone else changed the file.
+    pub remote_mtime: Option<u64>,
+}
+
+impl RemoteEdit {
+    /// Upload task for the temp copy if it was modified since the last check.
+    fn poll(&mut self) -> Option<IOTask> {
+        let modified = std::fs::metadata(&self.local_path)
+            .and_then(|m| m.modified())
+            .ok();
+        if modified.is_none() || modified == self.local_mtime {
+            return None;
+        }
+        self.local_mtime = modified;
+        match std::fs::read(&self.local_path) {
+            Ok(contents) => Some(IOTask::WriteRemoteFile {
+                host: self.host.clone(),
+                path: self.remote_path.clone(),
+                contents,
+                expected_mtime: self.remote_mtime,
+            }),
+            Err(e) => {
+                eprintln!("Read {}: {e}", self.local_path.display());
+                None
+            }
+        }
+    }
+}
+
 pub struct ErrorLogEntry {
     pub when: Instant,
     pub source: String,
@@ -288,6 +410,10 @@ pub enum QuickJumpCategory {
     Home,
     Mount,
     Ssh,
+    /// Saved bookmark; the path holds its location string.
+    Bookmark,
+    /// The virtual view of all trash directories.
+    Trash,
 }
 
 pub struct QuickJumpState {
@@ -356,6 +482,45 @@ pub struct PreviewState {
     pub image_zoom: f32,
     /// Image pan offset (x, y) in pixels, used when zoomed image exceeds panel.
     pub image_pan: [f32; 2],
+    pub source: PreviewSource,
+}
+
+impl PreviewState {
+    /// An empty preview waiting for request `request_id` to fill it.
+    pub fn new(key: String, ext: Option<String>, request_id: u64, source: PreviewSource) -> Self {
+        Self {
+            content: None,
+            key: Some(key),
+            ext,
+            scroll: 0.0,
+            line_height: 16.0,
+            page_height: 240.0,
+            max_scroll: 0.0,
+            can_scroll: false,
+            find_open: false,
+            find_query: String::new(),
+            find_query_built: String::new(),
+            find_matches: Vec::new(),
+            find_match_num: 0,
+            find_focus: false,
+            request_id,
+            wrap: true,
+            bytes_per_row: 16,
+            bytes_per_row_auto: true,
+            loading_since: Some(Instant::now()),
+            image_zoom: 0.0,
+            image_pan: [0.0, 0.0],
+            source,
+        }
+    }
+}
+
+/// What a preview shows. Selection previews follow the cursor in the other
+/// panel; command output stays until the preview is closed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PreviewSource {
+    Selection,
+    Command,
 }
 
 /// State of the editor's background file load. The three cases are mutually
@@ -403,7 +568,7 @@ pub struct PanelSnapshot {
 }
 
 #[cfg(target_os = "linux")]
-fn unescape_proc_mount(s: &str) -> String {
+pub(crate) fn unescape_proc_mount(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     let bytes = s.as_bytes();
     let mut i = 0;
@@ -423,6 +588,44 @@ fn unescape_proc_mount(s: &str) -> String {
     out
 }
 
+/// The directory a panel shows, as far as comparing goes.
+fn compare_root(browser: &BrowserState) -> Option<EntryLocation> {
+    match browser.browser_mode {
+        BrowserMode::Fs => Some(EntryLocation::Fs(browser.current_path.clone())),
+        BrowserMode::Container {
+            kind,
+            ref archive_path,
+            ref cwd,
+            ref root,
+        } => {
+            // `cwd` is usually relative to the archive's single top-level
+            // directory, which the listing hides.
+            let inner_path = match *root {
+                Some(ref root)
+                    if !root.is_empty() && cwd != root && !cwd.starts_with(&format!("{root}/")) =>
+                {
+                    if cwd.is_empty() {
+                        root.clone()
+                    } else {
+                        format!("{root}/{cwd}")
+                    }
+                }
+                _ => cwd.clone(),
+            };
+            Some(EntryLocation::Container {
+                kind,
+                archive_path: archive_path.clone(),
+                inner_path,
+            })
+        }
+        BrowserMode::Remote { ref host, ref path } => Some(EntryLocation::Remote {
+            host: host.clone(),
+            path: path.clone(),
+        }),
+        BrowserMode::Search { .. } | BrowserMode::Trash | BrowserMode::Compare { .. } => None,
+    }
+}
+
 fn history_key(snapshot: &PanelSnapshot) -> String {
     match snapshot.mode {
         BrowserMode::Fs => format!("fs:{}", snapshot.current_path.to_string_lossy()),
@@ -462,6 +665,11 @@ fn history_key(snapshot: &PanelSnapshot) -> String {
                 SearchCase::Insensitive => "i",
             }
         ),
+        BrowserMode::Trash => "trash".to_string(),
+        BrowserMode::Compare {
+            ref left,
+            ref right,
+        } => format!("compare:{left}:{right}"),
     }
 }
 
@@ -489,10 +697,41 @@ pub enum Modal {
     },
     /// A fatal/operation error message.
     Error(String),
+    /// The remote file changed since its temp copy was opened; offers to
+    /// overwrite it with the local edits.
+    RemoteChanged {
+        message: String,
+        task: crate::core::IOTask,
+    },
     /// Shown while an SFTP connection is being established (holds the host).
     Connecting(String),
     /// External-theme picker.
     ThemePicker,
+    /// SFTP connection manager (holds the selected row).
+    Connections(usize),
+    /// Undo journal, newest first (holds the selected row).
+    UndoHistory(usize),
+    /// IO queue: the running task, then pending ones (holds the selected row).
+    Queue(usize),
+    MultiRename(MultiRename),
+    Sync(SyncDialog),
+}
+
+/// Whether a queue row has started.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QueueRowState {
+    Running,
+    Pending,
+}
+
+/// A live SFTP session as listed in the connection manager.
+pub struct ConnectionInfo {
+    pub host: String,
+    pub user: String,
+    pub banner: Option<String>,
+    pub uptime: Duration,
+    pub bytes: u64,
+    pub reconnects: u64,
 }
 
 pub struct AppState {
@@ -505,19 +744,37 @@ pub struct AppState {
     pub preview_tx: mpsc::Sender<PreviewRequest>,
     pub preview_rx: mpsc::Receiver<(u64, PreviewContent)>,
     pub preview_request_id: u64,
-    pub io_tx: mpsc::Sender<IOTask>,
     pub io_rx: mpsc::Receiver<IOResult>,
-    pub io_cancel_tx: mpsc::Sender<()>,
-    pub io_cancel_flag: Arc<std::sync::atomic::AtomicBool>,
+    /// Tasks waiting for the IO workers, and the ones running now.
+    pub io_queue: Arc<crate::core::IoQueue>,
     pub io_in_flight: usize,
     /// Total tasks enqueued for the current batch — peak of io_in_flight
     /// since the queue last emptied. Used by the progress modal to show
     /// "file X of N". Reset to 0 when io_in_flight reaches 0.
     pub io_batch_total: usize,
+    /// When the current batch started (io_in_flight went from 0 to 1), for
+    /// the elapsed time in the progress modal. None while idle.
+    pub io_batch_started: Option<Instant>,
+    /// Slowest way file data moved in the local copies of the current (or
+    /// last) batch, for the status line. Reset when a batch starts.
+    pub io_copy_method: Option<crate::core::CopyMethod>,
     pub io_cancel_requested: bool,
-    /// Shared transfer progress for IO/preview/edit workers.
+    /// The progress window was sent to the background so browsing can go on
+    /// while the queue runs. Cleared when the queue empties.
+    pub io_background: bool,
+    /// "Apply to all" in the conflict prompt; cleared when the batch ends.
+    pub conflict_apply_all: bool,
+    /// What local copies and moves do with symlinks, picked in their
+    /// confirmation (L). Folder sizes have their own setting.
+    pub symlink_policy: crate::core::SymlinkPolicy,
+    /// Kind of link Alt+F6 makes, and whether symlinks name their target
+    /// relative to themselves; both switchable in its confirmation (H, R).
+    pub link_kind: crate::core::LinkKind,
+    pub link_target: crate::core::LinkTarget,
+    /// Shared transfer progress for IO/preview/edit workers. Running IO
+    /// tasks each report into a child of it.
     pub transfer_progress: Arc<crate::core::TransferProgress>,
-    pub dir_size_tx: mpsc::Sender<path::PathBuf>,
+    pub dir_size_tx: mpsc::Sender<(path::PathBuf, crate::core::SymlinkPolicy)>,
     pub dir_size_rx: mpsc::Receiver<(path::PathBuf, u64)>,
     pub dir_sizes: HashMap<path::PathBuf, u64>,
     pub dir_size_pending: HashSet<path::PathBuf>,
@@ -525,6 +782,11 @@ pub struct AppState {
     pub remote_dir_size_rx: mpsc::Receiver<(String, String, u64)>,
     pub remote_dir_sizes: HashMap<(String, String), u64>,
     pub remote_dir_size_pending: HashSet<(String, String)>,
+    pub remote_free_space_tx: mpsc::Sender<(String, String)>,
+    pub remote_free_space_rx: mpsc::Receiver<(String, String, Option<u64>)>,
+    /// Free bytes per remote `(host, dir)`, with when they were measured.
+    pub remote_free_space: HashMap<(String, String), (Option<u64>, Instant)>,
+    pub remote_free_space_pending: HashSet<(String, String)>,
     pub fs_last_selected_name: HashMap<path::PathBuf, String>,
     pub container_last_selected_name: HashMap<(path::PathBuf, String, ContainerKind), String>,
     pub container_dir_cache: HashMap<(path::PathBuf, String, ContainerKind), ContainerDirCache>,
@@ -560,6 +822,31 @@ pub struct AppState {
     pub search_rx: mpsc::Receiver<crate::core::SearchEvent>,
     /// Host of the currently-running remote search, if any.
     pub search_remote_host: Option<String>,
+    /// Whether the next comparison descends into subdirectories, and how it
+    /// tells same-sized files apart; both switchable in its confirmation
+    /// (R, H).
+    pub compare_depth: CompareDepth,
+    pub compare_content: CompareContent,
+    pub compare_request_id: u64,
+    /// The comparison the worker is running, if any.
+    pub compare_run: Option<CompareRun>,
+    /// Differences found by the last recursive comparison, sorted by path.
+    /// The Compare listing is rebuilt from them, like search results.
+    pub compare_results: Vec<Diff>,
+    pub compare_tx: mpsc::Sender<CompareRequest>,
+    pub compare_rx: mpsc::Receiver<CompareEvent>,
+    /// Options of the last sync, offered again the next time.
+    pub sync_options: SyncOptions,
+    /// Text of the command prompt (Ctrl+O) while it is open.
+    pub command_input: Option<String>,
+    pub command_focus: bool,
+    pub command_tx: mpsc::Sender<crate::core::CommandRequest>,
+    pub command_rx: mpsc::Receiver<crate::core::CommandEvent>,
+    /// Stops the running command; see `start_command_worker`.
+    pub command_cancel: Arc<std::sync::atomic::AtomicBool>,
+    /// Preview request id and directory of the command still running, so the
+    /// panels showing that directory refresh when it exits.
+    pub command_running: Option<(u64, crate::core::CommandCwd)>,
     pub refresh_tick: u64,
     pub update_status: UpdateStatus,
     pub update_rx: Option<mpsc::Receiver<UpdateStatus>>,
@@ -567,18 +854,50 @@ pub struct AppState {
     /// Persistent error log surfaced in the Help screen. New entries are
     /// pushed via `record_error`; capped at `ERROR_LOG_CAP` entries.
     pub error_log: Vec<ErrorLogEntry>,
+    /// Completed local operations that can be taken back, oldest first.
+    /// Pushed via `record_undo`; capped at `UNDO_JOURNAL_CAP` entries.
+    pub undo_journal: Vec<crate::core::UndoRecord>,
     /// User-editable settings persisted to RON at config_dir().
     pub settings: crate::settings::Settings,
-    /// Active SFTP sessions keyed by hostname — local reference for quick lookups.
-    pub sftp_sessions: HashMap<String, Arc<Mutex<crate::sftp::SftpSession>>>,
-    /// Shared SFTP sessions for worker threads (IO, preview).
-    pub sftp_sessions_shared: Arc<Mutex<HashMap<String, Arc<Mutex<crate::sftp::SftpSession>>>>>,
-    /// Receives the result of an async SFTP connection.
-    pub sftp_connect_rx: Option<mpsc::Receiver<Result<crate::sftp::SftpSession, String>>>,
+    /// Active remote sessions keyed by host key (`k6`, `sftp://k6`, …) —
+    /// local reference for quick lookups.
+    pub sftp_sessions: HashMap<String, crate::remote::SharedRemote>,
+    /// Shared remote sessions for worker threads (IO, preview).
+    pub sftp_sessions_shared: Arc<Mutex<HashMap<String, crate::remote::SharedRemote>>>,
+    /// Receives the result of an async remote connection.
+    pub sftp_connect_rx: Option<mpsc::Receiver<Result<Box<dyn crate::remote::RemoteFs>, String>>>,
     /// Pending navigation after SFTP connect completes.
     pub sftp_pending_nav: Option<(String, String, crate::core::ActivePanel)>, // (host, path, panel)
     /// Queued navigations waiting for prior SFTP connection to finish.
     pub sftp_nav_queue: std::collections::VecDeque<(String, String, crate::core::ActivePanel)>,
+    /// Per-host connection statistics, readable without locking the session.
+    pub sftp_stats: HashMap<String, Arc<crate::remote::SessionStats>>,
+    /// Temp copies of remote files opened externally, watched for edits.
+    pub remote_edits: Vec<RemoteEdit>,
+    /// When `remote_edits` were last checked for modification.
+    pub remote_edit_polled: Instant,
+}
+
+/// A comparison sent to the worker, with what is needed to show its result
+/// where it was asked for.
+pub struct CompareRun {
+    pub id: u64,
+    /// Headers of the compared directories. A shallow result only marks
+    /// panels still showing them.
+    pub left: String,
+    pub right: String,
+    pub target: CompareTarget,
+}
+
+/// Where the result of a comparison goes.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareTarget {
+    /// A shallow comparison marks the differences in the panels.
+    Marks,
+    /// A recursive one fills this (panel, tab) listing.
+    Listing(ActivePanel, usize),
+    /// The sync dialog makes its plan from it.
+    Sync,
 }
 
 #[derive(Clone)]
@@ -605,6 +924,15 @@ pub enum PendingOp {
     },
     Delete {
         targets: Vec<path::PathBuf>,
+        mode: DeleteMode,
+    },
+    /// Put trashed entries back where they came from (Trash view, F6).
+    Restore {
+        targets: Vec<path::PathBuf>,
+    },
+    /// Delete trashed entries for good (Trash view, F8).
+    Purge {
+        targets: Vec<path::PathBuf>,
     },
     Rename {
         src: path::PathBuf,
@@ -613,6 +941,25 @@ pub enum PendingOp {
         sources: Vec<path::PathBuf>,
         dst_dir: path::PathBuf,
     },
+    /// Link to the items from the other panel's directory (Alt+F6). Both
+    /// sides are on the same machine.
+    Link {
+        items: Vec<CopyItem>,
+        dst: CopyDest,
+    },
+    /// Compare the left panel's directory with the right one's (Shift+F2).
+    Compare {
+        left: EntryLocation,
+        right: EntryLocation,
+    },
+}
+
+/// What F8 does with local files. Remote files have no trash and are always
+/// deleted.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DeleteMode {
+    Trash,
+    Permanent,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -654,9 +1001,16 @@ pub enum UpdateStatus {
 
 pub struct AsyncStatus {
     pub io_in_flight: usize,
+    pub io_batch_total: usize,
+    pub io_elapsed: Option<Duration>,
+    /// Bytes per second, once there is enough history to estimate it.
+    pub io_rate: Option<f64>,
+    pub io_eta: Option<Duration>,
+    pub io_copy_method: Option<crate::core::CopyMethod>,
     pub io_cancel_requested: bool,
     pub dir_size_pending: usize,
     pub search: SearchStatus,
+    pub compare_running: bool,
     pub update: UpdateStatus,
     pub gpu_info: String,
 }
@@ -680,6 +1034,51 @@ impl AppState {
         }
     }
 
+    pub fn record_undo(&mut self, record: crate::core::UndoRecord) {
+        self.undo_journal.push(record);
+        if self.undo_journal.len() > UNDO_JOURNAL_CAP {
+            let overflow = self.undo_journal.len() - UNDO_JOURNAL_CAP;
+            self.undo_journal.drain(..overflow);
+        }
+    }
+
+    /// Put back a record whose undo was refused, where it was in the journal.
+    pub fn restore_undo(&mut self, record: crate::core::UndoRecord) {
+        let pos = self
+            .undo_journal
+            .partition_point(|entry| entry.when <= record.when);
+        self.undo_journal.insert(pos, record);
+        if self.undo_journal.len() > UNDO_JOURNAL_CAP {
+            let overflow = self.undo_journal.len() - UNDO_JOURNAL_CAP;
+            self.undo_journal.drain(..overflow);
+        }
+    }
+
+    /// Note how a finished local copy moved its data.
+    pub fn note_copy_method(&mut self, method: crate::core::CopyMethod) {
+        self.io_copy_method = self.io_copy_method.max(Some(method));
+    }
+
+    /// Revert the most recent journaled operation (Ctrl+Z).
+    pub fn undo_last(&mut self) {
+        if self.undo_journal.is_empty() {
+            self.open_modal(Modal::Error("Nothing to undo.".to_string()));
+        } else {
+            self.undo_at(0);
+        }
+    }
+
+    /// Revert the `index`-th most recent operation. The worker re-checks the
+    /// filesystem first and refuses if anything changed since; a refused
+    /// entry comes back through `restore_undo`.
+    pub fn undo_at(&mut self, index: usize) {
+        let Some(pos) = self.undo_journal.len().checked_sub(index + 1) else {
+            return;
+        };
+        let record = self.undo_journal.remove(pos);
+        self.enqueue_io(IOTask::Undo(record));
+    }
+
     pub fn poll_update_status(&mut self) {
         if let Some(ref rx) = self.update_rx
             && let Ok(status) = rx.try_recv()
@@ -692,9 +1091,21 @@ impl AppState {
     pub fn async_status(&self) -> AsyncStatus {
         AsyncStatus {
             io_in_flight: self.io_in_flight,
+            io_batch_total: self.io_batch_total,
+            io_elapsed: self.io_batch_started.map(|t| t.elapsed()),
+            io_rate: self.transfer_progress.rate(),
+            io_eta: self
+                .io_queue
+                .snapshot()
+                .0
+                .iter()
+                .filter_map(|item| item.progress.as_ref()?.eta())
+                .max(),
+            io_copy_method: self.io_copy_method,
             io_cancel_requested: self.io_cancel_requested,
             dir_size_pending: self.dir_size_pending.len(),
             search: self.search_status,
+            compare_running: self.compare_run.is_some(),
             update: self.update_status.clone(),
             gpu_info: self.gpu_info.clone(),
         }
@@ -741,6 +1152,24 @@ impl AppState {
         }
     }
 
+    /// Whether the pending operation copies or moves local files locally,
+    /// where [`AppState::symlink_policy`] applies.
+    pub fn pending_op_meets_symlinks(&self) -> bool {
+        match self.pending_op() {
+            Some(&PendingOp::Copy {
+                ref items,
+                dst: CopyDest::Local(_),
+            })
+            | Some(&PendingOp::Move {
+                ref items,
+                dst: CopyDest::Local(_),
+            }) => items
+                .iter()
+                .any(|item| matches!(item.src, EntryLocation::Fs(_))),
+            _ => false,
+        }
+    }
+
     pub fn props_dialog(&self) -> Option<&PropsDialog> {
         match self.modal {
             Some(Modal::Props(ref d)) => Some(d),
@@ -755,6 +1184,34 @@ impl AppState {
         }
     }
 
+    pub fn multi_rename(&self) -> Option<&MultiRename> {
+        match self.modal {
+            Some(Modal::MultiRename(ref d)) => Some(d),
+            _ => None,
+        }
+    }
+
+    pub fn multi_rename_mut(&mut self) -> Option<&mut MultiRename> {
+        match self.modal {
+            Some(Modal::MultiRename(ref mut d)) => Some(d),
+            _ => None,
+        }
+    }
+
+    pub fn sync_dialog(&self) -> Option<&SyncDialog> {
+        match self.modal {
+            Some(Modal::Sync(ref d)) => Some(d),
+            _ => None,
+        }
+    }
+
+    pub fn sync_dialog_mut(&mut self) -> Option<&mut SyncDialog> {
+        match self.modal {
+            Some(Modal::Sync(ref mut d)) => Some(d),
+            _ => None,
+        }
+    }
+
     pub fn settings_draft_mut(&mut self) -> Option<&mut crate::settings::Settings> {
         match self.modal {
             Some(Modal::Settings(ref mut s)) => Some(s),
@@ -762,6 +1219,20 @@ impl AppState {
         }
     }
 
+    /// Push the transfer speed cap and hold threshold from settings to the
+    /// workers, which read them from the shared progress.
+    pub fn apply_transfer_settings(&self) {
+        let progress = &self.transfer_progress;
+        progress.limit.store(
+            self.settings.bandwidth_limit_kib.saturating_mul(1024),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+        progress.hold_threshold.store(
+            self.settings.hold_transfers_mib.saturating_mul(1024 * 1024),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
     pub fn settings_open(&self) -> bool {
         matches!(self.modal, Some(Modal::Settings(_)))
     }
@@ -828,6 +1299,25 @@ impl AppState {
         }
     }
 
+    pub fn remote_changed_message(&self) -> Option<&str> {
+        match self.modal {
+            Some(Modal::RemoteChanged { ref message, .. }) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Take the pending remote-overwrite prompt, if that is the open modal.
+    pub fn take_remote_changed(&mut self) -> Option<crate::core::IOTask> {
+        if matches!(self.modal, Some(Modal::RemoteChanged { .. })) {
+            match self.modal.take() {
+                Some(Modal::RemoteChanged { task, .. }) => Some(task),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
     /// Take the "connecting…" host and close that modal, if it is the open one.
     pub fn take_connecting(&mut self) -> Option<String> {
         if matches!(self.modal, Some(Modal::Connecting(_))) {
@@ -957,7 +1447,10 @@ impl AppState {
             } else if browser.selected_index >= browser.top_index + window_rows {
                 browser.top_index = browser.selected_index + 1 - window_rows;
             }
-            if self.preview_panel_side().is_some() {
+            if self
+                .preview_panel()
+                .is_some_and(|preview| preview.source == PreviewSource::Selection)
+            {
                 self.update_preview_for_current_selection();
             }
         } else {
@@ -1004,7 +1497,10 @@ impl AppState {
                     Some((archive_path.clone(), cwd.clone(), kind)),
                     Some(selected_name),
                 ),
-                BrowserMode::Search { .. } | BrowserMode::Remote { .. } => (None, None, None),
+                BrowserMode::Search { .. }
+                | BrowserMode::Remote { .. }
+                | BrowserMode::Trash
+                | BrowserMode::Compare { .. } => (None, None, None),
             }
         };
         if let Some(selected_name) = selected_name_opt {
@@ -1137,6 +1633,10 @@ impl AppState {
     }
 
     pub fn prepare_rename_selected(&mut self) {
+        if !self.get_active_panel().browser().marked.is_empty() {
+            self.prepare_multi_rename();
+            return;
+        }
         let name = {
             let panel = self.get_active_panel();
             let browser = panel.browser();
@@ -1144,7 +1644,8 @@ impl AppState {
                 return;
             }
             let entry = &browser.entries[browser.selected_index];
-            if entry.name == ".." {
+            // Renaming inside the trash would orphan the item's info file.
+            if entry.name == ".." || matches!(browser.browser_mode, BrowserMode::Trash) {
                 return;
             }
             match entry.location {
@@ -1173,6 +1674,105 @@ impl AppState {
         }
     }
 
+    /// Open the batch rename dialog for the marked entries of a plain local
+    /// or remote directory.
+    fn prepare_multi_rename(&mut self) {
+        let browser = self.get_active_panel().browser();
+        if !matches!(
+            browser.browser_mode,
+            BrowserMode::Fs | BrowserMode::Remote { .. }
+        ) {
+            return;
+        }
+        let marked: Vec<&DirEntry> = browser
+            .entries
+            .iter()
+            .filter(|e| e.name != ".." && browser.marked.contains(&e.name))
+            .collect();
+        if marked.is_empty() {
+            return;
+        }
+        let items: Vec<EntryLocation> = marked.iter().map(|e| e.location.clone()).collect();
+        let sources: Vec<crate::rename::Source> = marked
+            .iter()
+            .map(|e| crate::rename::Source {
+                name: e.name.clone(),
+                is_dir: e.is_dir,
+                modified: e.modified,
+                taken: None,
+            })
+            .collect();
+        let existing = browser.entries.iter().map(|e| e.name.clone()).collect();
+        // EXIF dates of local photos come from reading them, off this thread.
+        let photos: Vec<Option<path::PathBuf>> = marked
+            .iter()
+            .map(|e| match e.location {
+                EntryLocation::Fs(ref path) if !e.is_dir && is_image_name(&e.name) => {
+                    Some(path.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        let taken_rx = photos.iter().any(Option::is_some).then(|| {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let taken = photos
+                    .iter()
+                    .map(|p| p.as_deref().and_then(crate::rename::exif_taken))
+                    .collect();
+                let _ = tx.send(taken);
+            });
+            rx
+        });
+        self.modal = Some(Modal::MultiRename(MultiRename {
+            items,
+            sources,
+            existing,
+            rule: crate::rename::Rule::default(),
+            taken_rx,
+            focus: true,
+        }));
+    }
+
+    /// Queue the renames of the batch rename dialog and close it. Does
+    /// nothing while any new name is a problem.
+    pub fn apply_multi_rename(&mut self) -> bool {
+        let Some(dialog) = self.multi_rename() else {
+            return false;
+        };
+        let Ok((names, status)) = dialog.preview() else {
+            return false;
+        };
+        if status.iter().any(|s| s.is_problem()) {
+            return false;
+        }
+        let tasks: Vec<IOTask> = dialog
+            .items
+            .iter()
+            .zip(names)
+            .zip(status)
+            .filter(|&(_, status)| status == crate::rename::Status::Renamed)
+            .filter_map(|((location, new_name), _)| match *location {
+                EntryLocation::Fs(ref src) => Some(IOTask::Rename {
+                    src: src.clone(),
+                    new_name,
+                }),
+                EntryLocation::Remote { ref host, ref path } => Some(IOTask::RenameRemote {
+                    host: host.clone(),
+                    src: path.clone(),
+                    new_name,
+                }),
+                EntryLocation::Container { .. } => None,
+            })
+            .collect();
+        self.modal = None;
+        for task in tasks {
+            self.enqueue_io(task);
+        }
+        self.get_active_panel_mut().browser_mut().marked.clear();
+        true
+    }
+
     pub fn start_inline_new_file(&mut self) {
         enum InsertMode {
             Fs(path::PathBuf),
@@ -1325,7 +1925,7 @@ impl AppState {
                 EntryLocation::Remote { ref host, ref path } => {
                     let remote_name = path.rsplit('/').next().unwrap_or("remote_file");
                     let ext = syntax_ext_for_path(std::path::Path::new(remote_name));
-                    let synthetic = std::path::PathBuf::from(format!("/sftp/{host}{path}"));
+                    let synthetic = crate::remote::synthetic_path(host, path);
                     (synthetic, ext, Some((host.clone(), path.clone())))
                 }
                 _ => return,
@@ -1429,29 +2029,7 @@ impl AppState {
         let _target_panel_clone = target_panel;
         {
             let panel = self.panel_mut(target_panel);
-            let preview = PreviewState {
-                content: None,
-                key: Some(key),
-                ext,
-                scroll: 0.0,
-                line_height: 16.0,
-                page_height: 240.0,
-                max_scroll: 0.0,
-                can_scroll: false,
-                find_open: false,
-                find_query: String::new(),
-                find_query_built: String::new(),
-                find_matches: Vec::new(),
-                find_match_num: 0,
-                find_focus: false,
-                request_id,
-                wrap: true,
-                bytes_per_row: 16,
-                bytes_per_row_auto: true,
-                loading_since: Some(Instant::now()),
-                image_zoom: 0.0,
-                image_pan: [0.0, 0.0],
-            };
+            let preview = PreviewState::new(key, ext, request_id, PreviewSource::Selection);
             panel.mode = PanelMode::Preview(preview);
         }
         let Some(preview) = self.preview_panel_mut() else {
@@ -1557,6 +2135,89 @@ impl AppState {
         }
     }
 
+    /// Ask for free space on the directories remote panels show, where the
+    /// last reading is missing or older than [`REMOTE_FREE_SPACE_REFRESH`].
+    pub fn request_remote_free_space(&mut self) {
+        for side in [ActivePanel::Left, ActivePanel::Right] {
+            let BrowserMode::Remote { ref host, ref path } =
+                self.panel(side).browser().browser_mode
+            else {
+                continue;
+            };
+            let key = (host.clone(), path.clone());
+            let fresh = self
+                .remote_free_space
+                .get(&key)
+                .is_some_and(|entry| entry.1.elapsed() < REMOTE_FREE_SPACE_REFRESH);
+            if fresh || self.remote_free_space_pending.contains(&key) {
+                continue;
+            }
+            if self.remote_free_space_tx.send(key.clone()).is_ok() {
+                self.remote_free_space_pending.insert(key);
+            }
+        }
+    }
+
+    pub fn open_command_prompt(&mut self) {
+        self.command_input = Some(String::new());
+        self.command_focus = true;
+    }
+
+    /// Run the prompt's command in the active panel's directory, streaming
+    /// its output into a preview in the other panel.
+    pub fn run_command(&mut self) {
+        let Some(command) = self.command_input.take() else {
+            return;
+        };
+        let command = command.trim().to_string();
+        if command.is_empty() {
+            return;
+        }
+        let browser = self.get_active_panel().browser();
+        let cwd = match browser.browser_mode {
+            BrowserMode::Fs => CommandCwd::Local(browser.current_path.clone()),
+            BrowserMode::Remote { ref host, ref path } => CommandCwd::Remote {
+                host: host.clone(),
+                path: path.clone(),
+            },
+            BrowserMode::Container { .. }
+            | BrowserMode::Search { .. }
+            | BrowserMode::Trash
+            | BrowserMode::Compare { .. } => {
+                self.open_modal(Modal::Error(
+                    "Commands run in a local or remote directory.".to_string(),
+                ));
+                return;
+            }
+        };
+        if self.command_running.is_some() {
+            self.command_cancel
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+        let target_panel = match self.active_panel {
+            ActivePanel::Left => ActivePanel::Right,
+            ActivePanel::Right => ActivePanel::Left,
+        };
+        self.preview_return_focus = Some(self.active_panel);
+        let request_id = self.preview_request_id.wrapping_add(1);
+        self.preview_request_id = request_id;
+        let mut preview = PreviewState::new(
+            format!("$ {command}"),
+            None,
+            request_id,
+            PreviewSource::Command,
+        );
+        preview.content = Some(PreviewContent::Text(String::new()));
+        preview.loading_since = None;
+        self.panel_mut(target_panel).mode = PanelMode::Preview(preview);
+        self.command_running = Some((request_id, cwd.clone()));
+        let _ = self.command_tx.send(CommandRequest {
+            id: request_id,
+            command,
+            cwd,
+        });
+    }
+
     pub fn toggle_preview(&mut self) {
         if self.preview_panel_side().is_some() {
             self.clear_preview();
@@ -1632,50 +2293,350 @@ impl AppState {
             .collect()
     }
 
-    pub fn prepare_delete_selected(&mut self) {
+    pub fn prepare_link_selected(&mut self) {
         if self.modal.is_some() {
             return;
         }
-        if let Some(op) = self.build_delete_op() {
+        if let Some(op) = self.build_link_op() {
             self.modal = Some(Modal::Confirm(op));
         }
     }
 
-    pub fn prepare_pack_selected(&mut self) {
+    /// Confirm comparing the two panels' directories. Both have to be
+    /// directory listings: local, inside an archive or remote.
+    pub fn prepare_compare(&mut self) {
         if self.modal.is_some() {
             return;
         }
-        if let Some(op) = self.build_pack_op() {
-            // Pre-fill the archive name from the first source, before `op` moves
-            // into the modal.
-            let name = match op {
-                PendingOp::Pack { ref sources, .. } => sources
-                    .first()
-                    .and_then(|p| p.file_name())
-                    .and_then(|n| n.to_str())
-                    .map(|n| format!("{n}.zip"))
-                    .unwrap_or_else(|| "archive.zip".to_string()),
-                _ => "archive.zip".to_string(),
-            };
-            self.modal = Some(Modal::Confirm(op));
-            self.rename_input = Some(name);
-            self.rename_focus = true;
-        }
+        let left = compare_root(self.left_panel.browser());
+        let right = compare_root(self.right_panel.browser());
+        let (Some(left), Some(right)) = (left, right) else {
+            self.open_modal(Modal::Error(
+                "Compare needs a directory in both panels.".to_string(),
+            ));
+            return;
+        };
+        self.modal = Some(Modal::Confirm(PendingOp::Compare { left, right }));
+    }
+
+    /// Send a comparison to the worker. A recursive one turns the active
+    /// panel into its listing right away; a shallow one marks both panels
+    /// when it finishes.
+    pub fn start_compare(&mut self, left: EntryLocation, right: EntryLocation) {
+        let id = self.compare_request_id.wrapping_add(1);
+        self.compare_request_id = id;
+        let (left_label, right_label) = (describe(&left), describe(&right));
+        let target = match self.compare_depth {
+            CompareDepth::Shallow => CompareTarget::Marks,
+            CompareDepth::Recursive => {
+                self.compare_results.clear();
+                let which = self.active_panel;
+                self.push_history(which);
+                let panel = self.panel_mut(which);
+                let browser = panel.browser_mut();
+                browser.browser_mode = BrowserMode::Compare {
+                    left: left_label.clone(),
+                    right: right_label.clone(),
+                };
+                browser.entries.clear();
+                browser.marked.clear();
+                browser.load = LoadState::Idle;
+                browser.progress_override = Some((0, None));
+                browser.selected_index = 0;
+                browser.top_index = 0;
+                browser.dir_token = browser.dir_token.wrapping_add(1);
+                panel.mode = PanelMode::Browser;
+                CompareTarget::Listing(which, panel.active_tab)
+            }
+        };
+        self.compare_run = Some(CompareRun {
+            id,
+            left: left_label,
+            right: right_label,
+            target,
+        });
+        let _ = self.compare_tx.send(CompareRequest {
+            id,
+            left,
+            right,
+            depth: self.compare_depth,
+            content: self.compare_content,
+            excludes: Vec::new(),
+        });
     }
 
-    pub fn take_pending_op(&mut self) -> Option<PendingOp> {
-        self.pending_collisions.clear();
-        match self.modal {
-            Some(Modal::Confirm(_)) => match self.modal.take() {
-                Some(Modal::Confirm(op)) => Some(op),
-                _ => None,
-            },
-            _ => None,
+    /// Forget the running comparison; the worker's answer will be ignored.
+    pub fn cancel_compare(&mut self) {
+        self.compare_request_id = self.compare_request_id.wrapping_add(1);
+        self.compare_run = None;
+    }
+
+    /// The browser tab listing the running recursive comparison, if it
+    /// still exists.
+    pub fn compare_target_browser_mut(&mut self) -> Option<&mut BrowserState> {
+        match self.compare_run.as_ref()?.target {
+            CompareTarget::Listing(which, tab) => self.panel_mut(which).tabs.get_mut(tab),
+            CompareTarget::Marks | CompareTarget::Sync => None,
         }
     }
 
-    pub fn clear_pending_op(&mut self) {
-        self.modal = None;
+    /// Mark what a shallow comparison found on each side, replacing the
+    /// marks, in panels still showing the compared directories.
+    pub fn mark_differences(&mut self, run: &CompareRun, diffs: &[Diff]) {
+        for (which, label) in [
+            (ActivePanel::Left, &run.left),
+            (ActivePanel::Right, &run.right),
+        ] {
+            let browser = self.panel_mut(which).browser_mut();
+            if compare_root(browser).map(|dir| describe(&dir)).as_ref() != Some(label) {
+                continue;
+            }
+            browser.marked = diffs
+                .iter()
+                .filter(|diff| match which {
+                    ActivePanel::Left => diff.difference.on_left(),
+                    ActivePanel::Right => diff.difference.on_right(),
+                })
+                .map(|diff| diff.path.clone())
+                .collect();
+        }
+    }
+
+    /// Why an entry of the Compare listing is there.
+    pub fn compare_difference(&self, path: &str) -> Option<Difference> {
+        let idx = self
+            .compare_results
+            .binary_search_by(|diff| diff.path.as_str().cmp(path))
+            .ok()?;
+        Some(self.compare_results[idx].difference)
+    }
+
+    /// Open the sync dialog for the two panels' directories and start
+    /// comparing them.
+    pub fn prepare_sync(&mut self) {
+        if self.modal.is_some() {
+            return;
+        }
+        let left = compare_root(self.left_panel.browser());
+        let right = compare_root(self.right_panel.browser());
+        let (Some(left), Some(right)) = (left, right) else {
+            self.open_modal(Modal::Error(
+                "Sync needs a directory in both panels.".to_string(),
+            ));
+            return;
+        };
+        if describe(&left) == describe(&right) {
+            self.open_modal(Modal::Error(
+                "Both panels show the same directory.".to_string(),
+            ));
+            return;
+        }
+        self.modal = Some(Modal::Sync(SyncDialog {
+            left,
+            right,
+            options: self.sync_options.clone(),
+            diffs: None,
+            scanned: 0,
+            error: None,
+            focus: true,
+        }));
+        self.start_sync_compare();
+    }
+
+    /// Compare the sync dialog's directories afresh with its options.
+    pub fn start_sync_compare(&mut self) {
+        let Some(dialog) = self.sync_dialog_mut() else {
+            return;
+        };
+        dialog.diffs = None;
+        dialog.scanned = 0;
+        dialog.error = None;
+        let (left, right) = (dialog.left.clone(), dialog.right.clone());
+        let content = dialog.options.content;
+        let excludes = dialog.options.exclude_patterns();
+        // A recursive listing this supersedes would never finish loading.
+        if let Some(browser) = self.compare_target_browser_mut() {
+            browser.progress_override = None;
+        }
+        let id = self.compare_request_id.wrapping_add(1);
+        self.compare_request_id = id;
+        self.compare_run = Some(CompareRun {
+            id,
+            left: describe(&left),
+            right: describe(&right),
+            target: CompareTarget::Sync,
+        });
+        let _ = self.compare_tx.send(CompareRequest {
+            id,
+            left,
+            right,
+            depth: CompareDepth::Recursive,
+            content,
+            excludes,
+        });
+    }
+
+    pub fn sync_comparing(&self) -> bool {
+        self.compare_run
+            .as_ref()
+            .is_some_and(|run| run.target == CompareTarget::Sync)
+    }
+
+    /// Close the sync dialog, keeping its options for next time.
+    pub fn close_sync(&mut self) {
+        if self.sync_comparing() {
+            self.cancel_compare();
+        }
+        if let Some(dialog) = self.sync_dialog() {
+            self.sync_options = dialog.options.clone();
+        }
+        self.modal = None;
+    }
+
+    /// Queue the plan of the sync dialog and close it. The overwrites were
+    /// reviewed in the plan, so the sync's copies overwrite without asking
+    /// again; other operations still ask.
+    pub fn apply_sync(&mut self) -> bool {
+        let Some(dialog) = self.sync_dialog() else {
+            return false;
+        };
+        let Some(plan) = dialog.plan() else {
+            return false;
+        };
+        let root = |side| match side {
+            ActivePanel::Left => &dialog.left,
+            ActivePanel::Right => &dialog.right,
+        };
+        // Deletes go first, making room for what is copied.
+        let mut deletes = Vec::new();
+        let mut remote_deletes: HashMap<String, Vec<(String, bool)>> = HashMap::new();
+        let mut copies = Vec::new();
+        for step in &plan.steps {
+            match step.action {
+                Action::Delete => match step.entry.location {
+                    EntryLocation::Fs(ref target) => deletes.push(IOTask::Trash {
+                        target: target.clone(),
+                    }),
+                    EntryLocation::Remote { ref host, ref path } => remote_deletes
+                        .entry(host.clone())
+                        .or_default()
+                        .push((path.clone(), step.entry.is_dir)),
+                    EntryLocation::Container { .. } => {}
+                },
+                // Made before what is copied into it: both touch its path.
+                Action::Create => {
+                    let name = &step.entry.name;
+                    copies.push(match crate::sync::parent_dir(root(step.to), &step.path) {
+                        EntryLocation::Fs(dir) => IOTask::Mkdir {
+                            path: dir.join(name),
+                        },
+                        EntryLocation::Remote { host, path } => IOTask::MkdirRemote {
+                            host,
+                            path: crate::remote::join_path(&path, name),
+                        },
+                        EntryLocation::Container { .. } => continue,
+                    });
+                }
+                Action::Copy | Action::Overwrite => {
+                    let dst = match crate::sync::parent_dir(root(step.to), &step.path) {
+                        EntryLocation::Fs(dir) => CopyDest::Local(dir),
+                        EntryLocation::Remote { host, path } => CopyDest::Remote { host, path },
+                        EntryLocation::Container { .. } => continue,
+                    };
+                    let item = CopyItem {
+                        src: step.entry.location.clone(),
+                        kind: if step.entry.is_dir {
+                            CopyKind::Directory
+                        } else {
+                            CopyKind::File
+                        },
+                    };
+                    copies.extend(self.copy_task(&item, &dst).map(|task| IOTask::Answered {
+                        task: Box::new(task),
+                        conflicts: ConflictAction::Overwrite,
+                    }));
+                }
+            }
+        }
+        deletes.extend(
+            remote_deletes
+                .into_iter()
+                .map(|(host, items)| IOTask::DeleteRemote { host, items }),
+        );
+        deletes.extend(copies);
+        if deletes.is_empty() {
+            return false;
+        }
+        self.sync_options = dialog.options.clone();
+        self.modal = None;
+        for task in deletes {
+            self.enqueue_io(task);
+        }
+        true
+    }
+
+    pub fn prepare_delete_selected(&mut self, mode: DeleteMode) {
+        if self.modal.is_some() {
+            return;
+        }
+        if let Some(op) = self.build_delete_op(mode) {
+            self.modal = Some(Modal::Confirm(op));
+        }
+    }
+
+    /// Confirm restoring the selected Trash view entries. Does nothing
+    /// outside the Trash view.
+    pub fn prepare_restore_selected(&mut self) {
+        if self.modal.is_some() {
+            return;
+        }
+        if !matches!(
+            self.get_active_panel().browser().browser_mode,
+            BrowserMode::Trash
+        ) {
+            return;
+        }
+        let targets = self.selected_fs_paths();
+        if !targets.is_empty() {
+            self.modal = Some(Modal::Confirm(PendingOp::Restore { targets }));
+        }
+    }
+
+    pub fn prepare_pack_selected(&mut self) {
+        if self.modal.is_some() {
+            return;
+        }
+        if let Some(op) = self.build_pack_op() {
+            // Pre-fill the archive name from the first source, before `op` moves
+            // into the modal.
+            let name = match op {
+                PendingOp::Pack { ref sources, .. } => sources
+                    .first()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .map(|n| format!("{n}.zip"))
+                    .unwrap_or_else(|| "archive.zip".to_string()),
+                _ => "archive.zip".to_string(),
+            };
+            self.modal = Some(Modal::Confirm(op));
+            self.rename_input = Some(name);
+            self.rename_focus = true;
+        }
+    }
+
+    pub fn take_pending_op(&mut self) -> Option<PendingOp> {
+        self.pending_collisions.clear();
+        match self.modal {
+            Some(Modal::Confirm(_)) => match self.modal.take() {
+                Some(Modal::Confirm(op)) => Some(op),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn clear_pending_op(&mut self) {
+        self.modal = None;
         self.pending_collisions.clear();
         self.rename_input = None;
         self.rename_focus = false;
@@ -1697,7 +2658,7 @@ impl AppState {
             // Remote home (if we have a session with home_dir)
             if let Some(session_arc) = self.sftp_sessions.get(&host) {
                 if let Ok(session) = session_arc.try_lock() {
-                    if let Some(ref home) = session.home_dir {
+                    if let Some(home) = session.home_dir() {
                         entries.push(QuickJumpEntry {
                             label: format!("{host}:~"),
                             path: path::PathBuf::from(home),
@@ -1720,6 +2681,14 @@ impl AppState {
             });
         }
 
+        if let Some(trash) = crate::trash::home_trash() {
+            entries.push(QuickJumpEntry {
+                label: "Trash (restore or purge)".to_string(),
+                path: trash,
+                category: QuickJumpCategory::Trash,
+            });
+        }
+
         // Windows: enumerate drive letters
         #[cfg(target_os = "windows")]
         {
@@ -1817,6 +2786,19 @@ impl AppState {
             });
         }
 
+        // Saved bookmarks (SSH hosts or URLs such as davs://)
+        for bm in &self.settings.bookmarks {
+            if bm.host.is_empty() {
+                continue;
+            }
+            let location = bm.location();
+            entries.push(QuickJumpEntry {
+                label: format!("bookmark: {} ({location})", bm.label),
+                path: path::PathBuf::from(location),
+                category: QuickJumpCategory::Bookmark,
+            });
+        }
+
         let filtered: Vec<usize> = (0..entries.len()).collect();
         self.modal = Some(Modal::QuickJump(QuickJumpState {
             input: String::new(),
@@ -1833,17 +2815,117 @@ impl AppState {
         }
     }
 
+    /// Put `task` on the IO queue, where a cancel or the queue window sees
+    /// it right away.
     pub fn enqueue_io(&mut self, task: IOTask) {
-        if let Err(e) = self.io_tx.send(task) {
-            eprintln!("Failed to enqueue IO: {e}");
-        } else {
-            self.io_in_flight = self.io_in_flight.saturating_add(1);
-            // Peak tracking — io_in_flight only grows during enqueue, so
-            // taking the max here captures the batch's high-water mark.
-            if self.io_in_flight > self.io_batch_total {
-                self.io_batch_total = self.io_in_flight;
-            }
+        self.io_queue.push(task);
+        self.note_io_enqueued();
+    }
+
+    /// Count a task that was just queued.
+    fn note_io_enqueued(&mut self) {
+        if self.io_in_flight == 0 {
+            self.io_batch_started = Some(Instant::now());
+            self.io_copy_method = None;
+            self.transfer_progress.start_batch();
         }
+        self.io_in_flight = self.io_in_flight.saturating_add(1);
+        // Peak tracking — io_in_flight only grows during enqueue, so
+        // taking the max here captures the batch's high-water mark.
+        if self.io_in_flight > self.io_batch_total {
+            self.io_batch_total = self.io_in_flight;
+        }
+    }
+
+    /// The task copying `item` into `dst`, unless that pair isn't supported.
+    // This rule is misfiring when coupled with "pattern_type_mismatch"
+    #[allow(clippy::needless_borrowed_reference)]
+    fn copy_task(&self, item: &CopyItem, dst: &CopyDest) -> Option<IOTask> {
+        Some(match (&item.src, dst) {
+            // Local → Local
+            (&EntryLocation::Fs(ref src), &CopyDest::Local(ref dst_dir)) => IOTask::Copy {
+                src: src.clone(),
+                dst_dir: dst_dir.clone(),
+                preserve: self.settings.preserve(),
+                symlinks: self.symlink_policy,
+            },
+            // Local → Remote
+            (&EntryLocation::Fs(ref src), &CopyDest::Remote { ref host, ref path }) => {
+                IOTask::CopyLocalToRemote {
+                    src: src.clone(),
+                    host: host.clone(),
+                    remote_dir: path.clone(),
+                    is_dir: item.kind == CopyKind::Directory,
+                    delete_source_on_success: false,
+                    verify: self.settings.verify_transfers,
+                }
+            }
+            // Container → Local
+            (
+                &EntryLocation::Container {
+                    ref kind,
+                    ref archive_path,
+                    ref inner_path,
+                },
+                &CopyDest::Local(ref dst_dir),
+            ) => match item.kind {
+                CopyKind::File => IOTask::CopyContainer {
+                    kind: *kind,
+                    archive_path: archive_path.clone(),
+                    inner_path: inner_path.clone(),
+                    dst_dir: dst_dir.clone(),
+                    display_name: item.src.display_name(),
+                },
+                CopyKind::Directory => IOTask::CopyContainerDir {
+                    kind: *kind,
+                    archive_path: archive_path.clone(),
+                    inner_path: inner_path.clone(),
+                    dst_dir: dst_dir.clone(),
+                    display_name: item.src.display_name(),
+                },
+            },
+            // Remote → Local
+            (&EntryLocation::Remote { ref host, ref path }, &CopyDest::Local(ref dst_dir)) => {
+                IOTask::CopyRemoteToLocal {
+                    host: host.clone(),
+                    remote_path: path.clone(),
+                    dst_dir: dst_dir.clone(),
+                    name: item.src.display_name(),
+                    is_dir: item.kind == CopyKind::Directory,
+                    delete_source_on_success: false,
+                    verify: self.settings.verify_transfers,
+                }
+            }
+            // Remote → Remote
+            (
+                &EntryLocation::Remote { ref host, ref path },
+                &CopyDest::Remote {
+                    host: ref dst_host,
+                    path: ref dst_dir,
+                },
+            ) => {
+                if host == dst_host {
+                    IOTask::CopyRemoteSameHost {
+                        host: host.clone(),
+                        src_path: path.clone(),
+                        dst_dir: dst_dir.clone(),
+                        name: item.src.display_name(),
+                    }
+                } else {
+                    IOTask::CopyRemoteCrossHost {
+                        src_host: host.clone(),
+                        src_path: path.clone(),
+                        dst_host: dst_host.clone(),
+                        dst_dir: dst_dir.clone(),
+                        name: item.src.display_name(),
+                        is_dir: item.kind == CopyKind::Directory,
+                        verify: self.settings.verify_transfers,
+                    }
+                }
+            }
+            // Container → Remote: not supported yet
+            (&EntryLocation::Container { .. }, &CopyDest::Remote { .. }) => return None,
+        })
     }
 
     // This rule is misfiring when coupled with "pattern_type_mismatch"
@@ -1852,90 +2934,9 @@ impl AppState {
         match *op {
             PendingOp::Copy { ref items, ref dst } => {
                 for item in items {
-                    let task = match (&item.src, dst) {
-                        // Local → Local
-                        (&EntryLocation::Fs(ref src), &CopyDest::Local(ref dst_dir)) => {
-                            IOTask::Copy {
-                                src: src.clone(),
-                                dst_dir: dst_dir.clone(),
-                            }
-                        }
-                        // Local → Remote
-                        (&EntryLocation::Fs(ref src), &CopyDest::Remote { ref host, ref path }) => {
-                            IOTask::CopyLocalToRemote {
-                                src: src.clone(),
-                                host: host.clone(),
-                                remote_dir: path.clone(),
-                                is_dir: item.kind == CopyKind::Directory,
-                                delete_source_on_success: false,
-                            }
-                        }
-                        // Container → Local
-                        (
-                            &EntryLocation::Container {
-                                ref kind,
-                                ref archive_path,
-                                ref inner_path,
-                            },
-                            &CopyDest::Local(ref dst_dir),
-                        ) => match item.kind {
-                            CopyKind::File => IOTask::CopyContainer {
-                                kind: *kind,
-                                archive_path: archive_path.clone(),
-                                inner_path: inner_path.clone(),
-                                dst_dir: dst_dir.clone(),
-                                display_name: item.src.display_name(),
-                            },
-                            CopyKind::Directory => IOTask::CopyContainerDir {
-                                kind: *kind,
-                                archive_path: archive_path.clone(),
-                                inner_path: inner_path.clone(),
-                                dst_dir: dst_dir.clone(),
-                                display_name: item.src.display_name(),
-                            },
-                        },
-                        // Remote → Local
-                        (
-                            &EntryLocation::Remote { ref host, ref path },
-                            &CopyDest::Local(ref dst_dir),
-                        ) => IOTask::CopyRemoteToLocal {
-                            host: host.clone(),
-                            remote_path: path.clone(),
-                            dst_dir: dst_dir.clone(),
-                            name: item.src.display_name(),
-                            is_dir: item.kind == CopyKind::Directory,
-                            delete_source_on_success: false,
-                        },
-                        // Remote → Remote
-                        (
-                            &EntryLocation::Remote { ref host, ref path },
-                            &CopyDest::Remote {
-                                host: ref dst_host,
-                                path: ref dst_dir,
-                            },
-                        ) => {
-                            if host == dst_host {
-                                IOTask::CopyRemoteSameHost {
-                                    host: host.clone(),
-                                    src_path: path.clone(),
-                                    dst_dir: dst_dir.clone(),
-                                    name: item.src.display_name(),
-                                }
-                            } else {
-                                IOTask::CopyRemoteCrossHost {
-                                    src_host: host.clone(),
-                                    src_path: path.clone(),
-                                    dst_host: dst_host.clone(),
-                                    dst_dir: dst_dir.clone(),
-                                    name: item.src.display_name(),
-                                    is_dir: item.kind == CopyKind::Directory,
-                                }
-                            }
-                        }
-                        // Container → Remote: not supported yet
-                        (&EntryLocation::Container { .. }, &CopyDest::Remote { .. }) => continue,
-                    };
-                    self.enqueue_io(task);
+                    if let Some(task) = self.copy_task(item, dst) {
+                        self.enqueue_io(task);
+                    }
                 }
             }
             PendingOp::Move { ref items, ref dst } => {
@@ -1946,6 +2947,7 @@ impl AppState {
                             self.enqueue_io(IOTask::Move {
                                 src: src.clone(),
                                 dst_dir: dst_dir.clone(),
+                                symlinks: self.symlink_policy,
                             });
                         }
                         // Local → Remote: copy, then delete local only on success.
@@ -1958,6 +2960,7 @@ impl AppState {
                                 remote_dir: path.clone(),
                                 is_dir: item.kind == CopyKind::Directory,
                                 delete_source_on_success: true,
+                                verify: self.settings.verify_transfers,
                             });
                         }
                         // Remote → Local: copy, then delete remote only on success.
@@ -1972,6 +2975,7 @@ impl AppState {
                                 name: item.src.display_name(),
                                 is_dir: item.kind == CopyKind::Directory,
                                 delete_source_on_success: true,
+                                verify: self.settings.verify_transfers,
                             });
                         }
                         // Remote → Remote same host: use SFTP rename
@@ -1999,18 +3003,13 @@ impl AppState {
                     }
                 }
             }
-            PendingOp::Delete { ref targets } => {
+            PendingOp::Delete { ref targets, mode } => {
                 // Collect remote items by host so they go in one batched task each,
                 // keeping the progress counter from resetting between files.
                 let mut remote_batches: std::collections::HashMap<String, Vec<(String, bool)>> =
                     std::collections::HashMap::new();
                 for target in targets {
-                    let target_str = target.to_string_lossy();
-                    if let Some(rest) = target_str.strip_prefix("/sftp/")
-                        && let Some(slash) = rest.find('/')
-                    {
-                        let host = rest[..slash].to_string();
-                        let path = rest[slash..].to_string();
+                    if let Some((host, path)) = crate::remote::parse_synthetic_path(target) {
                         let is_dir = self
                             .get_active_panel()
                             .browser()
@@ -2032,14 +3031,29 @@ impl AppState {
                         remote_batches.entry(host).or_default().push((path, is_dir));
                         continue;
                     }
-                    self.enqueue_io(IOTask::Delete {
-                        target: target.clone(),
+                    self.enqueue_io(match mode {
+                        DeleteMode::Trash => IOTask::Trash {
+                            target: target.clone(),
+                        },
+                        DeleteMode::Permanent => IOTask::Delete {
+                            target: target.clone(),
+                        },
                     });
                 }
                 for (host, items) in remote_batches {
                     self.enqueue_io(IOTask::DeleteRemote { host, items });
                 }
             }
+            PendingOp::Restore { ref targets } => {
+                for item in targets {
+                    self.enqueue_io(IOTask::RestoreTrash { item: item.clone() });
+                }
+            }
+            PendingOp::Purge { ref targets } => {
+                for item in targets {
+                    self.enqueue_io(IOTask::PurgeTrash { item: item.clone() });
+                }
+            }
             PendingOp::Rename { ref src } => {
                 if let Some(new_name) = self.rename_input.clone() {
                     self.enqueue_io(IOTask::Rename {
@@ -2063,6 +3077,38 @@ impl AppState {
                     });
                 }
             }
+            PendingOp::Link { ref items, ref dst } => {
+                for item in items {
+                    match (&item.src, dst) {
+                        (&EntryLocation::Fs(ref src), &CopyDest::Local(ref dst_dir)) => {
+                            self.enqueue_io(IOTask::Link {
+                                src: src.clone(),
+                                dst_dir: dst_dir.clone(),
+                                kind: self.link_kind,
+                                target: self.link_target,
+                            });
+                        }
+                        (
+                            &EntryLocation::Remote { ref host, ref path },
+                            &CopyDest::Remote {
+                                path: ref dst_dir, ..
+                            },
+                        ) => {
+                            self.enqueue_io(IOTask::LinkRemoteSameHost {
+                                host: host.clone(),
+                                src_path: path.clone(),
+                                dst_dir: dst_dir.clone(),
+                                name: item.src.display_name(),
+                                kind: self.link_kind,
+                                target: self.link_target,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // Started by `start_compare` instead; nothing to queue.
+            PendingOp::Compare { .. } => {}
         }
         // Clear marks after operation is enqueued
         self.get_active_panel_mut().browser_mut().marked.clear();
@@ -2072,9 +3118,122 @@ impl AppState {
         self.io_in_flight = self.io_in_flight.saturating_sub(count);
         if self.io_in_flight == 0 {
             self.io_cancel_requested = false;
-            self.io_cancel_flag
-                .store(false, std::sync::atomic::Ordering::Relaxed);
             self.io_batch_total = 0;
+            self.io_batch_started = None;
+            self.conflict_apply_all = false;
+            self.io_background = false;
+        }
+    }
+
+    /// Whether the progress window is up (and so owns the keyboard). A
+    /// backgrounded queue comes back to the front when it needs an answer.
+    pub fn io_modal_visible(&self) -> bool {
+        self.io_in_flight > 0
+            && (!self.io_background
+                || self.conflicting_task().is_some()
+                || self.held_tasks().next().is_some())
+    }
+
+    /// Progress of the first running task waiting on a name collision.
+    pub fn conflicting_task(&self) -> Option<Arc<crate::core::TransferProgress>> {
+        self.io_queue
+            .snapshot()
+            .0
+            .into_iter()
+            .filter_map(|item| item.progress)
+            .find(|progress| progress.pending_conflict().is_some())
+    }
+
+    /// Progress of the running tasks held for being large.
+    pub fn held_tasks(&self) -> impl Iterator<Item = Arc<crate::core::TransferProgress>> {
+        self.io_queue
+            .snapshot()
+            .0
+            .into_iter()
+            .filter_map(|item| item.progress)
+            .filter(|progress| progress.held.load(std::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// Let every held transfer start.
+    pub fn release_held(&mut self) {
+        for progress in self.held_tasks() {
+            progress.release();
+        }
+    }
+
+    /// Answer the name collision shown in the progress window.
+    pub fn answer_conflict(&mut self, action: ConflictAction) {
+        let scope = if self.conflict_apply_all {
+            ConflictScope::All
+        } else {
+            ConflictScope::Once
+        };
+        if let Some(progress) = self.conflicting_task() {
+            progress.answer_conflict(action, scope);
+        }
+    }
+
+    /// Start watching a freshly downloaded temp copy of a remote file.
+    /// Re-opening the same file replaces the previous watch.
+    pub fn track_remote_edit(
+        &mut self,
+        host: String,
+        remote_path: String,
+        local_path: path::PathBuf,
+        remote_mtime: Option<u64>,
+    ) {
+        self.remote_edits.retain(|e| e.local_path != local_path);
+        let local_mtime = std::fs::metadata(&local_path)
+            .and_then(|m| m.modified())
+            .ok();
+        self.remote_edits.push(RemoteEdit {
+            host,
+            remote_path,
+            local_path,
+            local_mtime,
+            remote_mtime,
+        });
+    }
+
+    /// Record the new remote mtime after an upload so the next save of the
+    /// same temp copy isn't mistaken for a conflicting remote change.
+    pub fn on_remote_file_written(&mut self, host: &str, path: &str, mtime: Option<u64>) {
+        for edit in &mut self.remote_edits {
+            if edit.host == host && edit.remote_path == path {
+                edit.remote_mtime = mtime;
+            }
+        }
+    }
+
+    /// Check the watched temp copies (at most once per
+    /// `REMOTE_EDIT_POLL_INTERVAL`) and enqueue uploads for modified ones.
+    /// Copies that no longer exist stop being watched.
+    pub fn poll_remote_edits(&mut self) -> bool {
+        if self.remote_edits.is_empty()
+            || self.remote_edit_polled.elapsed() < REMOTE_EDIT_POLL_INTERVAL
+        {
+            return false;
+        }
+        self.remote_edit_polled = Instant::now();
+        self.remote_edits.retain(|e| e.local_path.exists());
+        let tasks: Vec<IOTask> = self
+            .remote_edits
+            .iter_mut()
+            .filter_map(RemoteEdit::poll)
+            .collect();
+        let changed = !tasks.is_empty();
+        for task in tasks {
+            self.enqueue_io(task);
+        }
+        changed
+    }
+
+    /// Next time the event loop must wake up to poll `remote_edits`.
+    pub fn remote_edit_deadline(&self) -> Option<Instant> {
+        if self.remote_edits.is_empty() {
+            None
+        } else {
+            Some(self.remote_edit_polled + REMOTE_EDIT_POLL_INTERVAL)
         }
     }
 
@@ -2083,14 +3242,25 @@ impl AppState {
             return;
         }
         self.io_cancel_requested = true;
-        self.io_cancel_flag
-            .store(true, std::sync::atomic::Ordering::Relaxed);
-        // Only signal the worker channel when there are queued tasks to skip.
-        // A single in-flight task is cancelled via the AtomicBool above; sending
-        // the channel message would leave it in the queue and wrongly skip the
-        // next independent task dispatched after the cancel completes.
-        if self.io_in_flight > 1 {
-            let _ = self.io_cancel_tx.send(());
+        // Tasks that never start send no result, so count them off here.
+        let dropped = self.io_queue.cancel_all();
+        self.on_io_completed(dropped);
+    }
+
+    /// Cancel one queue entry: drop it if still pending, stop it if running.
+    pub fn cancel_queue_item(&mut self, id: u64) {
+        if self.io_queue.remove(id) {
+            self.on_io_completed(1);
+        } else {
+            self.io_queue.cancel(id);
+        }
+    }
+
+    pub fn toggle_queue_pause(&mut self) {
+        if self.io_queue.is_paused() {
+            self.io_queue.resume();
+        } else {
+            self.io_queue.pause();
         }
     }
 
@@ -2185,19 +3355,62 @@ impl AppState {
         Some(PendingOp::Move { items, dst })
     }
 
-    fn build_delete_op(&self) -> Option<PendingOp> {
+    fn build_link_op(&self) -> Option<PendingOp> {
         let indices = self.effective_selection();
         if indices.is_empty() {
             return None;
         }
+        let dst = self.other_panel_copy_dest()?;
+        let browser = self.get_active_panel().browser();
+        let items: Vec<CopyItem> = indices
+            .iter()
+            .filter_map(|&i| {
+                let entry = &browser.entries[i];
+                let reachable = match entry.location {
+                    EntryLocation::Fs(_) => matches!(dst, CopyDest::Local(_)),
+                    EntryLocation::Remote { ref host, .. } => matches!(
+                        dst,
+                        CopyDest::Remote { host: ref dst_host, .. } if dst_host == host
+                    ),
+                    EntryLocation::Container { .. } => false,
+                };
+                reachable.then(|| CopyItem {
+                    src: entry.location.clone(),
+                    kind: if entry.is_dir {
+                        CopyKind::Directory
+                    } else {
+                        CopyKind::File
+                    },
+                })
+            })
+            .collect();
+        if items.is_empty() {
+            return None;
+        }
+        Some(PendingOp::Link { items, dst })
+    }
+
+    fn build_delete_op(&self, mode: DeleteMode) -> Option<PendingOp> {
         let browser = self.get_active_panel().browser();
+        let mode = match browser.browser_mode {
+            BrowserMode::Trash => {
+                let targets = self.selected_fs_paths();
+                return (!targets.is_empty()).then_some(PendingOp::Purge { targets });
+            }
+            BrowserMode::Remote { .. } => DeleteMode::Permanent,
+            _ => mode,
+        };
+        let indices = self.effective_selection();
+        if indices.is_empty() {
+            return None;
+        }
         // For remote entries, use the synthetic /sftp/host/path as target
         let targets: Vec<path::PathBuf> = indices
             .iter()
             .filter_map(|&i| match browser.entries[i].location {
                 EntryLocation::Fs(ref path) => Some(path.clone()),
                 EntryLocation::Remote { ref host, ref path } => {
-                    Some(path::PathBuf::from(format!("/sftp/{host}{path}")))
+                    Some(crate::remote::synthetic_path(host, path))
                 }
                 EntryLocation::Container { .. } => None,
             })
@@ -2205,7 +3418,19 @@ impl AppState {
         if targets.is_empty() {
             return None;
         }
-        Some(PendingOp::Delete { targets })
+        Some(PendingOp::Delete { targets, mode })
+    }
+
+    /// Local paths of the selected (or marked) entries.
+    fn selected_fs_paths(&self) -> Vec<path::PathBuf> {
+        let browser = self.get_active_panel().browser();
+        self.effective_selection()
+            .iter()
+            .filter_map(|&i| match browser.entries[i].location {
+                EntryLocation::Fs(ref path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect()
     }
 
     fn build_pack_op(&self) -> Option<PendingOp> {
@@ -2243,6 +3468,142 @@ impl AppState {
         self.theme_picker_selected = self.theme.selected_external.or(Some(0));
     }
 
+    pub fn open_undo_history(&mut self) {
+        self.modal = Some(Modal::UndoHistory(0));
+    }
+
+    pub fn undo_history_selected(&self) -> Option<usize> {
+        match self.modal {
+            Some(Modal::UndoHistory(selected)) => Some(selected),
+            _ => None,
+        }
+    }
+
+    pub fn select_undo_entry(&mut self, index: usize) {
+        let count = self.undo_journal.len();
+        if let Some(Modal::UndoHistory(ref mut selected)) = self.modal {
+            *selected = index.min(count.saturating_sub(1));
+        }
+    }
+
+    /// Show the IO queue. Whatever runs carries on in the background once
+    /// the queue is closed.
+    pub fn open_queue(&mut self) {
+        self.io_background = true;
+        self.modal = Some(Modal::Queue(0));
+    }
+
+    pub fn queue_selected(&self) -> Option<usize> {
+        match self.modal {
+            Some(Modal::Queue(selected)) => Some(selected),
+            _ => None,
+        }
+    }
+
+    /// Queue rows as listed: the running task, if any, then pending ones.
+    pub fn queue_rows(&self) -> Vec<(crate::core::QueueItem, QueueRowState)> {
+        let (running, pending) = self.io_queue.snapshot();
+        running
+            .into_iter()
+            .map(|item| (item, QueueRowState::Running))
+            .chain(
+                pending
+                    .into_iter()
+                    .map(|item| (item, QueueRowState::Pending)),
+            )
+            .collect()
+    }
+
+    pub fn select_queue_row(&mut self, index: usize) {
+        let count = self.queue_rows().len();
+        if let Some(Modal::Queue(ref mut selected)) = self.modal {
+            *selected = index.min(count.saturating_sub(1));
+        }
+    }
+
+    /// Move the selected pending row one place, keeping it selected.
+    /// Running tasks stay where they are.
+    pub fn shift_queue_row(&mut self, direction: crate::core::QueueShift) {
+        let Some(selected) = self.queue_selected() else {
+            return;
+        };
+        let rows = self.queue_rows();
+        let pending_id = |index: usize| {
+            rows.get(index)
+                .filter(|row| row.1 == QueueRowState::Pending)
+                .map(|row| row.0.id)
+        };
+        let Some(id) = pending_id(selected) else {
+            return;
+        };
+        let target = match direction {
+            crate::core::QueueShift::Earlier => selected.checked_sub(1),
+            crate::core::QueueShift::Later => Some(selected + 1),
+        };
+        if let Some(target) = target
+            && pending_id(target).is_some()
+        {
+            self.io_queue.shift(id, direction);
+            self.select_queue_row(target);
+        }
+    }
+
+    /// Cancel the selected queue row.
+    pub fn cancel_queue_row(&mut self) {
+        let Some(selected) = self.queue_selected() else {
+            return;
+        };
+        if let Some(id) = self.queue_rows().get(selected).map(|row| row.0.id) {
+            self.cancel_queue_item(id);
+        }
+        self.select_queue_row(selected);
+    }
+
+    pub fn open_connections(&mut self) {
+        self.modal = Some(Modal::Connections(0));
+    }
+
+    pub fn connections_selected(&self) -> Option<usize> {
+        match self.modal {
+            Some(Modal::Connections(selected)) => Some(selected),
+            _ => None,
+        }
+    }
+
+    pub fn select_connection(&mut self, index: usize) {
+        let count = self.sftp_stats.len();
+        if let Some(Modal::Connections(ref mut selected)) = self.modal {
+            *selected = index.min(count.saturating_sub(1));
+        }
+    }
+
+    /// Live SFTP sessions sorted by host, for the connection manager.
+    pub fn connection_infos(&self) -> Vec<ConnectionInfo> {
+        let mut infos: Vec<ConnectionInfo> = self
+            .sftp_stats
+            .iter()
+            .map(|(host, stats)| ConnectionInfo {
+                host: host.clone(),
+                user: stats.user.clone(),
+                banner: stats.banner.clone(),
+                uptime: stats.uptime(),
+                bytes: stats.bytes.load(std::sync::atomic::Ordering::Relaxed),
+                reconnects: stats.reconnects.load(std::sync::atomic::Ordering::Relaxed),
+            })
+            .collect();
+        infos.sort_by(|a, b| a.host.cmp(&b.host));
+        infos
+    }
+
+    /// Host of the selected row in the connection manager.
+    pub fn selected_connection_host(&self) -> Option<String> {
+        let selected = self.connections_selected()?;
+        self.connection_infos()
+            .into_iter()
+            .nth(selected)
+            .map(|info| info.host)
+    }
+
     pub fn close_theme_picker(&mut self) {
         if self.theme_picker_open() {
             self.modal = None;
@@ -2291,6 +3652,14 @@ impl AppState {
     }
 
     pub fn clear_preview(&mut self) {
+        if self
+            .preview_panel()
+            .is_some_and(|preview| preview.source == PreviewSource::Command)
+            && self.command_running.is_some()
+        {
+            self.command_cancel
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+        }
         let request_id = self.preview_request_id.wrapping_add(1);
         self.preview_request_id = request_id;
         let side = self.preview_panel_side();
@@ -2368,3 +3737,65 @@ mod collision_tests {
         assert!(AppState::op_collisions(&op).is_empty());
     }
 }
+
+#[cfg(test)]
+mod remote_edit_tests {
+    use super::*;
+
+    fn temp_copy(name: &str) -> path::PathBuf {
+        let p =
+            std::env::temp_dir().join(format!("fileman-remote-edit-{}-{name}", std::process::id()));
+        std::fs::write(&p, b"old").unwrap();
+        p
+    }
+
+    fn watch(local_path: path::PathBuf) -> RemoteEdit {
+        let local_mtime = std::fs::metadata(&local_path)
+            .and_then(|m| m.modified())
+            .ok();
+        RemoteEdit {
+            host: "h".into(),
+            remote_path: "/etc/motd".into(),
+            local_path,
+            local_mtime,
+            remote_mtime: Some(42),
+        }
+    }
+
+    #[test]
+    fn unchanged_copy_is_not_uploaded() {
+        let path = temp_copy("unchanged");
+        let mut edit = watch(path.clone());
+        assert!(edit.poll().is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn modified_copy_uploads_once_with_expected_mtime() {
+        let path = temp_copy("modified");
+        let mut edit = watch(path.clone());
+        std::fs::write(&path, b"new").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        match edit.poll() {
+            Some(IOTask::WriteRemoteFile {
+                ref path,
+                ref contents,
+                expected_mtime,
+                ..
+            }) => {
+                assert_eq!(path, "/etc/motd");
+                assert_eq!(contents, b"new");
+                assert_eq!(expected_mtime, Some(42));
+            }
+            _ => panic!("expected an upload task"),
+        }
+        assert!(edit.poll().is_none());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -17,14 +17,20 @@ where
     F: FnOnce(&mut (dyn ReadSeek + '_)) -> io::Result<R>,
 {
     if let Some((host, remote_path)) = crate::sftp::decode_archive_path(archive_path) {
-        let session = crate::sftp::get_session(&host)
-            .ok_or_else(|| io::Error::other(format!("no active SFTP session for host {host}")))?;
+        let session = crate::remote::get_session(&host)
+            .ok_or_else(|| io::Error::other(format!("no active remote session for host {host}")))?;
         let locked = session
             .lock()
             .map_err(|_| io::Error::other("session mutex poisoned"))?;
+        // Every seek would start a new SCP transfer.
+        if locked.stats().scp_only {
+            return Err(io::Error::other(crate::scp::unavailable(
+                "Browsing zip archives",
+                &host,
+            )));
+        }
         let file = locked
-            .sftp
-            .open(Path::new(&remote_path))
+            .open_read(&remote_path)
             .map_err(|e| io::Error::other(format!("open remote {remote_path}: {e}")))?;
         // Buffer the remote handle: the zip reader parses the central directory
         // with many tiny field-sized reads, and each unbuffered read is a
@@ -45,14 +51,13 @@ where
     F: FnOnce(Box<dyn Read + '_>) -> io::Result<R>,
 {
     if let Some((host, remote_path)) = crate::sftp::decode_archive_path(archive_path) {
-        let session = crate::sftp::get_session(&host)
-            .ok_or_else(|| io::Error::other(format!("no active SFTP session for host {host}")))?;
+        let session = crate::remote::get_session(&host)
+            .ok_or_else(|| io::Error::other(format!("no active remote session for host {host}")))?;
         let locked = session
             .lock()
             .map_err(|_| io::Error::other("session mutex poisoned"))?;
         let file = locked
-            .sftp
-            .open(Path::new(&remote_path))
+            .open_read(&remote_path)
             .map_err(|e| io::Error::other(format!("open remote {remote_path}: {e}")))?;
         f(Box::new(file))
     } else {
@@ -115,7 +120,9 @@ pub fn container_display_path(
 ) -> String {
     let _ = kind;
     let base = if let Some((host, remote)) = crate::sftp::decode_archive_path(archive_path) {
-        format!("/sftp/{host}{remote}")
+        crate::remote::synthetic_path(&host, &remote)
+            .to_string_lossy()
+            .into_owned()
     } else {
         archive_path.to_string_lossy().to_string()
     };
@@ -551,7 +558,20 @@ fn read_zip_bytes_prefix(
     inner_path: &str,
     max_bytes: usize,
 ) -> anyhow::Result<Vec<u8>> {
-    let data = with_seek_reader(archive_path, |reader| {
+    let mut data = Vec::new();
+    with_zip_entry(archive_path, inner_path, &mut |entry| {
+        entry.take(max_bytes as u64).read_to_end(&mut data)?;
+        Ok(())
+    })?;
+    Ok(data)
+}
+
+fn with_zip_entry(
+    archive_path: &Path,
+    inner_path: &str,
+    f: &mut dyn FnMut(&mut dyn Read) -> io::Result<()>,
+) -> io::Result<()> {
+    with_seek_reader(archive_path, |reader| {
         let mut zip = zip::ZipArchive::new(reader).map_err(io::Error::other)?;
         let normalized = inner_path.trim_start_matches('/');
         let mut found = None;
@@ -566,19 +586,16 @@ fn read_zip_bytes_prefix(
                 break;
             }
         }
-        let mut data = Vec::new();
         if let Some(idx) = found {
             let mut zf = zip.by_index(idx).map_err(io::Error::other)?;
-            zf.by_ref().take(max_bytes as u64).read_to_end(&mut data)?;
-            Ok(data)
+            f(&mut zf)
         } else {
             Err(io::Error::new(
                 io::ErrorKind::NotFound,
                 format!("Entry not found in zip: {}", inner_path),
             ))
         }
-    })?;
-    Ok(data)
+    })
 }
 
 fn collect_tar_listing<R: Read>(
@@ -810,6 +827,20 @@ fn read_tar_entry_prefix<R: Read>(
     max_bytes: usize,
     kind_label: &str,
 ) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    with_tar_entry(reader, inner_path, kind_label, &mut |entry| {
+        entry.take(max_bytes as u64).read_to_end(&mut data)?;
+        Ok(())
+    })?;
+    Ok(data)
+}
+
+fn with_tar_entry<R: Read>(
+    reader: R,
+    inner_path: &str,
+    kind_label: &str,
+    f: &mut dyn FnMut(&mut dyn Read) -> io::Result<()>,
+) -> io::Result<()> {
     let mut archive = tar::Archive::new(reader);
     let normalized = inner_path.trim_start_matches('/');
     for entry in archive.entries()? {
@@ -817,12 +848,7 @@ fn read_tar_entry_prefix<R: Read>(
         let path = entry.path()?;
         let name = normalize_archive_path(&path);
         if name == normalized {
-            let mut data = Vec::new();
-            entry
-                .by_ref()
-                .take(max_bytes as u64)
-                .read_to_end(&mut data)?;
-            return Ok(data);
+            return f(&mut entry);
         }
     }
     Err(io::Error::new(
@@ -1016,6 +1042,38 @@ pub fn read_container_bytes_prefix(
     plugin_for_kind(kind).read_bytes_prefix(archive_path, inner_path, max_bytes)
 }
 
+/// Run `f` on the contents of the member at `inner_path`, streamed from
+/// the archive rather than read into memory.
+pub fn read_container_entry(
+    kind: ContainerKind,
+    archive_path: &Path,
+    inner_path: &str,
+    f: &mut dyn FnMut(&mut dyn Read) -> io::Result<()>,
+) -> io::Result<()> {
+    match kind {
+        ContainerKind::Zip => with_zip_entry(archive_path, inner_path, f),
+        ContainerKind::Tar => with_reader(archive_path, |reader| {
+            with_tar_entry(reader, inner_path, "tar", f)
+        }),
+        ContainerKind::TarGz => with_reader(archive_path, |reader| {
+            with_tar_entry(
+                flate2::read::GzDecoder::new(reader),
+                inner_path,
+                "tar.gz",
+                f,
+            )
+        }),
+        ContainerKind::TarBz2 => with_reader(archive_path, |reader| {
+            with_tar_entry(
+                bzip2::read::BzDecoder::new(reader),
+                inner_path,
+                "tar.bz2",
+                f,
+            )
+        }),
+    }
+}
+
 pub fn read_container_metadata(
     kind: ContainerKind,
     archive_path: &Path,
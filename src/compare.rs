@@ -0,0 +1,531 @@
+//! Directory comparison (Shift+F2): what differs between the two panels.
+//! Either side may be local, inside an archive, or on a remote host.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+};
+
+use crate::core::{DirEntry, EntryLocation};
+
+/// Modification times closer than this count as equal. FAT and zip store
+/// them with two-second resolution, and copies onto them round.
+pub const MTIME_TOLERANCE: u64 = 2;
+
+/// Whether subdirectories present on both sides are compared too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompareDepth {
+    /// Only the two listings; differences are marked in place.
+    #[default]
+    Shallow,
+    /// The whole trees; differences open as a virtual listing.
+    Recursive,
+}
+
+impl CompareDepth {
+    pub const ALL: [Self; 2] = [Self::Shallow, Self::Recursive];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CompareDepth::Shallow => "this directory",
+            CompareDepth::Recursive => "recursive",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CompareDepth::Shallow => CompareDepth::Recursive,
+            CompareDepth::Recursive => CompareDepth::Shallow,
+        }
+    }
+}
+
+/// How two files of the same size are told apart.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompareContent {
+    /// By modification time.
+    #[default]
+    Metadata,
+    /// By SHA-256 of the contents, ignoring modification times.
+    Hash,
+}
+
+impl CompareContent {
+    pub const ALL: [Self; 2] = [Self::Metadata, Self::Hash];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CompareContent::Metadata => "size and date",
+            CompareContent::Hash => "size and hash",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CompareContent::Metadata => CompareContent::Hash,
+            CompareContent::Hash => CompareContent::Metadata,
+        }
+    }
+}
+
+/// Why an entry shows up in the comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difference {
+    OnlyLeft,
+    OnlyRight,
+    LeftNewer,
+    RightNewer,
+    Size,
+    Content,
+    /// A file on one side, a directory on the other.
+    Kind,
+}
+
+impl Difference {
+    pub fn label(self) -> &'static str {
+        match self {
+            Difference::OnlyLeft => "left only",
+            Difference::OnlyRight => "right only",
+            Difference::LeftNewer => "left newer",
+            Difference::RightNewer => "right newer",
+            Difference::Size => "size",
+            Difference::Content => "content",
+            Difference::Kind => "file/dir",
+        }
+    }
+
+    /// Whether the entry on the left side takes part in this difference.
+    pub fn on_left(self) -> bool {
+        self != Difference::OnlyRight
+    }
+
+    /// Whether the entry on the right side takes part in this difference.
+    pub fn on_right(self) -> bool {
+        self != Difference::OnlyLeft
+    }
+}
+
+/// One differing entry. `path` is relative to the compared directories,
+/// with `/` separators.
+#[derive(Clone)]
+pub struct Diff {
+    pub path: String,
+    pub difference: Difference,
+    pub left: Option<DirEntry>,
+    pub right: Option<DirEntry>,
+}
+
+impl Diff {
+    /// The entry to show: the left one, unless only the right exists.
+    pub fn entry(&self) -> &DirEntry {
+        self.left
+            .as_ref()
+            .or(self.right.as_ref())
+            .expect("a diff has at least one side")
+    }
+}
+
+/// What the compare worker is asked to do. A newer request supersedes
+/// the one running.
+pub struct CompareRequest {
+    pub id: u64,
+    pub left: EntryLocation,
+    pub right: EntryLocation,
+    pub depth: CompareDepth,
+    pub content: CompareContent,
+    /// Glob patterns of entries to leave out; see [`excluded`].
+    pub excludes: Vec<String>,
+}
+
+pub enum CompareEvent {
+    Progress { id: u64, scanned: usize },
+    Done { id: u64, diffs: Vec<Diff> },
+    Error { id: u64, message: String },
+}
+
+impl CompareEvent {
+    pub fn id(&self) -> u64 {
+        match *self {
+            CompareEvent::Progress { id, .. }
+            | CompareEvent::Done { id, .. }
+            | CompareEvent::Error { id, .. } => id,
+        }
+    }
+}
+
+/// How a compared directory is named in the panel header.
+pub fn describe(dir: &EntryLocation) -> String {
+    match *dir {
+        EntryLocation::Fs(ref path) => path.to_string_lossy().into_owned(),
+        EntryLocation::Container {
+            kind,
+            ref archive_path,
+            ref inner_path,
+        } => crate::core::container_display_path(kind, archive_path, inner_path),
+        EntryLocation::Remote { ref host, ref path } => format!("{host}:{path}"),
+    }
+}
+
+/// Entries of a directory, without "..".
+pub fn list(dir: &EntryLocation) -> Result<Vec<DirEntry>, String> {
+    let mut entries = match *dir {
+        EntryLocation::Fs(ref path) => {
+            crate::core::read_fs_directory(path).map_err(|e| format!("{}: {e}", path.display()))?
+        }
+        EntryLocation::Container {
+            kind,
+            ref archive_path,
+            ref inner_path,
+        } => crate::core::read_container_directory(kind, archive_path, inner_path)
+            .map_err(|e| format!("{}: {e}", archive_path.display()))?,
+        EntryLocation::Remote { ref host, ref path } => {
+            let session = crate::remote::get_session(host)
+                .ok_or_else(|| format!("No remote session for host: {host}"))?;
+            let locked = session
+                .lock()
+                .map_err(|_| "session mutex poisoned".to_string())?;
+            crate::remote::read_directory(&**locked, path)?
+        }
+    };
+    entries.retain(|e| e.name != "..");
+    Ok(entries)
+}
+
+/// Lowercase hex SHA-256 of a file's contents.
+pub fn hash(file: &EntryLocation) -> Result<String, String> {
+    match *file {
+        EntryLocation::Fs(ref path) => fs::File::open(path)
+            .and_then(crate::core::sha256_hex)
+            .map_err(|e| format!("{}: {e}", path.display())),
+        EntryLocation::Container {
+            kind,
+            ref archive_path,
+            ref inner_path,
+        } => {
+            let mut digest = String::new();
+            crate::core::read_container_entry(kind, archive_path, inner_path, &mut |entry| {
+                digest = crate::core::sha256_hex(entry)?;
+                Ok(())
+            })
+            .map_err(|e| format!("{inner_path}: {e}"))?;
+            Ok(digest)
+        }
+        EntryLocation::Remote { ref host, ref path } => {
+            let session = crate::remote::get_session(host)
+                .ok_or_else(|| format!("No remote session for host: {host}"))?;
+            let locked = session
+                .lock()
+                .map_err(|_| "session mutex poisoned".to_string())?;
+            crate::remote::sha256(&**locked, path, false)?
+                .remove("")
+                .ok_or_else(|| format!("{host}:{path}: no checksum"))
+        }
+    }
+}
+
+/// How two files of the same name differ by size and date alone, if they
+/// do. Sizes or times one side doesn't report never count as different.
+pub fn classify(left: &DirEntry, right: &DirEntry) -> Option<Difference> {
+    if left.is_dir != right.is_dir {
+        return Some(Difference::Kind);
+    }
+    if left.is_dir {
+        return None;
+    }
+    if let (Some(l), Some(r)) = (left.size, right.size)
+        && l != r
+    {
+        return Some(Difference::Size);
+    }
+    match (left.modified, right.modified) {
+        (Some(l), Some(r)) if l > r + MTIME_TOLERANCE => Some(Difference::LeftNewer),
+        (Some(l), Some(r)) if r > l + MTIME_TOLERANCE => Some(Difference::RightNewer),
+        _ => None,
+    }
+}
+
+/// Whether the entry at relative `path` matches one of the `*`/`?` globs.
+/// A pattern with a `/` is matched against the whole path, one without
+/// against each name in it, so `target` leaves out every `target` directory
+/// and `*.log` every log file.
+pub fn excluded(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_matches('/');
+        if pattern.contains('/') {
+            crate::workers::wildcard_match(path, pattern)
+        } else {
+            path.split('/')
+                .any(|name| crate::workers::wildcard_match(name, pattern))
+        }
+    })
+}
+
+/// Compare the directories `left` and `right`, sorted by path, leaving out
+/// what `excludes` matches. A directory on one side only is one entry,
+/// unless something in it is excluded; then it comes with its contents, so
+/// copying or deleting them one by one leaves the excluded ones. Symlinked
+/// directories are compared as entries, not descended into, so links back
+/// up the tree end. Files that can't be read for hashing count as
+/// differing in content. `progress` gets the number of entries looked at
+/// so far and returns false to stop early, in which case the differences
+/// found up to then are returned.
+pub fn compare(
+    left: &EntryLocation,
+    right: &EntryLocation,
+    depth: CompareDepth,
+    content: CompareContent,
+    excludes: &[String],
+    progress: &mut dyn FnMut(usize) -> bool,
+) -> Result<Vec<Diff>, String> {
+    let mut diffs: Vec<Diff> = Vec::new();
+    let mut scanned = 0;
+    // One-sided directories are only looked into when something in them
+    // may be excluded; `trimmed` collects the directories that had some.
+    let expand = depth == CompareDepth::Recursive && !excludes.is_empty();
+    let mut trimmed: HashSet<String> = HashSet::new();
+    let mut stack = vec![(String::new(), Some(left.clone()), Some(right.clone()))];
+    'walk: while let Some((prefix, left_dir, right_dir)) = stack.pop() {
+        let mut pairs: BTreeMap<String, (Option<DirEntry>, Option<DirEntry>)> = BTreeMap::new();
+        for entry in left_dir.as_ref().map_or(Ok(Vec::new()), list)? {
+            let name = entry.name.clone();
+            pairs.entry(name).or_default().0 = Some(entry);
+        }
+        for entry in right_dir.as_ref().map_or(Ok(Vec::new()), list)? {
+            let name = entry.name.clone();
+            pairs.entry(name).or_default().1 = Some(entry);
+        }
+        for (name, (l, r)) in pairs {
+            scanned += 1;
+            if !progress(scanned) {
+                break 'walk;
+            }
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            if excluded(&path, excludes) {
+                trimmed.extend(ancestors(&path).map(str::to_string));
+                continue;
+            }
+            let descend = |e: &DirEntry| expand && e.is_dir && !e.is_symlink;
+            let difference = match (l.as_ref(), r.as_ref()) {
+                (Some(l), None) => {
+                    if descend(l) {
+                        stack.push((path.clone(), Some(l.location.clone()), None));
+                    }
+                    Some(Difference::OnlyLeft)
+                }
+                (None, Some(r)) => {
+                    if descend(r) {
+                        stack.push((path.clone(), None, Some(r.location.clone())));
+                    }
+                    Some(Difference::OnlyRight)
+                }
+                (Some(l), Some(r)) if l.is_dir && r.is_dir => {
+                    if depth == CompareDepth::Recursive && !l.is_symlink && !r.is_symlink {
+                        stack.push((
+                            path.clone(),
+                            Some(l.location.clone()),
+                            Some(r.location.clone()),
+                        ));
+                    }
+                    None
+                }
+                (Some(l), Some(r)) => match classify(l, r) {
+                    Some(d @ (Difference::Size | Difference::Kind)) => Some(d),
+                    by_date if content == CompareContent::Metadata => by_date,
+                    _ => match (hash(&l.location), hash(&r.location)) {
+                        (Ok(l), Ok(r)) => (l != r).then_some(Difference::Content),
+                        (Err(e), _) | (_, Err(e)) => {
+                            log::warn!("compare: {e}");
+                            Some(Difference::Content)
+                        }
+                    },
+                },
+                (None, None) => None,
+            };
+            if let Some(difference) = difference {
+                diffs.push(Diff {
+                    path,
+                    difference,
+                    left: l,
+                    right: r,
+                });
+            }
+        }
+    }
+    // One-sided directories with nothing excluded inside stay one entry.
+    let whole: HashSet<String> = diffs
+        .iter()
+        .filter(|d| {
+            matches!(d.difference, Difference::OnlyLeft | Difference::OnlyRight)
+                && d.entry().is_dir
+                && !trimmed.contains(&d.path)
+        })
+        .map(|d| d.path.clone())
+        .collect();
+    diffs.retain(|d| !ancestors(&d.path).any(|dir| whole.contains(dir)));
+    diffs.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(diffs)
+}
+
+/// The directories above the relative `path`, outermost first.
+pub fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/').map(|(i, _)| &path[..i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(size: u64, modified: u64) -> DirEntry {
+        DirEntry {
+            name: "a".to_string(),
+            is_dir: false,
+            is_symlink: false,
+            link_target: None,
+            location: EntryLocation::Fs(PathBuf::from("a")),
+            size: Some(size),
+            modified: Some(modified),
+        }
+    }
+
+    #[test]
+    fn size_wins_over_dates_within_tolerance() {
+        assert_eq!(
+            classify(&file(1, 100), &file(2, 100)),
+            Some(Difference::Size)
+        );
+        assert_eq!(classify(&file(1, 100), &file(1, 101)), None);
+        assert_eq!(
+            classify(&file(1, 110), &file(1, 100)),
+            Some(Difference::LeftNewer)
+        );
+        assert_eq!(
+            classify(&file(1, 100), &file(1, 110)),
+            Some(Difference::RightNewer)
+        );
+        let mut dir = file(0, 100);
+        dir.is_dir = true;
+        dir.size = None;
+        assert_eq!(classify(&file(1, 100), &dir), Some(Difference::Kind));
+        assert_eq!(classify(&dir, &dir), None);
+    }
+
+    #[test]
+    fn trees_differ_by_presence_size_and_content() {
+        let root = std::env::temp_dir().join(format!("fileman-compare-{}", std::process::id()));
+        let (left, right) = (root.join("build"), root.join("deploy"));
+        for side in [&left, &right] {
+            fs::create_dir_all(side.join("assets")).unwrap();
+            fs::write(side.join("index.html"), b"<html>").unwrap();
+        }
+        fs::write(left.join("assets/app.js"), b"let a = 1;").unwrap();
+        fs::write(right.join("assets/app.js"), b"let a = 2;").unwrap();
+        fs::write(left.join("assets/new.css"), b"").unwrap();
+        fs::write(right.join("old.txt"), b"stale").unwrap();
+        fs::write(left.join("README"), b"short").unwrap();
+        fs::write(right.join("README"), b"longer").unwrap();
+
+        let (l, r) = (EntryLocation::Fs(left), EntryLocation::Fs(right));
+        let run = |depth, content| {
+            compare(&l, &r, depth, content, &[], &mut |_| true)
+                .unwrap()
+                .into_iter()
+                .map(|d| (d.path, d.difference))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            run(CompareDepth::Shallow, CompareContent::Metadata),
+            [
+                ("README".to_string(), Difference::Size),
+                ("old.txt".to_string(), Difference::OnlyRight),
+            ]
+        );
+        assert_eq!(
+            run(CompareDepth::Recursive, CompareContent::Hash),
+            [
+                ("README".to_string(), Difference::Size),
+                ("assets/app.js".to_string(), Difference::Content),
+                ("assets/new.css".to_string(), Difference::OnlyLeft),
+                ("old.txt".to_string(), Difference::OnlyRight),
+            ]
+        );
+        let excludes = ["assets".to_string(), "*.txt".to_string()];
+        let kept = compare(
+            &l,
+            &r,
+            CompareDepth::Recursive,
+            CompareContent::Metadata,
+            &excludes,
+            &mut |_| true,
+        )
+        .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].path, "README");
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_up_the_tree_are_not_followed() {
+        let root = std::env::temp_dir().join(format!("fileman-compare-up-{}", std::process::id()));
+        let (left, right) = (root.join("a"), root.join("b"));
+        for side in [&left, &right] {
+            fs::create_dir_all(side.join("sub")).unwrap();
+            std::os::unix::fs::symlink("..", side.join("sub/up")).unwrap();
+        }
+        fs::write(left.join("sub/only.txt"), b"x").unwrap();
+        let diffs = compare(
+            &EntryLocation::Fs(left),
+            &EntryLocation::Fs(right),
+            CompareDepth::Recursive,
+            CompareContent::Hash,
+            &[],
+            &mut |_| true,
+        )
+        .unwrap();
+        let paths: Vec<_> = diffs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["sub/only.txt"]);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn one_sided_dirs_open_up_around_excludes() {
+        let root = std::env::temp_dir().join(format!("fileman-compare-ex-{}", std::process::id()));
+        let (left, right) = (root.join("src"), root.join("dst"));
+        fs::create_dir_all(left.join("web/node_modules")).unwrap();
+        fs::create_dir_all(left.join("docs")).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("web/index.html"), b"<html>").unwrap();
+        fs::write(left.join("web/node_modules/x.js"), b"").unwrap();
+        fs::write(left.join("docs/a.md"), b"").unwrap();
+
+        let excludes = ["node_modules".to_string()];
+        let diffs = compare(
+            &EntryLocation::Fs(left),
+            &EntryLocation::Fs(right),
+            CompareDepth::Recursive,
+            CompareContent::Metadata,
+            &excludes,
+            &mut |_| true,
+        )
+        .unwrap();
+        let paths: Vec<&str> = diffs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["docs", "web", "web/index.html"]);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn excludes_match_names_or_whole_paths() {
+        let patterns = ["node_modules".to_string(), "docs/*.pdf".to_string()];
+        assert!(excluded("node_modules", &patterns));
+        assert!(excluded("web/node_modules/left-pad", &patterns));
+        assert!(excluded("docs/manual.pdf", &patterns));
+        assert!(!excluded("web/docs/manual.pdf", &patterns));
+        assert!(!excluded("src/modules.rs", &patterns));
+        assert!(!excluded("anything", &[]));
+    }
+}
@@ -4,13 +4,15 @@ use std::{
     path::{self, Path},
     sync::{
         Arc,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
-    time::UNIX_EPOCH,
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 
 /// Shared transfer progress, updated atomically by worker threads and read by
-/// the UI. One instance lives in AppState behind an Arc.
+/// the UI. The root instance lives in AppState behind an Arc and holds the
+/// settings and the batch-wide rate; each running task reports into a
+/// [`TransferProgress::child`] of it.
 pub struct TransferProgress {
     /// Bytes transferred so far.
     pub bytes_done: AtomicU64,
@@ -22,8 +24,42 @@ pub struct TransferProgress {
     /// at the start of each task, displayed in the progress modal. None
     /// when no task is in flight.
     pub current_name: std::sync::Mutex<Option<String>>,
+    /// Remote transfer speed cap in bytes per second (0 = unlimited). Read
+    /// on every chunk, so changing it takes effect mid-transfer.
+    pub limit: AtomicU64,
+    /// Remote transfers of at least this many bytes wait for the user
+    /// before starting (0 = never hold).
+    pub hold_threshold: AtomicU64,
+    /// Set while a large transfer waits for [`TransferProgress::release`].
+    pub held: AtomicBool,
+    /// Name collision the worker is waiting on, and the user's answer.
+    conflict: std::sync::Mutex<Option<Conflict>>,
+    conflict_answer: std::sync::Mutex<Option<ConflictAction>>,
+    /// Answer chosen with "apply to all"; lasts until the next batch. On a
+    /// task's progress, the answer the task was queued with.
+    conflict_policy: std::sync::Mutex<Option<ConflictAction>>,
+    /// Token bucket for `limit`: available bytes (negative while in debt)
+    /// and when it was last refilled.
+    bucket: std::sync::Mutex<(f64, Instant)>,
+    /// Bytes moved since [`TransferProgress::start_batch`]. Unlike
+    /// `bytes_done` it is not reset per task, so the rate spans a batch.
+    moved: AtomicU64,
+    /// `(when, moved)` samples over the last [`RATE_WINDOW`].
+    samples: std::sync::Mutex<std::collections::VecDeque<(Instant, u64)>>,
+    /// The batch-wide progress a task's progress reports into.
+    parent: Option<Arc<TransferProgress>>,
 }
 
+/// Longest single sleep while throttling, so limit changes and cancels are
+/// noticed promptly.
+const THROTTLE_TICK: Duration = Duration::from_millis(100);
+
+/// Span the transfer rate is averaged over: long enough to smooth out
+/// per-chunk jitter, short enough to follow a changed speed limit.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+/// Minimum spacing of rate samples.
+const RATE_SAMPLE_EVERY: Duration = Duration::from_millis(250);
+
 impl TransferProgress {
     pub fn new() -> Self {
         Self {
@@ -31,9 +67,33 @@ impl TransferProgress {
             bytes_total: AtomicU64::new(0),
             items_done: AtomicU64::new(0),
             current_name: std::sync::Mutex::new(None),
+            limit: AtomicU64::new(0),
+            hold_threshold: AtomicU64::new(0),
+            held: AtomicBool::new(false),
+            conflict: std::sync::Mutex::new(None),
+            conflict_answer: std::sync::Mutex::new(None),
+            conflict_policy: std::sync::Mutex::new(None),
+            bucket: std::sync::Mutex::new((0.0, Instant::now())),
+            moved: AtomicU64::new(0),
+            samples: std::sync::Mutex::new(std::collections::VecDeque::new()),
+            parent: None,
         }
     }
 
+    /// Progress for one task. Moved bytes also count towards this one's
+    /// rate; the speed limit, hold threshold and "apply to all" conflict
+    /// answers are shared with it.
+    pub fn child(self: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Self {
+            parent: Some(self.clone()),
+            ..Self::new()
+        })
+    }
+
+    fn root(&self) -> &Self {
+        self.parent.as_deref().unwrap_or(self)
+    }
+
     pub fn reset(&self, total: u64) {
         self.bytes_done.store(0, Ordering::Relaxed);
         self.bytes_total.store(total, Ordering::Relaxed);
@@ -42,6 +102,66 @@ impl TransferProgress {
 
     pub fn add(&self, n: u64) {
         self.bytes_done.fetch_add(n, Ordering::Relaxed);
+        self.record_moved(n);
+        if let Some(ref parent) = self.parent {
+            parent.record_moved(n);
+        }
+    }
+
+    fn record_moved(&self, n: u64) {
+        let moved = self.moved.fetch_add(n, Ordering::Relaxed) + n;
+        if let Ok(mut samples) = self.samples.lock() {
+            let now = Instant::now();
+            if samples
+                .back()
+                .is_none_or(|last| now.duration_since(last.0) >= RATE_SAMPLE_EVERY)
+            {
+                samples.push_back((now, moved));
+            }
+            while samples
+                .front()
+                .is_some_and(|first| now.duration_since(first.0) > RATE_WINDOW)
+            {
+                samples.pop_front();
+            }
+        }
+    }
+
+    /// Start measuring the rate afresh for a new batch of tasks.
+    pub fn start_batch(&self) {
+        self.moved.store(0, Ordering::Relaxed);
+        if let Ok(mut samples) = self.samples.lock() {
+            samples.clear();
+        }
+        if let Ok(mut policy) = self.conflict_policy.lock() {
+            *policy = None;
+        }
+    }
+
+    /// Bytes per second over the last few seconds, counting up to now so a
+    /// stalled transfer decays towards zero. `None` until there is enough
+    /// history to say.
+    pub fn rate(&self) -> Option<f64> {
+        let samples = self.samples.lock().ok()?;
+        let first = *samples.front()?;
+        let span = first.0.elapsed().as_secs_f64();
+        if span < 1.0 {
+            return None;
+        }
+        let moved = self.moved.load(Ordering::Relaxed);
+        Some(moved.saturating_sub(first.1) as f64 / span)
+    }
+
+    /// Time left for the current task at the current rate.
+    pub fn eta(&self) -> Option<Duration> {
+        let (done, total) = self.snapshot();
+        let rate = self.rate()?;
+        if total == 0 || rate < 1.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            total.saturating_sub(done) as f64 / rate,
+        ))
     }
 
     pub fn add_item(&self) {
@@ -64,13 +184,238 @@ impl TransferProgress {
     pub fn current_name(&self) -> Option<String> {
         self.current_name.lock().ok().and_then(|g| g.clone())
     }
+
+    /// Charge `n` transferred bytes against the speed limit, sleeping until
+    /// the token bucket is out of debt. The bucket holds at most one
+    /// second's worth of bytes, so an idle pause doesn't turn into a burst.
+    pub fn throttle(&self, n: u64, cancel: Option<&AtomicBool>) {
+        let root = self.root();
+        let Ok(mut bucket) = root.bucket.lock() else {
+            return;
+        };
+        let mut charged = false;
+        loop {
+            let limit = root.limit.load(Ordering::Relaxed) as f64;
+            if limit == 0.0 || cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+                return;
+            }
+            let now = Instant::now();
+            bucket.0 = (bucket.0 + now.duration_since(bucket.1).as_secs_f64() * limit).min(limit);
+            bucket.1 = now;
+            if !charged {
+                bucket.0 -= n as f64;
+                charged = true;
+            }
+            if bucket.0 >= 0.0 {
+                return;
+            }
+            let wait = Duration::from_secs_f64(-bucket.0 / limit);
+            std::thread::sleep(wait.min(THROTTLE_TICK));
+        }
+    }
+
+    /// Hold a transfer of `size` bytes until the user releases it, when it
+    /// reaches the hold threshold. Returns false if cancelled while held.
+    pub fn hold_if_large(&self, size: u64, cancel: &AtomicBool) -> bool {
+        let threshold = self.root().hold_threshold.load(Ordering::Relaxed);
+        if threshold == 0 || size < threshold {
+            return true;
+        }
+        self.bytes_total.store(size, Ordering::Relaxed);
+        self.held.store(true, Ordering::Relaxed);
+        while self.held.load(Ordering::Relaxed) {
+            if cancel.load(Ordering::Relaxed) {
+                self.held.store(false, Ordering::Relaxed);
+                return false;
+            }
+            std::thread::sleep(THROTTLE_TICK);
+        }
+        true
+    }
+
+    /// Whether large transfers are held at all; measuring a transfer's size
+    /// can be skipped otherwise.
+    pub fn holds_large(&self) -> bool {
+        self.root().hold_threshold.load(Ordering::Relaxed) != 0
+    }
+
+    /// Let a held transfer start.
+    pub fn release(&self) {
+        self.held.store(false, Ordering::Relaxed);
+    }
+
+    /// Ask the user what to do about `conflict`, blocking until they answer
+    /// unless the task or the whole batch already has an answer. Returns
+    /// `None` if cancelled while waiting.
+    pub fn ask_conflict(&self, conflict: Conflict, cancel: &AtomicBool) -> Option<Resolution> {
+        let policy = |progress: &Self| progress.conflict_policy.lock().ok().and_then(|p| *p);
+        if let Some(action) = policy(self).or_else(|| policy(self.root())) {
+            return Some(action.resolve(&conflict));
+        }
+        if let Ok(mut answer) = self.conflict_answer.lock() {
+            *answer = None;
+        }
+        let resolve = |action: ConflictAction| action.resolve(&conflict);
+        if let Ok(mut slot) = self.conflict.lock() {
+            *slot = Some(conflict.clone());
+        }
+        let action = loop {
+            if cancel.load(Ordering::Relaxed) {
+                break None;
+            }
+            if let Some(action) = self.conflict_answer.lock().ok().and_then(|mut a| a.take()) {
+                break Some(action);
+            }
+            std::thread::sleep(THROTTLE_TICK);
+        };
+        if let Ok(mut slot) = self.conflict.lock() {
+            *slot = None;
+        }
+        action.map(resolve)
+    }
+
+    /// Answer every collision of this progress's task with `action` without
+    /// asking; other tasks of the batch still ask.
+    pub fn answer_task_conflicts(&self, action: ConflictAction) {
+        if let Ok(mut policy) = self.conflict_policy.lock() {
+            *policy = Some(action);
+        }
+    }
+
+    /// The collision the worker is waiting on, if any.
+    pub fn pending_conflict(&self) -> Option<Conflict> {
+        self.conflict.lock().ok().and_then(|c| c.clone())
+    }
+
+    /// Answer the pending collision; with [`ConflictScope::All`] the same
+    /// answer is given to every later collision in this batch.
+    pub fn answer_conflict(&self, action: ConflictAction, scope: ConflictScope) {
+        if matches!(scope, ConflictScope::All)
+            && let Ok(mut policy) = self.root().conflict_policy.lock()
+        {
+            *policy = Some(action);
+        }
+        if let Ok(mut answer) = self.conflict_answer.lock() {
+            *answer = Some(action);
+        }
+    }
+}
+
+/// One side of a name collision: enough to compare the two at a glance.
+#[derive(Clone, Copy)]
+pub struct ConflictSide {
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch.
+    pub mtime: Option<u64>,
+}
+
+impl ConflictSide {
+    /// A side nothing is known about.
+    pub const UNKNOWN: Self = Self {
+        is_dir: false,
+        size: None,
+        mtime: None,
+    };
+
+    /// Describe a local path, or `None` if nothing is there.
+    pub fn local(path: &Path) -> Option<Self> {
+        let meta = fs::symlink_metadata(path).ok()?;
+        Some(Self {
+            is_dir: meta.is_dir(),
+            size: (!meta.is_dir()).then_some(meta.len()),
+            mtime: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+        })
+    }
+}
+
+/// An item about to land on an existing entry of the same name.
+#[derive(Clone)]
+pub struct Conflict {
+    pub name: String,
+    pub source: ConflictSide,
+    pub target: ConflictSide,
+}
+
+/// What to do with a colliding item.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAction {
+    Overwrite,
+    Skip,
+    /// Write under a free name with a numeric suffix.
+    Rename,
+    /// Overwrite only when the source is known to be newer.
+    OverwriteIfNewer,
+    /// Overwrite unless both sizes are known and equal.
+    OverwriteIfSizeDiffers,
+}
+
+/// Whether a conflict answer covers only this collision or the whole batch.
+#[derive(Clone, Copy)]
+pub enum ConflictScope {
+    Once,
+    All,
+}
+
+/// A [`ConflictAction`] applied to a particular collision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    Rename,
+}
+
+impl ConflictAction {
+    pub fn resolve(self, conflict: &Conflict) -> Resolution {
+        let overwrite_if = |yes: bool| {
+            if yes {
+                Resolution::Overwrite
+            } else {
+                Resolution::Skip
+            }
+        };
+        match self {
+            ConflictAction::Overwrite => Resolution::Overwrite,
+            ConflictAction::Skip => Resolution::Skip,
+            ConflictAction::Rename => Resolution::Rename,
+            ConflictAction::OverwriteIfNewer => {
+                overwrite_if(match (conflict.source.mtime, conflict.target.mtime) {
+                    (Some(src), Some(dst)) => src > dst,
+                    _ => false,
+                })
+            }
+            ConflictAction::OverwriteIfSizeDiffers => {
+                overwrite_if(match (conflict.source.size, conflict.target.size) {
+                    (Some(src), Some(dst)) => src != dst,
+                    _ => true,
+                })
+            }
+        }
+    }
+}
+
+/// First of `name (1)`, `name (2)`, … (keeping any extension) for which
+/// `taken` is false.
+pub fn free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| format!("{stem} ({n}){ext}"))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| name.to_string())
 }
 
 pub use crate::archive::{
     ContainerKind, container_display_path, container_kind_from_path, copy_container_dir,
     copy_container_entry, create_archive, format_container_listing, is_container_path,
     normalize_archive_path, read_container_bytes_prefix, read_container_directory,
-    read_container_directory_with_progress, read_container_metadata,
+    read_container_directory_with_progress, read_container_entry, read_container_metadata,
 };
 
 #[derive(Clone)]
@@ -153,6 +498,15 @@ pub enum BrowserMode {
         host: String,
         path: String,
     },
+    /// Items of all trash directories; entries point into `<trash>/files`.
+    Trash,
+    /// What differs between two directory trees (Shift+F2, recursive).
+    /// Entries are named by their relative path and point at the left side
+    /// unless they exist only on the right.
+    Compare {
+        left: String,
+        right: String,
+    },
 }
 
 pub enum PreviewContent {
@@ -196,6 +550,9 @@ pub enum IOTask {
     Copy {
         src: path::PathBuf,
         dst_dir: path::PathBuf,
+        /// Metadata to carry over, from Settings.
+        preserve: Preserve,
+        symlinks: SymlinkPolicy,
     },
     CopyContainer {
         kind: ContainerKind,
@@ -214,10 +571,31 @@ pub enum IOTask {
     Move {
         src: path::PathBuf,
         dst_dir: path::PathBuf,
+        /// Anything but `Links` moves by copying, even on one filesystem.
+        symlinks: SymlinkPolicy,
+    },
+    /// Link to `src` from `dst_dir`, under the same name.
+    Link {
+        src: path::PathBuf,
+        dst_dir: path::PathBuf,
+        kind: LinkKind,
+        target: LinkTarget,
     },
     Delete {
         target: path::PathBuf,
     },
+    /// Move a local file or directory to the freedesktop trash.
+    Trash {
+        target: path::PathBuf,
+    },
+    /// Put a trashed entry (`<trash>/files/NAME`) back where it came from.
+    RestoreTrash {
+        item: path::PathBuf,
+    },
+    /// Delete a trashed entry and its info file for good.
+    PurgeTrash {
+        item: path::PathBuf,
+    },
     Rename {
         src: path::PathBuf,
         new_name: String,
@@ -249,6 +627,9 @@ pub enum IOTask {
         host: String,
         path: String,
         contents: Vec<u8>,
+        /// Remote mtime the contents were based on. When set and the remote
+        /// file no longer matches, the write is refused with `RemoteChanged`.
+        expected_mtime: Option<u64>,
     },
     CopyRemoteToLocal {
         host: String,
@@ -259,6 +640,9 @@ pub enum IOTask {
         /// When true (a cross-location Move), delete the remote source after the
         /// copy has verifiably succeeded. The delete never runs on copy failure.
         delete_source_on_success: bool,
+        /// Compare SHA-256 checksums of source and destination after the
+        /// copy; a mismatch fails the task before any source delete.
+        verify: bool,
     },
     CopyLocalToRemote {
         src: path::PathBuf,
@@ -268,6 +652,9 @@ pub enum IOTask {
         /// When true (a cross-location Move), delete the local source after the
         /// copy has verifiably succeeded. The delete never runs on copy failure.
         delete_source_on_success: bool,
+        /// Compare SHA-256 checksums of source and destination after the
+        /// copy; a mismatch fails the task before any source delete.
+        verify: bool,
     },
     DeleteRemote {
         host: String,
@@ -300,6 +687,14 @@ pub enum IOTask {
         dst_dir: String,
         name: String,
     },
+    LinkRemoteSameHost {
+        host: String,
+        src_path: String,
+        dst_dir: String,
+        name: String,
+        kind: LinkKind,
+        target: LinkTarget,
+    },
     CopyContainerAndOpen {
         kind: crate::archive::ContainerKind,
         archive_path: path::PathBuf,
@@ -314,16 +709,27 @@ pub enum IOTask {
         dst_dir: String,
         name: String,
         is_dir: bool,
+        /// Compare SHA-256 checksums of source and destination after the
+        /// copy; a mismatch fails the task before any source delete.
+        verify: bool,
     },
     /// Re-run the inner task with OS-level privilege elevation.
     Elevated(Box<IOTask>),
+    /// Run the inner task answering its name collisions with `conflicts`
+    /// instead of asking, e.g. for overwrites already reviewed in a plan.
+    Answered {
+        task: Box<IOTask>,
+        conflicts: ConflictAction,
+    },
+    /// Take back a journaled operation, if nothing touched it since.
+    Undo(UndoRecord),
 }
 
 impl IOTask {
     /// Best-effort filename for display in the progress modal. Walks into
-    /// `Elevated` so a wrapped task still shows its target. Returns "…" for
-    /// tasks where no single name is meaningful (Pack, batched delete with
-    /// multiple items).
+    /// `Elevated` and `Answered` so a wrapped task still shows its target.
+    /// Returns "…" for tasks where no single name is meaningful (Pack,
+    /// batched delete with multiple items).
     pub fn display_name(&self) -> String {
         fn fs_name(p: &path::Path) -> String {
             p.file_name()
@@ -335,8 +741,10 @@ impl IOTask {
         }
         match *self {
             IOTask::Copy { ref src, .. } => fs_name(src),
-            IOTask::Move { ref src, .. } => fs_name(src),
+            IOTask::Move { ref src, .. } | IOTask::Link { ref src, .. } => fs_name(src),
             IOTask::Delete { ref target } => fs_name(target),
+            IOTask::Trash { ref target } => fs_name(target),
+            IOTask::RestoreTrash { ref item } | IOTask::PurgeTrash { ref item } => fs_name(item),
             IOTask::Rename { ref src, .. } => fs_name(src),
             IOTask::WriteFile { ref path, .. } => fs_name(path),
             IOTask::Mkdir { ref path } => fs_name(path),
@@ -369,9 +777,537 @@ impl IOTask {
                 ref remote_path, ..
             } => remote_name(remote_path),
             IOTask::CopyRemoteSameHost { ref name, .. }
-            | IOTask::MoveRemoteSameHost { ref name, .. } => name.clone(),
+            | IOTask::MoveRemoteSameHost { ref name, .. }
+            | IOTask::LinkRemoteSameHost { ref name, .. } => name.clone(),
             IOTask::CopyRemoteCrossHost { ref name, .. } => name.clone(),
-            IOTask::Elevated(ref inner) => inner.display_name(),
+            IOTask::Elevated(ref inner)
+            | IOTask::Answered {
+                task: ref inner, ..
+            } => inner.display_name(),
+            IOTask::Undo(ref record) => format!("Undo {}", record.label),
+        }
+    }
+
+    /// SFTP hosts the task talks to.
+    pub fn remote_hosts(&self) -> Vec<&str> {
+        match *self {
+            IOTask::WriteRemoteFile { ref host, .. }
+            | IOTask::CopyRemoteToLocal { ref host, .. }
+            | IOTask::CopyLocalToRemote { ref host, .. }
+            | IOTask::DeleteRemote { ref host, .. }
+            | IOTask::RenameRemote { ref host, .. }
+            | IOTask::MkdirRemote { ref host, .. }
+            | IOTask::CopyRemoteToLocalAndOpen { ref host, .. }
+            | IOTask::CopyRemoteSameHost { ref host, .. }
+            | IOTask::MoveRemoteSameHost { ref host, .. }
+            | IOTask::LinkRemoteSameHost { ref host, .. } => vec![host],
+            IOTask::CopyRemoteCrossHost {
+                ref src_host,
+                ref dst_host,
+                ..
+            } => vec![src_host, dst_host],
+            IOTask::Answered { ref task, .. } => task.remote_hosts(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether re-running the task after a partial failure gives the same
+    /// result, so it may be retried on a fresh connection. Deletes, renames,
+    /// mkdirs and moves are not: a first attempt may have half-applied them.
+    pub fn is_idempotent(&self) -> bool {
+        match *self {
+            // A partial write changes the remote mtime, so a retry would
+            // refuse it as changed by someone else.
+            IOTask::WriteRemoteFile { expected_mtime, .. } => expected_mtime.is_none(),
+            IOTask::CopyRemoteToLocalAndOpen { .. }
+            | IOTask::CopyRemoteSameHost { .. }
+            | IOTask::CopyRemoteCrossHost { .. } => true,
+            IOTask::CopyRemoteToLocal {
+                delete_source_on_success,
+                ..
+            }
+            | IOTask::CopyLocalToRemote {
+                delete_source_on_success,
+                ..
+            } => !delete_source_on_success,
+            IOTask::Answered { ref task, .. } => task.is_idempotent(),
+            _ => false,
+        }
+    }
+
+    /// Everything the task reads or writes, for ordering tasks that touch
+    /// the same files and for picking their lanes.
+    pub fn touched_paths(&self) -> Vec<TaskPath> {
+        let local = |p: &Path| TaskPath::Local(p.to_path_buf());
+        let remote = |host: &str, p: &str| TaskPath::Remote(host.to_string(), p.to_string());
+        let named = |dir: &Path, name: Option<&std::ffi::OsStr>| {
+            TaskPath::Local(name.map_or_else(|| dir.to_path_buf(), |name| dir.join(name)))
+        };
+        let sibling = |p: &str, name: &str| match p.rsplit_once('/') {
+            Some((parent, _)) => format!("{parent}/{name}"),
+            None => name.to_string(),
+        };
+        match *self {
+            IOTask::Copy {
+                ref src,
+                ref dst_dir,
+                ..
+            }
+            | IOTask::Move {
+                ref src,
+                ref dst_dir,
+                ..
+            }
+            | IOTask::Link {
+                ref src,
+                ref dst_dir,
+                ..
+            } => vec![local(src), named(dst_dir, src.file_name())],
+            IOTask::Delete { ref target } | IOTask::Trash { ref target } => vec![local(target)],
+            IOTask::RestoreTrash { ref item } | IOTask::PurgeTrash { ref item } => {
+                vec![local(item)]
+            }
+            IOTask::Rename {
+                ref src,
+                ref new_name,
+            } => vec![local(src), local(&src.with_file_name(new_name))],
+            IOTask::WriteFile { ref path, .. }
+            | IOTask::Mkdir { ref path }
+            | IOTask::SetProps { ref path, .. } => vec![local(path)],
+            IOTask::CopyContainer {
+                ref archive_path,
+                ref dst_dir,
+                ref display_name,
+                ..
+            }
+            | IOTask::CopyContainerDir {
+                ref archive_path,
+                ref dst_dir,
+                ref display_name,
+                ..
+            }
+            | IOTask::CopyContainerAndOpen {
+                ref archive_path,
+                ref dst_dir,
+                ref display_name,
+                ..
+            } => vec![local(archive_path), local(&dst_dir.join(display_name))],
+            IOTask::Pack {
+                ref sources,
+                ref archive_path,
+                ..
+            } => sources
+                .iter()
+                .map(|src| local(src))
+                .chain([local(archive_path)])
+                .collect(),
+            IOTask::WriteRemoteFile {
+                ref host, ref path, ..
+            }
+            | IOTask::MkdirRemote { ref host, ref path } => vec![remote(host, path)],
+            IOTask::CopyRemoteToLocal {
+                ref host,
+                ref remote_path,
+                ref dst_dir,
+                ref name,
+                ..
+            } => vec![remote(host, remote_path), local(&dst_dir.join(name))],
+            IOTask::CopyLocalToRemote {
+                ref src,
+                ref host,
+                ref remote_dir,
+                ..
+            } => {
+                let name = src.file_name().unwrap_or_default().to_string_lossy();
+                vec![
+                    local(src),
+                    remote(host, &crate::remote::join_path(remote_dir, &name)),
+                ]
+            }
+            IOTask::DeleteRemote {
+                ref host,
+                ref items,
+            } => items.iter().map(|item| remote(host, &item.0)).collect(),
+            IOTask::RenameRemote {
+                ref host,
+                ref src,
+                ref new_name,
+            } => vec![remote(host, src), remote(host, &sibling(src, new_name))],
+            IOTask::CopyRemoteToLocalAndOpen {
+                ref host,
+                ref remote_path,
+                ref local_path,
+            } => vec![remote(host, remote_path), local(local_path)],
+            IOTask::CopyRemoteSameHost {
+                ref host,
+                ref src_path,
+                ref dst_dir,
+                ref name,
+            }
+            | IOTask::MoveRemoteSameHost {
+                ref host,
+                ref src_path,
+                ref dst_dir,
+                ref name,
+            }
+            | IOTask::LinkRemoteSameHost {
+                ref host,
+                ref src_path,
+                ref dst_dir,
+                ref name,
+                ..
+            } => vec![
+                remote(host, src_path),
+                remote(host, &crate::remote::join_path(dst_dir, name)),
+            ],
+            IOTask::CopyRemoteCrossHost {
+                ref src_host,
+                ref src_path,
+                ref dst_host,
+                ref dst_dir,
+                ref name,
+                ..
+            } => vec![
+                remote(src_host, src_path),
+                remote(dst_host, &crate::remote::join_path(dst_dir, name)),
+            ],
+            IOTask::Elevated(ref inner)
+            | IOTask::Answered {
+                task: ref inner, ..
+            } => inner.touched_paths(),
+            IOTask::Undo(ref record) => match record.action {
+                UndoAction::MoveBack { ref from, ref to } => vec![local(from), local(to)],
+                UndoAction::TrashCreated { ref path } => vec![local(path)],
+                UndoAction::RestoreTrash { ref item } => vec![local(item)],
+            },
+        }
+    }
+}
+
+/// A file or directory an IO task works on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TaskPath {
+    Local(path::PathBuf),
+    Remote(String, String),
+}
+
+impl TaskPath {
+    /// Whether one path is the other or lies inside it.
+    fn overlaps(&self, other: &TaskPath) -> bool {
+        let nested = |a: &Path, b: &Path| a.starts_with(b) || b.starts_with(a);
+        match *self {
+            TaskPath::Local(ref a) => {
+                matches!(*other, TaskPath::Local(ref b) if nested(a, b))
+            }
+            TaskPath::Remote(ref host_a, ref a) => matches!(
+                *other,
+                TaskPath::Remote(ref host_b, ref b) if host_a == host_b && nested(Path::new(a), Path::new(b))
+            ),
+        }
+    }
+
+    /// The device or connection the path is on.
+    fn lane(&self) -> Lane {
+        match *self {
+            TaskPath::Local(ref p) => Lane::Local(device_of(p)),
+            TaskPath::Remote(ref host, _) => Lane::Remote(host.clone()),
+        }
+    }
+}
+
+/// Tasks in one lane share a concurrency limit: a local device, or a remote
+/// connection (which runs one transfer at a time anyway).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Lane {
+    Local(u64),
+    Remote(String),
+}
+
+impl Lane {
+    fn limit(&self) -> usize {
+        match *self {
+            Lane::Local(_) => LOCAL_LANE_LIMIT,
+            Lane::Remote(_) => REMOTE_LANE_LIMIT,
+        }
+    }
+}
+
+/// Tasks running at once on one local device.
+const LOCAL_LANE_LIMIT: usize = 2;
+/// Tasks running at once per remote connection.
+const REMOTE_LANE_LIMIT: usize = 1;
+/// Worker threads; enough for a few lanes to move at the same time.
+pub const IO_WORKERS: usize = 4;
+
+/// Device id of `path`, or of its nearest existing ancestor (a copy target
+/// does not exist yet).
+#[cfg(unix)]
+fn device_of(path: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt as _;
+    path.ancestors()
+        .find_map(|p| fs::metadata(p).ok())
+        .map_or(0, |meta| meta.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> u64 {
+    0
+}
+
+/// Tasks waiting for the IO workers. Shared with the UI, which lists them
+/// and can pause the queue, reorder it or cancel single entries.
+///
+/// Workers take the first pending task whose lanes have room and whose
+/// paths overlap neither a running task nor an earlier pending one, so
+/// unrelated work runs side by side while work on the same files keeps its
+/// order.
+pub struct IoQueue {
+    state: std::sync::Mutex<IoQueueState>,
+    ready: std::sync::Condvar,
+}
+
+#[derive(Default)]
+struct IoQueueState {
+    pending: std::collections::VecDeque<Scheduled>,
+    running: Vec<(Scheduled, QueueItem)>,
+    paused: bool,
+    /// Set once no more tasks can arrive; `next` then returns `None`.
+    closed: bool,
+    next_id: u64,
+}
+
+struct Scheduled {
+    id: u64,
+    task: IOTask,
+    paths: Vec<TaskPath>,
+    lanes: Vec<Lane>,
+}
+
+/// One queue row as shown to the user. Running rows carry the task's own
+/// progress and cancel flag.
+#[derive(Clone)]
+pub struct QueueItem {
+    pub id: u64,
+    pub name: String,
+    pub progress: Option<Arc<TransferProgress>>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+/// A task handed to a worker.
+pub struct StartedTask {
+    pub id: u64,
+    pub task: IOTask,
+    pub progress: Arc<TransferProgress>,
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// Direction for [`IoQueue::shift`].
+#[derive(Clone, Copy)]
+pub enum QueueShift {
+    Earlier,
+    Later,
+}
+
+impl IoQueue {
+    pub fn new() -> Self {
+        Self {
+            state: std::sync::Mutex::new(IoQueueState::default()),
+            ready: std::sync::Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, IoQueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    pub fn push(&self, task: IOTask) {
+        let paths = task.touched_paths();
+        let mut lanes: Vec<Lane> = Vec::new();
+        for lane in paths.iter().map(TaskPath::lane) {
+            if !lanes.contains(&lane) {
+                lanes.push(lane);
+            }
+        }
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.pending.push_back(Scheduled {
+            id,
+            task,
+            paths,
+            lanes,
+        });
+        self.ready.notify_all();
+    }
+
+    /// Take the next task that may run now, waiting until there is one.
+    /// Its progress reports into `progress`. Returns `None` once the queue
+    /// is closed and drained.
+    pub fn next(&self, progress: &Arc<TransferProgress>) -> Option<StartedTask> {
+        let mut state = self.lock();
+        loop {
+            if let Some(index) = Self::runnable(&state) {
+                let scheduled = state.pending.remove(index)?;
+                let started = StartedTask {
+                    id: scheduled.id,
+                    task: scheduled.task.clone(),
+                    progress: progress.child(),
+                    cancel: Arc::new(AtomicBool::new(false)),
+                };
+                let item = QueueItem {
+                    id: scheduled.id,
+                    name: scheduled.task.display_name(),
+                    progress: Some(started.progress.clone()),
+                    cancel: Some(started.cancel.clone()),
+                };
+                state.running.push((scheduled, item));
+                return Some(started);
+            }
+            if state.closed && state.pending.is_empty() {
+                return None;
+            }
+            state = self
+                .ready
+                .wait(state)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+    }
+
+    fn runnable(state: &IoQueueState) -> Option<usize> {
+        if state.paused {
+            return None;
+        }
+        let mut claimed: Vec<&TaskPath> = state
+            .running
+            .iter()
+            .flat_map(|running| &running.0.paths)
+            .collect();
+        for (index, scheduled) in state.pending.iter().enumerate() {
+            let clear = !scheduled
+                .paths
+                .iter()
+                .any(|p| claimed.iter().any(|c| p.overlaps(c)));
+            let room = scheduled.lanes.iter().all(|lane| {
+                let busy = state
+                    .running
+                    .iter()
+                    .filter(|running| running.0.lanes.contains(lane))
+                    .count();
+                busy < lane.limit()
+            });
+            if clear && room {
+                return Some(index);
+            }
+            // Waiting tasks keep their paths, so later work on the same
+            // files can't overtake them.
+            claimed.extend(&scheduled.paths);
+        }
+        None
+    }
+
+    /// Task `id` is done.
+    pub fn finish(&self, id: u64) {
+        self.lock().running.retain(|running| running.1.id != id);
+        self.ready.notify_all();
+    }
+
+    /// No more tasks will be pushed.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_all();
+    }
+
+    /// Running tasks, then pending ones in order.
+    pub fn snapshot(&self) -> (Vec<QueueItem>, Vec<QueueItem>) {
+        let state = self.lock();
+        let running = state
+            .running
+            .iter()
+            .map(|running| running.1.clone())
+            .collect();
+        let pending = state
+            .pending
+            .iter()
+            .map(|scheduled| QueueItem {
+                id: scheduled.id,
+                name: scheduled.task.display_name(),
+                progress: None,
+                cancel: None,
+            })
+            .collect();
+        (running, pending)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused
+    }
+
+    /// Stop starting new tasks; running ones carry on.
+    pub fn pause(&self) {
+        self.lock().paused = true;
+    }
+
+    pub fn resume(&self) {
+        self.lock().paused = false;
+        self.ready.notify_all();
+    }
+
+    /// Drop a pending task. Returns false if it is not pending (anymore).
+    pub fn remove(&self, id: u64) -> bool {
+        let mut state = self.lock();
+        let before = state.pending.len();
+        state.pending.retain(|scheduled| scheduled.id != id);
+        let removed = state.pending.len() != before;
+        if removed {
+            self.ready.notify_all();
+        }
+        removed
+    }
+
+    /// Ask running task `id` to stop.
+    pub fn cancel(&self, id: u64) {
+        for running in &self.lock().running {
+            if running.1.id == id
+                && let Some(ref cancel) = running.1.cancel
+            {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Drop every pending task and stop the running ones. Returns how many
+    /// were dropped; those send no result.
+    pub fn cancel_all(&self) -> usize {
+        let mut state = self.lock();
+        for running in &state.running {
+            if let Some(ref cancel) = running.1.cancel {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+        let count = state.pending.len();
+        state.pending.clear();
+        count
+    }
+
+    /// Swap a pending task with its neighbour.
+    pub fn shift(&self, id: u64, direction: QueueShift) {
+        let mut state = self.lock();
+        let Some(pos) = state
+            .pending
+            .iter()
+            .position(|scheduled| scheduled.id == id)
+        else {
+            return;
+        };
+        let other = match direction {
+            QueueShift::Earlier => pos.checked_sub(1),
+            QueueShift::Later => Some(pos + 1).filter(|&p| p < state.pending.len()),
+        };
+        if let Some(other) = other {
+            state.pending.swap(pos, other);
+            self.ready.notify_all();
         }
     }
 }
@@ -392,158 +1328,1028 @@ pub enum IOResult {
     ErrorRemote(String, String),
     /// Local operation failed with a permission error — offer elevation retry.
     PermissionError { message: String, task: IOTask },
+    /// A remote file was downloaded to a temp copy and opened externally —
+    /// watch the copy so edits are uploaded back.
+    OpenedRemote {
+        host: String,
+        remote_path: String,
+        local_path: path::PathBuf,
+        mtime: Option<u64>,
+    },
+    /// A remote file was written; `mtime` is its new modification time.
+    RemoteFileWritten {
+        host: String,
+        path: String,
+        mtime: Option<u64>,
+    },
+    /// The remote file changed since it was downloaded — offer to overwrite
+    /// it anyway by re-running `task` without the mtime check.
+    RemoteChanged { message: String, task: IOTask },
+    /// Like `Completed`, for a local operation that can be taken back;
+    /// the record goes into the undo journal.
+    CompletedUndoable(UndoRecord),
+    /// A local copy, or a move that went by copying, finished; `method` is
+    /// how the file data moved, for the status line. `undo`, if any, goes
+    /// into the undo journal.
+    Copied {
+        method: CopyMethod,
+        undo: Option<UndoRecord>,
+    },
+    /// An undo was refused or failed; the record goes back into the journal.
+    UndoFailed { message: String, record: UndoRecord },
+}
+
+/// How to take back a completed local operation. Removals never delete:
+/// created items go to the trash.
+#[derive(Clone)]
+pub enum UndoAction {
+    /// Rename or move `from` back to `to` (renames, and moves that did not
+    /// replace anything).
+    MoveBack {
+        from: path::PathBuf,
+        to: path::PathBuf,
+    },
+    /// Trash what the operation created (copies that did not replace
+    /// anything, new files and directories).
+    TrashCreated { path: path::PathBuf },
+    /// Restore an entry the operation moved to the trash.
+    RestoreTrash { item: path::PathBuf },
+}
+
+/// A journaled operation and the state of what its undo acts on.
+#[derive(Clone)]
+pub struct UndoRecord {
+    /// What was done, for the history list: "Rename a → b".
+    pub label: String,
+    pub action: UndoAction,
+    /// When the operation completed.
+    pub when: Instant,
+    /// Taken right after the operation; undo refuses when it differs.
+    stamp: Option<TreeStamp>,
+}
+
+/// Summary of a file or directory tree: enough to notice that anything in
+/// it was added, removed, resized or modified.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct TreeStamp {
+    entries: u64,
+    bytes: u64,
+    newest: Option<std::time::SystemTime>,
+}
+
+impl TreeStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::symlink_metadata(path).ok()?;
+        let mut stamp = TreeStamp {
+            entries: 1,
+            bytes: if meta.is_file() { meta.len() } else { 0 },
+            newest: meta.modified().ok(),
+        };
+        if meta.is_dir() {
+            for entry in fs::read_dir(path).ok()?.flatten() {
+                let child = TreeStamp::of(&entry.path())?;
+                stamp.entries += child.entries;
+                stamp.bytes += child.bytes;
+                stamp.newest = stamp.newest.max(child.newest);
+            }
+        }
+        Some(stamp)
+    }
+}
+
+impl UndoAction {
+    /// The path the undo acts on.
+    fn subject(&self) -> &Path {
+        match *self {
+            UndoAction::MoveBack { ref from, .. } => from,
+            UndoAction::TrashCreated { ref path } => path,
+            UndoAction::RestoreTrash { ref item } => item,
+        }
+    }
+}
+
+impl UndoRecord {
+    pub fn new(label: String, action: UndoAction) -> Self {
+        let stamp = TreeStamp::of(action.subject());
+        Self {
+            label,
+            action,
+            when: Instant::now(),
+            stamp,
+        }
+    }
+
+    /// Whether the filesystem still looks the way the operation left it.
+    pub fn check(&self) -> Result<(), String> {
+        let subject = self.action.subject();
+        let now = TreeStamp::of(subject);
+        if now.is_none() {
+            return Err(format!("{} no longer exists", subject.display()));
+        }
+        if now != self.stamp {
+            return Err(format!("{} changed since", subject.display()));
+        }
+        if let UndoAction::MoveBack { ref to, .. } = self.action
+            && to.symlink_metadata().is_ok()
+        {
+            return Err(format!("{} exists again", to.display()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Name,
+    Date,
+    Size,
+    Raw,
+}
+
+pub struct EditLoadRequest {
+    pub id: u64,
+    pub path: path::PathBuf,
+    pub remote: Option<(String, String)>, // (host, remote_path)
+}
+
+pub struct EditLoadResult {
+    pub id: u64,
+    pub path: path::PathBuf,
+    pub text: String,
+    /// Whether the original file used CRLF line endings.
+    pub crlf: bool,
+    /// True when the load failed or the file was refused (binary, unreadable,
+    /// no session). The buffer holds an explanatory message rather than file
+    /// contents, so saving must be blocked to avoid overwriting the file.
+    pub failed: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Name,
+    Content,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchCase {
+    Sensitive,
+    Insensitive,
+}
+
+pub struct SearchRequest {
+    pub id: u64,
+    pub root: path::PathBuf,
+    pub needle: String,
+    pub case: SearchCase,
+    pub mode: SearchMode,
+    /// Set for remote searches: (host, remote_root_path).
+    pub remote: Option<(String, String)>,
+}
+
+#[derive(Clone)]
+pub struct SearchResult {
+    pub path: path::PathBuf,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    pub modified: Option<u64>,
+    /// For remote searches: the actual remote path (e.g. `/home/user/file.rs`).
+    /// `path` in that case is the synthetic `/sftp/<host>/…` path used for display.
+    pub remote_path: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub struct SearchProgress {
+    pub scanned: usize,
+    pub matched: usize,
+}
+
+pub enum SearchEvent {
+    Match { id: u64, result: SearchResult },
+    Progress { id: u64, progress: SearchProgress },
+    Done { id: u64, progress: SearchProgress },
+    Error { id: u64, message: String },
+}
+
+/// Where a command from the command prompt runs.
+#[derive(Clone)]
+pub enum CommandCwd {
+    Local(path::PathBuf),
+    Remote { host: String, path: String },
+}
+
+pub struct CommandRequest {
+    pub id: u64,
+    pub command: String,
+    pub cwd: CommandCwd,
+}
+
+pub enum CommandEvent {
+    /// Interleaved stdout and stderr.
+    Output { id: u64, text: String },
+    /// The command exited with this status, or could not be run.
+    Done {
+        id: u64,
+        status: Result<i32, String>,
+    },
+}
+
+/// Whether two paths refer to the same filesystem location. Uses canonical
+/// paths when both exist; if the destination does not exist yet (the common
+/// case for a copy target), it compares the canonical source against the
+/// canonical destination directory joined with the final component, and falls
+/// back to a lexical comparison when canonicalization is unavailable.
+fn is_same_path(src: &Path, dest: &Path) -> bool {
+    if let (Ok(a), Ok(b)) = (src.canonicalize(), dest.canonicalize()) {
+        return a == b;
+    }
+    if let (Ok(canon_src), Some(dest_parent), Some(dest_name)) =
+        (src.canonicalize(), dest.parent(), dest.file_name())
+    {
+        if let Ok(canon_dest_parent) = dest_parent.canonicalize() {
+            return canon_dest_parent.join(dest_name) == canon_src;
+        }
+    }
+    src == dest
+}
+
+/// Which metadata a local copy carries over besides the contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Preserve {
+    /// Access and modification times.
+    pub timestamps: bool,
+    /// Permission bits, including setuid/setgid/sticky. Without it new
+    /// files and directories get the default mode.
+    pub mode: bool,
+    /// Owner and group; only possible when running as root.
+    pub ownership: bool,
+    /// Extended attributes, which also hold POSIX ACLs (Linux only).
+    pub xattrs: bool,
+    /// Write only the data regions of sparse files, leaving holes as holes.
+    pub sparse: bool,
+}
+
+impl Preserve {
+    /// Everything, as a move across filesystems should.
+    pub const ALL: Self = Self {
+        timestamps: true,
+        mode: true,
+        ownership: true,
+        xattrs: true,
+        sparse: true,
+    };
+    /// Contents only.
+    pub const NONE: Self = Self {
+        timestamps: false,
+        mode: false,
+        ownership: false,
+        xattrs: false,
+        sparse: false,
+    };
+}
+
+/// How file contents were copied, fastest first. For a tree this is the
+/// slowest method any of its files needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CopyMethod {
+    /// Copy-on-write clone (`FICLONE` on Btrfs, XFS and the like): instant,
+    /// and no extra space until either side changes.
+    Reflink,
+    /// `copy_file_range`: the kernel copies without a trip through user
+    /// space, and some filesystems share blocks or copy server-side.
+    CopyFileRange,
+    /// Plain reads and writes.
+    Bytes,
+}
+
+impl CopyMethod {
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyMethod::Reflink => "reflink",
+            CopyMethod::CopyFileRange => "in-kernel copy",
+            CopyMethod::Bytes => "byte copy",
+        }
+    }
+}
+
+/// What a copy does with symbolic links it meets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SymlinkPolicy {
+    /// Recreate them as links. Relative links that point outside the copied
+    /// tree are rewritten to reach the same place from the new location.
+    #[default]
+    Links,
+    /// Copy what they point to. Links back into a directory being copied
+    /// are skipped, as are dangling ones.
+    Follow,
+    /// Leave them out.
+    Skip,
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [Self; 3] = [Self::Links, Self::Follow, Self::Skip];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SymlinkPolicy::Links => "copy as links",
+            SymlinkPolicy::Follow => "copy targets",
+            SymlinkPolicy::Skip => "skip",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SymlinkPolicy::Links => SymlinkPolicy::Follow,
+            SymlinkPolicy::Follow => SymlinkPolicy::Skip,
+            SymlinkPolicy::Skip => SymlinkPolicy::Links,
+        }
+    }
+}
+
+/// Copy `src` into `dst_dir` under its own name. Returns how the contents
+/// were copied, `None` if there were no regular files.
+pub fn copy_recursively(
+    src: &Path,
+    dst_dir: &Path,
+    preserve: Preserve,
+    symlinks: SymlinkPolicy,
+) -> io::Result<Option<CopyMethod>> {
+    let src_name = src
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "source has no file name"))?;
+    copy_as(src, &dst_dir.join(src_name), preserve, symlinks)
+}
+
+/// Copy a file or directory tree to `dest`, which names the copy itself
+/// rather than the directory it goes into.
+pub fn copy_as(
+    src: &Path,
+    dest: &Path,
+    preserve: Preserve,
+    symlinks: SymlinkPolicy,
+) -> io::Result<Option<CopyMethod>> {
+    let dst_dir = dest.parent().unwrap_or(dest);
+
+    // Prevent copying a file or directory onto itself. When both panels show
+    // the same directory, `dest` resolves to `src`; without this guard
+    // `fs::copy` would open `src` and then truncate the very same inode to
+    // zero bytes before copying, irreversibly destroying the file (and, for a
+    // directory, every file in the tree via the recursion below).
+    if is_same_path(src, dest) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "source and destination are the same file",
+        ));
+    }
+
+    // Prevent copying a directory into itself (infinite recursion / disk fill).
+    if src.is_dir() {
+        if let (Ok(canon_src), Ok(canon_dst)) = (src.canonicalize(), dst_dir.canonicalize()) {
+            if canon_dst.starts_with(&canon_src) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "cannot copy a directory into itself",
+                ));
+            }
+        }
+    }
+
+    TreeCopy {
+        src_root: normalize_lexically(src),
+        preserve,
+        symlinks,
+        open_dirs: Vec::new(),
+        dest_root: None,
+    }
+    .copy(src, dest)
+}
+
+/// State of one `copy_as` walk.
+struct TreeCopy {
+    /// Links resolving inside here are copied along and need no rewrite.
+    src_root: path::PathBuf,
+    preserve: Preserve,
+    symlinks: SymlinkPolicy,
+    /// Canonical paths of the source directories being copied, innermost
+    /// last: a followed link to one of them would loop forever.
+    open_dirs: Vec<path::PathBuf>,
+    /// Canonical path of the copy, so following a link into it can't feed
+    /// the copy with itself.
+    dest_root: Option<path::PathBuf>,
+}
+
+impl TreeCopy {
+    fn copy(&mut self, src: &Path, dest: &Path) -> io::Result<Option<CopyMethod>> {
+        let mut meta = fs::symlink_metadata(src)?;
+        let mut method = None;
+
+        if meta.file_type().is_symlink() {
+            match self.symlinks {
+                SymlinkPolicy::Skip => return Ok(None),
+                SymlinkPolicy::Links => {
+                    self.copy_link(src, dest)?;
+                    copy_metadata(src, dest, &meta, self.preserve)?;
+                    return Ok(None);
+                }
+                SymlinkPolicy::Follow => match fs::metadata(src) {
+                    Ok(target) => meta = target,
+                    Err(_) => {
+                        log::warn!("{}: dangling symlink skipped", src.display());
+                        return Ok(None);
+                    }
+                },
+            }
+        }
+
+        if meta.is_dir() {
+            let canonical = src.canonicalize()?;
+            let loops = self.open_dirs.contains(&canonical)
+                || self
+                    .dest_root
+                    .as_ref()
+                    .is_some_and(|root| canonical.starts_with(root));
+            if loops {
+                log::warn!("{}: symlink loop skipped", src.display());
+                return Ok(None);
+            }
+            fs::create_dir_all(dest)?;
+            if self.dest_root.is_none() {
+                self.dest_root = dest.canonicalize().ok();
+            }
+            self.open_dirs.push(canonical);
+            for entry in fs::read_dir(src)? {
+                let entry = entry?;
+                let inner = self.copy(&entry.path(), &dest.join(entry.file_name()))?;
+                method = method.max(inner);
+            }
+            self.open_dirs.pop();
+        } else {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            method = Some(copy_file_contents(src, dest, &meta, self.preserve)?);
+        }
+        // Directories last, once their entries no longer change them.
+        copy_metadata(src, dest, &meta, self.preserve)?;
+        Ok(method)
+    }
+
+    #[cfg(unix)]
+    fn copy_link(&self, src: &Path, dest: &Path) -> io::Result<()> {
+        let mut target = fs::read_link(src)?;
+        if target.is_relative()
+            && let (Some(src_dir), Some(dest_dir)) = (src.parent(), dest.parent())
+        {
+            let resolved = normalize_lexically(&src_dir.join(&target));
+            if !resolved.starts_with(&self.src_root) {
+                target = relative_path(&normalize_lexically(dest_dir), &resolved);
+            }
+        }
+        std::os::unix::fs::symlink(&target, dest)
+    }
+
+    #[cfg(not(unix))]
+    fn copy_link(&self, src: &Path, dest: &Path) -> io::Result<()> {
+        // On Windows, copy the target rather than recreating the link.
+        fs::copy(src, dest).map(|_| ())
+    }
+}
+
+/// `path` made absolute with `.` and `..` folded away, without touching
+/// the filesystem (a link target may not exist).
+fn normalize_lexically(path: &Path) -> path::PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut out = path::PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            path::Component::CurDir => {}
+            path::Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// A relative path leading from directory `base` to `target`, both
+/// normalized and absolute.
+fn relative_path(base: &Path, target: &Path) -> path::PathBuf {
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    // Different roots (drives): only an absolute path gets there.
+    if base.first() != target.first() {
+        return target.iter().collect();
+    }
+    let mut out: path::PathBuf = relative_components(&base, &target, path::Component::ParentDir)
+        .into_iter()
+        .collect();
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
+
+/// The components leading from directory `base` to `target`, both
+/// normalized: `up` for each level out of `base`, then the rest of
+/// `target`. Shared by local paths and `/`-separated remote ones.
+pub fn relative_components<T: PartialEq + Clone>(base: &[T], target: &[T], up: T) -> Vec<T> {
+    let common = base
+        .iter()
+        .zip(target)
+        .take_while(|pair| pair.0 == pair.1)
+        .count();
+    let mut out = vec![up; base.len() - common];
+    out.extend_from_slice(&target[common..]);
+    out
+}
+
+/// What Alt+F6 creates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkKind {
+    #[default]
+    Symbolic,
+    /// Another name for the same file; directories can't have them.
+    Hard,
+}
+
+impl LinkKind {
+    pub const ALL: [Self; 2] = [Self::Symbolic, Self::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkKind::Symbolic => "symbolic",
+            LinkKind::Hard => "hard",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            LinkKind::Symbolic => LinkKind::Hard,
+            LinkKind::Hard => LinkKind::Symbolic,
+        }
+    }
+}
+
+/// How a new symbolic link names what it points to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkTarget {
+    #[default]
+    Absolute,
+    /// From the link's own directory, so the pair can move together.
+    Relative,
+}
+
+impl LinkTarget {
+    pub const ALL: [Self; 2] = [Self::Absolute, Self::Relative];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkTarget::Absolute => "absolute",
+            LinkTarget::Relative => "relative",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            LinkTarget::Absolute => LinkTarget::Relative,
+            LinkTarget::Relative => LinkTarget::Absolute,
+        }
+    }
+}
+
+/// What a symbolic link at `link` should contain to reach `src`.
+pub fn link_contents(src: &Path, link: &Path, target: LinkTarget) -> path::PathBuf {
+    let src = normalize_lexically(src);
+    match (target, link.parent()) {
+        (LinkTarget::Relative, Some(dir)) => relative_path(&normalize_lexically(dir), &src),
+        _ => src,
+    }
+}
+
+/// Create a link to `src` at `link`.
+pub fn create_link(src: &Path, link: &Path, kind: LinkKind, target: LinkTarget) -> io::Result<()> {
+    match kind {
+        // Linux answers EPERM, which would look like a case for elevation.
+        LinkKind::Hard if src.is_dir() => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "directories can't be hard linked",
+        )),
+        LinkKind::Hard => fs::hard_link(src, link),
+        LinkKind::Symbolic => {
+            let contents = link_contents(src, link, target);
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(&contents, link)
+            }
+            #[cfg(windows)]
+            {
+                if src.is_dir() {
+                    std::os::windows::fs::symlink_dir(&contents, link)
+                } else {
+                    std::os::windows::fs::symlink_file(&contents, link)
+                }
+            }
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum SortMode {
-    Name,
-    Date,
-    Size,
-    Raw,
+#[cfg(unix)]
+fn copy_file_contents(
+    src: &Path,
+    dest: &Path,
+    meta: &fs::Metadata,
+    preserve: Preserve,
+) -> io::Result<CopyMethod> {
+    use std::os::unix::fs::{MetadataExt as _, OpenOptionsExt as _};
+    let reader = fs::File::open(src)?;
+    let writer = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(if preserve.mode {
+            meta.mode() & 0o777
+        } else {
+            0o666
+        })
+        .open(dest)?;
+    if reflink(&reader, &writer) {
+        return Ok(CopyMethod::Reflink);
+    }
+    // Fewer blocks than the length needs means there are holes.
+    let method = if preserve.sparse && meta.blocks() * 512 < meta.len() {
+        copy_sparse(&reader, &writer, meta.len())?
+    } else {
+        copy_range(&reader, &writer, 0, meta.len())?
+    };
+    // Pseudo-files (`/proc`, `/sys`) report no length, and a file still
+    // being written grows past it: read on until the end.
+    let end = copy_bytes(&reader, &writer, meta.len(), u64::MAX)?;
+    Ok(if end > meta.len() {
+        method.max(CopyMethod::Bytes)
+    } else {
+        method
+    })
 }
 
-pub struct EditLoadRequest {
-    pub id: u64,
-    pub path: path::PathBuf,
-    pub remote: Option<(String, String)>, // (host, remote_path)
+/// Share `reader`'s blocks with the empty `writer`, where the filesystem
+/// can. Returns false when it can't (other filesystems, across devices).
+#[cfg(target_os = "linux")]
+fn reflink(reader: &fs::File, writer: &fs::File) -> bool {
+    use std::os::fd::AsRawFd as _;
+    unsafe { libc::ioctl(writer.as_raw_fd(), libc::FICLONE, reader.as_raw_fd()) == 0 }
 }
 
-pub struct EditLoadResult {
-    pub id: u64,
-    pub path: path::PathBuf,
-    pub text: String,
-    /// Whether the original file used CRLF line endings.
-    pub crlf: bool,
-    /// True when the load failed or the file was refused (binary, unreadable,
-    /// no session). The buffer holds an explanatory message rather than file
-    /// contents, so saving must be blocked to avoid overwriting the file.
-    pub failed: bool,
+#[cfg(all(unix, not(target_os = "linux")))]
+fn reflink(_reader: &fs::File, _writer: &fs::File) -> bool {
+    false
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum SearchMode {
-    Name,
-    Content,
+/// Copy bytes `start..end` to the same offsets, with `copy_file_range`
+/// while the kernel takes it, then by reading and writing.
+#[cfg(target_os = "linux")]
+fn copy_range(
+    reader: &fs::File,
+    writer: &fs::File,
+    start: u64,
+    end: u64,
+) -> io::Result<CopyMethod> {
+    use std::os::fd::AsRawFd as _;
+    let mut pos = start;
+    while pos < end {
+        let mut off_in = pos as libc::off64_t;
+        let mut off_out = pos as libc::off64_t;
+        let want = (end - pos).min(1 << 30) as usize;
+        let n = unsafe {
+            libc::copy_file_range(
+                reader.as_raw_fd(),
+                &mut off_in,
+                writer.as_raw_fd(),
+                &mut off_out,
+                want,
+                0,
+            )
+        };
+        match n {
+            // The file shrank meanwhile.
+            0 => return Ok(CopyMethod::CopyFileRange),
+            n if n > 0 => pos += n as u64,
+            _ => {
+                let err = io::Error::last_os_error();
+                // Not supported here (old kernel, across filesystems, special
+                // files); whatever was copied stays, the rest goes by hand.
+                return match err.raw_os_error() {
+                    Some(libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP) => {
+                        copy_bytes(reader, writer, pos, end).map(|_| CopyMethod::Bytes)
+                    }
+                    _ => Err(err),
+                };
+            }
+        }
+    }
+    Ok(CopyMethod::CopyFileRange)
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum SearchCase {
-    Sensitive,
-    Insensitive,
+#[cfg(all(unix, not(target_os = "linux")))]
+fn copy_range(
+    reader: &fs::File,
+    writer: &fs::File,
+    start: u64,
+    end: u64,
+) -> io::Result<CopyMethod> {
+    copy_bytes(reader, writer, start, end).map(|_| CopyMethod::Bytes)
 }
 
-pub struct SearchRequest {
-    pub id: u64,
-    pub root: path::PathBuf,
-    pub needle: String,
-    pub case: SearchCase,
-    pub mode: SearchMode,
-    /// Set for remote searches: (host, remote_root_path).
-    pub remote: Option<(String, String)>,
+/// Copy bytes `start..end` to the same offsets by reading and writing,
+/// stopping early at the end of the file. Returns where it stopped.
+#[cfg(unix)]
+fn copy_bytes(reader: &fs::File, writer: &fs::File, start: u64, end: u64) -> io::Result<u64> {
+    use std::os::unix::fs::FileExt as _;
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut pos = start;
+    while pos < end {
+        let want = (end - pos).min(buf.len() as u64) as usize;
+        let n = reader.read_at(&mut buf[..want], pos)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all_at(&buf[..n], pos)?;
+        pos += n as u64;
+    }
+    Ok(pos)
 }
 
-#[derive(Clone)]
-pub struct SearchResult {
-    pub path: path::PathBuf,
-    pub is_dir: bool,
-    pub size: Option<u64>,
-    pub modified: Option<u64>,
-    /// For remote searches: the actual remote path (e.g. `/home/user/file.rs`).
-    /// `path` in that case is the synthetic `/sftp/<host>/…` path used for display.
-    pub remote_path: Option<String>,
+#[cfg(not(unix))]
+fn copy_file_contents(
+    src: &Path,
+    dest: &Path,
+    _meta: &fs::Metadata,
+    _preserve: Preserve,
+) -> io::Result<CopyMethod> {
+    fs::copy(src, dest).map(|_| CopyMethod::Bytes)
 }
 
-#[derive(Clone, Copy)]
-pub struct SearchProgress {
-    pub scanned: usize,
-    pub matched: usize,
+/// Copy only the data regions found with `SEEK_DATA`/`SEEK_HOLE`, then
+/// extend the file to `len` so a trailing hole stays a hole.
+#[cfg(target_os = "linux")]
+fn copy_sparse(reader: &fs::File, writer: &fs::File, len: u64) -> io::Result<CopyMethod> {
+    use std::os::fd::AsRawFd as _;
+    let seek = |offset: u64, whence: libc::c_int| -> io::Result<Option<u64>> {
+        let pos = unsafe { libc::lseek(reader.as_raw_fd(), offset as libc::off_t, whence) };
+        if pos >= 0 {
+            return Ok(Some(pos as u64));
+        }
+        let err = io::Error::last_os_error();
+        // ENXIO: no data after `offset`.
+        match err.raw_os_error() {
+            Some(libc::ENXIO) => Ok(None),
+            _ => Err(err),
+        }
+    };
+    let mut method = CopyMethod::CopyFileRange;
+    let mut offset = 0;
+    while offset < len {
+        let Some(start) = seek(offset, libc::SEEK_DATA)? else {
+            break;
+        };
+        let end = seek(start, libc::SEEK_HOLE)?.unwrap_or(len).min(len);
+        method = method.max(copy_range(reader, writer, start, end)?);
+        offset = end;
+    }
+    writer.set_len(len)?;
+    Ok(method)
 }
 
-pub enum SearchEvent {
-    Match { id: u64, result: SearchResult },
-    Progress { id: u64, progress: SearchProgress },
-    Done { id: u64, progress: SearchProgress },
-    Error { id: u64, message: String },
+#[cfg(all(unix, not(target_os = "linux")))]
+fn copy_sparse(reader: &fs::File, writer: &fs::File, len: u64) -> io::Result<CopyMethod> {
+    copy_bytes(reader, writer, 0, len).map(|_| CopyMethod::Bytes)
 }
 
-/// Whether two paths refer to the same filesystem location. Uses canonical
-/// paths when both exist; if the destination does not exist yet (the common
-/// case for a copy target), it compares the canonical source against the
-/// canonical destination directory joined with the final component, and falls
-/// back to a lexical comparison when canonicalization is unavailable.
-fn is_same_path(src: &Path, dest: &Path) -> bool {
-    if let (Ok(a), Ok(b)) = (src.canonicalize(), dest.canonicalize()) {
-        return a == b;
+/// Apply the metadata `preserve` asks for from `src` (described by `meta`)
+/// to the fresh copy at `dest`. Whatever the destination refuses (ownership,
+/// modes and times on vfat or CIFS, extended attributes) is skipped with a
+/// warning, as `cp -a` does.
+#[cfg(unix)]
+fn copy_metadata(
+    src: &Path,
+    dest: &Path,
+    meta: &fs::Metadata,
+    preserve: Preserve,
+) -> io::Result<()> {
+    use std::os::unix::{ffi::OsStrExt as _, fs::MetadataExt as _};
+    let c_dest = std::ffi::CString::new(dest.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let is_symlink = meta.file_type().is_symlink();
+    if preserve.ownership && unsafe { libc::geteuid() } == 0 {
+        let res = unsafe { libc::lchown(c_dest.as_ptr(), meta.uid(), meta.gid()) };
+        if res != 0 {
+            log::warn!(
+                "{}: keeping owner: {}",
+                dest.display(),
+                io::Error::last_os_error()
+            );
+        }
     }
-    if let (Ok(canon_src), Some(dest_parent), Some(dest_name)) =
-        (src.canonicalize(), dest.parent(), dest.file_name())
+    // Symlink modes are meaningless; chmod would follow the link.
+    if preserve.mode
+        && !is_symlink
+        && let Err(e) = fs::set_permissions(dest, meta.permissions())
     {
-        if let Ok(canon_dest_parent) = dest_parent.canonicalize() {
-            return canon_dest_parent.join(dest_name) == canon_src;
+        log::warn!("{}: keeping mode: {e}", dest.display());
+    }
+    if preserve.xattrs {
+        copy_xattrs(src, &c_dest);
+    }
+    if preserve.timestamps {
+        let times = [
+            timespec(meta.atime(), meta.atime_nsec()),
+            timespec(meta.mtime(), meta.mtime_nsec()),
+        ];
+        let res = unsafe {
+            libc::utimensat(
+                libc::AT_FDCWD,
+                c_dest.as_ptr(),
+                times.as_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        if res != 0 {
+            log::warn!(
+                "{}: keeping times: {}",
+                dest.display(),
+                io::Error::last_os_error()
+            );
         }
     }
-    src == dest
+    Ok(())
 }
 
-pub fn copy_recursively(src: &Path, dst_dir: &Path) -> io::Result<()> {
-    let src_name = src
-        .file_name()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "source has no file name"))?;
-
-    let dest = dst_dir.join(src_name);
-
-    // Prevent copying a file or directory onto itself. When both panels show
-    // the same directory, `dest` resolves to `src`; without this guard
-    // `fs::copy` would open `src` and then truncate the very same inode to
-    // zero bytes before copying, irreversibly destroying the file (and, for a
-    // directory, every file in the tree via the recursion below).
-    if is_same_path(src, &dest) {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "source and destination are the same file",
-        ));
+// `time_t` and `c_long` are 32 bits on some targets.
+#[cfg(unix)]
+#[allow(trivial_numeric_casts)]
+fn timespec(secs: i64, nsecs: i64) -> libc::timespec {
+    libc::timespec {
+        tv_sec: secs as libc::time_t,
+        tv_nsec: nsecs as libc::c_long,
     }
+}
 
-    // Prevent copying a directory into itself (infinite recursion / disk fill).
-    if src.is_dir() {
-        if let (Ok(canon_src), Ok(canon_dst)) = (src.canonicalize(), dst_dir.canonicalize()) {
-            if canon_dst.starts_with(&canon_src) {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "cannot copy a directory into itself",
-                ));
-            }
+#[cfg(not(unix))]
+fn copy_metadata(
+    _src: &Path,
+    dest: &Path,
+    meta: &fs::Metadata,
+    preserve: Preserve,
+) -> io::Result<()> {
+    if preserve.timestamps && meta.is_file() {
+        let mut times = fs::FileTimes::new().set_modified(meta.modified()?);
+        if let Ok(accessed) = meta.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        let res = fs::OpenOptions::new()
+            .write(true)
+            .open(dest)
+            .and_then(|file| file.set_times(times));
+        if let Err(e) = res {
+            log::warn!("{}: keeping times: {e}", dest.display());
         }
     }
+    Ok(())
+}
 
-    let meta = fs::symlink_metadata(src)?;
-
-    if meta.file_type().is_symlink() {
-        #[cfg(unix)]
-        {
-            let target = fs::read_link(src)?;
-            std::os::unix::fs::symlink(&target, &dest)?;
+/// Copy every extended attribute of `src`, POSIX ACLs included. Attributes
+/// the destination rejects (e.g. `security.*` without privileges, or a
+/// filesystem without xattr support) are logged and skipped.
+#[cfg(target_os = "linux")]
+fn copy_xattrs(src: &Path, dest: &std::ffi::CStr) {
+    use std::os::unix::ffi::OsStrExt as _;
+    let Ok(c_src) = std::ffi::CString::new(src.as_os_str().as_bytes()) else {
+        return;
+    };
+    // Sizes come from a first call with an empty buffer.
+    let read = |fetch: &dyn Fn(*mut libc::c_void, usize) -> isize| -> Option<Vec<u8>> {
+        let len = fetch(std::ptr::null_mut(), 0);
+        if len <= 0 {
+            return (len == 0).then(Vec::new);
         }
-        #[cfg(not(unix))]
-        {
-            // On Windows, copy the target rather than recreating the link.
-            fs::copy(src, &dest)?;
+        let mut buf = vec![0u8; len as usize];
+        let len = fetch(buf.as_mut_ptr().cast(), buf.len());
+        if len < 0 {
+            return None;
         }
-    } else if meta.is_dir() {
-        fs::create_dir_all(&dest)?;
-        for entry in fs::read_dir(src)? {
-            let entry = entry?;
-            copy_recursively(&entry.path(), &dest)?;
+        buf.truncate(len as usize);
+        Some(buf)
+    };
+    let Some(names) =
+        read(&|buf, size| unsafe { libc::llistxattr(c_src.as_ptr(), buf.cast(), size) })
+    else {
+        return;
+    };
+    for name in names.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+        let Ok(c_name) = std::ffi::CString::new(name) else {
+            continue;
+        };
+        let Some(value) = read(&|buf, size| unsafe {
+            libc::lgetxattr(c_src.as_ptr(), c_name.as_ptr(), buf, size)
+        }) else {
+            continue;
+        };
+        let res = unsafe {
+            libc::lsetxattr(
+                dest.as_ptr(),
+                c_name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if res != 0 {
+            log::warn!(
+                "{}: keeping {}: {}",
+                dest.to_string_lossy(),
+                String::from_utf8_lossy(name),
+                io::Error::last_os_error()
+            );
         }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn copy_xattrs(_src: &Path, _dest: &std::ffi::CStr) {}
+
+/// SHA-256 digests keyed by the `/`-separated path relative to the root of a
+/// transfer. A single file is stored under the empty key.
+pub type Checksums = std::collections::BTreeMap<String, String>;
+
+/// Lowercase hex SHA-256 of everything `reader` yields.
+pub fn sha256_hex(mut reader: impl Read) -> io::Result<String> {
+    use sha2::Digest as _;
+    let mut hasher = sha2::Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Hash a local file, or every regular file under a local directory.
+/// Symlinks inside a directory are skipped, matching `find -type f` on the
+/// remote side (tar transfers them as links, not contents).
+pub fn sha256_local(path: &Path) -> io::Result<Checksums> {
+    let mut sums = Checksums::new();
+    if fs::metadata(path)?.is_dir() {
+        sha256_local_dir(path, "", &mut sums)?;
     } else {
-        if let Some(parent) = dest.parent() {
-            fs::create_dir_all(parent)?;
+        sums.insert(String::new(), sha256_hex(fs::File::open(path)?)?);
+    }
+    Ok(sums)
+}
+
+fn sha256_local_dir(dir: &Path, prefix: &str, sums: &mut Checksums) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            sha256_local_dir(&entry.path(), &rel, sums)?;
+        } else if file_type.is_file() {
+            sums.insert(rel, sha256_hex(fs::File::open(entry.path())?)?);
         }
-        fs::copy(src, dest)?;
     }
     Ok(())
 }
 
+/// Check that every source digest is present and equal on the destination.
+/// Extra destination files are ignored — a copy may merge into an existing
+/// directory.
+pub fn compare_checksums(src: &Checksums, dst: &Checksums) -> Result<(), String> {
+    const SHOWN: usize = 3;
+    let bad: Vec<&str> = src
+        .iter()
+        .filter(|&(key, sum)| dst.get(key) != Some(sum))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    match bad.len() {
+        0 => Ok(()),
+        1 if bad[0].is_empty() => Err("checksum mismatch".to_string()),
+        n if n <= SHOWN => Err(format!("checksum mismatch: {}", bad.join(", "))),
+        n => Err(format!(
+            "checksum mismatch: {} and {} more",
+            bad[..SHOWN].join(", "),
+            n - SHOWN
+        )),
+    }
+}
+
 pub fn read_fs_directory(path: &path::Path) -> anyhow::Result<Vec<DirEntry>> {
     let mut entries = Vec::new();
 
@@ -710,6 +2516,43 @@ pub fn is_text_name(name: &str) -> bool {
     is_text_path(Path::new(name))
 }
 
+/// Percent-encode everything in a path except unreserved characters and `/`.
+pub fn percent_encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'-' | b'.' | b'_' | b'~') {
+            out.push(char::from(byte));
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// Undo `%XX` escapes; malformed ones are kept as they are.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 pub fn format_size(bytes: u64) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;
@@ -726,16 +2569,36 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Compact duration for progress displays: "45s", "3m 05s", "1h 05m".
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs / 60 % 60)
+    }
+}
+
 /// Format a Unix timestamp (seconds since epoch) as a compact date/time string.
 /// Returns "YYYY-MM-DD HH:MM" for compactness.
 pub fn format_date(epoch_secs: u64) -> String {
+    let (y, m, d, hours, minutes, _) = civil_from_epoch(epoch_secs);
+    format!("{y:04}-{m:02}-{d:02} {hours:02}:{minutes:02}")
+}
+
+/// UTC `(year, month, day, hour, minute, second)` of a Unix timestamp, the
+/// inverse of [`epoch_from_civil`].
+pub fn civil_from_epoch(epoch_secs: u64) -> (i64, u32, u32, u32, u32, u32) {
     // Convert epoch seconds to calendar date/time using basic arithmetic.
     // This handles leap years correctly.
     let secs = epoch_secs as i64;
     let days_since_epoch = secs.div_euclid(86400);
-    let time_of_day = secs.rem_euclid(86400) as u64;
+    let time_of_day = secs.rem_euclid(86400) as u32;
     let hours = time_of_day / 3600;
     let minutes = (time_of_day % 3600) / 60;
+    let seconds = time_of_day % 60;
 
     // Civil date from days since 1970-01-01 (algorithm from Howard Hinnant)
     let z = days_since_epoch + 719468;
@@ -745,11 +2608,67 @@ pub fn format_date(epoch_secs: u64) -> String {
     let y = yoe as i64 + era * 400;
     let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
     let mp = (5 * doy + 2) / 153;
-    let d = doy - (153 * mp + 2) / 5 + 1;
-    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
     let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d, hours, minutes, seconds)
+}
 
-    format!("{y:04}-{m:02}-{d:02} {hours:02}:{minutes:02}")
+/// Month number (1-12) from an English abbreviation like "Jan", as used in
+/// HTTP dates and `ls -l` listings.
+pub fn month_from_abbrev(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let index = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(name))?;
+    u32::try_from(index + 1).ok()
+}
+
+/// Seconds since the Unix epoch for a UTC date and time, the inverse of
+/// `format_date`. Returns `None` for out-of-range fields or pre-1970 dates.
+pub fn epoch_from_civil(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Option<u64> {
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    // Days since 1970-01-01 (algorithm from Howard Hinnant)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let secs = days * 86400 + i64::from(hour * 3600 + minute * 60 + second);
+    u64::try_from(secs).ok()
+}
+
+/// RFC 1123 date as sent in HTTP headers: `Tue, 15 Nov 1994 12:45:26 GMT`.
+pub fn epoch_from_http_date(s: &str) -> Option<u64> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    let [_, day, month, year, time, ..] = fields[..] else {
+        return None;
+    };
+    let mut hms = time.split(':').map(|n| n.parse::<u32>().ok());
+    epoch_from_civil(
+        year.parse().ok()?,
+        month_from_abbrev(month)?,
+        day.parse().ok()?,
+        hms.next()??,
+        hms.next()??,
+        hms.next()??,
+    )
 }
 
 pub fn format_mode(mode: u32) -> String {
@@ -899,6 +2818,232 @@ mod tests {
         }
     }
 
+    #[test]
+    fn conflict_actions_resolve_by_metadata() {
+        let side = |size, mtime| ConflictSide {
+            is_dir: false,
+            size: Some(size),
+            mtime: Some(mtime),
+        };
+        let conflict = Conflict {
+            name: "a.txt".to_string(),
+            source: side(10, 200),
+            target: side(10, 100),
+        };
+        assert_eq!(
+            ConflictAction::OverwriteIfNewer.resolve(&conflict),
+            Resolution::Overwrite
+        );
+        assert_eq!(
+            ConflictAction::OverwriteIfSizeDiffers.resolve(&conflict),
+            Resolution::Skip
+        );
+        let unknown = Conflict {
+            source: ConflictSide::UNKNOWN,
+            ..conflict
+        };
+        assert_eq!(
+            ConflictAction::OverwriteIfNewer.resolve(&unknown),
+            Resolution::Skip
+        );
+        assert_eq!(
+            ConflictAction::OverwriteIfSizeDiffers.resolve(&unknown),
+            Resolution::Overwrite
+        );
+
+        assert_eq!(free_name("a.txt", |n| n == "a (1).txt"), "a (2).txt");
+        assert_eq!(free_name(".bashrc", |_| false), ".bashrc (1)");
+        assert_eq!(free_name("dir", |_| false), "dir (1)");
+    }
+
+    #[test]
+    fn conflict_answer_for_all_sticks_until_next_batch() {
+        let progress = Arc::new(TransferProgress::new());
+        let cancel = AtomicBool::new(false);
+        let conflict = Conflict {
+            name: "a".to_string(),
+            source: ConflictSide::UNKNOWN,
+            target: ConflictSide::UNKNOWN,
+        };
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                while progress.pending_conflict().is_none() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                progress.answer_conflict(ConflictAction::Rename, ConflictScope::All);
+            });
+            assert_eq!(
+                progress.ask_conflict(conflict.clone(), &cancel),
+                Some(Resolution::Rename)
+            );
+        });
+        assert!(progress.pending_conflict().is_none());
+        // Answered for the whole batch: no second prompt.
+        assert_eq!(
+            progress.ask_conflict(conflict.clone(), &cancel),
+            Some(Resolution::Rename)
+        );
+        progress.start_batch();
+        cancel.store(true, Ordering::Relaxed);
+        assert_eq!(progress.ask_conflict(conflict, &cancel), None);
+    }
+
+    #[test]
+    fn task_conflict_answer_stays_with_its_task() {
+        let batch = Arc::new(TransferProgress::new());
+        let answered = batch.child();
+        let other = batch.child();
+        let cancel = AtomicBool::new(false);
+        let conflict = Conflict {
+            name: "a".to_string(),
+            source: ConflictSide::UNKNOWN,
+            target: ConflictSide::UNKNOWN,
+        };
+        answered.answer_task_conflicts(ConflictAction::Overwrite);
+        assert_eq!(
+            answered.ask_conflict(conflict.clone(), &cancel),
+            Some(Resolution::Overwrite)
+        );
+        // A sibling task still asks; cancelling gives up on the question.
+        cancel.store(true, Ordering::Relaxed);
+        assert_eq!(other.ask_conflict(conflict, &cancel), None);
+    }
+
+    #[test]
+    fn io_queue_pauses_and_reorders() {
+        let queue = IoQueue::new();
+        for name in ["a", "b", "c"] {
+            queue.push(IOTask::Mkdir {
+                path: path::PathBuf::from(name),
+            });
+        }
+        let names = |queue: &IoQueue| -> Vec<String> {
+            queue
+                .snapshot()
+                .1
+                .into_iter()
+                .map(|item| item.name)
+                .collect()
+        };
+        let (_, pending) = queue.snapshot();
+        queue.shift(pending[2].id, QueueShift::Earlier);
+        queue.shift(pending[0].id, QueueShift::Earlier);
+        assert_eq!(names(&queue), ["a", "c", "b"]);
+        assert!(queue.remove(pending[0].id));
+        assert!(!queue.remove(pending[0].id));
+
+        queue.pause();
+        queue.close();
+        let root = Arc::new(TransferProgress::new());
+        std::thread::scope(|s| {
+            let worker = s.spawn(|| queue.next(&root).map(|started| started.id));
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(!worker.is_finished());
+            queue.resume();
+            assert_eq!(worker.join().unwrap(), Some(pending[2].id));
+        });
+        let running: Vec<String> = queue
+            .snapshot()
+            .0
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        assert_eq!(running, ["c"]);
+        queue.finish(pending[2].id);
+        assert_eq!(queue.cancel_all(), 1);
+        assert!(queue.next(&root).is_none());
+    }
+
+    #[test]
+    fn io_queue_runs_independent_tasks_side_by_side() {
+        let dir = TmpDir::new("io-lanes");
+        let root = Arc::new(TransferProgress::new());
+        let queue = IoQueue::new();
+        for name in ["a", "a/inner", "c", "d"] {
+            queue.push(IOTask::Mkdir {
+                path: dir.path().join(name),
+            });
+        }
+        queue.close();
+        let first = queue.next(&root).unwrap();
+        // "a/inner" waits for "a"; "c" doesn't.
+        let second = queue.next(&root).unwrap();
+        assert_eq!(first.task.display_name(), "a");
+        assert_eq!(second.task.display_name(), "c");
+        std::thread::scope(|s| {
+            let worker = s.spawn(|| queue.next(&root).unwrap());
+            std::thread::sleep(Duration::from_millis(20));
+            // The device's lane is full.
+            assert!(!worker.is_finished());
+            queue.finish(first.id);
+            let third = worker.join().unwrap();
+            assert_eq!(third.task.display_name(), "inner");
+            queue.finish(second.id);
+            queue.finish(third.id);
+        });
+        let last = queue.next(&root).unwrap();
+        assert_eq!(last.task.display_name(), "d");
+
+        // Each running task has its own cancel flag and progress.
+        queue.cancel(last.id);
+        assert!(last.cancel.load(Ordering::Relaxed));
+        last.progress.add(10);
+        assert_eq!(last.progress.snapshot().0, 10);
+        assert_eq!(root.snapshot().0, 0);
+        queue.finish(last.id);
+        assert!(queue.next(&root).is_none());
+    }
+
+    #[test]
+    fn remote_tasks_on_one_host_run_one_at_a_time() {
+        let task = |host: &str, path: &str| IOTask::MkdirRemote {
+            host: host.to_string(),
+            path: path.to_string(),
+        };
+        let paths = |task: IOTask| task.touched_paths();
+        assert!(
+            paths(task("h", "/x"))[0].overlaps(&paths(task("h", "/x/y"))[0]),
+            "nested remote paths overlap"
+        );
+        assert!(!paths(task("h", "/x"))[0].overlaps(&paths(task("g", "/x"))[0]));
+        assert!(!paths(task("h", "/x"))[0].overlaps(&paths(task("h", "/xy"))[0]));
+
+        let root = Arc::new(TransferProgress::new());
+        let queue = IoQueue::new();
+        queue.push(task("h", "/one"));
+        queue.push(task("h", "/two"));
+        queue.push(task("g", "/one"));
+        queue.close();
+        let first = queue.next(&root).unwrap();
+        let second = queue.next(&root).unwrap();
+        assert_eq!(first.task.remote_hosts(), ["h"]);
+        assert_eq!(second.task.remote_hosts(), ["g"]);
+        queue.finish(first.id);
+        let third = queue.next(&root).unwrap();
+        assert_eq!(third.task.display_name(), "two");
+    }
+
+    #[test]
+    fn undo_refuses_after_changes() {
+        let dir = TmpDir::new("undo-check");
+        let moved = dir.path().join("moved.txt");
+        fs::write(&moved, b"first").unwrap();
+        let record = UndoRecord::new(
+            "Move".to_string(),
+            UndoAction::MoveBack {
+                from: moved.clone(),
+                to: dir.path().join("orig.txt"),
+            },
+        );
+        assert!(record.check().is_ok());
+
+        fs::write(&moved, b"second, longer").unwrap();
+        assert!(record.check().is_err());
+
+        fs::remove_file(&moved).unwrap();
+        assert!(record.check().is_err());
+    }
+
     #[test]
     fn copy_into_same_directory_is_rejected_and_preserves_file() {
         let dir = TmpDir::new("same-dir-copy");
@@ -907,7 +3052,8 @@ mod tests {
 
         // Copying a file into the directory it already lives in must be
         // rejected rather than truncating the source to zero bytes.
-        let err = copy_recursively(&file, dir.path()).unwrap_err();
+        let err =
+            copy_recursively(&file, dir.path(), Preserve::ALL, SymlinkPolicy::Links).unwrap_err();
         assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
 
         let after = fs::read(&file).unwrap();
@@ -922,7 +3068,8 @@ mod tests {
         let inner = sub.join("file.bin");
         fs::write(&inner, b"payload").unwrap();
 
-        let err = copy_recursively(&sub, dir.path()).unwrap_err();
+        let err =
+            copy_recursively(&sub, dir.path(), Preserve::ALL, SymlinkPolicy::Links).unwrap_err();
         assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
         assert_eq!(fs::read(&inner).unwrap(), b"payload");
     }
@@ -934,9 +3081,346 @@ mod tests {
         let file = src_dir.path().join("doc.txt");
         fs::write(&file, b"hello").unwrap();
 
-        copy_recursively(&file, dst_dir.path()).unwrap();
+        copy_recursively(&file, dst_dir.path(), Preserve::ALL, SymlinkPolicy::Links).unwrap();
         assert_eq!(fs::read(dst_dir.path().join("doc.txt")).unwrap(), b"hello");
         // Original must remain.
         assert_eq!(fs::read(&file).unwrap(), b"hello");
     }
+
+    #[test]
+    fn civil_dates_round_trip_through_format_date() {
+        let epoch = epoch_from_civil(2024, 2, 29, 13, 5, 0).unwrap();
+        assert_eq!(epoch, 1_709_211_900);
+        assert_eq!(format_date(epoch), "2024-02-29 13:05");
+        assert_eq!(epoch_from_civil(1970, 1, 1, 0, 0, 0), Some(0));
+        assert_eq!(epoch_from_civil(1969, 12, 31, 0, 0, 0), None);
+        assert_eq!(epoch_from_civil(2024, 13, 1, 0, 0, 0), None);
+        assert_eq!(civil_from_epoch(epoch + 59), (2024, 2, 29, 13, 5, 59));
+        assert_eq!(
+            epoch_from_http_date("Thu, 29 Feb 2024 13:05:00 GMT"),
+            Some(epoch)
+        );
+        assert_eq!(epoch_from_http_date("yesterday"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_keeps_metadata_and_holes() {
+        use std::os::unix::fs::{MetadataExt as _, PermissionsExt as _};
+        let dir = TmpDir::new("preserve");
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        let file = src.join("data.bin");
+        // 4 MiB with one written block in the middle.
+        let f = fs::File::create(&file).unwrap();
+        f.set_len(4 << 20).unwrap();
+        std::os::unix::fs::FileExt::write_all_at(&f, b"middle", 2 << 20).unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        f.set_times(fs::FileTimes::new().set_modified(mtime).set_accessed(mtime))
+            .unwrap();
+        drop(f);
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o750)).unwrap();
+        let sparse_source = fs::metadata(&file).unwrap().blocks() * 512 < 4 << 20;
+        #[cfg(target_os = "linux")]
+        let xattr_value = {
+            use std::os::unix::ffi::OsStrExt as _;
+            let path = std::ffi::CString::new(file.as_os_str().as_bytes()).unwrap();
+            let res = unsafe {
+                libc::lsetxattr(
+                    path.as_ptr(),
+                    c"user.fileman".as_ptr(),
+                    b"tag".as_ptr().cast(),
+                    3,
+                    0,
+                )
+            };
+            // Not every filesystem takes user attributes.
+            (res == 0).then(|| {
+                let kept = dir.path().join("kept").join("data.bin");
+                std::ffi::CString::new(kept.as_os_str().as_bytes()).unwrap()
+            })
+        };
+
+        let kept = dir.path().join("kept");
+        copy_as(&src, &kept, Preserve::ALL, SymlinkPolicy::Links).unwrap();
+        let meta = fs::metadata(kept.join("data.bin")).unwrap();
+        assert_eq!(meta.len(), 4 << 20);
+        assert_eq!(meta.modified().unwrap(), mtime);
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o640);
+        assert_eq!(
+            fs::metadata(&kept).unwrap().permissions().mode() & 0o7777,
+            0o750
+        );
+        if sparse_source {
+            assert!(meta.blocks() * 512 < 4 << 20, "holes were filled in");
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(path) = xattr_value {
+            let mut buf = [0u8; 8];
+            let len = unsafe {
+                libc::lgetxattr(
+                    path.as_ptr(),
+                    c"user.fileman".as_ptr(),
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                )
+            };
+            assert_eq!(len, 3);
+            assert_eq!(&buf[..3], b"tag");
+        }
+        let contents = fs::read(kept.join("data.bin")).unwrap();
+        assert_eq!(&contents[2 << 20..(2 << 20) + 6], b"middle");
+
+        let plain = dir.path().join("plain");
+        copy_as(&src, &plain, Preserve::NONE, SymlinkPolicy::Links).unwrap();
+        let meta = fs::metadata(plain.join("data.bin")).unwrap();
+        assert_ne!(meta.modified().unwrap(), mtime);
+        assert_eq!(fs::read(plain.join("data.bin")).unwrap(), contents);
+    }
+
+    #[test]
+    fn copy_reports_how_contents_moved() {
+        let dir = TmpDir::new("copy-method");
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("empty")).unwrap();
+        fs::write(src.join("a.txt"), vec![7u8; 300_000]).unwrap();
+
+        let method = copy_as(
+            &src.join("empty"),
+            &dir.path().join("e"),
+            Preserve::ALL,
+            SymlinkPolicy::Links,
+        )
+        .unwrap();
+        assert_eq!(method, None, "nothing to copy");
+        let method = copy_as(
+            &src,
+            &dir.path().join("dst"),
+            Preserve::ALL,
+            SymlinkPolicy::Links,
+        )
+        .unwrap();
+        assert!(method.is_some());
+        // Same filesystem: the kernel does the work.
+        if cfg!(target_os = "linux") {
+            assert_ne!(method, Some(CopyMethod::Bytes));
+        }
+        assert_eq!(
+            fs::read(dir.path().join("dst").join("a.txt")).unwrap(),
+            vec![7u8; 300_000]
+        );
+        assert!(CopyMethod::Reflink < CopyMethod::Bytes);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn copy_reads_pseudo_files_to_the_end() {
+        let dir = TmpDir::new("copy-proc");
+        let src = Path::new("/proc/self/cmdline");
+        assert_eq!(fs::metadata(src).unwrap().len(), 0);
+        let dst = dir.path().join("cmdline");
+        let method = copy_as(src, &dst, Preserve::NONE, SymlinkPolicy::Links).unwrap();
+        assert_eq!(method, Some(CopyMethod::Bytes));
+        assert!(!fs::read(&dst).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_follows_skips_and_rewrites_links() {
+        use std::os::unix::fs::symlink;
+        let dir = TmpDir::new("copy-symlinks");
+        fs::write(dir.path().join("outside.txt"), b"out").unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub").join("a.txt"), b"alpha").unwrap();
+        symlink("a.txt", src.join("sub").join("inner")).unwrap();
+        symlink("../../outside.txt", src.join("sub").join("outer")).unwrap();
+        symlink("..", src.join("sub").join("up")).unwrap();
+        let dst = dir.path().join("deeper").join("dst");
+        fs::create_dir_all(&dst).unwrap();
+
+        // Links: inner links stay as they are, outer ones are re-aimed.
+        let links = dst.join("links");
+        copy_as(&src, &links, Preserve::ALL, SymlinkPolicy::Links).unwrap();
+        let sub = links.join("sub");
+        assert_eq!(
+            fs::read_link(sub.join("inner")).unwrap(),
+            Path::new("a.txt")
+        );
+        assert_eq!(
+            fs::read_link(sub.join("outer")).unwrap(),
+            Path::new("../../../../outside.txt")
+        );
+        assert_eq!(fs::read(sub.join("outer")).unwrap(), b"out");
+
+        // Follow: targets are copied; the link back up is a loop.
+        let follow = dst.join("follow");
+        copy_as(&src, &follow, Preserve::ALL, SymlinkPolicy::Follow).unwrap();
+        let sub = follow.join("sub");
+        assert!(
+            !fs::symlink_metadata(sub.join("outer"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert_eq!(fs::read(sub.join("outer")).unwrap(), b"out");
+        assert_eq!(fs::read(sub.join("inner")).unwrap(), b"alpha");
+        assert!(!sub.join("up").exists());
+
+        // Skip: links are left out.
+        let skip = dst.join("skip");
+        copy_as(&src, &skip, Preserve::ALL, SymlinkPolicy::Skip).unwrap();
+        let names: Vec<_> = fs::read_dir(skip.join("sub"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["a.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_point_back_at_their_source() {
+        use std::os::unix::fs::MetadataExt as _;
+        let dir = TmpDir::new("links");
+        let releases = dir.path().join("releases");
+        fs::create_dir_all(releases.join("v2")).unwrap();
+        fs::write(releases.join("app.conf"), b"conf").unwrap();
+        let current = dir.path().join("current");
+
+        create_link(
+            &releases.join("v2"),
+            &current,
+            LinkKind::Symbolic,
+            LinkTarget::Relative,
+        )
+        .unwrap();
+        assert_eq!(fs::read_link(&current).unwrap(), Path::new("releases/v2"));
+        assert!(current.is_dir());
+
+        let absolute = dir.path().join("abs.conf");
+        let src = releases.join("app.conf");
+        create_link(&src, &absolute, LinkKind::Symbolic, LinkTarget::Absolute).unwrap();
+        assert_eq!(fs::read_link(&absolute).unwrap(), src);
+
+        let hard = dir.path().join("hard.conf");
+        create_link(&src, &hard, LinkKind::Hard, LinkTarget::Relative).unwrap();
+        assert_eq!(
+            fs::metadata(&hard).unwrap().ino(),
+            fs::metadata(&src).unwrap().ino()
+        );
+
+        let err = create_link(
+            &releases,
+            &dir.path().join("hard-dir"),
+            LinkKind::Hard,
+            LinkTarget::Absolute,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        // An existing name is never replaced.
+        let err = create_link(&src, &hard, LinkKind::Symbolic, LinkTarget::Absolute).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn checksums_detect_changed_and_missing_files() {
+        let dir = TmpDir::new("checksums");
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), b"alpha").unwrap();
+        fs::write(src.join("sub").join("b.txt"), b"beta").unwrap();
+        let dst_parent = dir.path().join("dst");
+        fs::create_dir_all(&dst_parent).unwrap();
+        copy_recursively(&src, &dst_parent, Preserve::ALL, SymlinkPolicy::Links).unwrap();
+        let dst = dst_parent.join("src");
+
+        let src_sums = sha256_local(&src).unwrap();
+        assert_eq!(src_sums.len(), 2);
+        assert!(src_sums.contains_key("sub/b.txt"));
+        assert_eq!(
+            src_sums["a.txt"],
+            "8ed3f6ad685b959ead7022518e1af76cd816f8e8ec7ccdda1ed4018e8f2223f8"
+        );
+        assert!(compare_checksums(&src_sums, &sha256_local(&dst).unwrap()).is_ok());
+
+        fs::write(dst.join("a.txt"), b"alphA").unwrap();
+        fs::remove_file(dst.join("sub").join("b.txt")).unwrap();
+        let err = compare_checksums(&src_sums, &sha256_local(&dst).unwrap()).unwrap_err();
+        assert_eq!(err, "checksum mismatch: a.txt, sub/b.txt");
+
+        let single = sha256_local(&src.join("a.txt")).unwrap();
+        assert!(single.contains_key(""));
+        let other = sha256_local(&dst.join("a.txt")).unwrap();
+        assert_eq!(
+            compare_checksums(&single, &other).unwrap_err(),
+            "checksum mismatch"
+        );
+    }
+
+    #[test]
+    fn durations_format_compactly() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m 05s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 05m");
+        assert_eq!(format_duration(Duration::from_secs(100 * 3600)), "100h 00m");
+    }
+
+    #[test]
+    fn rate_needs_history_and_spans_tasks() {
+        let progress = TransferProgress::new();
+        progress.start_batch();
+        progress.add(1000);
+        assert_eq!(progress.rate(), None);
+        // Back-date the first sample instead of sleeping.
+        progress.samples.lock().unwrap()[0].0 -= Duration::from_secs(2);
+        progress.reset(10_000);
+        progress.add(3000);
+        let rate = progress.rate().unwrap();
+        // 3000 bytes after the first sample, over a little more than 2s.
+        assert!((1400.0..=1500.0).contains(&rate), "{rate}");
+        let eta = progress.eta().unwrap();
+        assert!((4..=5).contains(&eta.as_secs()), "{eta:?}");
+    }
+
+    #[test]
+    fn throttle_holds_to_the_limit() {
+        let progress = TransferProgress::new();
+        let start = Instant::now();
+        progress.throttle(1 << 20, None);
+        assert!(start.elapsed() < Duration::from_millis(50), "unlimited");
+
+        progress.limit.store(100_000, Ordering::Relaxed);
+        let start = Instant::now();
+        for _ in 0..5 {
+            progress.throttle(10_000, None);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(400));
+
+        let cancel = AtomicBool::new(true);
+        let start = Instant::now();
+        progress.throttle(1 << 30, Some(&cancel));
+        assert!(start.elapsed() < Duration::from_millis(50), "cancelled");
+    }
+
+    #[test]
+    fn large_transfers_wait_for_release() {
+        let progress = Arc::new(TransferProgress::new());
+        let cancel = AtomicBool::new(false);
+        progress.hold_threshold.store(1000, Ordering::Relaxed);
+        assert!(progress.hold_if_large(999, &cancel));
+        let releaser = {
+            let progress = progress.clone();
+            std::thread::spawn(move || {
+                while !progress.held.load(Ordering::Relaxed) {
+                    std::thread::yield_now();
+                }
+                progress.release();
+            })
+        };
+        assert!(progress.hold_if_large(1000, &cancel));
+        releaser.join().unwrap();
+        cancel.store(true, Ordering::Relaxed);
+        assert!(!progress.hold_if_large(5000, &cancel));
+        assert!(!progress.held.load(Ordering::Relaxed));
+    }
 }
@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use crate::core::IOTask;
+use crate::core::{IOTask, LinkKind, LinkTarget, Preserve, SymlinkPolicy, link_contents};
 
 /// Execute a command with elevated privileges using the platform-native mechanism.
 ///
@@ -210,10 +210,14 @@ pub fn execute_elevated(task: &IOTask) -> Result<(), String> {
         IOTask::Copy {
             ref src,
             ref dst_dir,
-        } => elevated_copy(src, dst_dir),
+            preserve,
+            symlinks,
+        } => elevated_copy(src, dst_dir, preserve, symlinks),
+        // `mv` keeps links as links whatever the policy.
         IOTask::Move {
             ref src,
             ref dst_dir,
+            ..
         } => elevated_move(src, dst_dir),
         IOTask::Rename {
             ref src,
@@ -223,6 +227,12 @@ pub fn execute_elevated(task: &IOTask) -> Result<(), String> {
             elevated_rename(src, &target)
         }
         IOTask::Mkdir { ref path } => elevated_mkdir(path),
+        IOTask::Link {
+            ref src,
+            ref dst_dir,
+            kind,
+            target,
+        } => elevated_link(src, dst_dir, kind, target),
         IOTask::WriteFile {
             ref path,
             ref contents,
@@ -258,14 +268,51 @@ fn elevated_delete(target: &Path) -> Result<(), String> {
 }
 
 #[cfg(unix)]
-fn elevated_copy(src: &Path, dst_dir: &Path) -> Result<(), String> {
+fn elevated_copy(
+    src: &Path,
+    dst_dir: &Path,
+    preserve: Preserve,
+    symlinks: SymlinkPolicy,
+) -> Result<(), String> {
     let src_str = src.to_string_lossy();
     let dst_str = dst_dir.to_string_lossy();
-    run_elevated("cp", &["-r", &src_str, &dst_str])
+    let mut args = vec!["-r".to_string()];
+    // `cp` can't leave links out; `Skip` copies them as links.
+    if symlinks == SymlinkPolicy::Follow {
+        args.push("-L".to_string());
+    }
+    if cfg!(target_os = "linux") {
+        // GNU cp: only the attributes asked for; holes stay holes by default.
+        let kept: Vec<&str> = [
+            (preserve.timestamps, "timestamps"),
+            (preserve.mode, "mode"),
+            (preserve.ownership, "ownership"),
+            (preserve.xattrs, "xattr"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+        if !kept.is_empty() {
+            args.push(format!("--preserve={}", kept.join(",")));
+        }
+        if !preserve.sparse {
+            args.push("--sparse=never".to_string());
+        }
+    } else if preserve.timestamps || preserve.mode || preserve.ownership {
+        args.push("-p".to_string());
+    }
+    let mut argv: Vec<&str> = args.iter().map(String::as_str).collect();
+    argv.extend([&*src_str, &*dst_str]);
+    run_elevated("cp", &argv)
 }
 
 #[cfg(windows)]
-fn elevated_copy(src: &Path, dst_dir: &Path) -> Result<(), String> {
+fn elevated_copy(
+    src: &Path,
+    dst_dir: &Path,
+    _preserve: Preserve,
+    _symlinks: SymlinkPolicy,
+) -> Result<(), String> {
     let dst = dst_dir.join(src.file_name().unwrap_or_default());
     let src_str = src.to_string_lossy();
     let dst_str = dst.to_string_lossy();
@@ -317,6 +364,51 @@ fn elevated_mkdir(path: &Path) -> Result<(), String> {
     run_elevated("cmd", &["/C", "mkdir", &path.to_string_lossy()])
 }
 
+#[cfg(unix)]
+fn elevated_link(
+    src: &Path,
+    dst_dir: &Path,
+    kind: LinkKind,
+    target: LinkTarget,
+) -> Result<(), String> {
+    let link = dst_dir.join(src.file_name().unwrap_or_default());
+    let link_str = link.to_string_lossy();
+    match kind {
+        LinkKind::Symbolic => {
+            let contents = link_contents(src, &link, target);
+            run_elevated("ln", &["-s", "--", &contents.to_string_lossy(), &link_str])
+        }
+        LinkKind::Hard => run_elevated("ln", &["--", &src.to_string_lossy(), &link_str]),
+    }
+}
+
+#[cfg(windows)]
+fn elevated_link(
+    src: &Path,
+    dst_dir: &Path,
+    kind: LinkKind,
+    target: LinkTarget,
+) -> Result<(), String> {
+    let link = dst_dir.join(src.file_name().unwrap_or_default());
+    let link_str = link.to_string_lossy();
+    match kind {
+        LinkKind::Symbolic => {
+            let contents = link_contents(src, &link, target);
+            let mut args = vec!["/C", "mklink"];
+            if src.is_dir() {
+                args.push("/D");
+            }
+            let contents_str = contents.to_string_lossy();
+            args.extend([&*link_str, &*contents_str]);
+            run_elevated("cmd", &args)
+        }
+        LinkKind::Hard => run_elevated(
+            "cmd",
+            &["/C", "mklink", "/H", &link_str, &src.to_string_lossy()],
+        ),
+    }
+}
+
 fn elevated_write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
     // Write to a temp file, then elevated-copy over the target.
     let tmp_dir = std::env::temp_dir();
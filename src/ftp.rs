@@ -0,0 +1,1181 @@
+//! FTP and explicit-TLS FTPS backend for `ftp://` and `ftps://` host keys.
+//!
+//! Host keys look like `ftp://[user@]host[:port]`. Passwords never appear in
+//! the key (it is shown in the UI); they come from `~/.netrc`, and without a
+//! matching entry the login is anonymous. Transfers use passive mode (EPSV,
+//! falling back to PASV), listings use MLSD when the server advertises MLST
+//! and a parsed `LIST` otherwise, and downloads resume with `REST` when the
+//! data connection drops.
+//!
+//! An FTP control connection can only run one transfer at a time, so the
+//! session keeps a small pool of logged-in connections and opens another one
+//! when a copy reads and writes on the same server.
+
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    core::{DirEntry, EntryLocation},
+    remote::{self, RemoteStat, SessionStats},
+};
+
+const DEFAULT_PORT: u16 = 21;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// Logged-in control connections kept around between operations.
+const MAX_IDLE: usize = 2;
+/// Servers close idle control connections; probe pooled ones older than this.
+const PROBE_IDLE_AFTER: Duration = Duration::from_secs(15);
+/// Consecutive data-connection failures a download resumes from.
+const RESUME_ATTEMPTS: u32 = 3;
+/// Forward seeks up to this far read through the open transfer instead of
+/// restarting it.
+const SEEK_SKIP_LIMIT: u64 = 256 * 1024;
+
+enum Security {
+    Plain,
+    /// `AUTH TLS` on the control connection, `PROT P` for data.
+    Tls,
+}
+
+/// Failure of an FTP exchange. Only `Connection` errors poison the control
+/// connection; after a `Server` reply it stays usable.
+enum Error {
+    Connection(String),
+    Server(String),
+}
+
+impl Error {
+    fn into_message(self) -> String {
+        match self {
+            Error::Connection(msg) | Error::Server(msg) => msg,
+        }
+    }
+
+    fn into_listing_error(self) -> (String, bool) {
+        match self {
+            Error::Connection(msg) => (msg, true),
+            Error::Server(msg) => (msg, false),
+        }
+    }
+
+    fn into_io(self) -> io::Error {
+        io::Error::other(self.into_message())
+    }
+}
+
+/// Turn a server refusal into `None`, keeping connection failures.
+fn optional(result: Result<Reply, Error>) -> Result<Option<Reply>, Error> {
+    match result {
+        Ok(reply) => Ok(Some(reply)),
+        Err(Error::Server(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+struct Reply {
+    code: u16,
+    /// Text of the first line, followed by any further lines of a multi-line
+    /// reply verbatim.
+    text: String,
+}
+
+#[cfg(unix)]
+mod tls {
+    use std::net::TcpStream;
+
+    use openssl::ssl;
+
+    pub type Connector = ssl::SslConnector;
+    pub type Stream = ssl::SslStream<TcpStream>;
+    pub type Session = ssl::SslSession;
+
+    /// Connector verifying certificates against the system trust store.
+    pub fn connector() -> Result<Connector, String> {
+        ssl::SslConnector::builder(ssl::SslMethod::tls_client())
+            .map(|builder| builder.build())
+            .map_err(|e| format!("TLS setup: {e}"))
+    }
+
+    /// Handshake over `tcp`. Data connections pass the control connection's
+    /// session, since many servers refuse data channels that don't resume it.
+    pub fn connect(
+        connector: &Connector,
+        domain: &str,
+        tcp: TcpStream,
+        session: Option<&Session>,
+    ) -> Result<Stream, String> {
+        let mut ssl = connector
+            .configure()
+            .and_then(|config| config.into_ssl(domain))
+            .map_err(|e| format!("TLS setup: {e}"))?;
+        if let Some(session) = session {
+            // SAFETY: the session was negotiated by a connection built from
+            // the same connector, so it belongs to this SSL context.
+            unsafe { ssl.set_session(session) }.map_err(|e| format!("TLS session: {e}"))?;
+        }
+        ssl.connect(tcp)
+            .map_err(|e| format!("TLS handshake with {domain}: {e}"))
+    }
+
+    pub fn session(stream: &Stream) -> Option<Session> {
+        stream.ssl().session().map(|s| s.to_owned())
+    }
+
+    pub fn tcp(stream: &Stream) -> &TcpStream {
+        stream.get_ref()
+    }
+
+    pub fn shutdown(stream: &mut Stream) {
+        let _ = stream.shutdown();
+    }
+}
+
+/// FTPS builds on OpenSSL, which is only linked on Unix. These stand-ins
+/// can't be constructed, so `ftps://` fails at connect time.
+#[cfg(not(unix))]
+mod tls {
+    use std::{
+        io::{self, Read, Write},
+        net::TcpStream,
+    };
+
+    pub enum Connector {}
+    pub enum Stream {}
+    pub enum Session {}
+
+    pub fn connector() -> Result<Connector, String> {
+        Err("FTPS is not supported on this platform".to_string())
+    }
+
+    pub fn connect(
+        connector: &Connector,
+        _domain: &str,
+        _tcp: TcpStream,
+        _session: Option<&Session>,
+    ) -> Result<Stream, String> {
+        match *connector {}
+    }
+
+    pub fn session(stream: &Stream) -> Option<Session> {
+        match *stream {}
+    }
+
+    pub fn tcp(stream: &Stream) -> &TcpStream {
+        match *stream {}
+    }
+
+    pub fn shutdown(stream: &mut Stream) {
+        match *stream {}
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            match *self {}
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            match *self {}
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match *self {}
+        }
+    }
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<tls::Stream>),
+}
+
+impl Stream {
+    fn tcp(&self) -> &TcpStream {
+        match *self {
+            Stream::Plain(ref tcp) => tcp,
+            Stream::Tls(ref tls) => tls::tcp(tls),
+        }
+    }
+
+    /// End a data transfer cleanly; TLS servers treat a missing
+    /// close_notify as a truncated upload.
+    fn close(&mut self) {
+        match *self {
+            Stream::Plain(ref mut tcp) => {
+                let _ = tcp.flush();
+            }
+            Stream::Tls(ref mut tls) => tls::shutdown(tls),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Plain(ref mut tcp) => tcp.read(buf),
+            Stream::Tls(ref mut tls) => tls.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Plain(ref mut tcp) => tcp.write(buf),
+            Stream::Tls(ref mut tls) => tls.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Stream::Plain(ref mut tcp) => tcp.flush(),
+            Stream::Tls(ref mut tls) => tls.flush(),
+        }
+    }
+}
+
+fn dial(addr: impl ToSocketAddrs, what: &str) -> Result<TcpStream, Error> {
+    let addrs = addr
+        .to_socket_addrs()
+        .map_err(|e| Error::Connection(format!("resolve {what}: {e}")))?;
+    let mut last_err = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(tcp) => {
+                tcp.set_read_timeout(Some(IO_TIMEOUT)).ok();
+                tcp.set_write_timeout(Some(IO_TIMEOUT)).ok();
+                tcp.set_nodelay(true).ok();
+                return Ok(tcp);
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(Error::Connection(match last_err {
+        Some(e) => format!("TCP connect to {what}: {e}"),
+        None => format!("resolve {what}: no addresses"),
+    }))
+}
+
+/// A logged-in control connection.
+struct Control {
+    stream: BufReader<Stream>,
+}
+
+impl Control {
+    fn read_line(&mut self) -> Result<String, Error> {
+        let mut line = String::new();
+        match self.stream.read_line(&mut line) {
+            Ok(0) => Err(Error::Connection(
+                "FTP server closed the connection".to_string(),
+            )),
+            Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+            Err(e) => Err(Error::Connection(format!("FTP control connection: {e}"))),
+        }
+    }
+
+    fn read_reply(&mut self) -> Result<Reply, Error> {
+        let first = self.read_line()?;
+        let code = first
+            .get(..3)
+            .and_then(|c| c.parse::<u16>().ok())
+            .ok_or_else(|| Error::Connection(format!("malformed FTP reply: {first}")))?;
+        let mut text = first.get(4..).unwrap_or("").to_string();
+        if first.as_bytes().get(3) == Some(&b'-') {
+            let end = format!("{code} ");
+            loop {
+                let line = self.read_line()?;
+                text.push('\n');
+                match line.strip_prefix(&end) {
+                    Some(last) => {
+                        text.push_str(last);
+                        break;
+                    }
+                    None => text.push_str(&line),
+                }
+            }
+        }
+        Ok(Reply { code, text })
+    }
+
+    fn command(&mut self, line: &str) -> Result<Reply, Error> {
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{line}\r\n").as_bytes())
+            .and_then(|()| stream.flush())
+            .map_err(|e| Error::Connection(format!("FTP control connection: {e}")))?;
+        self.read_reply()
+    }
+
+    /// Send a command and require a reply of the given class (1 = preliminary,
+    /// 2 = completion, 3 = needs more).
+    fn expect(&mut self, line: &str, class: u16) -> Result<Reply, Error> {
+        let reply = self.command(line)?;
+        if reply.code / 100 == class {
+            Ok(reply)
+        } else {
+            Err(refused(line, &reply))
+        }
+    }
+
+    fn start_tls(self, connector: &tls::Connector, domain: &str) -> Result<Self, Error> {
+        let Stream::Plain(tcp) = self.stream.into_inner() else {
+            return Err(Error::Connection("TLS is already active".to_string()));
+        };
+        let tls = tls::connect(connector, domain, tcp, None).map_err(Error::Connection)?;
+        Ok(Self {
+            stream: BufReader::new(Stream::Tls(Box::new(tls))),
+        })
+    }
+
+    fn tls_session(&self) -> Option<tls::Session> {
+        match *self.stream.get_ref() {
+            Stream::Plain(_) => None,
+            Stream::Tls(ref tls) => tls::session(tls),
+        }
+    }
+}
+
+fn refused(line: &str, reply: &Reply) -> Error {
+    // Keep passwords out of error messages.
+    let shown = if line.starts_with("PASS ") {
+        "PASS"
+    } else {
+        line
+    };
+    let text = reply.text.lines().next().unwrap_or("");
+    Error::Server(format!("{shown}: {} {text}", reply.code))
+}
+
+/// Everything needed to open another control connection.
+struct Endpoint {
+    domain: String,
+    port: u16,
+    user: String,
+    password: String,
+    tls: Option<tls::Connector>,
+}
+
+impl Endpoint {
+    fn login(&self) -> Result<(Control, Reply), Error> {
+        let what = format!("{}:{}", self.domain, self.port);
+        let tcp = dial((self.domain.as_str(), self.port), &what)?;
+        let mut ctrl = Control {
+            stream: BufReader::new(Stream::Plain(tcp)),
+        };
+        let greeting = ctrl.read_reply()?;
+        if greeting.code != 220 {
+            return Err(refused(&what, &greeting));
+        }
+        if let Some(ref connector) = self.tls {
+            ctrl.expect("AUTH TLS", 2)?;
+            ctrl = ctrl.start_tls(connector, &self.domain)?;
+            ctrl.expect("PBSZ 0", 2)?;
+            ctrl.expect("PROT P", 2)?;
+        }
+        let reply = ctrl.command(&format!("USER {}", self.user))?;
+        match reply.code {
+            230 => {}
+            331 | 332 => {
+                ctrl.expect(&format!("PASS {}", self.password), 2)
+                    .map_err(|e| {
+                        Error::Server(format!(
+                            "Login failed for {}@{what} ({}). Add credentials to ~/.netrc.",
+                            self.user,
+                            e.into_message()
+                        ))
+                    })?;
+            }
+            _ => return Err(refused("USER", &reply)),
+        }
+        ctrl.expect("TYPE I", 2)?;
+        Ok((ctrl, greeting))
+    }
+}
+
+pub struct FtpSession {
+    pub host: String,
+    pub home_dir: Option<String>,
+    pub stats: Arc<SessionStats>,
+    endpoint: Endpoint,
+    /// Server advertises MLST, so MLSD/MLST replace LIST/SIZE/MDTM.
+    mlsd: bool,
+    /// EPSV was refused once; go straight to PASV.
+    no_epsv: Cell<bool>,
+    idle: RefCell<Vec<(Control, Instant)>>,
+}
+
+/// Connect to an `ftp://` or `ftps://` host key.
+pub fn connect(host: &str) -> Result<FtpSession, String> {
+    let (scheme, authority) = remote::split_scheme(host);
+    let security = match scheme {
+        "ftps" => Security::Tls,
+        _ => Security::Plain,
+    };
+    let (user, server) = match authority.rsplit_once('@') {
+        Some((user, server)) => (Some(user), server),
+        None => (None, authority),
+    };
+    let (domain, port) = remote::split_port(server, DEFAULT_PORT);
+    let (user, password) = remote::netrc_credentials(&remote::load_netrc(), domain, user)
+        .unwrap_or_else(|| match user {
+            Some(user) => (user.to_string(), String::new()),
+            None => ("anonymous".to_string(), "anonymous@".to_string()),
+        });
+    let tls = match security {
+        Security::Plain => None,
+        Security::Tls => Some(tls::connector()?),
+    };
+    let endpoint = Endpoint {
+        domain: domain.to_string(),
+        port,
+        user,
+        password,
+        tls,
+    };
+    let (mut ctrl, greeting) = endpoint.login().map_err(Error::into_message)?;
+    let features = optional(ctrl.expect("FEAT", 2)).map_err(Error::into_message)?;
+    let mlsd = features.is_some_and(|reply| {
+        reply
+            .text
+            .lines()
+            .any(|l| l.trim_start().to_ascii_uppercase().starts_with("MLST"))
+    });
+    let home_dir = optional(ctrl.expect("PWD", 2))
+        .map_err(Error::into_message)?
+        .and_then(|reply| parse_pwd(&reply.text));
+    Ok(FtpSession {
+        host: host.to_string(),
+        home_dir,
+        stats: SessionStats::new(&endpoint.user, Some(greeting.text)),
+        endpoint,
+        mlsd,
+        no_epsv: Cell::new(false),
+        idle: RefCell::new(vec![(ctrl, Instant::now())]),
+    })
+}
+
+impl FtpSession {
+    /// A logged-in control connection, reused from the pool when one still
+    /// answers.
+    fn control(&self) -> Result<Control, Error> {
+        loop {
+            let Some((mut ctrl, since)) = self.idle.borrow_mut().pop() else {
+                break;
+            };
+            if since.elapsed() < PROBE_IDLE_AFTER || ctrl.expect("NOOP", 2).is_ok() {
+                return Ok(ctrl);
+            }
+        }
+        self.endpoint.login().map(|(ctrl, _)| ctrl)
+    }
+
+    fn release(&self, ctrl: Control) {
+        let mut idle = self.idle.borrow_mut();
+        if idle.len() < MAX_IDLE {
+            idle.push((ctrl, Instant::now()));
+        }
+    }
+
+    /// Run `f` on a pooled connection, returning it to the pool unless the
+    /// connection itself failed.
+    fn with_control<T>(
+        &self,
+        f: impl FnOnce(&mut Control) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut ctrl = self.control()?;
+        let result = f(&mut ctrl);
+        if !matches!(result, Err(Error::Connection(_))) {
+            self.release(ctrl);
+        }
+        result
+    }
+
+    /// Address of the server's passive data port. The advertised PASV
+    /// address is ignored in favor of the control peer, which keeps servers
+    /// behind NAT reachable.
+    fn passive(&self, ctrl: &mut Control) -> Result<SocketAddr, Error> {
+        let peer = ctrl
+            .stream
+            .get_ref()
+            .tcp()
+            .peer_addr()
+            .map_err(|e| Error::Connection(format!("FTP control connection: {e}")))?;
+        if !self.no_epsv.get() {
+            let reply = ctrl.command("EPSV")?;
+            match parse_epsv(&reply.text) {
+                Some(port) if reply.code == 229 => return Ok(SocketAddr::new(peer.ip(), port)),
+                _ => self.no_epsv.set(true),
+            }
+        }
+        let reply = ctrl.expect("PASV", 2)?;
+        let port = parse_pasv(&reply.text)
+            .ok_or_else(|| Error::Server(format!("PASV: unexpected reply {}", reply.text)))?;
+        Ok(SocketAddr::new(peer.ip(), port))
+    }
+
+    /// Open a data connection and issue `command` over it.
+    fn start_transfer(&self, ctrl: &mut Control, command: &str) -> Result<Stream, Error> {
+        let addr = self.passive(ctrl)?;
+        let tcp = dial(addr, "FTP data port")?;
+        ctrl.expect(command, 1)?;
+        match self.endpoint.tls {
+            None => Ok(Stream::Plain(tcp)),
+            Some(ref connector) => {
+                let session = ctrl.tls_session();
+                tls::connect(connector, &self.endpoint.domain, tcp, session.as_ref())
+                    .map(|tls| Stream::Tls(Box::new(tls)))
+                    .map_err(Error::Connection)
+            }
+        }
+    }
+
+    /// Start a transfer on a connection of its own, resuming at `offset`.
+    fn open_transfer(&self, command: &str, offset: u64) -> Result<(Control, Stream), Error> {
+        let mut ctrl = self.control()?;
+        let result = match offset {
+            0 => self.start_transfer(&mut ctrl, command),
+            _ => ctrl
+                .expect(&format!("REST {offset}"), 3)
+                .and_then(|_| self.start_transfer(&mut ctrl, command)),
+        };
+        match result {
+            Ok(data) => Ok((ctrl, data)),
+            Err(e) => {
+                if matches!(e, Error::Server(_)) {
+                    self.release(ctrl);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Close the data connection and collect the transfer's final reply.
+    fn finish_transfer(&self, mut ctrl: Control, mut data: Stream) -> Result<(), Error> {
+        data.close();
+        drop(data);
+        let reply = ctrl.read_reply()?;
+        self.release(ctrl);
+        match reply.code / 100 {
+            2 => Ok(()),
+            _ => Err(refused("transfer", &reply)),
+        }
+    }
+
+    fn retrieve_lines(&self, ctrl: &mut Control, command: &str) -> Result<Vec<String>, Error> {
+        let mut data = self.start_transfer(ctrl, command)?;
+        let mut bytes = Vec::new();
+        data.read_to_end(&mut bytes)
+            .map_err(|e| Error::Connection(format!("{command}: {e}")))?;
+        data.close();
+        drop(data);
+        let reply = ctrl.read_reply()?;
+        if reply.code / 100 != 2 {
+            return Err(refused(command, &reply));
+        }
+        Ok(String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(|l| l.to_string())
+            .collect())
+    }
+
+    fn simple(&self, line: &str) -> Result<(), String> {
+        self.with_control(|ctrl| ctrl.expect(line, 2).map(|_| ()))
+            .map_err(Error::into_message)
+    }
+}
+
+impl remote::RemoteFs for FtpSession {
+    fn host(&self) -> &str {
+        &self.host
+    }
+
+    fn home_dir(&self) -> Option<&str> {
+        self.home_dir.as_deref()
+    }
+
+    fn stats(&self) -> &Arc<SessionStats> {
+        &self.stats
+    }
+
+    fn is_alive(&self) -> bool {
+        self.with_control(|ctrl| ctrl.expect("NOOP", 2)).is_ok()
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.idle.borrow_mut().clear();
+        let (ctrl, _) = self.endpoint.login().map_err(Error::into_message)?;
+        self.release(ctrl);
+        self.stats.mark_reconnected();
+        Ok(())
+    }
+
+    fn list(
+        &self,
+        path: &str,
+        on_batch: &mut dyn FnMut(Vec<DirEntry>),
+    ) -> Result<(), (String, bool)> {
+        let dir = if path.is_empty() { "/" } else { path };
+        let lines = self
+            .with_control(|ctrl| {
+                if self.mlsd {
+                    self.retrieve_lines(ctrl, &format!("MLSD {dir}"))
+                } else {
+                    // LIST arguments are passed to `ls` by some servers.
+                    ctrl.expect(&format!("CWD {dir}"), 2)?;
+                    self.retrieve_lines(ctrl, "LIST")
+                }
+            })
+            .map_err(Error::into_listing_error)?;
+        if let Some(parent) = remote::parent_entry(&self.host, dir) {
+            on_batch(vec![parent]);
+        }
+        let now = now_secs();
+        let entries = lines
+            .iter()
+            .filter_map(|line| {
+                if self.mlsd {
+                    parse_mlsd_line(line)
+                } else {
+                    parse_list_line(line, now)
+                }
+            })
+            .map(|listed| listed.into_entry(&self.host, dir))
+            .collect();
+        on_batch(entries);
+        Ok(())
+    }
+
+    fn stat(&self, path: &str) -> Result<RemoteStat, String> {
+        self.with_control(|ctrl| {
+            if self.mlsd {
+                let reply = ctrl.expect(&format!("MLST {path}"), 2)?;
+                // The facts line is the one indented by a space.
+                let line = reply
+                    .text
+                    .lines()
+                    .find_map(|l| l.strip_prefix(' '))
+                    .ok_or_else(|| Error::Server(format!("MLST {path}: no facts")))?;
+                let facts = parse_facts(line.split_once(' ').map_or(line, |(f, _)| f));
+                let is_dir = facts.is_dir();
+                return Ok(RemoteStat {
+                    is_dir,
+                    size: if is_dir { None } else { facts.size },
+                    mtime: facts.mtime,
+                });
+            }
+            // SIZE only answers for files.
+            match optional(ctrl.expect(&format!("SIZE {path}"), 2))? {
+                Some(reply) => {
+                    let mtime = optional(ctrl.expect(&format!("MDTM {path}"), 2))?
+                        .and_then(|r| parse_timeval(r.text.trim()));
+                    Ok(RemoteStat {
+                        is_dir: false,
+                        size: reply.text.trim().parse().ok(),
+                        mtime,
+                    })
+                }
+                None => {
+                    ctrl.expect(&format!("CWD {path}"), 2)?;
+                    Ok(RemoteStat {
+                        is_dir: true,
+                        size: None,
+                        mtime: None,
+                    })
+                }
+            }
+        })
+        .map_err(Error::into_message)
+    }
+
+    fn open_read(&self, path: &str) -> Result<Box<dyn crate::archive::ReadSeek + '_>, String> {
+        let stat = remote::RemoteFs::stat(self, path)?;
+        if stat.is_dir {
+            return Err(format!("open {path}: is a directory"));
+        }
+        Ok(Box::new(FtpReader {
+            session: self,
+            path: path.to_string(),
+            pos: 0,
+            size: stat.size,
+            transfer: None,
+        }))
+    }
+
+    fn create(&self, path: &str) -> Result<Box<dyn Write + '_>, String> {
+        let transfer = self
+            .open_transfer(&format!("STOR {path}"), 0)
+            .map_err(Error::into_message)?;
+        Ok(Box::new(FtpWriter {
+            session: self,
+            transfer: Some(transfer),
+        }))
+    }
+
+    fn rename(&self, src: &str, dst: &str) -> Result<(), String> {
+        self.with_control(|ctrl| {
+            ctrl.expect(&format!("RNFR {src}"), 3)?;
+            ctrl.expect(&format!("RNTO {dst}"), 2).map(|_| ())
+        })
+        .map_err(Error::into_message)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        self.simple(&format!("DELE {path}"))
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<(), String> {
+        self.simple(&format!("RMD {path}"))
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        self.simple(&format!("MKD {path}"))
+    }
+}
+
+/// Seekable download. Each (re)start issues `REST <pos>` + `RETR`, which is
+/// also how a dropped data connection resumes.
+struct FtpReader<'a> {
+    session: &'a FtpSession,
+    path: String,
+    pos: u64,
+    size: Option<u64>,
+    transfer: Option<(Control, Stream)>,
+}
+
+impl Read for FtpReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut failures = 0;
+        loop {
+            let Some((_, ref mut data)) = self.transfer else {
+                if self.size.is_some_and(|size| self.pos >= size) {
+                    return Ok(0);
+                }
+                let transfer = self
+                    .session
+                    .open_transfer(&format!("RETR {}", self.path), self.pos)
+                    .map_err(Error::into_io)?;
+                self.transfer = Some(transfer);
+                continue;
+            };
+            let error = match data.read(buf) {
+                Ok(0) => {
+                    let Some((ctrl, data)) = self.transfer.take() else {
+                        return Ok(0);
+                    };
+                    match self.session.finish_transfer(ctrl, data) {
+                        // A data connection dropped mid-file can look like a
+                        // normal end of data.
+                        Ok(()) if self.size.is_none_or(|size| self.pos >= size) => return Ok(0),
+                        Ok(()) => io::Error::from(io::ErrorKind::UnexpectedEof),
+                        Err(e) => e.into_io(),
+                    }
+                }
+                Ok(n) => {
+                    self.pos += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.transfer = None;
+                    e
+                }
+            };
+            // Pick up where the broken transfer stopped.
+            failures += 1;
+            if failures >= RESUME_ATTEMPTS {
+                return Err(error);
+            }
+        }
+    }
+}
+
+impl Seek for FtpReader<'_> {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let target = match from {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.size.and_then(|size| size.checked_add_signed(delta)),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start or from an unknown end",
+            )
+        })?;
+        if self.transfer.is_some() && target > self.pos && target - self.pos <= SEEK_SKIP_LIMIT {
+            let skip = target - self.pos;
+            io::copy(&mut self.by_ref().take(skip), &mut io::sink())?;
+        }
+        if target != self.pos {
+            // Closing the control connection aborts the transfer; the next
+            // read restarts at the new offset.
+            self.transfer = None;
+            self.pos = target;
+        }
+        Ok(target)
+    }
+}
+
+/// Upload in progress. The server's final reply is collected on drop, so a
+/// failed upload shows up as a size mismatch in the caller's check.
+struct FtpWriter<'a> {
+    session: &'a FtpSession,
+    transfer: Option<(Control, Stream)>,
+}
+
+impl Write for FtpWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.transfer {
+            Some((_, ref mut data)) => data.write(buf),
+            None => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.transfer {
+            Some((_, ref mut data)) => data.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FtpWriter<'_> {
+    fn drop(&mut self) {
+        if let Some((ctrl, data)) = self.transfer.take()
+            && let Err(e) = self.session.finish_transfer(ctrl, data)
+        {
+            log::warn!("FTP upload to {}: {}", self.session.host, e.into_message());
+        }
+    }
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Directory from a `257 "<dir>" ...` reply, with doubled quotes undone.
+fn parse_pwd(text: &str) -> Option<String> {
+    let start = text.find('"')? + 1;
+    let end = text.rfind('"')?;
+    (end > start).then(|| text[start..end].replace("\"\"", "\""))
+}
+
+/// Port from `229 Entering Extended Passive Mode (|||6446|)`.
+fn parse_epsv(text: &str) -> Option<u16> {
+    let inner = &text[text.find('(')? + 1..text.rfind(')')?];
+    let delim = inner.chars().next()?;
+    inner.split(delim).nth(3)?.parse().ok()
+}
+
+/// Port from `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`.
+fn parse_pasv(text: &str) -> Option<u16> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let numbers: Vec<u16> = text[start..]
+        .split(|c: char| !c.is_ascii_digit() && c != ',')
+        .next()?
+        .split(',')
+        .map(|n| n.parse().ok())
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [_, _, _, _, hi, lo] if hi < 256 && lo < 256 => Some(hi * 256 + lo),
+        _ => None,
+    }
+}
+
+/// `YYYYMMDDHHMMSS[.sss]` (UTC) as used by MDTM and MLSD `modify`.
+fn parse_timeval(s: &str) -> Option<u64> {
+    let digits = s.get(..14)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| digits[range].parse::<u32>().ok();
+    crate::core::epoch_from_civil(
+        i64::from(field(0..4)?),
+        field(4..6)?,
+        field(6..8)?,
+        field(8..10)?,
+        field(10..12)?,
+        field(12..14)?,
+    )
+}
+
+/// One parsed listing line, before it is tied to a host and directory.
+pub(crate) struct Listed {
+    pub(crate) name: String,
+    pub(crate) is_dir: bool,
+    pub(crate) is_symlink: bool,
+    pub(crate) link_target: Option<String>,
+    pub(crate) size: Option<u64>,
+    pub(crate) mtime: Option<u64>,
+}
+
+impl Listed {
+    pub(crate) fn into_entry(self, host: &str, dir: &str) -> DirEntry {
+        DirEntry {
+            location: EntryLocation::Remote {
+                host: host.to_string(),
+                path: remote::join_path(dir, &self.name),
+            },
+            name: self.name,
+            is_dir: self.is_dir,
+            is_symlink: self.is_symlink,
+            link_target: self.link_target,
+            size: if self.is_dir { None } else { self.size },
+            modified: self.mtime,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Facts {
+    /// Raw `type` fact: `file`, `dir`, `cdir`, `OS.unix=slink:<target>`, ...
+    kind: String,
+    size: Option<u64>,
+    mtime: Option<u64>,
+}
+
+impl Facts {
+    fn is_dir(&self) -> bool {
+        self.kind.eq_ignore_ascii_case("dir")
+            || self.kind.eq_ignore_ascii_case("cdir")
+            || self.kind.eq_ignore_ascii_case("pdir")
+    }
+
+    /// `Some(target)` for symlinks; the target is empty when not reported.
+    fn link_target(&self) -> Option<&str> {
+        let lower = self.kind.to_ascii_lowercase();
+        if lower == "os.unix=symlink" {
+            Some("")
+        } else if lower.starts_with("os.unix=slink") {
+            Some(self.kind.split_once(':').map_or("", |(_, target)| target))
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_facts(facts: &str) -> Facts {
+    let mut out = Facts::default();
+    for fact in facts.split(';') {
+        let Some((key, value)) = fact.split_once('=') else {
+            continue;
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "type" => out.kind = value.to_string(),
+            "size" | "sizd" => out.size = value.parse().ok(),
+            "modify" => out.mtime = parse_timeval(value),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// `type=file;size=42;modify=20240102030405; name`
+fn parse_mlsd_line(line: &str) -> Option<Listed> {
+    let (facts, name) = line.split_once(' ')?;
+    let facts = parse_facts(facts);
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    let kind = facts.kind.to_ascii_lowercase();
+    if kind == "cdir" || kind == "pdir" {
+        return None;
+    }
+    let link_target = facts.link_target().map(|t| t.to_string());
+    Some(Listed {
+        name: name.to_string(),
+        is_dir: facts.is_dir(),
+        is_symlink: link_target.is_some(),
+        link_target: link_target.filter(|t| !t.is_empty()),
+        size: facts.size,
+        mtime: facts.mtime,
+    })
+}
+
+/// Parse a `LIST` line in Unix `ls -l` or DOS/IIS format.
+pub(crate) fn parse_list_line(line: &str, now: u64) -> Option<Listed> {
+    if line.is_empty() || line.starts_with("total ") {
+        return None;
+    }
+    let listed = if line.starts_with(|c: char| c.is_ascii_digit()) {
+        parse_dos_line(line)?
+    } else {
+        parse_unix_line(line, now)?
+    };
+    (listed.name != "." && listed.name != "..").then_some(listed)
+}
+
+/// The first `n` whitespace-separated fields and the remainder of the line.
+pub(crate) fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(n);
+    let mut rest = line.trim_start();
+    for _ in 0..n {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    (!rest.is_empty()).then_some((fields, rest))
+}
+
+/// `drwxr-xr-x 2 user group 4096 Jan  2 03:04 name` (the group column is
+/// missing on some servers).
+fn parse_unix_line(line: &str, now: u64) -> Option<Listed> {
+    let kind = line.chars().next()?;
+    let (fields, rest) = [8, 7].into_iter().find_map(|n| {
+        let (fields, rest) = split_fields(line, n)?;
+        crate::core::month_from_abbrev(fields[n - 3])
+            .is_some()
+            .then_some((fields, rest))
+    })?;
+    let n = fields.len();
+    let mtime = parse_list_date(fields[n - 3], fields[n - 2], fields[n - 1], now);
+    let (name, link_target) = match rest.split_once(" -> ") {
+        Some((name, target)) if kind == 'l' => (name, Some(target.to_string())),
+        _ => (rest, None),
+    };
+    Some(Listed {
+        name: name.to_string(),
+        is_dir: kind == 'd',
+        is_symlink: kind == 'l',
+        link_target,
+        size: fields[n - 4].parse().ok(),
+        mtime,
+    })
+}
+
+/// `Jan  2 03:04` (within the last six months) or `Jan  2  2020`, in UTC.
+fn parse_list_date(month: &str, day: &str, time_or_year: &str, now: u64) -> Option<u64> {
+    let month = crate::core::month_from_abbrev(month)?;
+    let day = day.parse().ok()?;
+    let Some((hour, minute)) = time_or_year.split_once(':') else {
+        return crate::core::epoch_from_civil(time_or_year.parse().ok()?, month, day, 0, 0, 0);
+    };
+    let (hour, minute) = (hour.parse().ok()?, minute.parse().ok()?);
+    let year: i64 = crate::core::format_date(now).get(..4)?.parse().ok()?;
+    let this_year = crate::core::epoch_from_civil(year, month, day, hour, minute, 0)?;
+    // A date without a year that lands in the future belongs to last year.
+    if this_year > now + 86400 {
+        crate::core::epoch_from_civil(year - 1, month, day, hour, minute, 0)
+    } else {
+        Some(this_year)
+    }
+}
+
+/// `01-02-24  03:04PM       <DIR>          name` or with a size instead of
+/// `<DIR>`.
+fn parse_dos_line(line: &str) -> Option<Listed> {
+    let (fields, name) = split_fields(line, 3)?;
+    let mut date = fields[0].split('-').map(|p| p.parse::<u32>().ok());
+    let (month, day, year) = (date.next()??, date.next()??, date.next()??);
+    let year = match year {
+        0..70 => 2000 + year,
+        70..100 => 1900 + year,
+        _ => year,
+    };
+    let time = fields[1].to_ascii_uppercase();
+    let (clock, pm) = match time.strip_suffix("PM") {
+        Some(clock) => (clock, true),
+        None => (time.strip_suffix("AM").unwrap_or(&time), false),
+    };
+    let (hour, minute) = clock.split_once(':')?;
+    let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse().ok()?);
+    let hour = match (hour, pm) {
+        (12, false) => 0,
+        (h, true) if h < 12 => h + 12,
+        (h, _) => h,
+    };
+    let is_dir = fields[2].eq_ignore_ascii_case("<DIR>");
+    Some(Listed {
+        name: name.to_string(),
+        is_dir,
+        is_symlink: false,
+        link_target: None,
+        size: if is_dir { None } else { fields[2].parse().ok() },
+        mtime: crate::core::epoch_from_civil(i64::from(year), month, day, hour, minute, 0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mlsd_lines_parse_types_and_facts() {
+        let file = parse_mlsd_line("type=file;size=42;modify=20240229130500; a b.txt").unwrap();
+        assert_eq!(file.name, "a b.txt");
+        assert_eq!(file.size, Some(42));
+        assert_eq!(file.mtime, Some(1_709_211_900));
+        assert!(!file.is_dir);
+        let dir = parse_mlsd_line("Type=dir;Modify=20240229130500.123; logs").unwrap();
+        assert!(dir.is_dir);
+        let link = parse_mlsd_line("type=OS.unix=slink:/srv/data;size=9; data").unwrap();
+        assert!(link.is_symlink);
+        assert_eq!(link.link_target.as_deref(), Some("/srv/data"));
+        assert!(parse_mlsd_line("type=cdir;modify=20240229130500; /pub").is_none());
+    }
+
+    #[test]
+    fn list_lines_parse_unix_and_dos_formats() {
+        // 2024-06-01 00:00 UTC
+        let now = 1_717_200_000;
+        let file = parse_list_line(
+            "-rw-r--r--   1 ftp  ftp   1234 Feb 29 13:05 read me.txt",
+            now,
+        )
+        .unwrap();
+        assert_eq!(file.name, "read me.txt");
+        assert_eq!(file.size, Some(1234));
+        assert_eq!(file.mtime, Some(1_709_211_900));
+        // No year and a date after `now`: last year.
+        let old = parse_list_line("drwxr-xr-x 2 ftp 4096 Dec 24 10:00 archive", now).unwrap();
+        assert!(old.is_dir);
+        assert_eq!(
+            crate::core::format_date(old.mtime.unwrap()),
+            "2023-12-24 10:00"
+        );
+        let link =
+            parse_list_line("lrwxrwxrwx 1 root root 7 Jan  1  2020 current -> v2", now).unwrap();
+        assert!(link.is_symlink);
+        assert_eq!(link.name, "current");
+        assert_eq!(link.link_target.as_deref(), Some("v2"));
+        assert!(parse_list_line("total 12", now).is_none());
+
+        let dos =
+            parse_list_line("02-29-24  01:05PM       <DIR>          Vendor Drop", now).unwrap();
+        assert!(dos.is_dir);
+        assert_eq!(dos.name, "Vendor Drop");
+        assert_eq!(dos.mtime, Some(1_709_211_900));
+        let dos_file = parse_list_line("02-29-2024  13:05   77 fw.bin", now).unwrap();
+        assert_eq!(dos_file.size, Some(77));
+    }
+
+    #[test]
+    fn passive_and_pwd_replies_parse() {
+        assert_eq!(
+            parse_pasv("Entering Passive Mode (192,168,1,2,19,137)"),
+            Some(19 * 256 + 137)
+        );
+        assert_eq!(parse_pasv("Entering Passive Mode 10,0,0,1,4,1"), Some(1025));
+        assert_eq!(
+            parse_epsv("Entering Extended Passive Mode (|||6446|)"),
+            Some(6446)
+        );
+        assert_eq!(
+            parse_pwd("\"/home/a \"\"q\"\"\" is current directory").as_deref(),
+            Some("/home/a \"q\"")
+        );
+    }
+}
@@ -44,12 +44,11 @@ pub(crate) fn open_selected_external(app: &mut app_state::AppState) {
                 return;
             }
             let local_path = tmp_dir.join(&name);
-            let _ = app.io_tx.send(core::IOTask::CopyRemoteToLocalAndOpen {
+            app.enqueue_io(core::IOTask::CopyRemoteToLocalAndOpen {
                 host,
                 remote_path: path,
                 local_path,
             });
-            app.io_in_flight = app.io_in_flight.saturating_add(1);
         }
         core::EntryLocation::Container {
             kind,
@@ -69,14 +68,13 @@ pub(crate) fn open_selected_external(app: &mut app_state::AppState) {
                 eprintln!("Failed to create temp dir: {e}");
                 return;
             }
-            let _ = app.io_tx.send(core::IOTask::CopyContainerAndOpen {
+            app.enqueue_io(core::IOTask::CopyContainerAndOpen {
                 kind,
                 archive_path,
                 inner_path,
                 dst_dir: tmp_dir,
                 display_name,
             });
-            app.io_in_flight = app.io_in_flight.saturating_add(1);
         }
     }
 }
@@ -305,9 +303,16 @@ pub(crate) fn handle_keyboard(
     if app.settings_open() {
         return;
     }
-    // The search bar is a focused egui TextEdit; its select-all/copy/cut keys
-    // must reach it instead of triggering panel actions (Pack/Copy/Delete).
-    let search_typing = app.search_ui == app_state::SearchUiState::Open;
+    // Likewise the batch rename and sync dialogs, which handle Escape and
+    // Enter.
+    if app.multi_rename().is_some() || app.sync_dialog().is_some() {
+        return;
+    }
+    // The search bar and command prompt are focused egui TextEdits; their
+    // select-all/copy/cut keys must reach them instead of triggering panel
+    // actions (Pack/Copy/Delete).
+    let search_typing =
+        app.search_ui == app_state::SearchUiState::Open || app.command_input.is_some();
     // In edit mode, don't consume Ctrl+letter shortcuts that egui's TextEdit
     // needs (copy, paste, cut, select-all, undo, redo, etc.).
     let ctrl_h = !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::H));
@@ -326,6 +331,9 @@ pub(crate) fn handle_keyboard(
     let ctrl_m = !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::M));
     let ctrl_d = !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::D));
     let ctrl_g = ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::G));
+    let ctrl_k = !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::K));
+    let ctrl_j = !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::J));
+    let ctrl_o = !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::O));
     let ctrl_x = !in_edit
         && !search_typing
         && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::X));
@@ -337,6 +345,17 @@ pub(crate) fn handle_keyboard(
                 egui::Key::M,
             )
         });
+    let ctrl_shift_z = !in_edit
+        && !search_typing
+        && ctx.input_mut(|i| {
+            i.consume_key(
+                egui::Modifiers::CTRL.plus(egui::Modifiers::SHIFT),
+                egui::Key::Z,
+            )
+        });
+    let ctrl_z = !in_edit
+        && !search_typing
+        && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Z));
     let ctrl_shift_c = !in_edit
         && ctx.input_mut(|i| {
             i.consume_key(
@@ -344,8 +363,17 @@ pub(crate) fn handle_keyboard(
                 egui::Key::C,
             )
         });
+    let ctrl_shift_s = !in_edit
+        && !search_typing
+        && ctx.input_mut(|i| {
+            i.consume_key(
+                egui::Modifiers::CTRL.plus(egui::Modifiers::SHIFT),
+                egui::Key::S,
+            )
+        });
     let ctrl_comma =
         !in_edit && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Comma));
+    let shift_f2 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::F2));
     let f2 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F2));
 
     let f1 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F1));
@@ -354,11 +382,33 @@ pub(crate) fn handle_keyboard(
         ctx.request_repaint();
         return;
     }
-    // While IO is in flight (modal visible): only Escape is processed.
-    if app.io_in_flight > 0 {
-        if input.key_pressed(egui::Key::Escape) {
+    // While the progress window is up: only Escape, Enter to start held
+    // transfers, the answers to a name collision, and sending the work to
+    // the background (B, or the queue with Ctrl+J) are processed.
+    if app.io_modal_visible() {
+        if ctrl_j {
+            app.open_queue();
+            ctx.request_repaint();
+        } else if input.key_pressed(egui::Key::Escape) {
             app.request_io_cancel();
             ctx.request_repaint();
+        } else if input.key_pressed(egui::Key::Enter) {
+            app.release_held();
+            ctx.request_repaint();
+        } else if app.conflicting_task().is_some() {
+            if input.key_pressed(egui::Key::A) {
+                app.conflict_apply_all = !app.conflict_apply_all;
+            }
+            if let Some(choice) = crate::ui::modals::CONFLICT_CHOICES
+                .iter()
+                .find(|choice| input.key_pressed(choice.1))
+            {
+                app.answer_conflict(choice.2);
+            }
+            ctx.request_repaint();
+        } else if input.key_pressed(egui::Key::B) {
+            app.io_background = true;
+            ctx.request_repaint();
         }
         return;
     }
@@ -374,6 +424,18 @@ pub(crate) fn handle_keyboard(
         }
         return;
     }
+    if app.remote_changed_message().is_some() {
+        if input.key_pressed(egui::Key::Enter) {
+            if let Some(task) = app.take_remote_changed() {
+                app.enqueue_io(task);
+            }
+            ctx.request_repaint();
+        } else if input.key_pressed(egui::Key::Escape) {
+            app.close_modal();
+            ctx.request_repaint();
+        }
+        return;
+    }
     if app.error_message().is_some() {
         if input.key_pressed(egui::Key::Escape) || input.key_pressed(egui::Key::Enter) {
             app.close_modal();
@@ -437,6 +499,64 @@ pub(crate) fn handle_keyboard(
         }
         return;
     }
+    if let Some(selected) = app.connections_selected() {
+        if input.key_pressed(egui::Key::Escape) || ctrl_k {
+            app.close_modal();
+        } else if input.key_pressed(egui::Key::ArrowDown) {
+            app.select_connection(selected + 1);
+        } else if input.key_pressed(egui::Key::ArrowUp) {
+            app.select_connection(selected.saturating_sub(1));
+        } else if input.key_pressed(egui::Key::R) || input.key_pressed(egui::Key::Enter) {
+            if let Some(host) = app.selected_connection_host() {
+                crate::apply_connection_action(
+                    app,
+                    crate::ui::connections::ConnectionAction::Reconnect(host),
+                );
+            }
+        } else if input.key_pressed(egui::Key::Delete) {
+            if let Some(host) = app.selected_connection_host() {
+                crate::apply_connection_action(
+                    app,
+                    crate::ui::connections::ConnectionAction::Disconnect(host),
+                );
+            }
+        }
+        ctx.request_repaint();
+        return;
+    }
+    if let Some(selected) = app.queue_selected() {
+        if input.key_pressed(egui::Key::Escape) || ctrl_j {
+            app.close_modal();
+        } else if input.modifiers.shift && input.key_pressed(egui::Key::ArrowUp) {
+            app.shift_queue_row(core::QueueShift::Earlier);
+        } else if input.modifiers.shift && input.key_pressed(egui::Key::ArrowDown) {
+            app.shift_queue_row(core::QueueShift::Later);
+        } else if input.key_pressed(egui::Key::ArrowDown) {
+            app.select_queue_row(selected + 1);
+        } else if input.key_pressed(egui::Key::ArrowUp) {
+            app.select_queue_row(selected.saturating_sub(1));
+        } else if input.key_pressed(egui::Key::Space) {
+            app.toggle_queue_pause();
+        } else if input.key_pressed(egui::Key::Delete) {
+            app.cancel_queue_row();
+        }
+        ctx.request_repaint();
+        return;
+    }
+    if let Some(selected) = app.undo_history_selected() {
+        if input.key_pressed(egui::Key::Escape) || ctrl_shift_z {
+            app.close_modal();
+        } else if input.key_pressed(egui::Key::ArrowDown) {
+            app.select_undo_entry(selected + 1);
+        } else if input.key_pressed(egui::Key::ArrowUp) {
+            app.select_undo_entry(selected.saturating_sub(1));
+        } else if input.key_pressed(egui::Key::Enter) {
+            app.close_modal();
+            app.undo_at(selected);
+        }
+        ctx.request_repaint();
+        return;
+    }
     if app.help_panel_side().is_some() {
         if input.key_pressed(egui::Key::Escape) || input.key_pressed(egui::Key::Enter) {
             app.toggle_help();
@@ -448,21 +568,74 @@ pub(crate) fn handle_keyboard(
         if input.key_pressed(egui::Key::Enter) {
             confirm_pending_op(app);
         }
+        if input.key_pressed(egui::Key::L) && app.pending_op_meets_symlinks() {
+            app.symlink_policy = app.symlink_policy.next();
+        }
+        if let Some(&app_state::PendingOp::Link { .. }) = app.pending_op() {
+            if input.key_pressed(egui::Key::H) {
+                app.link_kind = app.link_kind.next();
+            }
+            if input.key_pressed(egui::Key::R) {
+                app.link_target = app.link_target.next();
+            }
+        }
+        if let Some(&app_state::PendingOp::Compare { .. }) = app.pending_op() {
+            if input.key_pressed(egui::Key::R) {
+                app.compare_depth = app.compare_depth.next();
+            }
+            if input.key_pressed(egui::Key::H) {
+                app.compare_content = app.compare_content.next();
+            }
+        }
         if input.key_pressed(egui::Key::Escape) {
             app.clear_pending_op();
         }
         ctx.request_repaint();
         return;
     }
+    if app.command_input.is_some() {
+        if input.key_pressed(egui::Key::Escape) {
+            app.command_input = None;
+        } else if input.key_pressed(egui::Key::Enter) {
+            app.run_command();
+        }
+        ctx.request_repaint();
+        return;
+    }
     if handle_inline_rename(app, input) {
         ctx.request_repaint();
         return;
     }
+    if ctrl_o {
+        app.open_command_prompt();
+        ctx.request_repaint();
+        return;
+    }
     if ctrl_g {
         app.open_quick_jump();
         ctx.request_repaint();
         return;
     }
+    if ctrl_k {
+        app.open_connections();
+        ctx.request_repaint();
+        return;
+    }
+    if ctrl_j {
+        app.open_queue();
+        ctx.request_repaint();
+        return;
+    }
+    if ctrl_shift_z {
+        app.open_undo_history();
+        ctx.request_repaint();
+        return;
+    }
+    if ctrl_z {
+        app.undo_last();
+        ctx.request_repaint();
+        return;
+    }
     if let app_state::PanelMode::Edit(ref mut edit) = app.panel_mut(app.active_panel).mode {
         let enter = input.key_pressed(egui::Key::Enter);
         let escape = input.key_pressed(egui::Key::Escape);
@@ -473,7 +646,7 @@ pub(crate) fn handle_keyboard(
         let mut remote_size_update: Option<(String, String, u64)> = None;
         // Tasks are collected here and enqueued through app.enqueue_io() after
         // the `edit` borrow of `app` ends, so io_in_flight/io_batch_total stay
-        // accurate (a raw io_tx.send would leave the counter one short and make
+        // accurate (a raw queue push would leave the counter one short and make
         // a later batch's progress/cancel-reset fire early).
         let mut pending_io_tasks: Vec<core::IOTask> = Vec::new();
         if edit.confirm_discard {
@@ -507,25 +680,20 @@ pub(crate) fn handle_keyboard(
                 };
                 let contents = save_text.as_bytes().to_vec();
                 // Check if this is a remote file (synthetic /sftp/host/path)
-                let path_str = path.to_string_lossy();
-                if let Some(rest) = path_str.strip_prefix("/sftp/") {
-                    // Parse host and remote path
-                    if let Some(slash) = rest.find('/') {
-                        let host = rest[..slash].to_string();
-                        let remote_path = rest[slash..].to_string();
-                        let size = contents.len() as u64;
-                        remote_size_update = Some((host.clone(), remote_path.clone(), size));
-                        save_payload = None; // Don't use the local write path
-                        edit.dirty = false;
-                        edit.confirm_discard = false;
-                        // Don't refresh the whole panel — just update file size in place
-                        close_editor = true;
-                        pending_io_tasks.push(core::IOTask::WriteRemoteFile {
-                            host,
-                            path: remote_path,
-                            contents,
-                        });
-                    }
+                if let Some((host, remote_path)) = fileman::remote::parse_synthetic_path(&path) {
+                    let size = contents.len() as u64;
+                    remote_size_update = Some((host.clone(), remote_path.clone(), size));
+                    save_payload = None; // Don't use the local write path
+                    edit.dirty = false;
+                    edit.confirm_discard = false;
+                    // Don't refresh the whole panel — just update file size in place
+                    close_editor = true;
+                    pending_io_tasks.push(core::IOTask::WriteRemoteFile {
+                        host,
+                        path: remote_path,
+                        contents,
+                        expected_mtime: None,
+                    });
                 } else {
                     save_payload = Some((path, contents));
                     edit.dirty = false;
@@ -638,8 +806,17 @@ pub(crate) fn handle_keyboard(
     if input.key_pressed(egui::Key::Escape) && app.preview_panel_side().is_none() {
         let panel = app.get_active_panel();
         let browser = panel.browser();
-        if matches!(browser.browser_mode, core::BrowserMode::Search { .. }) {
-            cancel_search(app);
+        if matches!(
+            browser.browser_mode,
+            core::BrowserMode::Search { .. }
+                | core::BrowserMode::Trash
+                | core::BrowserMode::Compare { .. }
+        ) {
+            match browser.browser_mode {
+                core::BrowserMode::Search { .. } => cancel_search(app),
+                core::BrowserMode::Compare { .. } => app.cancel_compare(),
+                _ => {}
+            }
             if let Some(snapshot) = app.pop_history_back(app.active_panel) {
                 apply_panel_snapshot(app, app.active_panel, snapshot);
             }
@@ -704,6 +881,14 @@ pub(crate) fn handle_keyboard(
     if ctrl_r || f2 {
         refresh_active_panel(app);
     }
+    if shift_f2 {
+        app.prepare_compare();
+        ctx.request_repaint();
+    }
+    if ctrl_shift_s {
+        app.prepare_sync();
+        ctx.request_repaint();
+    }
     let space = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Space));
     if space {
         let selected = {
@@ -718,7 +903,7 @@ pub(crate) fn handle_keyboard(
                 core::EntryLocation::Fs(path) => {
                     if !app.dir_size_pending.contains(&path) {
                         app.dir_size_pending.insert(path.clone());
-                        let _ = app.dir_size_tx.send(path);
+                        let _ = app.dir_size_tx.send((path, app.settings.dir_size_symlinks));
                     }
                 }
                 core::EntryLocation::Remote { host, path } => {
@@ -1008,13 +1193,25 @@ pub(crate) fn handle_keyboard(
         app.prepare_edit_selected();
         ctx.request_repaint();
     }
+    let alt_f6 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::F6));
+    if alt_f6 && !other_panel_preview {
+        app.prepare_link_selected();
+        ctx.request_repaint();
+    }
     let shift_f6 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::F6));
     if shift_f6 || ctrl_shift_m {
         app.prepare_rename_selected();
         ctx.request_repaint();
     }
     let f6 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F6));
-    if (f6 || ctrl_m) && !other_panel_preview {
+    let in_trash = matches!(
+        app.get_active_panel().browser().browser_mode,
+        core::BrowserMode::Trash
+    );
+    if (f6 || ctrl_m) && in_trash {
+        app.prepare_restore_selected();
+        ctx.request_repaint();
+    } else if (f6 || ctrl_m) && !other_panel_preview {
         app.prepare_move_selected();
         ctx.request_repaint();
     }
@@ -1031,8 +1228,14 @@ pub(crate) fn handle_keyboard(
         app.open_theme_picker();
         ctx.request_repaint();
     }
-    if input.key_pressed(egui::Key::F8) || ctrl_x {
-        app.prepare_delete_selected();
+    let shift_f8 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::F8));
+    if shift_f8 {
+        app.prepare_delete_selected(app_state::DeleteMode::Permanent);
+        ctx.request_repaint();
+    }
+    let f8 = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F8));
+    if f8 || ctrl_x {
+        app.prepare_delete_selected(app_state::DeleteMode::Trash);
         ctx.request_repaint();
     }
 }
@@ -1050,7 +1253,11 @@ fn open_parent(app: &mut app_state::AppState, window_rows: usize) {
 
 pub(crate) fn confirm_pending_op(app: &mut app_state::AppState) {
     if let Some(op) = app.take_pending_op() {
-        if let app_state::PendingOp::Delete { targets } = &op
+        if let app_state::PendingOp::Compare { left, right } = op {
+            app.start_compare(left, right);
+            return;
+        }
+        if let app_state::PendingOp::Delete { targets, .. } = &op
             && let Some(first) = targets.first()
         {
             let panel = app.get_active_panel();
@@ -1132,7 +1339,7 @@ pub(crate) fn confirm_pending_op(app: &mut app_state::AppState) {
         }
         app.enqueue_pending_op(&op);
         match op {
-            app_state::PendingOp::Copy { .. } => {
+            app_state::PendingOp::Copy { .. } | app_state::PendingOp::Link { .. } => {
                 // Only refresh the destination (other) panel — source is unchanged
                 let dst = match app.active_panel {
                     core::ActivePanel::Left => core::ActivePanel::Right,
@@ -1146,11 +1353,14 @@ pub(crate) fn confirm_pending_op(app: &mut app_state::AppState) {
                 refresh_fs_panels(app);
             }
             app_state::PendingOp::Rename { .. } => refresh_fs_panels(app),
-            app_state::PendingOp::Delete { .. } => {
+            app_state::PendingOp::Delete { .. }
+            | app_state::PendingOp::Restore { .. }
+            | app_state::PendingOp::Purge { .. } => {
                 store_neighbor_selection(app);
                 refresh_active_panel(app);
             }
             app_state::PendingOp::Pack { .. } => refresh_active_panel(app),
+            app_state::PendingOp::Compare { .. } => {}
         }
     }
 }
@@ -1235,6 +1445,7 @@ fn handle_inline_rename(app: &mut app_state::AppState, input: &egui::InputState)
                         host,
                         path: remote_path,
                         contents: Vec::new(),
+                        expected_mtime: None,
                     });
                 } else {
                     let dir = browser.current_path.clone();
@@ -1318,8 +1529,7 @@ fn handle_inline_rename(app: &mut app_state::AppState, input: &egui::InputState)
         return false;
     }
     if let Some(task) = action {
-        let _ = app.io_tx.send(task);
-        app.io_in_flight = app.io_in_flight.saturating_add(1);
+        app.enqueue_io(task);
         if let Some((dir, name)) = next_selection {
             app.fs_last_selected_name.insert(dir, name);
         }
@@ -23,11 +23,23 @@
 
 pub mod app_state;
 pub mod archive;
+pub mod compare;
 pub mod core;
 pub mod elevate;
+pub mod ftp;
+pub mod memfs;
+pub mod remote;
+pub mod rename;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scp;
 pub mod settings;
 pub mod sftp;
 pub mod snapshot;
+pub mod sync;
 pub mod syntax;
 pub mod theme;
+pub mod trash;
+#[cfg(feature = "webdav")]
+pub mod webdav;
 pub mod workers;
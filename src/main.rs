@@ -30,12 +30,14 @@ mod ui;
 #[cfg(feature = "self-update")]
 mod update;
 
-use fileman::{app_state, core, theme, workers};
+use fileman::{app_state, compare, core, sync, theme, workers};
 mod replay;
 
 const ROW_HEIGHT: f32 = 24.0;
 const SIZE_COL_WIDTH: f32 = 84.0;
 const DATE_COL_WIDTH: f32 = 110.0;
+/// Why an entry is in a Compare listing, left of the date.
+const DIFF_COL_WIDTH: f32 = 90.0;
 const SNAPSHOT_WIDTH: u32 = 800;
 const SNAPSHOT_HEIGHT: u32 = 600;
 const MAX_IMAGE_TEXTURES: usize = 64;
@@ -626,7 +628,9 @@ pub fn panel_path_segments(panel: &app_state::PanelState) -> PathSegments {
                 segments,
             }
         }
-        core::BrowserMode::Search { .. } => PathSegments {
+        core::BrowserMode::Search { .. }
+        | core::BrowserMode::Trash
+        | core::BrowserMode::Compare { .. } => PathSegments {
             prefix: panel_path_display(panel),
             segments: Vec::new(),
         },
@@ -717,6 +721,8 @@ fn panel_path_display(panel: &app_state::PanelState) -> String {
                 root.to_string_lossy()
             )
         }
+        core::BrowserMode::Trash => "Trash".to_string(),
+        core::BrowserMode::Compare { left, right } => format!("Compare: {left} vs {right}"),
     }
 }
 
@@ -809,6 +815,59 @@ fn sort_mode_label(mode: core::SortMode) -> &'static str {
     }
 }
 
+/// Show the items of every trash directory in `which`. Entries are named by
+/// their original path and dated by when they were deleted; they point at the
+/// trashed copies, so F5 and F3 work on them as on any local file.
+fn load_trash_view(
+    app: &mut app_state::AppState,
+    which: core::ActivePanel,
+    prefer_name: Option<String>,
+) {
+    let mut items = fileman::trash::list(&fileman::trash::trash_dirs());
+    items.sort_by(|a, b| (&a.original, a.deleted).cmp(&(&b.original, b.deleted)));
+    // Marks and selection go by name, so repeat deletions of one path get a
+    // counter instead of sharing it.
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+    let mut entries: Vec<core::DirEntry> = items
+        .into_iter()
+        .map(|item| {
+            let meta = item.path.symlink_metadata().ok();
+            let is_symlink = meta.as_ref().is_some_and(|m| m.file_type().is_symlink());
+            let is_dir = meta.as_ref().is_some_and(|m| m.is_dir());
+            let count = seen.entry(item.original.clone()).or_default();
+            *count += 1;
+            let mut name = item.original.to_string_lossy().into_owned();
+            if *count > 1 {
+                name = format!("{name} ({count})");
+            }
+            core::DirEntry {
+                name,
+                is_dir,
+                is_symlink,
+                link_target: None,
+                location: core::EntryLocation::Fs(item.path),
+                size: meta.filter(|m| m.is_file()).map(|m| m.len()),
+                modified: item.deleted,
+            }
+        })
+        .collect();
+    let panel = app.panel_mut(which);
+    let browser = panel.browser_mut();
+    sort_entries(&mut entries, browser.sort_mode, browser.sort_desc);
+    browser.selected_index = prefer_name
+        .and_then(|name| entries.iter().position(|e| e.name == name))
+        .unwrap_or(0);
+    browser.entries = entries;
+    browser.browser_mode = core::BrowserMode::Trash;
+    browser.current_path = fileman::trash::home_trash().unwrap_or_default();
+    browser.load = app_state::LoadState::Idle;
+    browser.progress_override = None;
+    browser.top_index = browser.top_index.min(browser.selected_index);
+    browser.marked.clear();
+    browser.dir_token = browser.dir_token.wrapping_add(1);
+    panel.mode = app_state::PanelMode::Browser;
+}
+
 fn rebuild_search_entries(browser: &mut app_state::BrowserState, results: &[core::SearchResult]) {
     let app_state::BrowserState {
         browser_mode: ref mode,
@@ -852,6 +911,19 @@ fn rebuild_search_entries(browser: &mut app_state::BrowserState, results: &[core
         .collect();
 }
 
+/// Fill a Compare listing from the differences: each entry is named by its
+/// relative path.
+fn rebuild_compare_entries(browser: &mut app_state::BrowserState, diffs: &[compare::Diff]) {
+    browser.entries = diffs
+        .iter()
+        .map(|diff| core::DirEntry {
+            name: diff.path.clone(),
+            ..diff.entry().clone()
+        })
+        .collect();
+    sort_entries(&mut browser.entries, browser.sort_mode, browser.sort_desc);
+}
+
 fn hexdump_job(
     bytes: &[u8],
     width: usize,
@@ -1161,6 +1233,7 @@ fn pump_async(app: &mut app_state::AppState) -> bool {
                     removed.push(s);
                 }
                 shared.remove(&host);
+                app.sftp_stats.remove(&host);
             }
         }
         if !removed.is_empty() {
@@ -1268,6 +1341,38 @@ fn pump_async(app: &mut app_state::AppState) -> bool {
         preview.loading_since = None;
         changed = true;
     }
+    while let Ok(event) = app.command_rx.try_recv() {
+        let (id, text) = match event {
+            core::CommandEvent::Output { id, text } => (id, text),
+            core::CommandEvent::Done { id, status } => {
+                if app
+                    .command_running
+                    .as_ref()
+                    .is_some_and(|running| running.0 == id)
+                    && let Some((_, cwd)) = app.command_running.take()
+                {
+                    match cwd {
+                        core::CommandCwd::Local(_) => refresh_local_panels(app),
+                        core::CommandCwd::Remote { ref host, .. } => {
+                            refresh_remote_panels(app, host)
+                        }
+                    }
+                }
+                let text = match status {
+                    Ok(code) => format!("\n[exit status {code}]\n"),
+                    Err(msg) => format!("\n[{msg}]\n"),
+                };
+                (id, text)
+            }
+        };
+        if let Some(preview) = app.preview_panel_mut()
+            && preview.request_id == id
+            && let Some(core::PreviewContent::Text(ref mut output)) = preview.content
+        {
+            output.push_str(&text);
+        }
+        changed = true;
+    }
     if let Some(preview) = app.preview_panel()
         && let Some(started) = preview.loading_since
         && started.elapsed() > std::time::Duration::from_millis(300)
@@ -1297,6 +1402,14 @@ fn pump_async(app: &mut app_state::AppState) -> bool {
         }
         changed = true;
     }
+    while let Ok((host, path, free)) = app.remote_free_space_rx.try_recv() {
+        let key = (host, path);
+        app.remote_free_space_pending.remove(&key);
+        app.remote_free_space
+            .insert(key, (free, std::time::Instant::now()));
+        changed = true;
+    }
+    app.request_remote_free_space();
     while let Ok((host, path, size)) = app.remote_dir_size_rx.try_recv() {
         let key = (host.clone(), path.clone());
         app.remote_dir_size_pending.remove(&key);
@@ -1333,7 +1446,9 @@ fn pump_async(app: &mut app_state::AppState) -> bool {
         app.sftp_connect_rx = None;
         match result {
             Ok(session) => {
-                let host_key = session.host.clone();
+                let host_key = session.host().to_string();
+                app.sftp_stats
+                    .insert(host_key.clone(), session.stats().clone());
                 let arc_session = Arc::new(std::sync::Mutex::new(session));
                 let old = app
                     .sftp_sessions
@@ -1497,7 +1612,63 @@ fn pump_async(app: &mut app_state::AppState) -> bool {
         }
     }
 
+    while let Ok(event) = app.compare_rx.try_recv() {
+        if app
+            .compare_run
+            .as_ref()
+            .is_none_or(|run| run.id != event.id())
+        {
+            continue;
+        }
+        changed = true;
+        match event {
+            compare::CompareEvent::Progress { scanned, .. } => {
+                if let Some(browser) = app.compare_target_browser_mut() {
+                    browser.progress_override = Some((scanned, None));
+                }
+                if app.sync_comparing()
+                    && let Some(dialog) = app.sync_dialog_mut()
+                {
+                    dialog.scanned = scanned;
+                }
+            }
+            compare::CompareEvent::Done { diffs, .. } => {
+                if let Some(browser) = app.compare_target_browser_mut() {
+                    rebuild_compare_entries(browser, &diffs);
+                    browser.progress_override = None;
+                }
+                let Some(run) = app.compare_run.take() else {
+                    continue;
+                };
+                match run.target {
+                    app_state::CompareTarget::Marks => app.mark_differences(&run, &diffs),
+                    app_state::CompareTarget::Listing(..) => app.compare_results = diffs,
+                    app_state::CompareTarget::Sync => {
+                        if let Some(dialog) = app.sync_dialog_mut() {
+                            dialog.diffs = Some(diffs);
+                        }
+                    }
+                }
+            }
+            compare::CompareEvent::Error { message, .. } => {
+                if let Some(browser) = app.compare_target_browser_mut() {
+                    browser.progress_override = None;
+                }
+                if app.sync_comparing()
+                    && let Some(dialog) = app.sync_dialog_mut()
+                {
+                    dialog.error = Some(message.clone());
+                }
+                app.compare_run = None;
+                app.record_error("Compare", message);
+            }
+        }
+    }
+
     app.poll_update_status();
+    if app.poll_remote_edits() {
+        changed = true;
+    }
 
     changed
 }
@@ -1584,6 +1755,44 @@ fn draw_elevation_modal(ctx: &egui::Context, message: &str) -> Option<bool> {
     result
 }
 
+fn draw_remote_changed_modal(ctx: &egui::Context, message: &str) -> Option<bool> {
+    let screen = ctx.content_rect();
+    let overlay_layer =
+        egui::LayerId::new(egui::Order::Foreground, "remote_changed_overlay".into());
+    ctx.layer_painter(overlay_layer).rect_filled(
+        screen,
+        egui::CornerRadius::ZERO,
+        egui::Color32::from_black_alpha(160),
+    );
+    let mut result = None;
+    egui::Window::new("Remote File Changed")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.add_space(4.0);
+            ui.colored_label(egui::Color32::from_rgb(255, 200, 120), message);
+            ui.add_space(4.0);
+            ui.label("Uploading the local edits will discard the remote changes.");
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui
+                    .add(egui::Button::new("Overwrite").min_size(egui::vec2(100.0, 0.0)))
+                    .clicked()
+                {
+                    result = Some(true);
+                }
+                if ui
+                    .add(egui::Button::new("Keep remote").min_size(egui::vec2(100.0, 0.0)))
+                    .clicked()
+                {
+                    result = Some(false);
+                }
+            });
+        });
+    result
+}
+
 fn draw_async_indicator(ctx: &egui::Context, app: &app_state::AppState) {
     let search_running = matches!(app.search_status, app_state::SearchStatus::Running(_));
     // Largest entry count among archives still being indexed, if any. Gives the
@@ -1616,6 +1825,9 @@ fn draw_async_indicator(ctx: &egui::Context, app: &app_state::AppState) {
     let mut label = spinner.to_string();
     if app.io_in_flight > 0 {
         label += &format!(" {}io", app.io_in_flight);
+        if app.io_queue.is_paused() {
+            label += " paused";
+        }
     }
     if search_running {
         label += " scan";
@@ -1646,11 +1858,32 @@ fn navigate_quick_jump(
     result: ui::quick_jump::QuickJumpResult,
     target_panel: core::ActivePanel,
 ) {
+    if result.category == app_state::QuickJumpCategory::Trash {
+        // Escape goes back, as from search results.
+        app.push_history(target_panel);
+        load_trash_view(app, target_panel, None);
+        return;
+    }
     if result.category == app_state::QuickJumpCategory::Ssh {
         let host = result.path.to_string_lossy().to_string();
         navigate_sftp(app, &host, "/", target_panel);
         return;
     }
+    if result.category == app_state::QuickJumpCategory::Bookmark {
+        if let Some((host, remote_path)) = result
+            .path
+            .to_str()
+            .and_then(fileman::remote::parse_location)
+        {
+            let remote_path = if remote_path.is_empty() {
+                "/"
+            } else {
+                remote_path.as_str()
+            };
+            navigate_sftp(app, &host, remote_path, target_panel);
+        }
+        return;
+    }
     if result.category == app_state::QuickJumpCategory::Remote {
         // Navigate on the current remote host
         let host = {
@@ -1667,6 +1900,16 @@ fn navigate_quick_jump(
         }
         return;
     }
+    // A typed `host:path` or `ftp://host/path` that isn't a local directory.
+    if !result.path.is_dir()
+        && let Some((host, remote_path)) = result
+            .path
+            .to_str()
+            .and_then(fileman::remote::parse_location)
+    {
+        navigate_sftp(app, &host, &remote_path, target_panel);
+        return;
+    }
     if result.path.is_dir() {
         load_fs_directory_async(app, result.path, target_panel, None);
     }
@@ -1692,13 +1935,18 @@ fn navigate_sftp(
             .push_back((host.to_string(), remote_path.to_string(), target_panel));
         return;
     }
-    // Spawn SFTP connection in a background thread
+    connect_sftp(app, host);
+    app.sftp_pending_nav = Some((host.to_string(), remote_path.to_string(), target_panel));
+}
+
+/// Spawn a remote connection in a background thread; the result is picked
+/// up by `pump_async`.
+fn connect_sftp(app: &mut app_state::AppState, host: &str) {
     let host_owned = host.to_string();
-    let ssh_config = fileman::sftp::load_ssh_config();
     let (tx, rx) = std::sync::mpsc::channel();
     let wake = app.wake.clone();
     std::thread::spawn(move || {
-        let result = fileman::sftp::connect(&host_owned, &ssh_config);
+        let result = fileman::remote::connect(&host_owned);
         let _ = tx.send(result);
         if let Some(ref wake) = wake {
             wake();
@@ -1706,7 +1954,62 @@ fn navigate_sftp(
     });
     app.open_modal(app_state::Modal::Connecting(host.to_string()));
     app.sftp_connect_rx = Some(rx);
-    app.sftp_pending_nav = Some((host.to_string(), remote_path.to_string(), target_panel));
+}
+
+/// Drop the session for `host`. Panels still browsing it reconnect on their
+/// next load.
+fn disconnect_sftp(app: &mut app_state::AppState, host: &str) {
+    let removed = app.sftp_sessions.remove(host);
+    app.sftp_sessions_shared
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .remove(host);
+    app.sftp_stats.remove(host);
+    // libssh2_session_free can block in poll() on a dead socket.
+    if let Some(removed) = removed {
+        std::thread::spawn(move || drop(removed));
+    }
+}
+
+/// Replace the session for `host` with a fresh connection and reload the
+/// panels browsing it.
+fn reconnect_sftp(app: &mut app_state::AppState, host: &str) {
+    if app.sftp_connecting().is_some() {
+        return;
+    }
+    disconnect_sftp(app, host);
+    let mut reloading = false;
+    for side in [core::ActivePanel::Left, core::ActivePanel::Right] {
+        let path = match app.panel(side).browser().browser_mode {
+            core::BrowserMode::Remote {
+                host: ref h,
+                ref path,
+                ..
+            } if h == host => path.clone(),
+            _ => continue,
+        };
+        navigate_sftp(app, host, &path, side);
+        reloading = true;
+    }
+    if !reloading {
+        connect_sftp(app, host);
+    }
+}
+
+fn apply_connection_action(
+    app: &mut app_state::AppState,
+    action: ui::connections::ConnectionAction,
+) {
+    match action {
+        ui::connections::ConnectionAction::Reconnect(host) => reconnect_sftp(app, &host),
+        ui::connections::ConnectionAction::Disconnect(host) => {
+            disconnect_sftp(app, &host);
+            // Re-clamp the selection to the shortened list.
+            if let Some(selected) = app.connections_selected() {
+                app.select_connection(selected);
+            }
+        }
+    }
 }
 
 fn load_sftp_directory_async(
@@ -1725,7 +2028,7 @@ fn load_sftp_directory_async(
     };
 
     // Use the synthetic path for stack comparisons.
-    let synthetic = PathBuf::from(format!("/sftp/{host}{remote_path}"));
+    let synthetic = fileman::remote::synthetic_path(host, remote_path);
     let auto_refresh = app.settings.auto_refresh;
     let wake_for_refresh = app.wake.clone();
     {
@@ -1797,7 +2100,6 @@ fn load_sftp_directory_async(
                         browser.load = app_state::LoadState::start(rx, browser.dir_token);
                         spawn_sftp_load_thread(
                             Arc::clone(&session),
-                            host.to_string(),
                             remote_path.to_string(),
                             tx,
                             wake_for_refresh,
@@ -1810,7 +2112,6 @@ fn load_sftp_directory_async(
         }
     }
 
-    let host_owned = host.to_string();
     let path_owned = remote_path.to_string();
 
     let (tx, rx) = mpsc::channel::<core::DirBatch>();
@@ -1846,7 +2147,7 @@ fn load_sftp_directory_async(
     browser.watching_archive = None;
 
     // Fresh load — stream for snappy first-paint, not atomic.
-    spawn_sftp_load_thread(session, host_owned, path_owned, tx, wake, false);
+    spawn_sftp_load_thread(session, path_owned, tx, wake, false);
 }
 
 /// Spawn the SFTP directory-streaming loader thread. Shared between the
@@ -1859,33 +2160,50 @@ fn load_sftp_directory_async(
 /// already-shown cache. When `atomic` is false (initial fresh load), it
 /// streams as before for snappy first-paint.
 fn spawn_sftp_load_thread(
-    session: Arc<std::sync::Mutex<fileman::sftp::SftpSession>>,
-    host: String,
+    session: fileman::remote::SharedRemote,
     path: String,
     tx: mpsc::Sender<core::DirBatch>,
     wake: Option<Arc<dyn Fn() + Send + Sync>>,
     atomic: bool,
 ) {
     thread::spawn(move || {
-        let locked = session.lock().unwrap_or_else(|p| p.into_inner());
+        let mut locked = session.lock().unwrap_or_else(|p| p.into_inner());
         let mut buffered: Vec<core::DirEntry> = Vec::new();
         let mut first = true;
-        let result = fileman::sftp::read_directory_streaming(&locked.sftp, &host, &path, |batch| {
-            if atomic {
-                buffered.extend(batch);
-                return;
-            }
-            let msg = if first {
-                first = false;
-                core::DirBatch::Replace(batch)
-            } else {
-                core::DirBatch::Append(batch)
-            };
-            let _ = tx.send(msg);
-            if let Some(ref wake) = wake {
-                wake();
+        let mut revived = false;
+        let result = loop {
+            let result = locked.list(&path, &mut |batch| {
+                if atomic {
+                    buffered.extend(batch);
+                    return;
+                }
+                let msg = if first {
+                    first = false;
+                    core::DirBatch::Replace(batch)
+                } else {
+                    core::DirBatch::Append(batch)
+                };
+                let _ = tx.send(msg);
+                if let Some(ref wake) = wake {
+                    wake();
+                }
+            });
+            // A dead connection (e.g. after the machine slept) is
+            // re-established once and the listing started over, as the IO
+            // worker does for its tasks.
+            if !revived && matches!(result, Err((_, true))) && !locked.is_alive() {
+                revived = true;
+                match locked.reconnect() {
+                    Ok(()) => {
+                        buffered.clear();
+                        first = true;
+                        continue;
+                    }
+                    Err(e) => eprintln!("Reconnect to {} failed: {e}", locked.host()),
+                }
             }
-        });
+            break result;
+        };
         if let Err((msg, is_connection_error)) = result {
             let batch = if is_connection_error {
                 core::DirBatch::ConnectionError(msg)
@@ -3052,6 +3370,23 @@ fn apply_panel_snapshot(
             browser.progress_override = None;
             browser.dir_token = browser.dir_token.wrapping_add(1);
         }
+        core::BrowserMode::Trash => load_trash_view(app, which, snapshot.selected_name),
+        core::BrowserMode::Compare { .. } => {
+            let diffs = std::mem::take(&mut app.compare_results);
+            let browser = app.panel_mut(which).browser_mut();
+            browser.browser_mode = snapshot.mode;
+            browser.current_path = snapshot.current_path;
+            rebuild_compare_entries(browser, &diffs);
+            browser.load = app_state::LoadState::Idle;
+            browser.selected_index = snapshot
+                .selected_name
+                .and_then(|name| browser.entries.iter().position(|e| e.name == name))
+                .unwrap_or(0);
+            browser.top_index = 0;
+            browser.progress_override = None;
+            browser.dir_token = browser.dir_token.wrapping_add(1);
+            app.compare_results = diffs;
+        }
     }
 }
 
@@ -3140,6 +3475,7 @@ fn refresh_active_panel(app: &mut app_state::AppState) {
         core::BrowserMode::Remote { ref host, ref path } => {
             load_sftp_directory_async(app, host, path, which, prefer_name);
         }
+        core::BrowserMode::Trash => load_trash_view(app, which, prefer_name),
         _ => {}
     }
 }
@@ -3167,18 +3503,24 @@ fn refresh_fs_panels(app: &mut app_state::AppState) {
 fn refresh_local_panels(app: &mut app_state::AppState) {
     for which in [core::ActivePanel::Left, core::ActivePanel::Right] {
         let browser = app.panel(which).browser();
-        if matches!(browser.browser_mode, core::BrowserMode::Fs) {
-            let path = browser.current_path.clone();
-            let current_name = browser
-                .entries
-                .get(browser.selected_index)
-                .map(|e| e.name.clone());
-            load_fs_directory_async(app, path, which, current_name);
+        let current_name = browser
+            .entries
+            .get(browser.selected_index)
+            .map(|e| e.name.clone());
+        match browser.browser_mode {
+            core::BrowserMode::Fs => {
+                let path = browser.current_path.clone();
+                load_fs_directory_async(app, path, which, current_name);
+            }
+            core::BrowserMode::Trash => load_trash_view(app, which, current_name),
+            _ => {}
         }
     }
 }
 
 fn refresh_remote_panels(app: &mut app_state::AppState, host: &str) {
+    // Whatever changed the listing may have changed the free space too.
+    app.remote_free_space.retain(|key, _| key.0 != host);
     for which in [core::ActivePanel::Left, core::ActivePanel::Right] {
         let browser = app.panel(which).browser();
         if let core::BrowserMode::Remote {
@@ -3214,6 +3556,7 @@ fn reload_panel(app: &mut app_state::AppState, which: core::ActivePanel) {
         core::BrowserMode::Fs => {
             load_fs_directory_async(app, current_path, which, selected_name);
         }
+        core::BrowserMode::Trash => load_trash_view(app, which, selected_name),
         core::BrowserMode::Container {
             kind,
             archive_path,
@@ -3247,6 +3590,9 @@ fn reload_panel(app: &mut app_state::AppState, which: core::ActivePanel) {
                 browser.top_index = browser.selected_index;
             }
         }
+        core::BrowserMode::Trash => load_trash_view(app, which, selected_name),
+        // A comparison is a snapshot; Shift+F2 runs it again.
+        core::BrowserMode::Compare { .. } => {}
     }
 }
 
@@ -3569,7 +3915,7 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
         let left_local = left_start
             .as_deref()
             .and_then(|s| {
-                if parse_remote_path(s).is_none() {
+                if fileman::remote::parse_location(s).is_none() {
                     Some(PathBuf::from(s))
                 } else {
                     None
@@ -3579,7 +3925,7 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
         let right_local = right_start
             .as_deref()
             .and_then(|s| {
-                if parse_remote_path(s).is_none() {
+                if fileman::remote::parse_location(s).is_none() {
                     Some(PathBuf::from(s))
                 } else {
                     None
@@ -3587,13 +3933,13 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
             })
             .unwrap_or_else(|| cwd.clone());
         let sftp_sessions_shared: Arc<
-            std::sync::Mutex<HashMap<String, Arc<std::sync::Mutex<fileman::sftp::SftpSession>>>>,
+            std::sync::Mutex<HashMap<String, fileman::remote::SharedRemote>>,
         > = Arc::new(std::sync::Mutex::new(HashMap::new()));
         // Register the shared map globally so archive streaming can access sessions.
-        fileman::sftp::init_shared_registry(sftp_sessions_shared.clone());
+        fileman::remote::init_shared_registry(sftp_sessions_shared.clone());
         let transfer_progress = Arc::new(core::TransferProgress::new());
-        let io_cancel_flag = Arc::new(std::sync::atomic::AtomicBool::new(false));
-        let (io_tx, io_rx, io_cancel_tx) = workers::start_io_worker(
+        let io_queue = Arc::new(core::IoQueue::new());
+        let io_rx = workers::start_io_worker(
             sftp_sessions_shared.clone(),
             transfer_progress.clone(),
             Some(Arc::new({
@@ -3602,7 +3948,7 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                     let _ = proxy.send_event(UserEvent::Wake);
                 }
             })),
-            io_cancel_flag.clone(),
+            io_queue.clone(),
         );
         let (preview_tx, preview_rx) = workers::start_preview_worker(
             Some(Arc::new({
@@ -3625,8 +3971,19 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
             sftp_sessions_shared.clone(),
             Some(worker_wake.clone()),
         );
+        let (remote_free_space_tx, remote_free_space_rx) = workers::start_remote_free_space_worker(
+            sftp_sessions_shared.clone(),
+            Some(worker_wake.clone()),
+        );
         let (search_tx, search_rx) =
-            workers::start_search_worker(Some(worker_wake), sftp_sessions_shared.clone());
+            workers::start_search_worker(Some(worker_wake.clone()), sftp_sessions_shared.clone());
+        let (compare_tx, compare_rx) = workers::start_compare_worker(Some(worker_wake.clone()));
+        let command_cancel = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (command_tx, command_rx) = workers::start_command_worker(
+            sftp_sessions_shared.clone(),
+            command_cancel.clone(),
+            Some(worker_wake),
+        );
         let (image_req_tx, image_req_rx) = mpsc::channel::<ImageRequest>();
         let (image_res_tx, image_res_rx) = wake_channel::<ImageResponse>(&self.proxy);
         let (highlight_req_tx, highlight_req_rx) = mpsc::channel::<HighlightRequest>();
@@ -3685,10 +4042,9 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                         .cloned();
                     let data = session.and_then(|s| {
                         let locked = s.lock().unwrap_or_else(|p| p.into_inner());
-                        let stat = locked.sftp.stat(std::path::Path::new(path)).ok();
+                        let stat = locked.stat(path).ok();
                         image_progress.reset(stat.and_then(|s| s.size).unwrap_or(0));
-                        let mut file =
-                            fileman::sftp::open_remote_reader(&locked.sftp, path).ok()?;
+                        let mut file = locked.open_read(path).ok()?;
                         let mut buf = Vec::new();
                         let mut chunk = vec![0u8; 32 * 1024];
                         // Phase 1: read header prefix; fire EXIF thumbnail immediately
@@ -3897,7 +4253,8 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                         match session {
                             Some(session) => {
                                 let locked = session.lock().unwrap_or_else(|p| p.into_inner());
-                                match fileman::sftp::read_file_full(&locked.sftp, &remote_path) {
+                                match fileman::remote::read_file_full(&**locked, &remote_path, None)
+                                {
                                     Ok(bytes) => match String::from_utf8(bytes) {
                                         Ok(text) => (text, false),
                                         Err(_) => {
@@ -3907,7 +4264,7 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                                     Err(e) => (format!("Failed to read remote file: {e}"), true),
                                 }
                             }
-                            None => (format!("No SFTP session for host: {host}"), true),
+                            None => (format!("No remote session for host: {host}"), true),
                         }
                     } else {
                         match std::fs::read(&req.path) {
@@ -3999,12 +4356,17 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
             preview_tx: preview_tx.clone(),
             preview_rx,
             preview_request_id: 0,
-            io_tx,
             io_rx,
-            io_cancel_tx,
-            io_cancel_flag,
+            io_queue,
             io_in_flight: 0,
             io_batch_total: 0,
+            io_batch_started: None,
+            io_copy_method: None,
+            conflict_apply_all: false,
+            symlink_policy: core::SymlinkPolicy::Links,
+            link_kind: core::LinkKind::Symbolic,
+            link_target: core::LinkTarget::Absolute,
+            io_background: false,
             io_cancel_requested: false,
             transfer_progress: transfer_progress.clone(),
             dir_size_tx,
@@ -4015,6 +4377,10 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
             remote_dir_size_rx,
             remote_dir_sizes: Default::default(),
             remote_dir_size_pending: Default::default(),
+            remote_free_space_tx,
+            remote_free_space_rx,
+            remote_free_space: Default::default(),
+            remote_free_space_pending: Default::default(),
             fs_last_selected_name: Default::default(),
             container_last_selected_name: Default::default(),
             container_dir_cache: Default::default(),
@@ -4041,6 +4407,20 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
             search_tx,
             search_rx,
             search_remote_host: None,
+            compare_depth: compare::CompareDepth::default(),
+            compare_content: compare::CompareContent::default(),
+            compare_request_id: 0,
+            compare_run: None,
+            compare_results: Vec::new(),
+            compare_tx,
+            compare_rx,
+            sync_options: sync::SyncOptions::default(),
+            command_input: None,
+            command_focus: false,
+            command_tx,
+            command_rx,
+            command_cancel,
+            command_running: None,
             refresh_tick: 0,
             update_status: app_state::UpdateStatus::Disabled,
             update_rx: None,
@@ -4056,24 +4436,32 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                 format!("{} ({})", dev.device_name, backend)
             },
             error_log: Vec::new(),
+            undo_journal: Vec::new(),
             settings: loaded_settings,
             sftp_sessions: HashMap::new(),
             sftp_sessions_shared: sftp_sessions_shared.clone(),
             sftp_connect_rx: None,
             sftp_pending_nav: None,
             sftp_nav_queue: std::collections::VecDeque::new(),
+            sftp_stats: HashMap::new(),
+            remote_edits: Vec::new(),
+            remote_edit_polled: std::time::Instant::now(),
         };
 
         app.theme
             .load_external_from_dir(std::path::Path::new("./themes"));
         apply_theme_preference(&mut app.theme, &app.settings.theme);
+        app.apply_transfer_settings();
 
         // Navigate each panel to its startup path (local or remote)
         for (path_str, panel, local_path) in [
             (left_start, core::ActivePanel::Left, left_local),
             (right_start, core::ActivePanel::Right, right_local),
         ] {
-            match path_str.as_deref().and_then(parse_remote_path) {
+            match path_str
+                .as_deref()
+                .and_then(fileman::remote::parse_location)
+            {
                 Some((host, rpath)) => navigate_sftp(&mut app, &host, &rpath, panel),
                 None => load_fs_directory_async(&mut app, local_path, panel, None),
             }
@@ -4173,6 +4561,22 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                 while let Ok(result) = runtime.app.io_rx.try_recv() {
                     match result {
                         core::IOResult::Completed => local_refresh = true,
+                        core::IOResult::CompletedUndoable(record) => {
+                            local_refresh = true;
+                            runtime.app.record_undo(record);
+                        }
+                        core::IOResult::Copied { method, undo } => {
+                            local_refresh = true;
+                            runtime.app.note_copy_method(method);
+                            if let Some(record) = undo {
+                                runtime.app.record_undo(record);
+                            }
+                        }
+                        core::IOResult::UndoFailed { message, record } => {
+                            local_refresh = true;
+                            runtime.app.restore_undo(record);
+                            io_errors.push(message);
+                        }
                         core::IOResult::CompletedRemote(host) => remote_hosts.push(host),
                         core::IOResult::CompletedMoved(host) => {
                             local_refresh = true;
@@ -4197,6 +4601,25 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                                 io_errors.push(message);
                             }
                         }
+                        core::IOResult::OpenedRemote {
+                            host,
+                            remote_path,
+                            local_path,
+                            mtime,
+                        } => {
+                            runtime
+                                .app
+                                .track_remote_edit(host, remote_path, local_path, mtime);
+                        }
+                        core::IOResult::RemoteFileWritten { host, path, mtime } => {
+                            runtime.app.on_remote_file_written(&host, &path, mtime);
+                            remote_hosts.push(host);
+                        }
+                        core::IOResult::RemoteChanged { message, task } => {
+                            runtime
+                                .app
+                                .open_modal(app_state::Modal::RemoteChanged { message, task });
+                        }
                     }
                     completed += 1;
                 }
@@ -4557,6 +4980,15 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                     if runtime.app.theme_picker_open() {
                         ui::theme_picker::draw_theme_picker(&ctx, &mut runtime.app);
                     }
+                    if let Some(action) = ui::connections::draw_connections(&ctx, &mut runtime.app)
+                    {
+                        apply_connection_action(&mut runtime.app, action);
+                    }
+                    ui::modals::draw_queue(&ctx, &mut runtime.app);
+                    if let Some(index) = ui::modals::draw_undo_history(&ctx, &mut runtime.app) {
+                        runtime.app.close_modal();
+                        runtime.app.undo_at(index);
+                    }
                     if runtime.app.settings_open() {
                         let outcome = {
                             let externals = runtime.app.theme.external.clone();
@@ -4585,8 +5017,14 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                     if runtime.app.props_dialog().is_some() {
                         ui::props_dialog::draw_props_modal(&ctx, &mut runtime.app);
                     }
-                    if runtime.app.io_in_flight > 0 {
-                        ui::modals::draw_progress_modal(&ctx, &runtime.app);
+                    if runtime.app.multi_rename().is_some() {
+                        ui::multi_rename::draw_multi_rename(&ctx, &mut runtime.app);
+                    }
+                    if runtime.app.sync_dialog().is_some() {
+                        ui::sync::draw_sync(&ctx, &mut runtime.app);
+                    }
+                    if runtime.app.io_modal_visible() {
+                        ui::modals::draw_progress_modal(&ctx, &mut runtime.app);
                     }
                     if runtime.app.quick_jump().is_some()
                         && let Some(result) =
@@ -4615,6 +5053,19 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
                             None => {}
                         }
                     }
+                    if let Some(msg) = runtime.app.remote_changed_message().map(|s| s.to_string()) {
+                        match draw_remote_changed_modal(&ctx, &msg) {
+                            Some(true) => {
+                                if let Some(task) = runtime.app.take_remote_changed() {
+                                    runtime.app.enqueue_io(task);
+                                }
+                            }
+                            Some(false) => {
+                                runtime.app.close_modal();
+                            }
+                            None => {}
+                        }
+                    }
                     if let Some(msg) = runtime.app.error_message().map(|s| s.to_string()) {
                         draw_error_modal(&ctx, &msg);
                     }
@@ -4773,14 +5224,19 @@ impl winit::application::ApplicationHandler<UserEvent> for App {
             if pump_async(&mut runtime.app) {
                 runtime.needs_redraw = true;
             }
+            // Temp copies of remote files are polled for edits even when idle.
+            let mut wake_at = runtime.app.remote_edit_deadline();
             if let Some(t) = runtime.next_repaint {
                 if t <= std::time::Instant::now() {
                     runtime.next_repaint = None;
                     runtime.needs_redraw = true;
                 } else {
-                    event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(t));
+                    wake_at = Some(wake_at.map_or(t, |w| w.min(t)));
                 }
             }
+            if let Some(t) = wake_at {
+                event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(t));
+            }
             if runtime.needs_redraw {
                 runtime.window.request_redraw();
                 runtime.needs_redraw = false;
@@ -4807,21 +5263,6 @@ struct CliArgs {
     update: bool,
 }
 
-/// Detect `host:path` format where the host part contains no slashes.
-/// Returns `(host, path)` or `None` for local paths.
-fn parse_remote_path(s: &str) -> Option<(String, String)> {
-    let colon = s.find(':')?;
-    if colon == 0 {
-        return None;
-    }
-    let host = &s[..colon];
-    let path = &s[colon + 1..];
-    if host.contains('/') || host.contains('\\') {
-        return None;
-    }
-    Some((host.to_string(), path.to_string()))
-}
-
 fn parse_cli_args() -> anyhow::Result<CliArgs> {
     let mut args = std::env::args().skip(1);
     let mut parsed = CliArgs::default();
@@ -4833,6 +5274,9 @@ fn parse_cli_args() -> anyhow::Result<CliArgs> {
                 eprintln!("Usage: fileman [OPTIONS] [LEFT] [RIGHT]");
                 eprintln!();
                 eprintln!("  LEFT/RIGHT: local path or host:path for remote (e.g. k6:/home/user)");
+                eprintln!(
+                    "              scheme://host/path picks a remote backend (sftp:// default)"
+                );
                 eprintln!();
                 eprintln!("Options:");
                 eprintln!("  -h, --help         Show this help message");
@@ -4868,7 +5312,7 @@ fn parse_cli_args() -> anyhow::Result<CliArgs> {
                     &mut parsed.right
                 };
                 // Resolve relative local paths; leave remote "host:path" strings as-is
-                let resolved = if parse_remote_path(other).is_none() {
+                let resolved = if fileman::remote::parse_location(other).is_none() {
                     let p = PathBuf::from(other);
                     let p = if p.is_relative() {
                         std::env::current_dir()?.join(p)
@@ -5119,7 +5563,13 @@ fn draw_root_ui(render: UiRender<'_>) {
     if app.props_dialog().is_some() {
         ui::props_dialog::draw_props_modal(&ctx, app);
     }
-    if app.io_in_flight > 0 {
+    if app.multi_rename().is_some() {
+        ui::multi_rename::draw_multi_rename(&ctx, app);
+    }
+    if app.sync_dialog().is_some() {
+        ui::sync::draw_sync(&ctx, app);
+    }
+    if app.io_modal_visible() {
         ui::modals::draw_progress_modal(&ctx, app);
     }
     if app.quick_jump().is_some()
@@ -0,0 +1,421 @@
+//! In-memory backend for `mem://` host keys, used by tests and replay cases.
+//!
+//! Each host key names a tree (`mem://fixture` → tree `fixture`) that lives
+//! for the rest of the process and is shared by every connection to it, so a
+//! test can seed it, let the app operate on it through the regular remote
+//! code paths, and inspect the result. The backend has no shell and no
+//! server-side copy, which makes the generic fallbacks (listing walks,
+//! streamed copies, walk-based search) the ones exercised.
+//!
+//! The scheme is not served by default; call [`register`] first.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Cursor, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    core::{DirEntry, EntryLocation},
+    remote::{self, RemoteStat, SessionStats},
+};
+
+pub const SCHEME: &str = "mem";
+
+enum Node {
+    Dir { mtime: u64 },
+    File { data: Vec<u8>, mtime: u64 },
+}
+
+/// Nodes keyed by absolute path; "/" is always present.
+type Tree = BTreeMap<String, Node>;
+type SharedTree = Arc<Mutex<Tree>>;
+
+static TREES: OnceLock<Mutex<HashMap<String, SharedTree>>> = OnceLock::new();
+
+/// Serve `mem://` host keys through [`remote::connect`].
+pub fn register() {
+    remote::register_backend(SCHEME, |host| Ok(Box::new(connect(host)?)));
+}
+
+fn shared_tree(name: &str) -> SharedTree {
+    let trees = TREES.get_or_init(Default::default);
+    let mut trees = trees.lock().unwrap_or_else(|p| p.into_inner());
+    trees
+        .entry(name.to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(empty_tree())))
+        .clone()
+}
+
+fn empty_tree() -> Tree {
+    let mut tree = Tree::new();
+    tree.insert("/".to_string(), Node::Dir { mtime: now() });
+    tree
+}
+
+fn lock(tree: &SharedTree) -> MutexGuard<'_, Tree> {
+    tree.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// `""` and `"/dir/"` become `"/"` and `"/dir"`.
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else if trimmed.starts_with('/') {
+        trimmed.to_string()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+/// Paths strictly below `dir`, as a key prefix.
+fn child_prefix(dir: &str) -> String {
+    if dir == "/" {
+        "/".to_string()
+    } else {
+        format!("{dir}/")
+    }
+}
+
+fn require_dir(tree: &Tree, path: &str) -> Result<(), String> {
+    match tree.get(path) {
+        Some(&Node::Dir { .. }) => Ok(()),
+        Some(&Node::File { .. }) => Err(format!("{path}: not a directory")),
+        None => Err(format!("{path}: no such directory")),
+    }
+}
+
+/// Replace the tree `name` with a copy of the local directory `dir`.
+pub fn seed_from_dir(name: &str, dir: &Path) -> io::Result<()> {
+    fn walk(tree: &mut Tree, local: &Path, path: &str) -> io::Result<()> {
+        for entry in std::fs::read_dir(local)? {
+            let entry = entry?;
+            let child = remote::join_path(path, &entry.file_name().to_string_lossy());
+            let mtime = entry
+                .metadata()?
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or_else(now, |d| d.as_secs());
+            if entry.file_type()?.is_dir() {
+                tree.insert(child.clone(), Node::Dir { mtime });
+                walk(tree, &entry.path(), &child)?;
+            } else {
+                let data = std::fs::read(entry.path())?;
+                tree.insert(child, Node::File { data, mtime });
+            }
+        }
+        Ok(())
+    }
+    let mut tree = empty_tree();
+    walk(&mut tree, dir, "/")?;
+    *lock(&shared_tree(name)) = tree;
+    Ok(())
+}
+
+/// Every path in the tree `name` except the root, with whether it is a
+/// directory, in sorted order.
+pub fn paths(name: &str) -> Vec<(String, bool)> {
+    lock(&shared_tree(name))
+        .iter()
+        .filter(|pair| pair.0 != "/")
+        .map(|pair| (pair.0.clone(), matches!(*pair.1, Node::Dir { .. })))
+        .collect()
+}
+
+/// Contents of a file in the tree `name`.
+pub fn read(name: &str, path: &str) -> Option<Vec<u8>> {
+    match *lock(&shared_tree(name)).get(&normalize(path))? {
+        Node::File { ref data, .. } => Some(data.clone()),
+        Node::Dir { .. } => None,
+    }
+}
+
+pub struct MemFs {
+    pub host: String,
+    pub stats: Arc<SessionStats>,
+    tree: SharedTree,
+}
+
+/// Connect to `mem://name`, creating an empty tree on first use.
+pub fn connect(host: &str) -> Result<MemFs, String> {
+    let (_, name) = remote::split_scheme(host);
+    let name = name.trim_end_matches('/');
+    if name.is_empty() {
+        return Err(format!("{host}: missing tree name"));
+    }
+    Ok(MemFs {
+        host: host.to_string(),
+        stats: SessionStats::new("", Some("in-memory".to_string())),
+        tree: shared_tree(name),
+    })
+}
+
+/// Appends to the file node as data arrives, like a streamed upload.
+struct MemWriter {
+    tree: SharedTree,
+    path: String,
+}
+
+impl Write for MemWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match lock(&self.tree).get_mut(&self.path) {
+            Some(&mut Node::File {
+                ref mut data,
+                ref mut mtime,
+            }) => {
+                data.extend_from_slice(buf);
+                *mtime = now();
+                Ok(buf.len())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: file was removed", self.path),
+            )),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl remote::RemoteFs for MemFs {
+    fn host(&self) -> &str {
+        &self.host
+    }
+
+    fn home_dir(&self) -> Option<&str> {
+        None
+    }
+
+    fn stats(&self) -> &Arc<SessionStats> {
+        &self.stats
+    }
+
+    fn is_alive(&self) -> bool {
+        true
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.stats.mark_reconnected();
+        Ok(())
+    }
+
+    fn list(
+        &self,
+        path: &str,
+        on_batch: &mut dyn FnMut(Vec<DirEntry>),
+    ) -> Result<(), (String, bool)> {
+        let dir = normalize(path);
+        let tree = lock(&self.tree);
+        require_dir(&tree, &dir).map_err(|e| (e, false))?;
+        let prefix = child_prefix(&dir);
+        let mut entries: Vec<DirEntry> =
+            remote::parent_entry(&self.host, &dir).into_iter().collect();
+        for (child, node) in tree.range(prefix.clone()..) {
+            let Some(name) = child.strip_prefix(&prefix) else {
+                break;
+            };
+            if name.is_empty() || name.contains('/') {
+                continue;
+            }
+            let (is_dir, size, mtime) = match *node {
+                Node::Dir { mtime } => (true, None, mtime),
+                Node::File { ref data, mtime } => (false, Some(data.len() as u64), mtime),
+            };
+            entries.push(DirEntry {
+                name: name.to_string(),
+                is_dir,
+                is_symlink: false,
+                link_target: None,
+                location: EntryLocation::Remote {
+                    host: self.host.clone(),
+                    path: child.clone(),
+                },
+                size,
+                modified: Some(mtime),
+            });
+        }
+        drop(tree);
+        on_batch(entries);
+        Ok(())
+    }
+
+    fn stat(&self, path: &str) -> Result<RemoteStat, String> {
+        let path = normalize(path);
+        match lock(&self.tree).get(&path) {
+            Some(&Node::Dir { mtime }) => Ok(RemoteStat {
+                is_dir: true,
+                size: None,
+                mtime: Some(mtime),
+            }),
+            Some(&Node::File { ref data, mtime }) => Ok(RemoteStat {
+                is_dir: false,
+                size: Some(data.len() as u64),
+                mtime: Some(mtime),
+            }),
+            None => Err(format!("{path}: no such file or directory")),
+        }
+    }
+
+    fn open_read(&self, path: &str) -> Result<Box<dyn crate::archive::ReadSeek + '_>, String> {
+        let path = normalize(path);
+        let tree = lock(&self.tree);
+        match *tree
+            .get(&path)
+            .ok_or_else(|| format!("{path}: no such file"))?
+        {
+            Node::File { ref data, .. } => Ok(Box::new(Cursor::new(data.clone()))),
+            Node::Dir { .. } => Err(format!("{path}: is a directory")),
+        }
+    }
+
+    fn create(&self, path: &str) -> Result<Box<dyn Write + '_>, String> {
+        let path = normalize(path);
+        let mut tree = lock(&self.tree);
+        require_dir(&tree, &remote::parent_path(&path))?;
+        if let Some(&Node::Dir { .. }) = tree.get(&path) {
+            return Err(format!("{path}: is a directory"));
+        }
+        tree.insert(
+            path.clone(),
+            Node::File {
+                data: Vec::new(),
+                mtime: now(),
+            },
+        );
+        Ok(Box::new(MemWriter {
+            tree: self.tree.clone(),
+            path,
+        }))
+    }
+
+    fn rename(&self, src: &str, dst: &str) -> Result<(), String> {
+        let (src, dst) = (normalize(src), normalize(dst));
+        let mut tree = lock(&self.tree);
+        let src_is_dir = match tree.get(&src) {
+            Some(&Node::Dir { .. }) => true,
+            Some(&Node::File { .. }) => false,
+            None => return Err(format!("{src}: no such file or directory")),
+        };
+        require_dir(&tree, &remote::parent_path(&dst))?;
+        if src == dst {
+            return Ok(());
+        }
+        if src_is_dir && dst.starts_with(&child_prefix(&src)) {
+            return Err(format!("cannot move {src} into itself"));
+        }
+        match tree.get(&dst) {
+            None => {}
+            Some(&Node::File { .. }) if !src_is_dir => {}
+            Some(_) => return Err(format!("{dst}: already exists")),
+        }
+        let prefix = child_prefix(&src);
+        let moved: Vec<String> = tree
+            .range(prefix.clone()..)
+            .map(|pair| pair.0)
+            .take_while(|path| path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        if let Some(node) = tree.remove(&src) {
+            tree.insert(dst.clone(), node);
+        }
+        for old in moved {
+            if let Some(node) = tree.remove(&old) {
+                tree.insert(format!("{dst}/{}", &old[prefix.len()..]), node);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        let path = normalize(path);
+        let mut tree = lock(&self.tree);
+        match tree.get(&path) {
+            Some(&Node::File { .. }) => {
+                tree.remove(&path);
+                Ok(())
+            }
+            Some(&Node::Dir { .. }) => Err(format!("{path}: is a directory")),
+            None => Err(format!("{path}: no such file")),
+        }
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<(), String> {
+        let path = normalize(path);
+        let mut tree = lock(&self.tree);
+        require_dir(&tree, &path)?;
+        if path == "/" {
+            return Err("cannot remove /".to_string());
+        }
+        let prefix = child_prefix(&path);
+        if tree
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|pair| pair.0.starts_with(&prefix))
+        {
+            return Err(format!("{path}: directory not empty"));
+        }
+        tree.remove(&path);
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        let path = normalize(path);
+        let mut tree = lock(&self.tree);
+        require_dir(&tree, &remote::parent_path(&path))?;
+        if tree.contains_key(&path) {
+            return Err(format!("{path}: already exists"));
+        }
+        tree.insert(path, Node::Dir { mtime: now() });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote::RemoteFs as _;
+
+    #[test]
+    fn rename_moves_whole_subtree() {
+        let fs = connect("mem://unit-rename").unwrap();
+        fs.mkdir("/a").unwrap();
+        fs.mkdir("/a/b").unwrap();
+        remote::write_file(&fs, "/a/b/c.txt", b"hi").unwrap();
+        remote::write_file(&fs, "/ab.txt", b"sibling").unwrap();
+        assert!(fs.rename("/a", "/a/b/x").is_err());
+        fs.rename("/a", "/z").unwrap();
+        let paths: Vec<String> = paths("unit-rename").into_iter().map(|p| p.0).collect();
+        assert_eq!(paths, ["/ab.txt", "/z", "/z/b", "/z/b/c.txt"]);
+        assert_eq!(read("unit-rename", "/z/b/c.txt").unwrap(), b"hi");
+    }
+
+    #[test]
+    fn listing_only_has_direct_children() {
+        let fs = connect("mem://unit-list").unwrap();
+        fs.mkdir("/d").unwrap();
+        remote::write_file(&fs, "/d/f", b"12345").unwrap();
+        remote::write_file(&fs, "/top", b"").unwrap();
+        let root = remote::read_directory(&fs, "/").unwrap();
+        let names: Vec<&str> = root.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["d", "top"]);
+        let mut first = Vec::new();
+        fs.list("/d/", &mut |batch| first.extend(batch)).unwrap();
+        assert_eq!(first[0].name, "..");
+        assert_eq!(first[1].size, Some(5));
+        assert!(fs.remove_dir("/d").is_err());
+        assert!(fs.list("/top", &mut |_| {}).is_err());
+    }
+}
@@ -0,0 +1,1249 @@
+//! Protocol-neutral access to remote filesystems.
+//!
+//! Every remote location is addressed by a host key. A plain key (`k6`,
+//! `user@box`) is an SSH host served over SFTP; a URL-like key
+//! (`sftp://k6`, `ftp://nas.local`, `davs://cloud.example.com`) picks the
+//! backend by its scheme. Backends implement [`RemoteFs`] with a handful of
+//! primitives, and the composite operations (recursive delete, copies with
+//! progress, checksums) are written once here on top of them. Backends with
+//! a shell also answer `exec`; SFTP additionally exposes itself through
+//! `as_sftp` so transfers can take the tar-over-SSH fast paths. SSH hosts
+//! without the SFTP subsystem get the degraded SCP backend instead.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use crate::core::{Checksums, DirEntry, EntryLocation, LinkKind, LinkTarget, TransferProgress};
+
+/// Scheme assumed for host keys without one.
+pub const DEFAULT_SCHEME: &str = "sftp";
+
+pub type SharedRemote = Arc<Mutex<Box<dyn RemoteFs>>>;
+type SessionMap = HashMap<String, SharedRemote>;
+
+/// Metadata for a single remote path.
+pub struct RemoteStat {
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch.
+    pub mtime: Option<u64>,
+}
+
+/// Result of a command run through [`RemoteFs::exec`].
+pub struct ExecOutput {
+    pub status: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: String,
+}
+
+/// Connection details readable without locking the session, which the IO
+/// worker may hold for the length of a transfer.
+pub struct SessionStats {
+    pub user: String,
+    /// Server identification string, e.g. `SSH-2.0-OpenSSH_9.6`.
+    pub banner: Option<String>,
+    pub connected_at: Mutex<Instant>,
+    /// Bytes moved by IO tasks on this host.
+    pub bytes: AtomicU64,
+    /// Times the connection was transparently re-established.
+    pub reconnects: AtomicU64,
+    /// SSH host without the SFTP subsystem; the panel shows the mode and
+    /// SFTP-only actions are refused.
+    pub scp_only: bool,
+}
+
+impl SessionStats {
+    pub fn new(user: &str, banner: Option<String>) -> Arc<Self> {
+        Arc::new(Self::fresh(user, banner, false))
+    }
+
+    /// Stats of an SSH connection that fell back to SCP.
+    pub fn scp_only(user: &str, banner: Option<String>) -> Arc<Self> {
+        Arc::new(Self::fresh(user, banner, true))
+    }
+
+    fn fresh(user: &str, banner: Option<String>, scp_only: bool) -> Self {
+        Self {
+            user: user.to_string(),
+            banner,
+            connected_at: Mutex::new(Instant::now()),
+            bytes: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            scp_only,
+        }
+    }
+
+    pub fn uptime(&self) -> std::time::Duration {
+        self.connected_at
+            .lock()
+            .map(|t| t.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Record a transparent reconnect.
+    pub fn mark_reconnected(&self) {
+        if let Ok(mut t) = self.connected_at.lock() {
+            *t = Instant::now();
+        }
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A connected remote filesystem. All paths are absolute, `/`-separated.
+pub trait RemoteFs: Send {
+    /// Host key this connection is registered under.
+    fn host(&self) -> &str;
+    /// Directory to start browsing in, if the backend has one.
+    fn home_dir(&self) -> Option<&str>;
+    fn stats(&self) -> &Arc<SessionStats>;
+    /// Whether the connection still answers.
+    fn is_alive(&self) -> bool;
+    /// Replace a dead connection in place, so every holder of the shared
+    /// handle picks it up. Statistics carry over.
+    fn reconnect(&mut self) -> Result<(), String>;
+
+    /// Incrementally list a directory, calling `on_batch` for each batch of
+    /// entries. The first batch contains the ".." entry unless `path` is "/".
+    /// Returns `Err((message, is_connection_error))`.
+    fn list(
+        &self,
+        path: &str,
+        on_batch: &mut dyn FnMut(Vec<DirEntry>),
+    ) -> Result<(), (String, bool)>;
+    fn stat(&self, path: &str) -> Result<RemoteStat, String>;
+    fn open_read(&self, path: &str) -> Result<Box<dyn crate::archive::ReadSeek + '_>, String>;
+    /// Create or truncate a file for writing.
+    fn create(&self, path: &str) -> Result<Box<dyn Write + '_>, String>;
+    fn rename(&self, src: &str, dst: &str) -> Result<(), String>;
+    fn remove_file(&self, path: &str) -> Result<(), String>;
+    /// Remove an empty directory.
+    fn remove_dir(&self, path: &str) -> Result<(), String>;
+    fn mkdir(&self, path: &str) -> Result<(), String>;
+    /// Copy a file or tree on the server side. `None` when the backend can't,
+    /// and the caller copies through this process instead.
+    fn copy(&self, _src: &str, _dst: &str) -> Option<Result<(), String>> {
+        None
+    }
+
+    /// Create a link at `path` pointing at `target`. `None` when the
+    /// backend has no links.
+    fn link(&self, _target: &str, _path: &str, _kind: LinkKind) -> Option<Result<(), String>> {
+        None
+    }
+
+    /// Run a shell command on the remote host. `None` when the backend has
+    /// no shell.
+    fn exec(&self, _command: &str) -> Option<Result<ExecOutput, String>> {
+        None
+    }
+    /// The SFTP session behind this backend, for SSH-only fast paths.
+    fn as_sftp(&self) -> Option<&crate::sftp::SftpSession> {
+        None
+    }
+}
+
+/// Opens a connection for a host key whose scheme the connector serves.
+pub type Connector = fn(&str) -> Result<Box<dyn RemoteFs>, String>;
+
+static BACKENDS: OnceLock<Mutex<HashMap<String, Connector>>> = OnceLock::new();
+
+/// The scheme registry, starting out with the built-in backends.
+fn backends() -> &'static Mutex<HashMap<String, Connector>> {
+    BACKENDS.get_or_init(|| {
+        let mut map: HashMap<String, Connector> = HashMap::new();
+        map.insert(DEFAULT_SCHEME.to_string(), connect_ssh);
+        map.insert("ftp".to_string(), connect_ftp);
+        map.insert("ftps".to_string(), connect_ftp);
+        #[cfg(feature = "webdav")]
+        {
+            map.insert("dav".to_string(), connect_webdav);
+            map.insert("davs".to_string(), connect_webdav);
+        }
+        #[cfg(feature = "s3")]
+        map.insert("s3".to_string(), connect_s3);
+        Mutex::new(map)
+    })
+}
+
+/// Serve `scheme://…` host keys with `connector`. Replaces any previous
+/// registration for the scheme, built-in ones included.
+pub fn register_backend(scheme: &str, connector: Connector) {
+    if let Ok(mut map) = backends().lock() {
+        map.insert(scheme.to_string(), connector);
+    }
+}
+
+/// Connect to a host key, dispatching on its scheme.
+pub fn connect(host: &str) -> Result<Box<dyn RemoteFs>, String> {
+    let (scheme, _) = split_scheme(host);
+    let connector = backends()
+        .lock()
+        .ok()
+        .and_then(|map| map.get(scheme).copied());
+    match connector {
+        Some(connector) => connector(host),
+        None => Err(format!("Unsupported remote scheme: {scheme}://")),
+    }
+}
+
+/// SSH hosts: SFTP, or SCP when the server has no SFTP subsystem.
+fn connect_ssh(host: &str) -> Result<Box<dyn RemoteFs>, String> {
+    let (session, user) = crate::sftp::authenticate(host, &crate::sftp::load_ssh_config())?;
+    match session.sftp() {
+        Ok(sftp) => Ok(Box::new(crate::sftp::SftpSession::new(
+            session, sftp, host, &user,
+        ))),
+        Err(e) => {
+            log::warn!("{host}: SFTP subsystem unavailable ({e}), falling back to SCP");
+            Ok(Box::new(crate::scp::ScpSession::new(session, host, &user)))
+        }
+    }
+}
+
+fn connect_ftp(host: &str) -> Result<Box<dyn RemoteFs>, String> {
+    Ok(Box::new(crate::ftp::connect(host)?))
+}
+
+#[cfg(feature = "webdav")]
+fn connect_webdav(host: &str) -> Result<Box<dyn RemoteFs>, String> {
+    Ok(Box::new(crate::webdav::connect(host)?))
+}
+
+#[cfg(feature = "s3")]
+fn connect_s3(host: &str) -> Result<Box<dyn RemoteFs>, String> {
+    Ok(Box::new(crate::s3::connect(host)?))
+}
+
+/// Split a host key into `(scheme, authority)`. Keys without a scheme are
+/// SSH hosts.
+pub fn split_scheme(host: &str) -> (&str, &str) {
+    match host.split_once("://") {
+        Some((scheme, rest)) if is_scheme(scheme) => (scheme, rest),
+        _ => (DEFAULT_SCHEME, host),
+    }
+}
+
+fn is_scheme(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Split a location typed by the user into `(host, path)`. Accepts
+/// `host:path` and `scheme://authority/path`; returns `None` for local paths.
+pub fn parse_location(s: &str) -> Option<(String, String)> {
+    if let Some((scheme, rest)) = s.split_once("://")
+        && is_scheme(scheme)
+    {
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            return None;
+        }
+        return Some((format!("{scheme}://{authority}"), path.to_string()));
+    }
+    let colon = s.find(':')?;
+    if colon == 0 {
+        return None;
+    }
+    let host = &s[..colon];
+    let path = &s[colon + 1..];
+    if host.contains('/') || host.contains('\\') {
+        return None;
+    }
+    Some((host.to_string(), path.to_string()))
+}
+
+/// Inverse of [`parse_location`]: `host:path`, or a URL for scheme hosts.
+pub fn format_location(host: &str, path: &str) -> String {
+    match host.split_once("://") {
+        Some((scheme, _)) if is_scheme(scheme) => {
+            format!("{host}/{}", path.trim_start_matches('/'))
+        }
+        _ => format!("{host}:{path}"),
+    }
+}
+
+/// Synthetic local path standing in for a remote one (`/sftp/<host><path>`),
+/// used for navigation stacks, editor buffers and delete targets. Slashes in
+/// URL-like host keys are escaped so the host stays one path segment.
+pub fn synthetic_path(host: &str, path: &str) -> PathBuf {
+    PathBuf::from(format!("/sftp/{}{path}", escape_host(host)))
+}
+
+/// Inverse of [`synthetic_path`].
+pub fn parse_synthetic_path(path: &Path) -> Option<(String, String)> {
+    let rest = path.to_str()?.strip_prefix("/sftp/")?;
+    let slash = rest.find('/')?;
+    Some((unescape_host(&rest[..slash]), rest[slash..].to_string()))
+}
+
+pub fn escape_host(host: &str) -> String {
+    host.replace('%', "%25").replace('/', "%2F")
+}
+
+pub fn unescape_host(host: &str) -> String {
+    host.replace("%2F", "/").replace("%25", "%")
+}
+
+/// Shell-quote a string with single quotes, escaping any internal single quotes.
+pub fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// `dir/name` without doubling the separator at the root.
+pub fn join_path(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// What a symbolic link at `link` should contain to reach `src`.
+pub fn link_contents(src: &str, link: &str, target: LinkTarget) -> String {
+    match target {
+        LinkTarget::Absolute => src.to_string(),
+        LinkTarget::Relative => {
+            let mut base = components(link);
+            base.pop();
+            let parts = crate::core::relative_components(&base, &components(src), "..");
+            if parts.is_empty() {
+                ".".to_string()
+            } else {
+                parts.join("/")
+            }
+        }
+    }
+}
+
+/// The components of an absolute remote path, with `.` and `..` folded
+/// away.
+fn components(path: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Parent directory of a remote path; the root is its own parent.
+pub fn parent_path(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(pos) => trimmed[..pos].to_string(),
+    }
+}
+
+/// The ".." entry a listing of `path` starts with; `None` at the root.
+pub fn parent_entry(host: &str, path: &str) -> Option<DirEntry> {
+    (!path.is_empty() && path != "/").then(|| DirEntry {
+        name: "..".to_string(),
+        is_dir: true,
+        is_symlink: false,
+        link_target: None,
+        location: EntryLocation::Remote {
+            host: host.to_string(),
+            path: parent_path(path),
+        },
+        size: None,
+        modified: None,
+    })
+}
+
+/// Split `host[:port]`, including bracketed IPv6 literals.
+pub fn split_port(server: &str, default: u16) -> (&str, u16) {
+    if let Some(rest) = server.strip_prefix('[')
+        && let Some((addr, tail)) = rest.split_once(']')
+    {
+        let port = tail.strip_prefix(':').and_then(|p| p.parse().ok());
+        return (addr, port.unwrap_or(default));
+    }
+    match server.rsplit_once(':') {
+        Some((domain, port)) if !domain.contains(':') => match port.parse() {
+            Ok(port) => (domain, port),
+            Err(_) => (server, default),
+        },
+        _ => (server, default),
+    }
+}
+
+/// Shared session registry for global access (e.g. from archive streaming).
+/// Initialized once at startup with the app's shared session map.
+static SHARED_SESSIONS: OnceLock<Arc<Mutex<SessionMap>>> = OnceLock::new();
+
+pub fn init_shared_registry(sessions: Arc<Mutex<SessionMap>>) {
+    let _ = SHARED_SESSIONS.set(sessions);
+}
+
+pub fn get_session(host: &str) -> Option<SharedRemote> {
+    SHARED_SESSIONS.get()?.lock().ok()?.get(host).cloned()
+}
+
+/// Entries of a remote directory, without "..".
+pub fn read_directory(fs: &dyn RemoteFs, path: &str) -> Result<Vec<DirEntry>, String> {
+    let mut all = Vec::new();
+    fs.list(path, &mut |entries| all.extend(entries))
+        .map_err(|(msg, _)| msg)?;
+    all.retain(|e| e.name != "..");
+    Ok(all)
+}
+
+fn entry_path(entry: &DirEntry) -> Option<&str> {
+    match entry.location {
+        EntryLocation::Remote { ref path, .. } => Some(path),
+        _ => None,
+    }
+}
+
+/// Modification time of a remote file, if it exists and the server reports one.
+pub fn mtime(fs: &dyn RemoteFs, path: &str) -> Option<u64> {
+    fs.stat(path).ok().and_then(|s| s.mtime)
+}
+
+/// Read an entire remote file into memory with optional progress reporting.
+pub fn read_file_full(
+    fs: &dyn RemoteFs,
+    path: &str,
+    progress: Option<&TransferProgress>,
+) -> Result<Vec<u8>, String> {
+    if let Some(p) = progress {
+        p.reset(fs.stat(path).ok().and_then(|s| s.size).unwrap_or(0));
+    }
+    let mut file = fs.open_read(path)?;
+    let mut buf = Vec::new();
+    pump(&mut file, &mut buf, None, progress, "read", "buffer")?;
+    Ok(buf)
+}
+
+/// Write bytes to a remote file (create or overwrite).
+pub fn write_file(fs: &dyn RemoteFs, path: &str, contents: &[u8]) -> Result<(), String> {
+    let mut file = fs.create(path)?;
+    file.write_all(contents)
+        .and_then(|()| file.flush())
+        .map_err(|e| format!("write {path}: {e}"))
+}
+
+/// Create a directory unless it already exists.
+fn ensure_dir(fs: &dyn RemoteFs, path: &str) -> Result<(), String> {
+    match fs.mkdir(path) {
+        Ok(()) => Ok(()),
+        Err(_) if fs.stat(path).is_ok_and(|s| s.is_dir) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Recursively delete a remote path (file or directory).
+/// Reports each deleted item via `progress.add_item()` when provided.
+pub fn recursive_delete(
+    fs: &dyn RemoteFs,
+    path: &str,
+    is_dir: bool,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    if is_dir {
+        for child in read_directory(fs, path)? {
+            if let Some(child_path) = entry_path(&child) {
+                recursive_delete(fs, child_path, child.is_dir, progress)?;
+            }
+        }
+        fs.remove_dir(path)?;
+    } else {
+        fs.remove_file(path)?;
+    }
+    if let Some(p) = progress {
+        p.add_item();
+    }
+    Ok(())
+}
+
+/// Recursively copy a file or directory within the same remote host.
+pub fn recursive_copy(
+    fs: &dyn RemoteFs,
+    src_path: &str,
+    dst_dir: &str,
+    name: &str,
+) -> Result<(), String> {
+    let dst_path = join_path(dst_dir, name);
+    if let Some(result) = fs.copy(src_path, &dst_path) {
+        return result;
+    }
+    if fs.stat(src_path)?.is_dir {
+        fs.mkdir(&dst_path)?;
+        for child in read_directory(fs, src_path)? {
+            if let Some(child_path) = entry_path(&child) {
+                recursive_copy(fs, child_path, &dst_path, &child.name)?;
+            }
+        }
+    } else {
+        let mut src = fs.open_read(src_path)?;
+        let mut dst = fs.create(&dst_path)?;
+        pump(&mut src, &mut dst, None, None, "read", "write")?;
+    }
+    Ok(())
+}
+
+/// Total byte size of a remote path. Uses `du` when the backend has a shell
+/// (one round-trip instead of O(dirs)), otherwise walks the tree.
+///
+/// Strategy (in order):
+///   1. `du -sb`  — Linux/GNU coreutils: exact bytes.
+///   2. `du -sk`  — macOS/BSD POSIX du: 1 KiB blocks → multiply by 1024.
+///   3. Listing walk — backends without a shell.
+pub fn count_bytes(fs: &dyn RemoteFs, path: &str) -> u64 {
+    let quoted = sh_quote(path);
+    for (cmd, scale) in [
+        (format!("du -sb {quoted} 2>/dev/null"), 1u64),
+        (format!("du -sk {quoted} 2>/dev/null"), 1024u64),
+    ] {
+        match fs.exec(&cmd) {
+            None => return count_bytes_walk(fs, path),
+            Some(Ok(out)) if out.status == 0 => {
+                // du output: "12345\t/path/name\n" — first token is the numeric value
+                if let Some(n) = String::from_utf8_lossy(&out.stdout)
+                    .split_whitespace()
+                    .next()
+                    .and_then(|s| s.parse::<u64>().ok())
+                {
+                    return n * scale;
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    0
+}
+
+/// Bytes available to the user on the volume holding a remote path. Asks
+/// the SFTP `statvfs` extension first, then `df -Pk` when the backend has
+/// a shell; `None` when neither answers.
+pub fn free_space(fs: &dyn RemoteFs, path: &str) -> Option<u64> {
+    if let Some(sftp) = fs.as_sftp()
+        && let Some(free) = crate::sftp::free_space(&sftp.sftp, path)
+    {
+        return Some(free);
+    }
+    let out = fs
+        .exec(&format!("df -Pk -- {} 2>/dev/null", sh_quote(path)))?
+        .ok()?;
+    parse_df_available(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// The "Available" column of `df -Pk` output, in bytes. Counted from the
+/// capacity column, since file system and mount names may contain spaces.
+fn parse_df_available(out: &str) -> Option<u64> {
+    let line = out.lines().skip(1).find(|l| !l.trim().is_empty())?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let capacity = fields.iter().position(|f| f.ends_with('%'))?;
+    let kib: u64 = fields.get(capacity.checked_sub(1)?)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn count_bytes_walk(fs: &dyn RemoteFs, path: &str) -> u64 {
+    match fs.stat(path) {
+        Ok(stat) if stat.is_dir => read_directory(fs, path)
+            .unwrap_or_default()
+            .iter()
+            .map(|child| match entry_path(child) {
+                Some(child_path) if child.is_dir => count_bytes_walk(fs, child_path),
+                _ => child.size.unwrap_or(0),
+            })
+            .sum(),
+        Ok(stat) => stat.size.unwrap_or(0),
+        Err(_) => 0,
+    }
+}
+
+/// Copy bytes until EOF, honoring `cancel` and reporting to `progress`.
+/// The labels name each side in error messages ("read remote: …").
+fn pump(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&TransferProgress>,
+    read_label: &str,
+    write_label: &str,
+) -> Result<u64, String> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut written: u64 = 0;
+    loop {
+        if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return Err("Cancelled".to_string());
+        }
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                writer
+                    .write_all(&buf[..n])
+                    .map_err(|e| format!("{write_label}: {e}"))?;
+                written += n as u64;
+                if let Some(p) = progress {
+                    p.add(n as u64);
+                    p.throttle(n as u64, cancel);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("{read_label}: {e}")),
+        }
+    }
+    writer.flush().map_err(|e| format!("{write_label}: {e}"))?;
+    Ok(written)
+}
+
+/// Copy a remote file to a local path, resetting `progress` to its size.
+pub fn copy_to_local(
+    fs: &dyn RemoteFs,
+    remote_path: &str,
+    local_dst: &Path,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    let expected_size = fs.stat(remote_path).ok().and_then(|s| s.size);
+    if let Some(p) = progress {
+        p.reset(expected_size.unwrap_or(0));
+    }
+    download(fs, remote_path, local_dst, expected_size, cancel, progress)
+}
+
+fn download(
+    fs: &dyn RemoteFs,
+    remote_path: &str,
+    local_dst: &Path,
+    expected_size: Option<u64>,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    // Inner closure: any Err returned here causes the destination file to
+    // be removed before the error propagates. Keeps partial / zero-size
+    // artifacts from accumulating when a transfer fails or is cancelled.
+    let inner = || -> Result<(), String> {
+        let mut remote_file = fs
+            .open_read(remote_path)
+            .map_err(|e| format!("open remote {remote_path}: {e}"))?;
+        let mut local_file = std::fs::File::create(local_dst)
+            .map_err(|e| format!("create local {}: {e}", local_dst.display()))?;
+        let written = pump(
+            &mut remote_file,
+            &mut local_file,
+            cancel,
+            progress,
+            "read remote",
+            "write local",
+        )?;
+        // Flush and verify final size against the source.
+        local_file
+            .sync_all()
+            .map_err(|e| format!("sync local: {e}"))?;
+        if let Some(expected) = expected_size
+            && written != expected
+        {
+            return Err(format!(
+                "size mismatch: wrote {written} bytes, expected {expected}"
+            ));
+        }
+        Ok(())
+    };
+    match inner() {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(local_dst);
+            Err(e)
+        }
+    }
+}
+
+/// Copy a local file to a remote path, resetting `progress` to its size.
+pub fn copy_from_local(
+    fs: &dyn RemoteFs,
+    local_src: &Path,
+    remote_path: &str,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    let expected_size = std::fs::metadata(local_src).map(|m| m.len()).ok();
+    if let Some(p) = progress {
+        p.reset(expected_size.unwrap_or(0));
+    }
+    upload(fs, local_src, remote_path, expected_size, cancel, progress)
+}
+
+fn upload(
+    fs: &dyn RemoteFs,
+    local_src: &Path,
+    remote_path: &str,
+    expected_size: Option<u64>,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    // Inner closure pattern (mirror of `download`): any Err triggers
+    // cleanup of the partial remote artifact before the error propagates.
+    let inner = || -> Result<(), String> {
+        let mut local_file = std::fs::File::open(local_src)
+            .map_err(|e| format!("open local {}: {e}", local_src.display()))?;
+        let mut remote_file = fs
+            .create(remote_path)
+            .map_err(|e| format!("create remote {remote_path}: {e}"))?;
+        let written = pump(
+            &mut local_file,
+            &mut remote_file,
+            cancel,
+            progress,
+            "read local",
+            "write remote",
+        )?;
+        // Close the remote handle by dropping so the server flushes, then
+        // stat to verify the on-disk size matches what we sent.
+        drop(remote_file);
+        if let Some(expected) = expected_size {
+            if written != expected {
+                return Err(format!(
+                    "size mismatch: read {written} bytes, expected {expected}"
+                ));
+            }
+            // Cross-check the destination — guards against a server-side
+            // short-write that the protocol layer didn't already surface as
+            // a write error.
+            let actual = fs
+                .stat(remote_path)
+                .map_err(|e| format!("stat remote after copy: {e}"))?
+                .size
+                .unwrap_or(0);
+            if actual != expected {
+                return Err(format!(
+                    "size mismatch on remote: {actual} bytes, expected {expected}"
+                ));
+            }
+        }
+        Ok(())
+    };
+    match inner() {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs.remove_file(remote_path);
+            Err(e)
+        }
+    }
+}
+
+/// Copy a remote directory tree to `dst_dir/name` file by file. Backends
+/// with a shell are better served by the tar stream in `sftp`; the caller
+/// resets `progress` to the tree size.
+pub fn copy_dir_to_local(
+    fs: &dyn RemoteFs,
+    remote_path: &str,
+    dst_dir: &Path,
+    name: &str,
+    cancel: &AtomicBool,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    let local = dst_dir.join(name);
+    std::fs::create_dir_all(&local)
+        .map_err(|e| format!("create local {}: {e}", local.display()))?;
+    for child in read_directory(fs, remote_path)? {
+        let Some(child_path) = entry_path(&child) else {
+            continue;
+        };
+        if child.is_dir {
+            copy_dir_to_local(fs, child_path, &local, &child.name, cancel, progress)?;
+        } else {
+            let dst = local.join(&child.name);
+            download(fs, child_path, &dst, child.size, Some(cancel), progress)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy a local directory tree into `remote_dir/name` file by file.
+/// Symlinks are skipped. The caller resets `progress` to the tree size.
+pub fn copy_dir_from_local(
+    fs: &dyn RemoteFs,
+    src: &Path,
+    remote_dir: &str,
+    name: &str,
+    cancel: &AtomicBool,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    let remote = join_path(remote_dir, name);
+    ensure_dir(fs, &remote)?;
+    let entries =
+        std::fs::read_dir(src).map_err(|e| format!("read local {}: {e}", src.display()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("read local {}: {e}", src.display()))?;
+        let file_type = entry
+            .file_type()
+            .map_err(|e| format!("stat local {}: {e}", entry.path().display()))?;
+        if file_type.is_dir() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            copy_dir_from_local(fs, &entry.path(), &remote, &name, cancel, progress)?;
+        } else if file_type.is_file() {
+            let dst = join_path(&remote, &entry.file_name().to_string_lossy());
+            let size = entry.metadata().ok().map(|m| m.len());
+            upload(fs, &entry.path(), &dst, size, Some(cancel), progress)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy a file or tree between two connections by relaying through this
+/// process. Used when the tar stream between two SSH hosts isn't available.
+pub fn copy_between(
+    src_fs: &dyn RemoteFs,
+    src_path: &str,
+    dst_fs: &dyn RemoteFs,
+    dst_dir: &str,
+    name: &str,
+    cancel: &AtomicBool,
+    progress: Option<&TransferProgress>,
+) -> Result<(), String> {
+    let dst_path = join_path(dst_dir, name);
+    if src_fs.stat(src_path)?.is_dir {
+        ensure_dir(dst_fs, &dst_path)?;
+        for child in read_directory(src_fs, src_path)? {
+            if let Some(child_path) = entry_path(&child) {
+                copy_between(
+                    src_fs,
+                    child_path,
+                    dst_fs,
+                    &dst_path,
+                    &child.name,
+                    cancel,
+                    progress,
+                )?;
+            }
+        }
+        return Ok(());
+    }
+    let mut reader = src_fs.open_read(src_path)?;
+    let mut writer = dst_fs.create(&dst_path)?;
+    let result = pump(
+        &mut reader,
+        &mut writer,
+        Some(cancel),
+        progress,
+        "read source",
+        "write destination",
+    );
+    drop(writer);
+    if result.is_err() {
+        let _ = dst_fs.remove_file(&dst_path);
+    }
+    result.map(|_| ())
+}
+
+/// Hash a remote file, or every regular file under a remote directory, for
+/// post-transfer verification. Keys follow `core::Checksums`.
+///
+/// Strategy (in order):
+///   1. `sha256sum` via exec — one round-trip, hashing stays on the server.
+///   2. `shasum -a 256` via exec — macOS/BSD.
+///   3. Read every file back and hash locally.
+pub fn sha256(fs: &dyn RemoteFs, path: &str, is_dir: bool) -> Result<Checksums, String> {
+    let quoted = sh_quote(path);
+    for tool in ["sha256sum", "shasum -a 256"] {
+        // Feeding a single file on stdin sidesteps name escaping entirely.
+        let cmd = if is_dir {
+            format!("cd {quoted} && find . -type f -exec {tool} {{}} +")
+        } else {
+            format!("{tool} < {quoted}")
+        };
+        match fs.exec(&cmd) {
+            None => break,
+            Some(Ok(out)) if out.status == 0 => {
+                if let Some(sums) =
+                    parse_sha256_output(&String::from_utf8_lossy(&out.stdout), is_dir)
+                {
+                    return Ok(sums);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    let mut sums = Checksums::new();
+    sha256_by_reading(fs, path, "", is_dir, &mut sums)?;
+    Ok(sums)
+}
+
+fn parse_sha256_output(out: &str, is_dir: bool) -> Option<Checksums> {
+    let mut sums = Checksums::new();
+    for line in out.lines() {
+        // Output: "<hex>  <name>" (or "<hex> *<name>" in binary mode).
+        // A leading backslash marks an escaped name; let the read-back path
+        // handle those rather than unescaping here.
+        let (hex, name) = line.split_once(' ')?;
+        if hex.len() != 64 || hex.starts_with('\\') {
+            return None;
+        }
+        let name = name.strip_prefix(' ').or_else(|| name.strip_prefix('*'))?;
+        let key = if is_dir {
+            name.strip_prefix("./").unwrap_or(name).to_string()
+        } else {
+            String::new()
+        };
+        sums.insert(key, hex.to_ascii_lowercase());
+    }
+    (is_dir || sums.len() == 1).then_some(sums)
+}
+
+fn sha256_by_reading(
+    fs: &dyn RemoteFs,
+    path: &str,
+    prefix: &str,
+    is_dir: bool,
+    sums: &mut Checksums,
+) -> Result<(), String> {
+    if !is_dir {
+        let file = io::BufReader::with_capacity(1 << 20, fs.open_read(path)?);
+        let hex = crate::core::sha256_hex(file).map_err(|e| format!("read {path}: {e}"))?;
+        sums.insert(prefix.to_string(), hex);
+        return Ok(());
+    }
+    for child in read_directory(fs, path)? {
+        let Some(child_path) = entry_path(&child) else {
+            continue;
+        };
+        let rel = if prefix.is_empty() {
+            child.name.clone()
+        } else {
+            format!("{prefix}/{}", child.name)
+        };
+        // Listings report the link itself, matching `find -type f`.
+        if !child.is_symlink {
+            sha256_by_reading(fs, child_path, &rel, child.is_dir, sums)?;
+        }
+    }
+    Ok(())
+}
+
+/// Consecutive dropped responses a [`RangedReader`] resumes from.
+const RESUME_ATTEMPTS: u32 = 3;
+/// Forward seeks up to this far read through the open response instead of
+/// requesting a new range.
+const SEEK_SKIP_LIMIT: u64 = 256 * 1024;
+
+/// Seekable download for backends that fetch byte ranges (HTTP `Range`).
+/// `open(offset)` returns a body positioned at `offset`, or `None` past the
+/// end; it is called on the first read, after seeks, and to resume when a
+/// body breaks off early.
+pub struct RangedReader<R, F> {
+    open: F,
+    body: Option<R>,
+    pos: u64,
+    size: Option<u64>,
+}
+
+impl<R: Read, F: FnMut(u64) -> io::Result<Option<R>>> RangedReader<R, F> {
+    pub fn new(size: Option<u64>, open: F) -> Self {
+        Self {
+            open,
+            body: None,
+            pos: 0,
+            size,
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u64) -> io::Result<Option<R>>> Read for RangedReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut failures = 0;
+        loop {
+            let Some(ref mut body) = self.body else {
+                if self.size.is_some_and(|size| self.pos >= size) {
+                    return Ok(0);
+                }
+                match (self.open)(self.pos)? {
+                    Some(body) => self.body = Some(body),
+                    None => return Ok(0),
+                }
+                continue;
+            };
+            let error = match body.read(buf) {
+                // A body cut short can look like a normal end of data.
+                Ok(0) if self.size.is_none_or(|size| self.pos >= size) => return Ok(0),
+                Ok(0) => io::Error::from(io::ErrorKind::UnexpectedEof),
+                Ok(n) => {
+                    self.pos += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => e,
+            };
+            self.body = None;
+            failures += 1;
+            if failures >= RESUME_ATTEMPTS {
+                return Err(error);
+            }
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u64) -> io::Result<Option<R>>> Seek for RangedReader<R, F> {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let target = match from {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.size.and_then(|size| size.checked_add_signed(delta)),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start or from an unknown end",
+            )
+        })?;
+        if self.body.is_some() && target > self.pos && target - self.pos <= SEEK_SKIP_LIMIT {
+            let skip = target - self.pos;
+            io::copy(&mut self.by_ref().take(skip), &mut io::sink())?;
+        }
+        if target != self.pos {
+            self.body = None;
+            self.pos = target;
+        }
+        Ok(target)
+    }
+}
+
+/// One `machine` block of a netrc file; `machine` is `None` for `default`.
+pub struct NetrcEntry {
+    pub machine: Option<String>,
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Parse netrc syntax: `machine`/`default` blocks with `login` and
+/// `password` tokens. `macdef` bodies and `#` comment lines are skipped.
+pub fn parse_netrc(content: &str) -> Vec<NetrcEntry> {
+    let mut entries: Vec<NetrcEntry> = Vec::new();
+    let mut in_macdef = false;
+    let mut pending: Option<&str> = None;
+    for line in content.lines() {
+        if in_macdef {
+            in_macdef = !line.trim().is_empty();
+            continue;
+        }
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            match pending.take() {
+                Some("machine") => entries.push(NetrcEntry {
+                    machine: Some(token.to_string()),
+                    login: None,
+                    password: None,
+                }),
+                Some("login") => {
+                    if let Some(entry) = entries.last_mut() {
+                        entry.login = Some(token.to_string());
+                    }
+                }
+                Some("password") => {
+                    if let Some(entry) = entries.last_mut() {
+                        entry.password = Some(token.to_string());
+                    }
+                }
+                Some(_) => {}
+                None => match token {
+                    "default" => entries.push(NetrcEntry {
+                        machine: None,
+                        login: None,
+                        password: None,
+                    }),
+                    "macdef" => {
+                        in_macdef = true;
+                        break;
+                    }
+                    _ => pending = Some(token),
+                },
+            }
+        }
+    }
+    entries
+}
+
+/// `(login, password)` for `host` from netrc entries. With `user` given,
+/// only entries for that login (or without one) match. A `default` entry
+/// applies when no `machine` does.
+pub fn netrc_credentials(
+    entries: &[NetrcEntry],
+    host: &str,
+    user: Option<&str>,
+) -> Option<(String, String)> {
+    let user_matches = |entry: &NetrcEntry| match (user, entry.login.as_deref()) {
+        (Some(user), Some(login)) => user == login,
+        _ => true,
+    };
+    let entry = entries
+        .iter()
+        .find(|e| {
+            e.machine
+                .as_deref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(host))
+                && user_matches(e)
+        })
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|e| e.machine.is_none() && user_matches(e))
+        })?;
+    let login = entry.login.as_deref().or(user)?;
+    Some((
+        login.to_string(),
+        entry.password.clone().unwrap_or_default(),
+    ))
+}
+
+/// Entries of `$NETRC`, or `~/.netrc` (`~/_netrc` on Windows).
+pub fn load_netrc() -> Vec<NetrcEntry> {
+    let path = match std::env::var_os("NETRC") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match crate::sftp::home_dir() {
+            Some(home) if cfg!(windows) => home.join("_netrc"),
+            Some(home) => home.join(".netrc"),
+            None => return Vec::new(),
+        },
+    };
+    std::fs::read_to_string(path)
+        .map(|content| parse_netrc(&content))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn df_output_parses_available_kib() {
+        let out = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                   /dev/nvme0n1p2   479151816 301235408 153500472      67% /\n";
+        assert_eq!(parse_df_available(out), Some(153_500_472 * 1024));
+        let spaced = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                      //nas/my share 1000 400 600 40% /mnt/my share\n";
+        assert_eq!(parse_df_available(spaced), Some(600 * 1024));
+        assert_eq!(parse_df_available("df: /gone: No such file\n"), None);
+    }
+
+    #[test]
+    fn relative_links_climb_to_the_common_parent() {
+        let cases = [
+            ("/srv/app/releases/v2", "/srv/app/current", "releases/v2"),
+            (
+                "/etc/shared.conf",
+                "/home/me/.config/app.conf",
+                "../../../etc/shared.conf",
+            ),
+            ("/srv/a.txt", "/srv/b.txt", "a.txt"),
+            ("/srv", "/srv/data/link", ".."),
+            ("/srv/./app/../a.txt", "/srv/data/../link", "a.txt"),
+            ("/srv/a.txt", "/srv/data/./sub/../link", "../a.txt"),
+        ];
+        for (src, link, expected) in cases {
+            assert_eq!(link_contents(src, link, LinkTarget::Relative), expected);
+        }
+        assert_eq!(
+            link_contents("/srv/a.txt", "/tmp/b", LinkTarget::Absolute),
+            "/srv/a.txt"
+        );
+    }
+
+    #[test]
+    fn host_keys_without_scheme_are_sftp() {
+        assert_eq!(split_scheme("k6"), ("sftp", "k6"));
+        assert_eq!(
+            split_scheme("dav://files.example.com"),
+            ("dav", "files.example.com")
+        );
+        assert_eq!(split_scheme("weird:host"), ("sftp", "weird:host"));
+    }
+
+    #[test]
+    fn locations_parse_both_forms() {
+        assert_eq!(
+            parse_location("k6:/home/user"),
+            Some(("k6".to_string(), "/home/user".to_string()))
+        );
+        assert_eq!(
+            parse_location("s3://bucket/logs/2024"),
+            Some(("s3://bucket".to_string(), "/logs/2024".to_string()))
+        );
+        assert_eq!(parse_location("/usr/share"), None);
+        assert_eq!(parse_location("file:///tmp"), None);
+        for (host, path) in [
+            ("k6", "/srv"),
+            ("davs://cloud.example.com", "/remote.php/dav"),
+        ] {
+            assert_eq!(
+                parse_location(&format_location(host, path)),
+                Some((host.to_string(), path.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn synthetic_paths_round_trip_url_hosts() {
+        for host in ["k6", "dav://files.example.com", "odd%2Fname"] {
+            let synthetic = synthetic_path(host, "/a/b.txt");
+            assert_eq!(
+                parse_synthetic_path(&synthetic),
+                Some((host.to_string(), "/a/b.txt".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn sha256_output_parses_tree_and_single_file() {
+        let hex = "8ed3f6ad685b959ead7022518e1af76cd816f8e8ec7ccdda1ed4018e8f2223f8";
+        let tree =
+            parse_sha256_output(&format!("{hex}  ./a.txt\n{hex} *./sub/b.txt\n"), true).unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree["sub/b.txt"], hex);
+        let single = parse_sha256_output(&format!("{hex}  -\n"), false).unwrap();
+        assert_eq!(single[""], hex);
+        assert!(parse_sha256_output(&format!("\\{hex}  ./a\\nb\n"), true).is_none());
+    }
+
+    #[test]
+    fn ports_split_from_hosts() {
+        assert_eq!(split_port("nas.local", 21), ("nas.local", 21));
+        assert_eq!(split_port("nas.local:2121", 21), ("nas.local", 2121));
+        assert_eq!(split_port("[::1]:8443", 443), ("::1", 8443));
+        assert_eq!(split_port("[::1]", 443), ("::1", 443));
+    }
+
+    #[test]
+    fn netrc_matches_machine_then_default() {
+        let entries = parse_netrc(
+            "# vendor boxes\n\
+             machine nas.local login backup password s3cret\n\
+             macdef init\ncd /pub\n\n\
+             machine cam.local\n  login admin\n  password admin\n\
+             default login anonymous password guest@\n",
+        );
+        assert_eq!(
+            netrc_credentials(&entries, "NAS.local", None),
+            Some(("backup".to_string(), "s3cret".to_string()))
+        );
+        assert_eq!(
+            netrc_credentials(&entries, "cam.local", Some("admin")),
+            Some(("admin".to_string(), "admin".to_string()))
+        );
+        assert_eq!(
+            netrc_credentials(&entries, "other", None),
+            Some(("anonymous".to_string(), "guest@".to_string()))
+        );
+        assert_eq!(netrc_credentials(&entries, "nas.local", Some("root")), None);
+    }
+}
@@ -0,0 +1,435 @@
+//! Batch rename (Shift+F6 with marked entries): each new name comes from a
+//! template, then an optional search and replace, then a case change.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use crate::core::civil_from_epoch;
+
+/// Letter case applied to the whole new name, last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Case {
+    #[default]
+    Keep,
+    Lower,
+    Upper,
+    /// First letter of every word upper, the rest lower.
+    Title,
+}
+
+impl Case {
+    pub const ALL: [Self; 4] = [Self::Keep, Self::Lower, Self::Upper, Self::Title];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Case::Keep => "keep",
+            Case::Lower => "lower",
+            Case::Upper => "UPPER",
+            Case::Title => "Title",
+        }
+    }
+
+    fn apply(self, name: &str) -> String {
+        match self {
+            Case::Keep => name.to_string(),
+            Case::Lower => name.to_lowercase(),
+            Case::Upper => name.to_uppercase(),
+            Case::Title => {
+                let mut out = String::with_capacity(name.len());
+                let mut word_start = true;
+                for c in name.chars() {
+                    if word_start {
+                        out.extend(c.to_uppercase());
+                    } else {
+                        out.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric();
+                }
+                out
+            }
+        }
+    }
+}
+
+/// A date and time in the form they take in names: `YYYY-MM-DD`, `HH-MM-SS`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stamp {
+    pub date: String,
+    pub time: String,
+}
+
+impl Stamp {
+    /// UTC, like the dates in the panels.
+    pub fn from_epoch(secs: u64) -> Self {
+        let (y, m, d, hours, minutes, seconds) = civil_from_epoch(secs);
+        Stamp {
+            date: format!("{y:04}-{m:02}-{d:02}"),
+            time: format!("{hours:02}-{minutes:02}-{seconds:02}"),
+        }
+    }
+
+    /// EXIF's `YYYY:MM:DD HH:MM:SS`, camera local time.
+    pub fn parse_exif(value: &str) -> Option<Self> {
+        let (date, time) = value.trim().split_once(' ')?;
+        let valid = |part: &str| {
+            let fields: Vec<&str> = part.split(':').collect();
+            fields.len() == 3
+                && fields
+                    .iter()
+                    .all(|f| !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit()))
+        };
+        if !valid(date) || !valid(time) {
+            return None;
+        }
+        Some(Stamp {
+            date: date.replace(':', "-"),
+            time: time.replace(':', "-"),
+        })
+    }
+}
+
+/// When a photo was taken, from its EXIF data.
+pub fn exif_taken(path: &Path) -> Option<Stamp> {
+    let file = std::fs::File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::BufReader::new(file))
+        .ok()?;
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+    match field.value {
+        exif::Value::Ascii(ref values) => {
+            Stamp::parse_exif(std::str::from_utf8(values.first()?).ok()?)
+        }
+        _ => None,
+    }
+}
+
+/// One entry to rename.
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub name: String,
+    pub is_dir: bool,
+    pub modified: Option<u64>,
+    /// When the photo was taken; `[XD]`/`[XT]` fall back to `modified`.
+    pub taken: Option<Stamp>,
+}
+
+/// How to build the new names.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    /// Template of the part before the extension.
+    pub name: String,
+    /// Template of the extension; an empty result drops the dot too.
+    pub ext: String,
+    pub search: String,
+    pub replace: String,
+    /// `search` is a regular expression and `replace` may use `$1`.
+    pub regex: bool,
+    pub case: Case,
+    pub counter_start: u64,
+    pub counter_step: u64,
+    /// Zero-padded width of `[C]`.
+    pub counter_digits: usize,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule {
+            name: "[N]".to_string(),
+            ext: "[E]".to_string(),
+            search: String::new(),
+            replace: String::new(),
+            regex: false,
+            case: Case::Keep,
+            counter_start: 1,
+            counter_step: 1,
+            counter_digits: 1,
+        }
+    }
+}
+
+/// Template fields, for the dialog's hint line.
+pub const FIELDS: &str = "[N] name · [E] extension · [C] counter · [D] [T] modified date, time · [XD] [XT] photo taken (EXIF)";
+
+/// What happens to one entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Unchanged,
+    Renamed,
+    /// Empty, `.`/`..`, or containing a path separator.
+    Invalid,
+    /// Another entry would get the same name.
+    Duplicate,
+    /// Another entry in the directory already has the name.
+    Exists,
+}
+
+impl Status {
+    pub fn is_problem(self) -> bool {
+        match self {
+            Status::Unchanged | Status::Renamed => false,
+            Status::Invalid | Status::Duplicate | Status::Exists => true,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Unchanged => "unchanged",
+            Status::Renamed => "",
+            Status::Invalid => "invalid name",
+            Status::Duplicate => "duplicate",
+            Status::Exists => "already exists",
+        }
+    }
+}
+
+/// Stem and extension. Directories and dotfiles like `.bashrc` have no
+/// extension.
+fn split_name(name: &str, is_dir: bool) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 && !is_dir => (&name[..pos], &name[pos + 1..]),
+        _ => (name, ""),
+    }
+}
+
+struct Fields<'a> {
+    stem: &'a str,
+    ext: &'a str,
+    counter: String,
+    modified: Option<Stamp>,
+    taken: Option<&'a Stamp>,
+}
+
+fn expand(template: &str, fields: &Fields) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('[') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let close = after
+            .find(']')
+            .ok_or_else(|| format!("unclosed [ in \"{template}\""))?;
+        let taken = fields.taken.or(fields.modified.as_ref());
+        match &after[..close] {
+            "N" => out.push_str(fields.stem),
+            "E" => out.push_str(fields.ext),
+            "C" => out.push_str(&fields.counter),
+            "D" => out.extend(fields.modified.as_ref().map(|s| s.date.as_str())),
+            "T" => out.extend(fields.modified.as_ref().map(|s| s.time.as_str())),
+            "XD" => out.extend(taken.map(|s| s.date.as_str())),
+            "XT" => out.extend(taken.map(|s| s.time.as_str())),
+            other => return Err(format!("unknown field [{other}]")),
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// New names for `sources`, in order; the counter goes up by one step per
+/// entry. Fails on a template or regular expression that doesn't parse.
+pub fn new_names(rule: &Rule, sources: &[Source]) -> Result<Vec<String>, String> {
+    let search = match rule.search.as_str() {
+        "" => None,
+        pattern if rule.regex => Some(regex::Regex::new(pattern).map_err(|e| e.to_string())?),
+        pattern => Some(regex::Regex::new(&regex::escape(pattern)).map_err(|e| e.to_string())?),
+    };
+    let mut counter = rule.counter_start;
+    let mut names = Vec::with_capacity(sources.len());
+    for source in sources {
+        let (stem, ext) = split_name(&source.name, source.is_dir);
+        let fields = Fields {
+            stem,
+            ext,
+            counter: format!("{counter:0width$}", width = rule.counter_digits),
+            modified: source.modified.map(Stamp::from_epoch),
+            taken: source.taken.as_ref(),
+        };
+        counter = counter.saturating_add(rule.counter_step);
+        let stem = expand(&rule.name, &fields)?;
+        let ext = expand(&rule.ext, &fields)?;
+        let mut name = if ext.is_empty() {
+            stem
+        } else {
+            format!("{stem}.{ext}")
+        };
+        if let Some(ref search) = search {
+            name = if rule.regex {
+                search.replace_all(&name, rule.replace.as_str())
+            } else {
+                search.replace_all(&name, regex::NoExpand(&rule.replace))
+            }
+            .into_owned();
+        }
+        names.push(rule.case.apply(&name));
+    }
+    Ok(names)
+}
+
+/// What renaming each of `sources` to `names` would do, given every name
+/// in the directory (`existing`, the sources' own included). Entries that
+/// keep their name are never in the way of others: renames run one by one.
+pub fn check(sources: &[Source], names: &[String], existing: &HashSet<String>) -> Vec<Status> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in names {
+        *counts.entry(name.as_str()).or_default() += 1;
+    }
+    sources
+        .iter()
+        .zip(names)
+        .map(|(source, name)| {
+            if *name == source.name {
+                Status::Unchanged
+            } else if name.is_empty()
+                || name == "."
+                || name == ".."
+                || name.contains('/')
+                || name.contains('\\')
+            {
+                Status::Invalid
+            } else if counts[name.as_str()] > 1 {
+                Status::Duplicate
+            } else if existing.contains(name) {
+                Status::Exists
+            } else {
+                Status::Renamed
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, modified: u64) -> Source {
+        Source {
+            name: name.to_string(),
+            is_dir: false,
+            modified: Some(modified),
+            taken: None,
+        }
+    }
+
+    #[test]
+    fn templates_fill_in_fields_and_counters() {
+        let mut photo = file("IMG_0042.JPG", 0);
+        photo.taken = Stamp::parse_exif("2024:07:15 18:30:05");
+        let sources = [
+            photo,
+            file("notes.tar.gz", 86_400 + 3_661),
+            Source {
+                name: "v1.2".to_string(),
+                is_dir: true,
+                modified: None,
+                taken: None,
+            },
+        ];
+        let rule = Rule {
+            name: "[XD] [XT] [C]".to_string(),
+            ext: "[E]".to_string(),
+            case: Case::Lower,
+            counter_start: 8,
+            counter_step: 2,
+            counter_digits: 3,
+            ..Rule::default()
+        };
+        assert_eq!(
+            new_names(&rule, &sources).unwrap(),
+            [
+                "2024-07-15 18-30-05 008.jpg",
+                "1970-01-02 01-01-01 010.gz",
+                "  012",
+            ]
+        );
+
+        let rule = Rule {
+            name: "[N]_[D]".to_string(),
+            ext: String::new(),
+            ..Rule::default()
+        };
+        assert_eq!(
+            new_names(&rule, &sources[1..2]).unwrap(),
+            ["notes.tar_1970-01-02"]
+        );
+        for bad in ["[Q]", "[N"] {
+            let rule = Rule {
+                name: bad.to_string(),
+                ..Rule::default()
+            };
+            assert!(new_names(&rule, &sources).is_err());
+        }
+    }
+
+    #[test]
+    fn search_replaces_plain_text_or_patterns() {
+        let sources = [file("shard-0007.bin", 0), file("shard-0012.bin", 0)];
+        let rule = Rule {
+            search: r"shard-0*(\d+)".to_string(),
+            replace: "part_$1".to_string(),
+            regex: true,
+            ..Rule::default()
+        };
+        assert_eq!(
+            new_names(&rule, &sources).unwrap(),
+            ["part_7.bin", "part_12.bin"]
+        );
+        // Without `regex`, both sides are taken literally.
+        let rule = Rule {
+            search: "-00".to_string(),
+            replace: "$1.".to_string(),
+            case: Case::Title,
+            ..Rule::default()
+        };
+        assert_eq!(
+            new_names(&rule, &sources).unwrap(),
+            ["Shard$1.07.Bin", "Shard$1.12.Bin"]
+        );
+        let rule = Rule {
+            search: "(".to_string(),
+            regex: true,
+            ..Rule::default()
+        };
+        assert!(new_names(&rule, &sources).is_err());
+    }
+
+    #[test]
+    fn check_flags_clashes_before_anything_runs() {
+        let sources = [
+            file("a.txt", 0),
+            file("b.txt", 0),
+            file("c.txt", 0),
+            file("d.txt", 0),
+            file("e.txt", 0),
+        ];
+        let existing: HashSet<String> = ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt", "keep.txt"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let names: Vec<String> = ["a.txt", "x.txt", "x.txt", "keep.txt", "../e"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            check(&sources, &names, &existing),
+            [
+                Status::Unchanged,
+                Status::Duplicate,
+                Status::Duplicate,
+                Status::Exists,
+                Status::Invalid,
+            ]
+        );
+        assert_eq!(
+            Stamp::from_epoch(1_700_000_000),
+            Stamp {
+                date: "2023-11-14".to_string(),
+                time: "22-13-20".to_string(),
+            }
+        );
+        assert_eq!(Stamp::parse_exif("2024:07:15"), None);
+    }
+}
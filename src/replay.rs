@@ -8,6 +8,9 @@ pub struct ReplayCase {
     pub root: PathBuf,
     pub left: Option<PathBuf>,
     pub right: Option<PathBuf>,
+    /// Local directory copied into the in-memory `mem://replay` tree before
+    /// the keys run, for cases that point a panel at `mem://replay/...`.
+    pub mem_seed: Option<PathBuf>,
     pub keys: Vec<ReplayKey>,
     pub state_dump: Option<PathBuf>,
     #[serde(default)]
@@ -24,6 +27,8 @@ pub struct ReplayKey {
 #[derive(Deserialize, Default)]
 pub struct ReplayAsserts {
     pub fs: Option<FsAssert>,
+    /// Like `fs`, but for the `mem://replay` tree.
+    pub mem_fs: Option<FsAssert>,
     #[serde(default, deserialize_with = "deserialize_vec_or_option")]
     pub files: Vec<FileAssert>,
     #[serde(default, deserialize_with = "deserialize_vec_or_option")]
@@ -157,6 +162,11 @@ pub enum BrowserModeDump {
         query: String,
         mode: String,
     },
+    Trash,
+    Compare {
+        left: String,
+        right: String,
+    },
 }
 
 #[derive(Serialize)]
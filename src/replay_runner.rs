@@ -5,7 +5,7 @@ use std::{
     thread,
 };
 
-use fileman::{app_state, core, snapshot, theme, workers};
+use fileman::{app_state, compare, core, memfs, snapshot, sync, theme, workers};
 
 use crate::input;
 use crate::replay::{
@@ -15,10 +15,14 @@ use crate::replay::{
 use crate::snapshot_render::render_snapshot;
 use crate::{
     HighlightRequest, HighlightResult, ImageCache, ImageRequest, SNAPSHOT_HEIGHT, SNAPSHOT_WIDTH,
-    ScrollMode, UiCache, UiRender, draw_root_ui, load_fs_directory_async, pump_async,
-    refresh_fs_panels,
+    ScrollMode, UiCache, UiRender, draw_root_ui, load_fs_directory_async, navigate_sftp,
+    pump_async, refresh_fs_panels,
 };
 
+/// In-memory tree a case's `mem_seed` is copied into; panels reach it as
+/// `mem://replay/...`.
+const REPLAY_MEM_TREE: &str = "replay";
+
 fn parse_modifiers(raw: &[String]) -> egui::Modifiers {
     let mut mods = egui::Modifiers::NONE;
     for item in raw {
@@ -267,6 +271,8 @@ fn is_app_pending(app: &app_state::AppState) -> bool {
         || right.watching_archive.is_some()
         || edit_loading
         || search_running
+        || app.sftp_connect_rx.is_some()
+        || app.command_running.is_some()
         || !app.dir_size_pending.is_empty()
 }
 
@@ -330,20 +336,15 @@ fn init_headless_app(root: Option<PathBuf>) -> anyhow::Result<app_state::AppStat
         None => std::env::current_dir().expect("current_dir"),
     };
     let sftp_sessions_shared: std::sync::Arc<
-        std::sync::Mutex<
-            std::collections::HashMap<
-                String,
-                std::sync::Arc<std::sync::Mutex<fileman::sftp::SftpSession>>,
-            >,
-        >,
+        std::sync::Mutex<std::collections::HashMap<String, fileman::remote::SharedRemote>>,
     > = std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new()));
     let transfer_progress = std::sync::Arc::new(core::TransferProgress::new());
-    let io_cancel_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-    let (io_tx, io_rx, io_cancel_tx) = workers::start_io_worker(
+    let io_queue = std::sync::Arc::new(core::IoQueue::new());
+    let io_rx = workers::start_io_worker(
         sftp_sessions_shared.clone(),
         transfer_progress.clone(),
         None,
-        io_cancel_flag.clone(),
+        io_queue.clone(),
     );
     let (preview_tx, preview_rx) = workers::start_preview_worker(
         None,
@@ -353,7 +354,13 @@ fn init_headless_app(root: Option<PathBuf>) -> anyhow::Result<app_state::AppStat
     let (dir_size_tx, dir_size_rx) = workers::start_dir_size_worker(None);
     let (remote_dir_size_tx, remote_dir_size_rx) =
         workers::start_remote_dir_size_worker(sftp_sessions_shared.clone(), None);
+    let (remote_free_space_tx, remote_free_space_rx) =
+        workers::start_remote_free_space_worker(sftp_sessions_shared.clone(), None);
     let (search_tx, search_rx) = workers::start_search_worker(None, sftp_sessions_shared.clone());
+    let (compare_tx, compare_rx) = workers::start_compare_worker(None);
+    let command_cancel = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let (command_tx, command_rx) =
+        workers::start_command_worker(sftp_sessions_shared.clone(), command_cancel.clone(), None);
     let (edit_tx, edit_rx) = mpsc::channel::<core::EditLoadRequest>();
     let (edit_res_tx, edit_res_rx) = mpsc::channel::<core::EditLoadResult>();
 
@@ -440,12 +447,17 @@ fn init_headless_app(root: Option<PathBuf>) -> anyhow::Result<app_state::AppStat
         preview_tx,
         preview_rx,
         preview_request_id: 0,
-        io_tx,
         io_rx,
-        io_cancel_tx,
-        io_cancel_flag,
+        io_queue,
         io_in_flight: 0,
         io_batch_total: 0,
+        io_batch_started: None,
+        io_copy_method: None,
+        conflict_apply_all: false,
+        symlink_policy: core::SymlinkPolicy::Links,
+        link_kind: core::LinkKind::Symbolic,
+        link_target: core::LinkTarget::Absolute,
+        io_background: false,
         io_cancel_requested: false,
         transfer_progress: transfer_progress.clone(),
         dir_size_tx,
@@ -456,6 +468,10 @@ fn init_headless_app(root: Option<PathBuf>) -> anyhow::Result<app_state::AppStat
         remote_dir_size_rx,
         remote_dir_sizes: Default::default(),
         remote_dir_size_pending: Default::default(),
+        remote_free_space_tx,
+        remote_free_space_rx,
+        remote_free_space: Default::default(),
+        remote_free_space_pending: Default::default(),
         fs_last_selected_name: Default::default(),
         container_last_selected_name: Default::default(),
         container_dir_cache: Default::default(),
@@ -482,17 +498,35 @@ fn init_headless_app(root: Option<PathBuf>) -> anyhow::Result<app_state::AppStat
         search_tx,
         search_rx,
         search_remote_host: None,
+        compare_depth: compare::CompareDepth::default(),
+        compare_content: compare::CompareContent::default(),
+        compare_request_id: 0,
+        compare_run: None,
+        compare_results: Vec::new(),
+        compare_tx,
+        compare_rx,
+        sync_options: sync::SyncOptions::default(),
+        command_input: None,
+        command_focus: false,
+        command_tx,
+        command_rx,
+        command_cancel,
+        command_running: None,
         refresh_tick: 0,
         update_status: app_state::UpdateStatus::Disabled,
         update_rx: None,
         gpu_info: String::from("Test"),
         error_log: Vec::new(),
+        undo_journal: Vec::new(),
         settings: fileman::settings::Settings::default(),
         sftp_sessions: std::collections::HashMap::new(),
         sftp_sessions_shared: sftp_sessions_shared.clone(),
         sftp_connect_rx: None,
         sftp_pending_nav: None,
         sftp_nav_queue: std::collections::VecDeque::new(),
+        sftp_stats: std::collections::HashMap::new(),
+        remote_edits: Vec::new(),
+        remote_edit_polled: std::time::Instant::now(),
     };
     app.theme
         .load_external_from_dir(std::path::Path::new("./themes"));
@@ -504,18 +538,28 @@ pub(crate) fn run_replay(case_path: &PathBuf, snapshot: Option<PathBuf>) -> anyh
     let repo_root = std::env::current_dir()?;
     let root = resolve_case_path(&repo_root, &case.root);
     let mut app = init_headless_app(Some(root.clone()))?;
-    let left_root = case
-        .left
-        .as_ref()
-        .map(|p| resolve_case_path(&repo_root, p))
-        .unwrap_or_else(|| root.clone());
-    let right_root = case
-        .right
-        .as_ref()
-        .map(|p| resolve_case_path(&repo_root, p))
-        .unwrap_or_else(|| root.clone());
-    load_fs_directory_async(&mut app, left_root, core::ActivePanel::Left, None);
-    load_fs_directory_async(&mut app, right_root, core::ActivePanel::Right, None);
+    if let Some(ref seed) = case.mem_seed {
+        memfs::register();
+        memfs::seed_from_dir(REPLAY_MEM_TREE, &resolve_case_path(&repo_root, seed))?;
+    }
+    for (side, panel_root) in [
+        (core::ActivePanel::Left, case.left.as_ref()),
+        (core::ActivePanel::Right, case.right.as_ref()),
+    ] {
+        let location = panel_root
+            .and_then(|p| p.to_str())
+            .filter(|p| p.contains("://"))
+            .and_then(fileman::remote::parse_location);
+        match location {
+            Some((host, path)) => navigate_sftp(&mut app, &host, &path, side),
+            None => {
+                let dir = panel_root
+                    .map(|p| resolve_case_path(&repo_root, p))
+                    .unwrap_or_else(|| root.clone());
+                load_fs_directory_async(&mut app, dir, side, None);
+            }
+        }
+    }
 
     let mut ui_cache = UiCache {
         left_rows: 20,
@@ -671,6 +715,34 @@ fn collect_fs_entries(
 fn assert_fs(root: &Path, fs: &FsAssert) -> anyhow::Result<()> {
     let mut actual = HashMap::new();
     collect_fs_entries(root, std::path::Path::new(""), &mut actual)?;
+    check_fs_entries(&actual, fs, &root.to_string_lossy())
+}
+
+/// Check the `mem://replay` tree the same way `assert_fs` checks the root.
+fn assert_mem_fs(fs: &FsAssert) -> anyhow::Result<()> {
+    let actual: HashMap<String, FsEntryKind> = memfs::paths(REPLAY_MEM_TREE)
+        .into_iter()
+        .map(|(path, is_dir)| {
+            let kind = if is_dir {
+                FsEntryKind::Dir
+            } else {
+                FsEntryKind::File
+            };
+            (path.trim_start_matches('/').to_string(), kind)
+        })
+        .collect();
+    check_fs_entries(
+        &actual,
+        fs,
+        &format!("{}://{REPLAY_MEM_TREE}", memfs::SCHEME),
+    )
+}
+
+fn check_fs_entries(
+    actual: &HashMap<String, FsEntryKind>,
+    fs: &FsAssert,
+    location: &str,
+) -> anyhow::Result<()> {
     for entry in &fs.entries {
         let expected_kind = entry.kind;
         let rel = entry.path.replace('\\', "/");
@@ -693,7 +765,7 @@ fn assert_fs(root: &Path, fs: &FsAssert) -> anyhow::Result<()> {
         if actual_count != expected_count {
             return Err(anyhow::anyhow!(
                 "FS entry count mismatch at {}: expected {}, got {}",
-                root.to_string_lossy(),
+                location,
                 expected_count,
                 actual_count
             ));
@@ -797,6 +869,11 @@ fn build_panel_dump(panel: &app_state::PanelState) -> PanelDump {
             .to_string(),
         },
         core::BrowserMode::Remote { .. } => BrowserModeDump::Fs,
+        core::BrowserMode::Trash => BrowserModeDump::Trash,
+        core::BrowserMode::Compare { left, right } => BrowserModeDump::Compare {
+            left: left.clone(),
+            right: right.clone(),
+        },
     };
     let sort_mode = match browser.sort_mode {
         core::SortMode::Name => "Name",
@@ -887,6 +964,8 @@ fn browser_mode_str(mode: &core::BrowserMode) -> &'static str {
         core::BrowserMode::Container { .. } => "Container",
         core::BrowserMode::Search { .. } => "Search",
         core::BrowserMode::Remote { .. } => "Remote",
+        core::BrowserMode::Trash => "Trash",
+        core::BrowserMode::Compare { .. } => "Compare",
     }
 }
 
@@ -977,6 +1056,9 @@ fn run_replay_asserts(
     if let Some(fs) = asserts.fs.as_ref() {
         assert_fs(root, fs)?;
     }
+    if let Some(fs) = asserts.mem_fs.as_ref() {
+        assert_mem_fs(fs)?;
+    }
     if !asserts.files.is_empty() {
         assert_files(root, &asserts.files)?;
     }
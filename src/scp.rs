@@ -0,0 +1,457 @@
+//! Degraded SSH backend for hosts that disable the `sftp` subsystem but still
+//! run commands and accept SCP — routers, appliances, hardened jump boxes.
+//!
+//! `remote::connect` falls back to it when an SSH host authenticates but
+//! refuses SFTP, and the session stays registered under the same host key.
+//! Listings parse `ls -la --time-style=+%s` (plain `ls -la` where the `ls`
+//! doesn't know the option), metadata changes are shell commands, and file
+//! contents move through `scp_recv` / `scp_send`. SCP can't start mid-file,
+//! so seeks reopen the file through `tail -c`. The tar fast paths, streamed
+//! search and streamed command output need SFTP and are not offered: copies
+//! go file by file, search walks the listings, and command output arrives
+//! when the command ends. Zip archives are refused, since reading one seeks
+//! all over the file. The panel status line shows the mode.
+
+use std::{
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use ssh2::Session;
+
+use crate::{
+    core::{DirEntry, LinkKind},
+    ftp::Listed,
+    remote::{self, ExecOutput, RemoteStat, SessionStats},
+};
+
+/// Added to the server banner so the connection manager shows the mode.
+pub const MODE_NOTE: &str = "SCP only, no SFTP";
+
+/// Error for an action that needs SFTP, refused up front on these hosts.
+pub fn unavailable(what: &str, host: &str) -> String {
+    format!("{what} is unavailable on SCP-only host {host}")
+}
+
+pub struct ScpSession {
+    pub session: Session,
+    pub host: String,
+    /// Remote user's home directory (from `pwd`), if resolved.
+    pub home_dir: Option<String>,
+    pub stats: Arc<SessionStats>,
+}
+
+impl ScpSession {
+    /// Wrap an authenticated session whose SFTP subsystem is unavailable.
+    pub fn new(session: Session, host: &str, user: &str) -> Self {
+        let home_dir = crate::sftp::exec(&session, "pwd")
+            .ok()
+            .filter(|out| out.status == 0)
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+            .filter(|dir| dir.starts_with('/'));
+        let banner = match session.banner() {
+            Some(banner) => format!("{banner} ({MODE_NOTE})"),
+            None => MODE_NOTE.to_string(),
+        };
+        Self {
+            stats: SessionStats::scp_only(user, Some(banner)),
+            session,
+            host: host.to_string(),
+            home_dir,
+        }
+    }
+
+    /// Run a command that should succeed, folding its stderr into the error.
+    fn run(&self, what: &str, command: &str) -> Result<Vec<u8>, String> {
+        let out = crate::sftp::exec(&self.session, command).map_err(|e| format!("{what}: {e}"))?;
+        check_status(what, out)
+    }
+
+    /// `ls` lines for `path`, with epoch times where the `ls` supports them.
+    /// `Err((message, is_connection_error))` like [`remote::RemoteFs::list`].
+    fn ls(&self, flags: &str, path: &str) -> Result<String, (String, bool)> {
+        let quoted = remote::sh_quote(path);
+        let mut failure = String::new();
+        for command in [
+            format!("ls {flags} --time-style=+%s -- {quoted}"),
+            format!("ls {flags} -- {quoted}"),
+        ] {
+            let out = crate::sftp::exec(&self.session, &command)
+                .map_err(|e| (format!("ls {path}: {e}"), true))?;
+            match check_status(&format!("ls {path}"), out) {
+                Ok(stdout) => return Ok(String::from_utf8_lossy(&stdout).into_owned()),
+                Err(e) => failure = e,
+            }
+        }
+        Err((failure, false))
+    }
+
+    /// A channel positioned at `offset` into `path`.
+    fn read_from(&self, path: &str, offset: u64) -> io::Result<io::Take<ssh2::Channel>> {
+        if offset == 0 {
+            let (channel, stat) = self.session.scp_recv(Path::new(path))?;
+            // The SCP stream ends with a status byte past the file data.
+            return Ok(channel.take(stat.size()));
+        }
+        let mut channel = self.session.channel_session()?;
+        channel.exec(&format!(
+            "tail -c +{} -- {}",
+            offset + 1,
+            remote::sh_quote(path)
+        ))?;
+        Ok(channel.take(u64::MAX))
+    }
+}
+
+fn check_status(what: &str, out: ExecOutput) -> Result<Vec<u8>, String> {
+    if out.status == 0 {
+        Ok(out.stdout)
+    } else {
+        let stderr = out.stderr.trim();
+        Err(if stderr.is_empty() {
+            format!("{what}: exit status {}", out.status)
+        } else {
+            format!("{what}: {stderr}")
+        })
+    }
+}
+
+/// Parse one `ls -la` line. Lines from `--time-style=+%s` carry the
+/// modification time as epoch seconds; anything else is read as the
+/// classic format FTP `LIST` replies also use.
+fn parse_ls_line(line: &str, now: u64) -> Option<Listed> {
+    let kind = line.chars().next()?;
+    // `mode links owner group size epoch name`; device nodes show
+    // `major, minor` in place of the size.
+    let epoch_line = [6, 7].into_iter().find_map(|n| {
+        let (fields, rest) = crate::ftp::split_fields(line, n)?;
+        let size_ok = match n {
+            6 => fields[4].parse::<u64>().is_ok(),
+            _ => fields[4].ends_with(','),
+        };
+        let mtime = fields[n - 1].parse::<u64>().ok().filter(|_| size_ok)?;
+        Some((fields, rest, mtime))
+    });
+    let Some((fields, rest, mtime)) = epoch_line else {
+        return crate::ftp::parse_list_line(line, now);
+    };
+    let (name, link_target) = match rest.split_once(" -> ") {
+        Some((name, target)) if kind == 'l' => (name, Some(target.to_string())),
+        _ => (rest, None),
+    };
+    if name == "." || name == ".." {
+        return None;
+    }
+    Some(Listed {
+        name: name.to_string(),
+        is_dir: kind == 'd',
+        is_symlink: kind == 'l',
+        link_target,
+        size: if fields.len() == 6 {
+            fields[4].parse().ok()
+        } else {
+            None
+        },
+        mtime: Some(mtime),
+    })
+}
+
+/// Upload spooled to a local temporary file, because `scp_send` needs the
+/// size up front. The file is sent on `flush`, which reports a failed
+/// send; a writer dropped unflushed sends nothing.
+struct ScpWriter<'a> {
+    session: &'a ScpSession,
+    path: String,
+    spool_path: PathBuf,
+    spool: Option<std::fs::File>,
+    len: u64,
+}
+
+impl ScpWriter<'_> {
+    fn send(&mut self) -> Result<(), String> {
+        drop(self.spool.take());
+        let mut spool = std::fs::File::open(&self.spool_path)
+            .map_err(|e| format!("reopen {}: {e}", self.spool_path.display()))?;
+        let mut channel = self
+            .session
+            .session
+            .scp_send(Path::new(&self.path), 0o644, self.len, None)
+            .map_err(|e| format!("scp {}: {e}", self.path))?;
+        io::copy(&mut spool, &mut channel).map_err(|e| format!("scp {}: {e}", self.path))?;
+        channel
+            .send_eof()
+            .and_then(|()| channel.wait_eof())
+            .and_then(|()| channel.close())
+            .and_then(|()| channel.wait_close())
+            .map_err(|e| format!("scp {}: {e}", self.path))
+    }
+}
+
+impl Write for ScpWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let spool = self
+            .spool
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        let n = spool.write(buf)?;
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.spool.is_none() {
+            return Ok(());
+        }
+        self.send().map_err(io::Error::other)
+    }
+}
+
+impl Drop for ScpWriter<'_> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.spool_path);
+    }
+}
+
+impl remote::RemoteFs for ScpSession {
+    fn host(&self) -> &str {
+        &self.host
+    }
+
+    fn home_dir(&self) -> Option<&str> {
+        self.home_dir.as_deref()
+    }
+
+    fn stats(&self) -> &Arc<SessionStats> {
+        &self.stats
+    }
+
+    fn is_alive(&self) -> bool {
+        crate::sftp::exec(&self.session, "true").is_ok()
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        let (session, user) =
+            crate::sftp::authenticate(&self.host, &crate::sftp::load_ssh_config())?;
+        let mut fresh = ScpSession::new(session, &self.host, &user);
+        fresh.stats = self.stats.clone();
+        fresh.stats.mark_reconnected();
+        let old = std::mem::replace(self, fresh);
+        // libssh2_session_free can block on a dead socket.
+        std::thread::spawn(move || drop(old));
+        Ok(())
+    }
+
+    fn list(
+        &self,
+        path: &str,
+        on_batch: &mut dyn FnMut(Vec<DirEntry>),
+    ) -> Result<(), (String, bool)> {
+        let dir = if path.is_empty() { "/" } else { path };
+        let listing = self.ls("-la", dir)?;
+        if let Some(parent) = remote::parent_entry(&self.host, dir) {
+            on_batch(vec![parent]);
+        }
+        let now = crate::ftp::now_secs();
+        let entries = listing
+            .lines()
+            .filter_map(|line| parse_ls_line(line, now))
+            .map(|listed| listed.into_entry(&self.host, dir))
+            .collect();
+        on_batch(entries);
+        Ok(())
+    }
+
+    fn stat(&self, path: &str) -> Result<RemoteStat, String> {
+        // `-L` follows symlinks, matching SFTP `stat`.
+        let listing = self.ls("-ldL", path).map_err(|(msg, _)| msg)?;
+        let listed = listing
+            .lines()
+            .find_map(|line| parse_ls_line(line, crate::ftp::now_secs()))
+            .ok_or_else(|| format!("stat {path}: unreadable `ls` output"))?;
+        Ok(RemoteStat {
+            is_dir: listed.is_dir,
+            size: if listed.is_dir { None } else { listed.size },
+            mtime: listed.mtime,
+        })
+    }
+
+    fn open_read(&self, path: &str) -> Result<Box<dyn crate::archive::ReadSeek + '_>, String> {
+        let stat = remote::RemoteFs::stat(self, path)?;
+        if stat.is_dir {
+            return Err(format!("open {path}: is a directory"));
+        }
+        let path = path.to_string();
+        Ok(Box::new(remote::RangedReader::new(
+            stat.size,
+            move |offset| self.read_from(&path, offset).map(Some),
+        )))
+    }
+
+    fn create(&self, path: &str) -> Result<Box<dyn Write + '_>, String> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let spool_path =
+            std::env::temp_dir().join(format!("fileman-scp-{}-{n}", std::process::id()));
+        let spool = std::fs::File::create(&spool_path)
+            .map_err(|e| format!("create {}: {e}", spool_path.display()))?;
+        Ok(Box::new(ScpWriter {
+            session: self,
+            path: path.to_string(),
+            spool_path,
+            spool: Some(spool),
+            len: 0,
+        }))
+    }
+
+    fn rename(&self, src: &str, dst: &str) -> Result<(), String> {
+        let command = format!("mv -- {} {}", remote::sh_quote(src), remote::sh_quote(dst));
+        self.run(&format!("rename {src} -> {dst}"), &command)
+            .map(drop)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        let command = format!("rm -f -- {}", remote::sh_quote(path));
+        self.run(&format!("unlink {path}"), &command).map(drop)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<(), String> {
+        let command = format!("rmdir -- {}", remote::sh_quote(path));
+        self.run(&format!("rmdir {path}"), &command).map(drop)
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        let command = format!("mkdir -- {}", remote::sh_quote(path));
+        self.run(&format!("mkdir {path}"), &command).map(drop)
+    }
+
+    fn copy(&self, src: &str, dst: &str) -> Option<Result<(), String>> {
+        let command = format!(
+            "cp -R -- {} {}",
+            remote::sh_quote(src),
+            remote::sh_quote(dst)
+        );
+        Some(
+            self.run(&format!("copy {src} -> {dst}"), &command)
+                .map(drop),
+        )
+    }
+
+    fn link(&self, target: &str, path: &str, kind: LinkKind) -> Option<Result<(), String>> {
+        let flags = match kind {
+            LinkKind::Symbolic => "-s ",
+            LinkKind::Hard => "",
+        };
+        let command = format!(
+            "ln {flags}-- {} {}",
+            remote::sh_quote(target),
+            remote::sh_quote(path)
+        );
+        Some(self.run(&format!("link {path}"), &command).map(drop))
+    }
+
+    fn exec(&self, command: &str) -> Option<Result<ExecOutput, String>> {
+        Some(crate::sftp::exec(&self.session, command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ls_lines_parse_epoch_and_classic_formats() {
+        let now = 1_717_200_000;
+        let file = parse_ls_line(
+            "-rw-r--r--    1 root     root          1234 1709211900 read me.txt",
+            now,
+        )
+        .unwrap();
+        assert_eq!(file.name, "read me.txt");
+        assert_eq!(file.size, Some(1234));
+        assert_eq!(file.mtime, Some(1_709_211_900));
+        assert!(!file.is_dir);
+        let link =
+            parse_ls_line("lrwxrwxrwx 1 root root 9 1709211900 data -> /srv/data", now).unwrap();
+        assert!(link.is_symlink);
+        assert_eq!(link.name, "data");
+        assert_eq!(link.link_target.as_deref(), Some("/srv/data"));
+        let device = parse_ls_line("crw-rw---- 1 root tty 4, 1 1709211900 tty1", now).unwrap();
+        assert_eq!(device.name, "tty1");
+        assert_eq!(device.size, None);
+        assert!(parse_ls_line("drwxr-xr-x 2 root root 4096 1709211900 .", now).is_none());
+        assert!(parse_ls_line("total 12", now).is_none());
+        // BusyBox `ls` without `--time-style` prints classic dates.
+        let classic = parse_ls_line(
+            "drwxr-xr-x    2 root     root          4096 Feb 29 13:05 etc",
+            now,
+        )
+        .unwrap();
+        assert!(classic.is_dir);
+        assert_eq!(classic.mtime, Some(1_709_211_900));
+    }
+
+    /// A session that never connected, so every SSH call on it fails.
+    fn unconnected() -> ScpSession {
+        ScpSession {
+            session: Session::new().unwrap(),
+            host: "router".to_string(),
+            home_dir: None,
+            stats: SessionStats::scp_only("admin", None),
+        }
+    }
+
+    #[test]
+    fn failed_upload_is_reported_to_the_writer() {
+        let scp = unconnected();
+        let err = remote::write_file(&scp, "/etc/motd", b"hello").unwrap_err();
+        assert!(err.contains("scp /etc/motd"), "{err}");
+
+        // The spool goes with the writer, sent or not.
+        let Ok(mut writer) = remote::RemoteFs::create(&scp, "/etc/motd") else {
+            panic!("the spool is local");
+        };
+        writer.write_all(b"hello").unwrap();
+        assert!(writer.flush().is_err());
+        drop(writer);
+        let prefix = format!("fileman-scp-{}-", std::process::id());
+        let leftover = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(Result::ok)
+            .any(|e| e.file_name().to_string_lossy().starts_with(&prefix));
+        assert!(!leftover);
+    }
+
+    #[test]
+    fn failed_commands_name_the_operation() {
+        let scp = unconnected();
+        let err = remote::RemoteFs::mkdir(&scp, "/srv/new").unwrap_err();
+        assert!(err.starts_with("mkdir /srv/new: "), "{err}");
+        let err = remote::RemoteFs::stat(&scp, "/srv").map(drop).unwrap_err();
+        assert!(err.starts_with("ls /srv: "), "{err}");
+        let err = remote::read_directory(&scp, "/srv").map(drop).unwrap_err();
+        assert!(err.starts_with("ls /srv: "), "{err}");
+    }
+
+    #[test]
+    fn zip_archives_are_refused_up_front() {
+        // The only test that sets up the shared registry.
+        let sessions = Arc::new(std::sync::Mutex::new(std::collections::HashMap::new()));
+        remote::init_shared_registry(sessions.clone());
+        let scp: Box<dyn remote::RemoteFs> = Box::new(unconnected());
+        assert!(scp.stats().scp_only);
+        sessions
+            .lock()
+            .unwrap()
+            .insert("router".to_string(), Arc::new(std::sync::Mutex::new(scp)));
+        let archive = crate::sftp::encode_archive_path("router", "/backup.zip");
+        let err = crate::archive::with_seek_reader(&archive, |_| Ok(())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Browsing zip archives is unavailable on SCP-only host router"
+        );
+    }
+}
@@ -9,6 +9,8 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use crate::remote;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Settings {
     /// Preferred theme. On startup the app applies this; the F10 theme picker
@@ -30,7 +32,43 @@ pub struct Settings {
     #[serde(default = "default_true")]
     pub auto_refresh: bool,
 
-    /// Saved SFTP host bookmarks. Surfaced in the quick-jump dropdown so a
+    /// After a local ↔ remote or cross-host copy, hash both sides with
+    /// SHA-256 and fail the task on mismatch. Moves only delete the source
+    /// once the checksums agree.
+    #[serde(default)]
+    pub verify_transfers: bool,
+
+    /// Cap on remote transfer speed in KiB/s, 0 for unlimited. Also
+    /// adjustable from the progress window while a transfer runs.
+    #[serde(default)]
+    pub bandwidth_limit_kib: u64,
+
+    /// Remote transfers of at least this many MiB wait in the progress
+    /// window until started by hand; 0 starts everything right away.
+    #[serde(default)]
+    pub hold_transfers_mib: u64,
+
+    /// Metadata local copies keep (see [`crate::core::Preserve`]). Moves
+    /// across filesystems always keep everything.
+    #[serde(default = "default_true")]
+    pub preserve_timestamps: bool,
+    #[serde(default = "default_true")]
+    pub preserve_mode: bool,
+    /// Only takes effect when running as root.
+    #[serde(default = "default_true")]
+    pub preserve_ownership: bool,
+    #[serde(default = "default_true")]
+    pub preserve_xattrs: bool,
+    #[serde(default = "default_true")]
+    pub preserve_sparse: bool,
+
+    /// What folder sizes (Space) count for symbolic links: their own size,
+    /// their targets', or nothing. Separate from the copy dialog's choice.
+    #[serde(default)]
+    pub dir_size_symlinks: crate::core::SymlinkPolicy,
+
+    /// Saved remote bookmarks: SSH hosts or URL host keys such as
+    /// `davs://cloud.example.com`. Surfaced in the quick-jump dropdown so a
     /// labeled bookmark expands to (host, initial_path).
     #[serde(default)]
     pub bookmarks: Vec<Bookmark>,
@@ -51,6 +89,29 @@ pub struct Bookmark {
     pub path: String,
 }
 
+impl Bookmark {
+    /// The bookmark as a location accepted by [`remote::parse_location`].
+    pub fn location(&self) -> String {
+        remote::format_location(&self.host, &self.path)
+    }
+
+    /// Move the path of a URL pasted into the host field (e.g.
+    /// `davs://cloud.example.com/remote.php/dav/files/alice`) into `path`,
+    /// so the host stays a session key.
+    pub fn normalize(&mut self) {
+        if self.host.contains("://")
+            && let Some((host, url_path)) = remote::parse_location(&self.host)
+            && !url_path.is_empty()
+        {
+            self.path = match self.path.trim_start_matches('/') {
+                "" => url_path,
+                rest => remote::join_path(&url_path, rest),
+            };
+            self.host = host;
+        }
+    }
+}
+
 fn default_true() -> bool {
     true
 }
@@ -62,11 +123,33 @@ impl Default for Settings {
             show_glyphs: true,
             row_striping: true,
             auto_refresh: true,
+            verify_transfers: false,
+            bandwidth_limit_kib: 0,
+            hold_transfers_mib: 0,
+            preserve_timestamps: true,
+            preserve_mode: true,
+            preserve_ownership: true,
+            preserve_xattrs: true,
+            preserve_sparse: true,
+            dir_size_symlinks: crate::core::SymlinkPolicy::Links,
             bookmarks: Vec::new(),
         }
     }
 }
 
+impl Settings {
+    /// What local copies should keep besides the contents.
+    pub fn preserve(&self) -> crate::core::Preserve {
+        crate::core::Preserve {
+            timestamps: self.preserve_timestamps,
+            mode: self.preserve_mode,
+            ownership: self.preserve_ownership,
+            xattrs: self.preserve_xattrs,
+            sparse: self.preserve_sparse,
+        }
+    }
+}
+
 /// Locate the OS-conventional config directory for fileman.
 /// Linux: `$XDG_CONFIG_HOME/fileman` or `$HOME/.config/fileman`.
 /// macOS: `$HOME/Library/Application Support/fileman`.
@@ -173,6 +256,9 @@ mod tests {
         let s = Settings {
             show_glyphs: false,
             auto_refresh: false,
+            verify_transfers: true,
+            bandwidth_limit_kib: 512,
+            preserve_xattrs: false,
             theme: ThemePref::External("solarized".into()),
             bookmarks: vec![Bookmark {
                 label: "home".into(),
@@ -185,11 +271,43 @@ mod tests {
         let back: Settings = ron::from_str(&text).unwrap();
         assert!(!back.show_glyphs);
         assert!(!back.auto_refresh);
+        assert!(back.verify_transfers);
+        assert_eq!(back.bandwidth_limit_kib, 512);
+        assert!(back.preserve_timestamps);
+        assert!(!back.preserve().xattrs);
         assert_eq!(back.bookmarks.len(), 1);
         assert_eq!(back.bookmarks[0].host, "example.com");
+        assert_eq!(back.bookmarks[0].location(), "example.com:/srv");
         assert!(matches!(back.theme, ThemePref::External(ref n) if n == "solarized"));
     }
 
+    #[test]
+    fn url_bookmarks_split_host_and_path() {
+        let mut bm = Bookmark {
+            label: "cloud".into(),
+            host: "davs://alice@cloud.example.com/remote.php/dav/files/alice".into(),
+            path: "/".into(),
+        };
+        bm.normalize();
+        assert_eq!(bm.host, "davs://alice@cloud.example.com");
+        assert_eq!(bm.path, "/remote.php/dav/files/alice");
+        assert_eq!(
+            remote::parse_location(&bm.location()),
+            Some((bm.host.clone(), bm.path.clone()))
+        );
+
+        // SSH hosts and already split URLs are left alone.
+        let mut ssh = Bookmark {
+            label: "box".into(),
+            host: "user@box".into(),
+            path: "/srv".into(),
+        };
+        ssh.normalize();
+        assert_eq!(ssh.location(), "user@box:/srv");
+        bm.normalize();
+        assert_eq!(bm.path, "/remote.php/dav/files/alice");
+    }
+
     #[test]
     fn garbage_does_not_parse() {
         assert!(ron::from_str::<Settings>("this is not ron {{{").is_err());
@@ -4,7 +4,7 @@ use std::{
     net::TcpStream,
     path::Path,
     sync::{
-        Arc, Mutex, OnceLock,
+        Arc,
         atomic::{AtomicBool, Ordering},
     },
 };
@@ -19,20 +19,25 @@ pub struct SftpSession {
     pub host: String,
     /// Remote user's home directory (from `realpath(".")`), if resolved.
     pub home_dir: Option<String>,
+    /// Shared with the connection manager, and carried over by `reconnect`.
+    pub stats: Arc<crate::remote::SessionStats>,
 }
 
-type SessionMap = HashMap<String, Arc<Mutex<SftpSession>>>;
-
-/// Shared SFTP session registry for global access (e.g. from archive streaming).
-/// Initialized once at startup with the app's shared session map.
-static SHARED_SESSIONS: OnceLock<Arc<Mutex<SessionMap>>> = OnceLock::new();
-
-pub fn init_shared_registry(sessions: Arc<Mutex<SessionMap>>) {
-    let _ = SHARED_SESSIONS.set(sessions);
-}
-
-pub fn get_session(host: &str) -> Option<Arc<Mutex<SftpSession>>> {
-    SHARED_SESSIONS.get()?.lock().ok()?.get(host).cloned()
+impl SftpSession {
+    /// Wrap an authenticated session whose SFTP subsystem is up.
+    pub fn new(session: Session, sftp: Sftp, host: &str, user: &str) -> Self {
+        let home_dir = sftp
+            .realpath(Path::new("."))
+            .ok()
+            .map(|p| p.to_string_lossy().into_owned());
+        Self {
+            stats: crate::remote::SessionStats::new(user, session.banner().map(|s| s.to_string())),
+            session,
+            sftp,
+            host: host.to_string(),
+            home_dir,
+        }
+    }
 }
 
 /// Synthetic path prefix used to encode remote archive locations as `PathBuf`,
@@ -43,6 +48,7 @@ pub const SFTP_ARCHIVE_PREFIX: &str = "/.sftp-archive/";
 
 pub fn encode_archive_path(host: &str, remote_path: &str) -> std::path::PathBuf {
     let trimmed = remote_path.trim_start_matches('/');
+    let host = crate::remote::escape_host(host);
     std::path::PathBuf::from(format!("{SFTP_ARCHIVE_PREFIX}{host}/{trimmed}"))
 }
 
@@ -50,7 +56,7 @@ pub fn decode_archive_path(path: &Path) -> Option<(String, String)> {
     let s = path.to_str()?;
     let rest = s.strip_prefix(SFTP_ARCHIVE_PREFIX)?;
     let slash = rest.find('/')?;
-    let host = rest[..slash].to_string();
+    let host = crate::remote::unescape_host(&rest[..slash]);
     let remote = format!("/{}", &rest[slash + 1..]);
     Some((host, remote))
 }
@@ -145,7 +151,7 @@ fn expand_tilde(path: &str) -> String {
     path.to_string()
 }
 
-fn home_dir() -> Option<std::path::PathBuf> {
+pub(crate) fn home_dir() -> Option<std::path::PathBuf> {
     #[cfg(windows)]
     {
         std::env::var("USERPROFILE")
@@ -273,12 +279,26 @@ fn set_tcp_keepalive(tcp: &TcpStream) {
 }
 
 /// Connect to an SSH host using config resolution. Tries ssh-agent, then key files.
+/// `host` may carry an `sftp://` prefix; the session stays registered under
+/// the key as given.
 pub fn connect(
     host: &str,
     ssh_config: &HashMap<String, SshHostConfig>,
 ) -> Result<SftpSession, String> {
-    let config = ssh_config.get(host);
-    let actual_host = config.and_then(|c| c.hostname.as_deref()).unwrap_or(host);
+    let (session, user) = authenticate(host, ssh_config)?;
+    let sftp = session.sftp().map_err(|e| format!("SFTP subsystem: {e}"))?;
+    Ok(SftpSession::new(session, sftp, host, &user))
+}
+
+/// Open an authenticated SSH session to `host`, returning it with the login
+/// user. Shared by the SFTP and SCP backends.
+pub fn authenticate(
+    host: &str,
+    ssh_config: &HashMap<String, SshHostConfig>,
+) -> Result<(Session, String), String> {
+    let alias = crate::remote::split_scheme(host).1;
+    let config = ssh_config.get(alias);
+    let actual_host = config.and_then(|c| c.hostname.as_deref()).unwrap_or(alias);
     let user = config
         .and_then(|c| c.user.as_deref())
         .map(|s| s.to_string())
@@ -307,17 +327,7 @@ pub fn connect(
 
     // Try ssh-agent first
     if session.userauth_agent(&user).is_ok() && session.authenticated() {
-        let sftp = session.sftp().map_err(|e| format!("SFTP subsystem: {e}"))?;
-        let home_dir = sftp
-            .realpath(Path::new("."))
-            .ok()
-            .map(|p| p.to_string_lossy().into_owned());
-        return Ok(SftpSession {
-            session,
-            sftp,
-            host: host.to_string(),
-            home_dir,
-        });
+        return Ok((session, user));
     }
 
     // Try key files from config, then default paths
@@ -337,17 +347,7 @@ pub fn connect(
             continue;
         }
         if session.userauth_pubkey_file(&user, None, key, None).is_ok() && session.authenticated() {
-            let sftp = session.sftp().map_err(|e| format!("SFTP subsystem: {e}"))?;
-            let home_dir = sftp
-                .realpath(Path::new("."))
-                .ok()
-                .map(|p| p.to_string_lossy().into_owned());
-            return Ok(SftpSession {
-                session,
-                sftp,
-                host: host.to_string(),
-                home_dir,
-            });
+            return Ok((session, user));
         }
     }
 
@@ -357,6 +357,160 @@ pub fn connect(
     ))
 }
 
+/// Whether the connection still answers. SFTP-level errors (e.g. permission
+/// denied) mean the server is there; transport errors mean it is gone.
+pub fn is_alive(session: &SftpSession) -> bool {
+    match session.sftp.stat(Path::new("/")) {
+        Ok(_) => true,
+        Err(e) => matches!(e.code(), ssh2::ErrorCode::SFTP(_)),
+    }
+}
+
+/// Replace a dead session with a fresh connection to the same host, in place,
+/// so every holder of the shared `Arc` picks it up. Statistics carry over.
+pub fn reconnect(session: &mut SftpSession) -> Result<(), String> {
+    let mut fresh = connect(&session.host, &load_ssh_config())?;
+    fresh.stats = session.stats.clone();
+    fresh.stats.mark_reconnected();
+    let old = std::mem::replace(session, fresh);
+    // libssh2_session_free can block on a dead socket.
+    std::thread::spawn(move || drop(old));
+    Ok(())
+}
+
+/// Run a command over an exec channel and collect its output.
+pub fn exec(session: &Session, command: &str) -> Result<crate::remote::ExecOutput, String> {
+    let mut ch = session
+        .channel_session()
+        .map_err(|e| format!("channel_session: {e}"))?;
+    ch.exec(command).map_err(|e| format!("exec: {e}"))?;
+    // Hashing or sizing a large tree can go quiet for longer than the
+    // normal timeout.
+    session.set_timeout(0);
+    let mut stdout = Vec::new();
+    let read = ch.read_to_end(&mut stdout);
+    let finished = finalize_readonly_channel(&mut ch);
+    session.set_timeout(30_000);
+    read.map_err(|e| format!("read: {e}"))?;
+    let (status, stderr) = finished?;
+    Ok(crate::remote::ExecOutput {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+impl crate::remote::RemoteFs for SftpSession {
+    fn host(&self) -> &str {
+        &self.host
+    }
+
+    fn home_dir(&self) -> Option<&str> {
+        self.home_dir.as_deref()
+    }
+
+    fn stats(&self) -> &Arc<crate::remote::SessionStats> {
+        &self.stats
+    }
+
+    fn is_alive(&self) -> bool {
+        is_alive(self)
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        reconnect(self)
+    }
+
+    fn list(
+        &self,
+        path: &str,
+        on_batch: &mut dyn FnMut(Vec<DirEntry>),
+    ) -> Result<(), (String, bool)> {
+        read_directory_streaming(&self.sftp, &self.host, path, on_batch)
+    }
+
+    fn stat(&self, path: &str) -> Result<crate::remote::RemoteStat, String> {
+        let stat = self
+            .sftp
+            .stat(Path::new(path))
+            .map_err(|e| format!("stat {path}: {e}"))?;
+        Ok(crate::remote::RemoteStat {
+            is_dir: stat.is_dir(),
+            size: stat.size,
+            mtime: stat.mtime,
+        })
+    }
+
+    fn open_read(&self, path: &str) -> Result<Box<dyn crate::archive::ReadSeek + '_>, String> {
+        Ok(Box::new(open_remote_reader(&self.sftp, path)?))
+    }
+
+    fn create(&self, path: &str) -> Result<Box<dyn Write + '_>, String> {
+        let file = self
+            .sftp
+            .create(Path::new(path))
+            .map_err(|e| format!("create {path}: {e}"))?;
+        Ok(Box::new(file))
+    }
+
+    fn rename(&self, src: &str, dst: &str) -> Result<(), String> {
+        rename(&self.sftp, src, dst)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        self.sftp
+            .unlink(Path::new(path))
+            .map_err(|e| format!("unlink {path}: {e}"))
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<(), String> {
+        self.sftp
+            .rmdir(Path::new(path))
+            .map_err(|e| format!("rmdir {path}: {e}"))
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        mkdir(&self.sftp, path)
+    }
+
+    fn link(
+        &self,
+        target: &str,
+        path: &str,
+        kind: crate::core::LinkKind,
+    ) -> Option<Result<(), String>> {
+        Some(match kind {
+            crate::core::LinkKind::Symbolic => self
+                .sftp
+                .symlink(Path::new(target), Path::new(path))
+                .map_err(|e| format!("symlink {path} -> {target}: {e}")),
+            // The protocol has no hard links; `ln` over SSH does.
+            crate::core::LinkKind::Hard => {
+                let command = format!(
+                    "ln -- {} {}",
+                    crate::remote::sh_quote(target),
+                    crate::remote::sh_quote(path)
+                );
+                exec(&self.session, &command).and_then(|out| {
+                    if out.status == 0 {
+                        Ok(())
+                    } else {
+                        Err(format!("ln {path}: {}", out.stderr.trim()))
+                    }
+                })
+            }
+        })
+    }
+
+    fn exec(&self, command: &str) -> Option<Result<crate::remote::ExecOutput, String>> {
+        Some(exec(&self.session, command))
+    }
+
+    fn as_sftp(&self) -> Option<&SftpSession> {
+        Some(self)
+    }
+}
+
 /// List a remote directory, producing DirEntry items with EntryLocation::Remote.
 /// Does not include ".." when path is "/".
 pub fn read_directory(sftp: &Sftp, host: &str, path: &str) -> Result<Vec<DirEntry>, String> {
@@ -387,20 +541,8 @@ pub fn read_directory_streaming(
     })?;
 
     // First batch: ".." entry if not at root
-    if remote_path != "/" {
-        let parent = parent_remote_path(remote_path);
-        on_batch(vec![DirEntry {
-            name: "..".to_string(),
-            is_dir: true,
-            is_symlink: false,
-            link_target: None,
-            location: EntryLocation::Remote {
-                host: host.to_string(),
-                path: parent,
-            },
-            size: None,
-            modified: None,
-        }]);
+    if let Some(parent) = crate::remote::parent_entry(host, remote_path) {
+        on_batch(vec![parent]);
     }
 
     const BATCH_SIZE: usize = 64;
@@ -458,42 +600,6 @@ pub fn read_directory_streaming(
     Ok(())
 }
 
-/// Read an entire remote file into memory, optionally reporting progress.
-pub fn read_file_full(sftp: &Sftp, path: &str) -> Result<Vec<u8>, String> {
-    read_file_full_progress(sftp, path, None)
-}
-
-/// Read an entire remote file into memory with progress reporting.
-pub fn read_file_full_progress(
-    sftp: &Sftp,
-    path: &str,
-    progress: Option<&crate::core::TransferProgress>,
-) -> Result<Vec<u8>, String> {
-    let stat = sftp.stat(Path::new(path)).ok();
-    if let Some(p) = progress {
-        p.reset(stat.and_then(|s| s.size).unwrap_or(0));
-    }
-    let mut file = sftp
-        .open(Path::new(path))
-        .map_err(|e| format!("open {path}: {e}"))?;
-    let mut buf = Vec::new();
-    let mut chunk = vec![0u8; 64 * 1024];
-    loop {
-        match file.read(&mut chunk) {
-            Ok(0) => break,
-            Ok(n) => {
-                buf.extend_from_slice(&chunk[..n]);
-                if let Some(p) = progress {
-                    p.add(n as u64);
-                }
-            }
-            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-            Err(e) => return Err(format!("read {path}: {e}")),
-        }
-    }
-    Ok(buf)
-}
-
 /// Read a prefix of a remote file for preview purposes.
 pub fn read_bytes_prefix(sftp: &Sftp, path: &str, max_bytes: usize) -> Result<Vec<u8>, String> {
     let mut file = sftp
@@ -519,49 +625,11 @@ pub fn open_remote_reader(sftp: &Sftp, path: &str) -> Result<ssh2::File, String>
         .map_err(|e| format!("open {path}: {e}"))
 }
 
-/// Recursively delete a remote path (file or directory).
-/// Reports each deleted item via `progress.add_item()` when provided.
-pub fn recursive_delete(
-    sftp: &Sftp,
-    path: &str,
-    is_dir: bool,
-    progress: Option<&crate::core::TransferProgress>,
-) -> Result<(), String> {
-    if is_dir {
-        let children = sftp
-            .readdir(Path::new(path))
-            .map_err(|e| format!("readdir {path}: {e}"))?;
-        for (child_path, stat) in children {
-            let name = child_path
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-            if name == "." || name == ".." {
-                continue;
-            }
-            let child_str = child_path.to_string_lossy().to_string();
-            recursive_delete(sftp, &child_str, stat.is_dir(), progress)?;
-        }
-        sftp.rmdir(Path::new(path))
-            .map_err(|e| format!("rmdir {path}: {e}"))?;
-    } else {
-        sftp.unlink(Path::new(path))
-            .map_err(|e| format!("unlink {path}: {e}"))?;
-    }
-    if let Some(p) = progress {
-        p.add_item();
-    }
-    Ok(())
-}
-
-/// Write bytes to a remote file (create or overwrite).
-pub fn write_file(sftp: &Sftp, path: &str, contents: &[u8]) -> Result<(), String> {
-    let mut file = sftp
-        .create(Path::new(path))
-        .map_err(|e| format!("create {path}: {e}"))?;
-    file.write_all(contents)
-        .map_err(|e| format!("write {path}: {e}"))?;
-    Ok(())
+/// Bytes available to the user on the volume holding `path`, through the
+/// OpenSSH `statvfs` extension. `None` when the server lacks it.
+pub fn free_space(sftp: &Sftp, path: &str) -> Option<u64> {
+    let stat = sftp.opendir(Path::new(path)).ok()?.statvfs().ok()?;
+    Some(stat.f_bavail.saturating_mul(stat.f_frsize))
 }
 
 /// Create a remote directory.
@@ -570,47 +638,8 @@ pub fn mkdir(sftp: &Sftp, path: &str) -> Result<(), String> {
         .map_err(|e| format!("mkdir {path}: {e}"))
 }
 
-/// Copy a file within the same remote host (read then write).
-pub fn copy_remote_remote(sftp: &Sftp, src_path: &str, dst_path: &str) -> Result<(), String> {
-    let data = read_file_full(sftp, src_path)?;
-    write_file(sftp, dst_path, &data)
-}
-
-/// Recursively copy a file or directory within the same remote host.
-pub fn recursive_copy_remote(
-    sftp: &Sftp,
-    src_path: &str,
-    dst_dir: &str,
-    name: &str,
-) -> Result<(), String> {
-    let dst_path = format!("{}/{}", dst_dir.trim_end_matches('/'), name);
-    let stat = sftp
-        .stat(Path::new(src_path))
-        .map_err(|e| format!("stat {src_path}: {e}"))?;
-    if stat.is_dir() {
-        sftp.mkdir(Path::new(&dst_path), 0o755)
-            .map_err(|e| format!("mkdir {dst_path}: {e}"))?;
-        let children = sftp
-            .readdir(Path::new(src_path))
-            .map_err(|e| format!("readdir {src_path}: {e}"))?;
-        for (child_path, _) in children {
-            let child_name = child_path
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-            if child_name == "." || child_name == ".." {
-                continue;
-            }
-            let child_src = format!("{}/{}", src_path.trim_end_matches('/'), child_name);
-            recursive_copy_remote(sftp, &child_src, &dst_path, child_name)?;
-        }
-    } else {
-        copy_remote_remote(sftp, src_path, &dst_path)?;
-    }
-    Ok(())
-}
-
-/// A `Read` wrapper that tracks transferred bytes and checks a cancel flag.
+/// A `Read` wrapper that tracks transferred bytes, checks a cancel flag,
+/// and holds to the speed limit.
 struct TrackedReader<'a, R: Read> {
     inner: R,
     cancel: &'a AtomicBool,
@@ -624,12 +653,14 @@ impl<R: Read> Read for TrackedReader<'_, R> {
         let n = self.inner.read(buf)?;
         if let Some(p) = self.progress {
             p.add(n as u64);
+            p.throttle(n as u64, Some(self.cancel));
         }
         Ok(n)
     }
 }
 
-/// A `Write` wrapper that tracks transferred bytes and checks a cancel flag.
+/// A `Write` wrapper that tracks transferred bytes, checks a cancel flag,
+/// and holds to the speed limit.
 struct TrackedWriter<'a, W: Write> {
     inner: W,
     cancel: &'a AtomicBool,
@@ -643,6 +674,7 @@ impl<W: Write> Write for TrackedWriter<'_, W> {
         let n = self.inner.write(buf)?;
         if let Some(p) = self.progress {
             p.add(n as u64);
+            p.throttle(n as u64, Some(self.cancel));
         }
         Ok(n)
     }
@@ -677,8 +709,8 @@ pub fn copy_remote_dir_to_local_via_tar(
 
     let src_cmd = format!(
         "tar cf - -C {} {}",
-        sh_quote(src_parent),
-        sh_quote(src_name)
+        crate::remote::sh_quote(src_parent),
+        crate::remote::sh_quote(src_name)
     );
     let mut src_ch = src_session
         .channel_session()
@@ -735,7 +767,7 @@ pub fn copy_local_dir_to_remote_via_tar(
         .and_then(|s| s.to_str())
         .unwrap_or("dir");
 
-    let dst_cmd = format!("tar xf - -C {}", sh_quote(dst_dir));
+    let dst_cmd = format!("tar xf - -C {}", crate::remote::sh_quote(dst_dir));
     let mut dst_ch = dst_session
         .channel_session()
         .map_err(|e| format!("dst channel_session: {e}"))?;
@@ -782,11 +814,6 @@ pub fn copy_local_dir_to_remote_via_tar(
     Ok(())
 }
 
-/// Shell-quote a string with single quotes, escaping any internal single quotes.
-fn sh_quote(s: &str) -> String {
-    format!("'{}'", s.replace('\'', r"'\''"))
-}
-
 /// Properly tear down a libssh2 exec channel after the local end is done
 /// writing. libssh2's `wait_close()` precondition is `channel->remote.eof`
 /// — it returns LIBSSH2_ERROR_INVAL (-34) with the message
@@ -822,45 +849,6 @@ fn finalize_readonly_channel(ch: &mut ssh2::Channel) -> Result<(i32, String), St
     Ok((ch.exit_status().unwrap_or(-1), stderr))
 }
 
-/// Return the total byte size of a remote path via SSH exec.
-/// Far faster than recursive SFTP readdir for large trees (one round-trip vs O(dirs)).
-///
-/// Strategy (in order):
-///   1. `du -sb`  — Linux/GNU coreutils: exact bytes.
-///   2. `du -sk`  — macOS/BSD POSIX du: 1 KiB blocks → multiply by 1024.
-///   3. Return 0  — Windows SSH or other exotic remote; progress bar shows animated form.
-pub fn count_bytes_via_exec(session: &Session, path: &str) -> u64 {
-    let quoted = sh_quote(path);
-    for (cmd, scale) in [
-        (format!("du -sb {quoted} 2>/dev/null"), 1u64),
-        (format!("du -sk {quoted} 2>/dev/null"), 1024u64),
-    ] {
-        let mut ch = match session.channel_session() {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-        if ch.exec(&cmd).is_err() {
-            let _ = ch.wait_close();
-            continue;
-        }
-        let mut out = String::new();
-        let _ = ch.read_to_string(&mut out);
-        let _ = ch.wait_close();
-        let ok = ch.exit_status().unwrap_or(1) == 0;
-        if ok {
-            // du output: "12345\t/path/name\n" — first token is the numeric value
-            if let Some(n) = out
-                .split_whitespace()
-                .next()
-                .and_then(|s| s.parse::<u64>().ok())
-            {
-                return n * scale;
-            }
-        }
-    }
-    0
-}
-
 /// Return the total byte size of all regular files under `path` on the local filesystem.
 pub fn count_bytes_local(path: &std::path::Path) -> u64 {
     match std::fs::metadata(path) {
@@ -902,10 +890,10 @@ pub fn copy_cross_host_via_tar(
     // connections (source → local → destination).
     let src_cmd = format!(
         "tar czf - -C {} {}",
-        sh_quote(src_parent),
-        sh_quote(src_name)
+        crate::remote::sh_quote(src_parent),
+        crate::remote::sh_quote(src_name)
     );
-    let dst_cmd = format!("tar xzf - -C {}", sh_quote(dst_dir));
+    let dst_cmd = format!("tar xzf - -C {}", crate::remote::sh_quote(dst_dir));
 
     let mut src_ch = src_session
         .channel_session()
@@ -940,6 +928,7 @@ pub fn copy_cross_host_via_tar(
                     .map_err(|e| format!("relay write: {e}"))?;
                 if let Some(p) = progress {
                     p.add(n as u64);
+                    p.throttle(n as u64, Some(cancel));
                 }
             }
             Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
@@ -969,8 +958,8 @@ pub fn copy_cross_host_via_tar(
     if name != src_name {
         let mv_cmd = format!(
             "mv {} {}",
-            sh_quote(&format!("{}/{}", dst_dir.trim_end_matches('/'), src_name)),
-            sh_quote(&format!("{}/{}", dst_dir.trim_end_matches('/'), name)),
+            crate::remote::sh_quote(&format!("{}/{}", dst_dir.trim_end_matches('/'), src_name)),
+            crate::remote::sh_quote(&format!("{}/{}", dst_dir.trim_end_matches('/'), name)),
         );
         let mut mv_ch = dst_session
             .channel_session()
@@ -991,226 +980,12 @@ pub fn copy_cross_host_via_tar(
     Ok(())
 }
 
-/// Count the total byte size of a remote path (file or directory tree).
-pub fn count_bytes_remote(sftp: &Sftp, path: &str) -> u64 {
-    match sftp.stat(Path::new(path)) {
-        Ok(stat) if stat.is_dir() => {
-            let children = sftp.readdir(Path::new(path)).unwrap_or_default();
-            children
-                .into_iter()
-                .filter_map(|(child_path, _)| {
-                    let name = child_path.file_name().and_then(|s| s.to_str())?;
-                    if name == "." || name == ".." {
-                        return None;
-                    }
-                    Some(count_bytes_remote(sftp, &child_path.to_string_lossy()))
-                })
-                .sum()
-        }
-        Ok(stat) => stat.size.unwrap_or(0),
-        Err(_) => 0,
-    }
-}
-
 /// Rename a remote file or directory.
 pub fn rename(sftp: &Sftp, src: &str, dst: &str) -> Result<(), String> {
     sftp.rename(Path::new(src), Path::new(dst), None)
         .map_err(|e| format!("rename {src} -> {dst}: {e}"))
 }
 
-/// Copy a remote file to a local path.
-pub fn copy_remote_to_local(
-    sftp: &Sftp,
-    remote_path: &str,
-    local_dst: &Path,
-) -> Result<(), String> {
-    copy_remote_to_local_progress(sftp, remote_path, local_dst, None, None)
-}
-
-pub fn copy_remote_to_local_progress(
-    sftp: &Sftp,
-    remote_path: &str,
-    local_dst: &Path,
-    cancel: Option<&AtomicBool>,
-    progress: Option<&crate::core::TransferProgress>,
-) -> Result<(), String> {
-    let stat = sftp.stat(Path::new(remote_path)).ok();
-    let expected_size = stat.as_ref().and_then(|s| s.size);
-    if let Some(p) = progress {
-        p.reset(expected_size.unwrap_or(0));
-    }
-    // Inner closure: any Err returned here causes the destination file to
-    // be removed before the error propagates. Keeps partial / zero-size
-    // artifacts from accumulating when a transfer fails or is cancelled.
-    let inner = || -> Result<(), String> {
-        let mut remote_file = sftp
-            .open(Path::new(remote_path))
-            .map_err(|e| format!("open remote {remote_path}: {e}"))?;
-        let mut local_file = std::fs::File::create(local_dst)
-            .map_err(|e| format!("create local {}: {e}", local_dst.display()))?;
-        let mut buf = vec![0u8; 64 * 1024];
-        let mut written: u64 = 0;
-        loop {
-            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
-                return Err("Cancelled".to_string());
-            }
-            match remote_file.read(&mut buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    local_file
-                        .write_all(&buf[..n])
-                        .map_err(|e| format!("write local: {e}"))?;
-                    written += n as u64;
-                    if let Some(p) = progress {
-                        p.add(n as u64);
-                    }
-                }
-                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                Err(e) => return Err(format!("read remote: {e}")),
-            }
-        }
-        // Flush and verify final size against the source.
-        local_file
-            .sync_all()
-            .map_err(|e| format!("sync local: {e}"))?;
-        if let Some(expected) = expected_size
-            && written != expected
-        {
-            return Err(format!(
-                "size mismatch: wrote {written} bytes, expected {expected}"
-            ));
-        }
-        Ok(())
-    };
-    match inner() {
-        Ok(()) => Ok(()),
-        Err(e) => {
-            let _ = std::fs::remove_file(local_dst);
-            Err(e)
-        }
-    }
-}
-
-/// Copy a local file to a remote path.
-pub fn copy_local_to_remote(
-    sftp: &Sftp,
-    local_src: &Path,
-    remote_path: &str,
-) -> Result<(), String> {
-    copy_local_to_remote_progress(sftp, local_src, remote_path, None, None)
-}
-
-pub fn copy_local_to_remote_progress(
-    sftp: &Sftp,
-    local_src: &Path,
-    remote_path: &str,
-    cancel: Option<&AtomicBool>,
-    progress: Option<&crate::core::TransferProgress>,
-) -> Result<(), String> {
-    let expected_size = std::fs::metadata(local_src).map(|m| m.len()).ok();
-    if let Some(p) = progress {
-        p.reset(expected_size.unwrap_or(0));
-    }
-    // Inner closure pattern (mirror of copy_remote_to_local_progress): any
-    // Err triggers cleanup of the partial remote artifact before the error
-    // propagates to the worker.
-    let inner = || -> Result<(), String> {
-        let mut local_file = std::fs::File::open(local_src)
-            .map_err(|e| format!("open local {}: {e}", local_src.display()))?;
-        let mut remote_file = sftp
-            .create(Path::new(remote_path))
-            .map_err(|e| format!("create remote {remote_path}: {e}"))?;
-        let mut buf = vec![0u8; 64 * 1024];
-        let mut written: u64 = 0;
-        loop {
-            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
-                return Err("Cancelled".to_string());
-            }
-            match local_file.read(&mut buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    remote_file
-                        .write_all(&buf[..n])
-                        .map_err(|e| format!("write remote: {e}"))?;
-                    written += n as u64;
-                    if let Some(p) = progress {
-                        p.add(n as u64);
-                    }
-                }
-                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                Err(e) => return Err(format!("read local: {e}")),
-            }
-        }
-        // Close the remote handle by dropping so the server flushes, then
-        // stat to verify the on-disk size matches what we sent.
-        drop(remote_file);
-        if let Some(expected) = expected_size {
-            if written != expected {
-                return Err(format!(
-                    "size mismatch: read {written} bytes, expected {expected}"
-                ));
-            }
-            // Cross-check the destination — guards against a server-side
-            // short-write that the SFTP layer didn't already surface as
-            // a write error.
-            let actual = sftp
-                .stat(Path::new(remote_path))
-                .map_err(|e| format!("stat remote after copy: {e}"))?
-                .size
-                .unwrap_or(0);
-            if actual != expected {
-                return Err(format!(
-                    "size mismatch on remote: {actual} bytes, expected {expected}"
-                ));
-            }
-        }
-        Ok(())
-    };
-    match inner() {
-        Ok(()) => Ok(()),
-        Err(e) => {
-            let _ = sftp.unlink(Path::new(remote_path));
-            Err(e)
-        }
-    }
-}
-
-/// Copy a remote file to another path on the same host.
-pub fn copy_remote(sftp: &Sftp, src: &str, dst: &str) -> Result<(), String> {
-    let mut src_file = sftp
-        .open(Path::new(src))
-        .map_err(|e| format!("open {src}: {e}"))?;
-    let mut dst_file = sftp
-        .create(Path::new(dst))
-        .map_err(|e| format!("create {dst}: {e}"))?;
-    let mut buf = vec![0u8; 64 * 1024];
-    loop {
-        match src_file.read(&mut buf) {
-            Ok(0) => break,
-            Ok(n) => {
-                dst_file
-                    .write_all(&buf[..n])
-                    .map_err(|e| format!("write {dst}: {e}"))?;
-            }
-            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-            Err(e) => return Err(format!("read {src}: {e}")),
-        }
-    }
-    Ok(())
-}
-
-fn parent_remote_path(path: &str) -> String {
-    if path == "/" || path.is_empty() {
-        return "/".to_string();
-    }
-    let trimmed = path.trim_end_matches('/');
-    match trimmed.rfind('/') {
-        Some(0) => "/".to_string(),
-        Some(pos) => trimmed[..pos].to_string(),
-        None => "/".to_string(),
-    }
-}
-
 /// Parse SSH hosts from ~/.ssh/config (cross-platform).
 pub fn discover_ssh_hosts() -> Vec<String> {
     let home = match std::env::var("HOME") {
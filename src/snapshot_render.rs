@@ -66,10 +66,8 @@ pub(crate) fn render_snapshot(
 
     let (preview_tx, _preview_req_rx) = mpsc::channel::<core::PreviewRequest>();
     let (_preview_content_tx, preview_rx) = mpsc::channel::<(u64, core::PreviewContent)>();
-    let (io_tx, _io_rx_unused) = mpsc::channel::<core::IOTask>();
     let (_io_res_tx, io_rx) = mpsc::channel::<core::IOResult>();
-    let (io_cancel_tx, _io_cancel_rx) = mpsc::channel::<()>();
-    let (dir_size_tx, _dir_size_req_rx) = mpsc::channel::<PathBuf>();
+    let (dir_size_tx, _dir_size_req_rx) = mpsc::channel::<(PathBuf, core::SymlinkPolicy)>();
     let (_dir_size_res_tx, dir_size_rx) = mpsc::channel::<(PathBuf, u64)>();
     let (edit_tx, _edit_req_rx) = mpsc::channel::<core::EditLoadRequest>();
     let (_edit_res_tx, edit_res_rx) = mpsc::channel::<core::EditLoadResult>();
@@ -91,9 +89,9 @@ pub(crate) fn render_snapshot(
 
     app.preview_tx = preview_tx;
     app.preview_rx = preview_rx;
-    app.io_tx = io_tx;
+    // A queue no worker takes from, so nothing queued here runs.
+    app.io_queue = std::sync::Arc::new(core::IoQueue::new());
     app.io_rx = io_rx;
-    app.io_cancel_tx = io_cancel_tx;
     app.dir_size_tx = dir_size_tx;
     app.dir_size_rx = dir_size_rx;
     app.edit_tx = edit_tx;
@@ -0,0 +1,478 @@
+//! Directory sync (Ctrl+Shift+S): a recursive comparison of the two panels
+//! turned into copy, overwrite and delete steps, reviewed before they are
+//! queued as ordinary IO tasks.
+
+use std::collections::HashSet;
+
+use crate::compare::{CompareContent, Diff, Difference, MTIME_TOLERANCE, ancestors};
+use crate::core::{ActivePanel, DirEntry, EntryLocation};
+
+/// Which way entries travel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+    /// Each side gets what it lacks, and the newer of two versions wins.
+    /// Nothing is deleted.
+    Both,
+}
+
+impl SyncDirection {
+    pub const ALL: [Self; 3] = [Self::LeftToRight, Self::RightToLeft, Self::Both];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncDirection::LeftToRight => "left → right",
+            SyncDirection::RightToLeft => "right → left",
+            SyncDirection::Both => "both ways",
+        }
+    }
+}
+
+/// What a one-way sync does with entries only the target has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncDeletes {
+    /// Leave them.
+    #[default]
+    Never,
+    /// Delete them, so the target mirrors the source.
+    Mirror,
+}
+
+impl SyncDeletes {
+    pub const ALL: [Self; 2] = [Self::Never, Self::Mirror];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncDeletes::Never => "never delete",
+            SyncDeletes::Mirror => "delete extra entries",
+        }
+    }
+}
+
+/// Everything the sync dialog asks for; kept between runs.
+#[derive(Clone, Default)]
+pub struct SyncOptions {
+    pub direction: SyncDirection,
+    pub deletes: SyncDeletes,
+    pub content: CompareContent,
+    /// Glob patterns to leave out, separated by spaces.
+    pub excludes: String,
+}
+
+impl SyncOptions {
+    pub fn exclude_patterns(&self) -> Vec<String> {
+        self.excludes
+            .split_whitespace()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Copy an entry the target lacks.
+    Copy,
+    /// Replace the target's version of a file.
+    Overwrite,
+    /// Remove an entry only the target has.
+    Delete,
+    /// Make a directory the target lacks, without its contents: some are
+    /// excluded, so the rest are copied one by one.
+    Create,
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Copy => "copy",
+            Action::Overwrite => "overwrite",
+            Action::Delete => "delete",
+            Action::Create => "create",
+        }
+    }
+}
+
+/// One thing the sync will do.
+#[derive(Clone)]
+pub struct Step {
+    /// Relative to the synced directories, with `/` separators.
+    pub path: String,
+    pub action: Action,
+    /// The side written to or deleted from.
+    pub to: ActivePanel,
+    /// What is copied from the other side, or what is deleted.
+    pub entry: DirEntry,
+}
+
+/// A difference the sync leaves alone, and why.
+pub struct Skipped {
+    pub path: String,
+    pub reason: &'static str,
+}
+
+#[derive(Default)]
+pub struct Plan {
+    pub steps: Vec<Step>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl Plan {
+    pub fn count(&self, action: Action) -> usize {
+        self.steps.iter().filter(|s| s.action == action).count()
+    }
+}
+
+fn other(side: ActivePanel) -> ActivePanel {
+    match side {
+        ActivePanel::Left => ActivePanel::Right,
+        ActivePanel::Right => ActivePanel::Left,
+    }
+}
+
+fn only_on(side: ActivePanel) -> Difference {
+    match side {
+        ActivePanel::Left => Difference::OnlyLeft,
+        ActivePanel::Right => Difference::OnlyRight,
+    }
+}
+
+/// The side whose file is known to be newer, if either.
+fn newer(diff: &Diff) -> Option<ActivePanel> {
+    let left = diff.left.as_ref()?.modified?;
+    let right = diff.right.as_ref()?.modified?;
+    if left > right + MTIME_TOLERANCE {
+        Some(ActivePanel::Left)
+    } else if right > left + MTIME_TOLERANCE {
+        Some(ActivePanel::Right)
+    } else {
+        None
+    }
+}
+
+/// What to do about one difference, and to which side.
+fn decide(
+    diff: &Diff,
+    direction: SyncDirection,
+    deletes: SyncDeletes,
+) -> Result<(Action, ActivePanel), &'static str> {
+    let from = match direction {
+        SyncDirection::LeftToRight => ActivePanel::Left,
+        SyncDirection::RightToLeft => ActivePanel::Right,
+        SyncDirection::Both => {
+            return match diff.difference {
+                Difference::Kind => Err("file on one side, directory on the other"),
+                Difference::OnlyLeft => Ok((Action::Copy, ActivePanel::Right)),
+                Difference::OnlyRight => Ok((Action::Copy, ActivePanel::Left)),
+                Difference::LeftNewer => Ok((Action::Overwrite, ActivePanel::Right)),
+                Difference::RightNewer => Ok((Action::Overwrite, ActivePanel::Left)),
+                Difference::Size | Difference::Content => newer(diff)
+                    .map(|side| (Action::Overwrite, other(side)))
+                    .ok_or("differs, but neither side is newer"),
+            };
+        }
+    };
+    let to = other(from);
+    match diff.difference {
+        Difference::Kind => Err("file on one side, directory on the other"),
+        d if d == only_on(from) => Ok((Action::Copy, to)),
+        d if d == only_on(to) => match deletes {
+            SyncDeletes::Mirror => Ok((Action::Delete, to)),
+            SyncDeletes::Never => Err("only on the target; deleting is off"),
+        },
+        _ => Ok((Action::Overwrite, to)),
+    }
+}
+
+/// Turn the differences of a recursive comparison of `left` and `right`
+/// into steps. Archives are never written to, and nothing is copied from
+/// an archive to a remote host. A directory listed along with its contents
+/// holds excluded entries, so it is created rather than copied and kept
+/// rather than deleted.
+pub fn plan(
+    diffs: &[Diff],
+    left: &EntryLocation,
+    right: &EntryLocation,
+    direction: SyncDirection,
+    deletes: SyncDeletes,
+) -> Plan {
+    let root = |side| match side {
+        ActivePanel::Left => left,
+        ActivePanel::Right => right,
+    };
+    let opened: HashSet<&str> = diffs.iter().flat_map(|d| ancestors(&d.path)).collect();
+    let mut plan = Plan::default();
+    for diff in diffs {
+        let decided = decide(diff, direction, deletes).and_then(|(action, to)| {
+            // A delete removes the target's entry; anything else copies the
+            // source's.
+            let side = if action == Action::Delete {
+                to
+            } else {
+                other(to)
+            };
+            let entry = match side {
+                ActivePanel::Left => &diff.left,
+                ActivePanel::Right => &diff.right,
+            };
+            let entry = entry.as_ref().ok_or("missing entry")?;
+            let action = match action {
+                Action::Copy if opened.contains(diff.path.as_str()) => Action::Create,
+                Action::Delete if opened.contains(diff.path.as_str()) => {
+                    return Err("holds excluded entries");
+                }
+                action => action,
+            };
+            match (root(to), &entry.location) {
+                (&EntryLocation::Container { .. }, _) => Err("archives are read-only"),
+                (&EntryLocation::Remote { .. }, &EntryLocation::Container { .. })
+                    if matches!(action, Action::Copy | Action::Overwrite) =>
+                {
+                    Err("can't copy from an archive to a remote host")
+                }
+                _ => Ok(Step {
+                    path: diff.path.clone(),
+                    action,
+                    to,
+                    entry: entry.clone(),
+                }),
+            }
+        });
+        match decided {
+            Ok(step) => plan.steps.push(step),
+            Err(reason) => plan.skipped.push(Skipped {
+                path: diff.path.clone(),
+                reason,
+            }),
+        }
+    }
+    plan
+}
+
+/// The directory on `root`'s side that holds the entry at relative `path`.
+pub fn parent_dir(root: &EntryLocation, path: &str) -> EntryLocation {
+    let Some((parent, _)) = path.rsplit_once('/') else {
+        return root.clone();
+    };
+    match *root {
+        EntryLocation::Fs(ref dir) => EntryLocation::Fs(
+            parent
+                .split('/')
+                .fold(dir.clone(), |dir, name| dir.join(name)),
+        ),
+        EntryLocation::Container {
+            kind,
+            ref archive_path,
+            ref inner_path,
+        } => EntryLocation::Container {
+            kind,
+            archive_path: archive_path.clone(),
+            inner_path: if inner_path.is_empty() {
+                parent.to_string()
+            } else {
+                format!("{}/{parent}", inner_path.trim_end_matches('/'))
+            },
+        },
+        EntryLocation::Remote { ref host, ref path } => EntryLocation::Remote {
+            host: host.clone(),
+            path: crate::remote::join_path(path, parent),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(name: &str, modified: u64) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            is_dir: false,
+            is_symlink: false,
+            link_target: None,
+            location: EntryLocation::Fs(PathBuf::from(name)),
+            size: Some(1),
+            modified: Some(modified),
+        }
+    }
+
+    fn diff(path: &str, difference: Difference, left: Option<u64>, right: Option<u64>) -> Diff {
+        Diff {
+            path: path.to_string(),
+            difference,
+            left: left.map(|m| entry(path, m)),
+            right: right.map(|m| entry(path, m)),
+        }
+    }
+
+    fn diffs() -> Vec<Diff> {
+        vec![
+            diff("new.rs", Difference::OnlyLeft, Some(10), None),
+            diff("old.rs", Difference::OnlyRight, None, Some(10)),
+            diff("lib.rs", Difference::RightNewer, Some(10), Some(20)),
+            diff("main.rs", Difference::Size, Some(30), Some(10)),
+            diff("same.rs", Difference::Content, Some(10), Some(10)),
+        ]
+    }
+
+    fn summary(plan: &Plan) -> Vec<(&str, Action, ActivePanel)> {
+        plan.steps
+            .iter()
+            .map(|s| (s.path.as_str(), s.action, s.to))
+            .collect()
+    }
+
+    #[test]
+    fn one_way_overwrites_regardless_of_age() {
+        let (l, r) = (
+            EntryLocation::Fs(PathBuf::from("/l")),
+            EntryLocation::Fs(PathBuf::from("/r")),
+        );
+        let diffs = diffs();
+        let plan = plan(
+            &diffs,
+            &l,
+            &r,
+            SyncDirection::LeftToRight,
+            SyncDeletes::Never,
+        );
+        assert_eq!(
+            summary(&plan),
+            [
+                ("new.rs", Action::Copy, ActivePanel::Right),
+                ("lib.rs", Action::Overwrite, ActivePanel::Right),
+                ("main.rs", Action::Overwrite, ActivePanel::Right),
+                ("same.rs", Action::Overwrite, ActivePanel::Right),
+            ]
+        );
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].path, "old.rs");
+
+        let mirror = super::plan(
+            &diffs,
+            &l,
+            &r,
+            SyncDirection::RightToLeft,
+            SyncDeletes::Mirror,
+        );
+        assert_eq!(mirror.count(Action::Delete), 1);
+        assert_eq!(mirror.steps[0].path, "new.rs");
+        assert_eq!(mirror.steps[0].to, ActivePanel::Left);
+        assert_eq!(mirror.count(Action::Copy), 1);
+    }
+
+    #[test]
+    fn both_ways_takes_the_newer_and_never_deletes() {
+        let (l, r) = (
+            EntryLocation::Fs(PathBuf::from("/l")),
+            EntryLocation::Fs(PathBuf::from("/r")),
+        );
+        let plan = plan(&diffs(), &l, &r, SyncDirection::Both, SyncDeletes::Mirror);
+        assert_eq!(
+            summary(&plan),
+            [
+                ("new.rs", Action::Copy, ActivePanel::Right),
+                ("old.rs", Action::Copy, ActivePanel::Left),
+                ("lib.rs", Action::Overwrite, ActivePanel::Left),
+                ("main.rs", Action::Overwrite, ActivePanel::Right),
+            ]
+        );
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].path, "same.rs");
+    }
+
+    #[test]
+    fn dirs_with_excluded_entries_are_created_or_kept() {
+        let (l, r) = (
+            EntryLocation::Fs(PathBuf::from("/l")),
+            EntryLocation::Fs(PathBuf::from("/r")),
+        );
+        let mut diffs = vec![
+            diff("old", Difference::OnlyRight, None, Some(1)),
+            diff("old/a.txt", Difference::OnlyRight, None, Some(1)),
+            diff("web", Difference::OnlyLeft, Some(1), None),
+            diff("web/index.html", Difference::OnlyLeft, Some(1), None),
+        ];
+        for d in diffs.iter_mut().filter(|d| !d.path.contains('.')) {
+            for side in [&mut d.left, &mut d.right].into_iter().flatten() {
+                side.is_dir = true;
+            }
+        }
+        let plan = plan(
+            &diffs,
+            &l,
+            &r,
+            SyncDirection::LeftToRight,
+            SyncDeletes::Mirror,
+        );
+        assert_eq!(
+            summary(&plan),
+            [
+                ("old/a.txt", Action::Delete, ActivePanel::Right),
+                ("web", Action::Create, ActivePanel::Right),
+                ("web/index.html", Action::Copy, ActivePanel::Right),
+            ]
+        );
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].path, "old");
+    }
+
+    #[test]
+    fn archives_are_only_read() {
+        let archive = EntryLocation::Container {
+            kind: crate::core::ContainerKind::Zip,
+            archive_path: PathBuf::from("/a.zip"),
+            inner_path: String::new(),
+        };
+        let remote = EntryLocation::Remote {
+            host: "test".to_string(),
+            path: "/srv".to_string(),
+        };
+        let mut from_archive = diff("a.txt", Difference::OnlyLeft, Some(1), None);
+        if let Some(ref mut left) = from_archive.left {
+            left.location = parent_dir(&archive, "docs/a.txt");
+        }
+        let diffs = [
+            from_archive,
+            diff("b.txt", Difference::OnlyRight, None, Some(1)),
+        ];
+        let plan = plan(
+            &diffs,
+            &archive,
+            &remote,
+            SyncDirection::Both,
+            SyncDeletes::Never,
+        );
+        assert!(plan.steps.is_empty());
+        let reasons: Vec<&str> = plan.skipped.iter().map(|s| s.reason).collect();
+        assert_eq!(
+            reasons,
+            [
+                "can't copy from an archive to a remote host",
+                "archives are read-only"
+            ]
+        );
+    }
+
+    #[test]
+    fn parent_dirs_join_on_each_side() {
+        let remote = EntryLocation::Remote {
+            host: "test".to_string(),
+            path: "/srv/app/".to_string(),
+        };
+        match parent_dir(&remote, "static/css/site.css") {
+            EntryLocation::Remote { ref path, .. } => assert_eq!(path, "/srv/app/static/css"),
+            _ => panic!("stays remote"),
+        }
+        match parent_dir(&EntryLocation::Fs(PathBuf::from("/p")), "src/main.rs") {
+            EntryLocation::Fs(ref path) => assert_eq!(path, &PathBuf::from("/p/src")),
+            _ => panic!("stays local"),
+        }
+        match parent_dir(&remote, "top.txt") {
+            EntryLocation::Remote { ref path, .. } => assert_eq!(path, "/srv/app/"),
+            _ => panic!("stays remote"),
+        }
+    }
+}
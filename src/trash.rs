@@ -0,0 +1,410 @@
+//! Freedesktop.org trash: F8 moves local files here instead of deleting them.
+//!
+//! Files on the home filesystem go to `$XDG_DATA_HOME/Trash`; files on other
+//! mounts go to the mount's `.Trash/$uid` (when an admin created a sticky
+//! `.Trash`) or `.Trash-$uid`, so trashing is always a rename and never a
+//! copy. Each trashed entry `files/NAME` has an `info/NAME.trashinfo` with the
+//! original path and deletion date, which is what restore reads back. Other
+//! desktop tools share the same directories, so their items show up too.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::core::{percent_decode, percent_encode_path};
+
+const INFO_EXT: &str = ".trashinfo";
+
+/// One entry in a trash directory.
+pub struct TrashItem {
+    /// The trashed file or directory, `<trash>/files/NAME`.
+    pub path: PathBuf,
+    /// Where it was deleted from.
+    pub original: PathBuf,
+    /// Deletion date as written in the info file (local wall-clock time),
+    /// read as if it were UTC so the date column shows it unchanged.
+    pub deleted: Option<u64>,
+}
+
+/// The home trash, `$XDG_DATA_HOME/Trash` (default `~/.local/share/Trash`).
+pub fn home_trash() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")))?;
+    Some(data_home.join("Trash"))
+}
+
+/// Every trash directory that currently exists: the home trash and the
+/// per-mount ones of the current user.
+pub fn trash_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = home_trash().into_iter().collect();
+    #[cfg(target_os = "linux")]
+    if let Ok(mounts) = fs::read_to_string("/proc/mounts") {
+        let uid = unsafe { libc::getuid() };
+        for line in mounts.lines() {
+            let Some(mount_point) = line.split_whitespace().nth(1) else {
+                continue;
+            };
+            let top = PathBuf::from(crate::app_state::unescape_proc_mount(mount_point));
+            for dir in [
+                top.join(".Trash").join(uid.to_string()),
+                top.join(format!(".Trash-{uid}")),
+            ] {
+                if dir.join("info").is_dir() && !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+    dirs.retain(|d| d.is_dir());
+    dirs
+}
+
+/// Move `path` to the trash on its filesystem. Returns the trashed location.
+pub fn move_to_trash(path: &Path) -> Result<PathBuf, String> {
+    let path = std::path::absolute(path).map_err(|e| e.to_string())?;
+    let (trash, top) = trash_dir_for(&path)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    trash_into(&trash, top.as_deref(), &path, now)
+}
+
+/// Put the file back where it was deleted from and drop its info file.
+/// Refuses to replace anything that has since appeared at the original path.
+pub fn restore(item: &Path) -> Result<PathBuf, String> {
+    let info = info_path(item).ok_or_else(|| format!("Not in a trash: {}", item.display()))?;
+    let text = fs::read_to_string(&info).map_err(|e| format!("{}: {e}", info.display()))?;
+    let (original, _) = parse_info(&text, &mount_top(item))
+        .ok_or_else(|| format!("Unreadable trash info: {}", info.display()))?;
+    if original.symlink_metadata().is_ok() {
+        return Err(format!("{} already exists", original.display()));
+    }
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+    }
+    fs::rename(item, &original).map_err(|e| format!("{}: {e}", original.display()))?;
+    if let Err(e) = fs::remove_file(&info) {
+        log::warn!("Failed to remove {}: {e}", info.display());
+    }
+    Ok(original)
+}
+
+/// Delete a trashed entry for good, along with its info file.
+pub fn purge(item: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(item) {
+        Ok(ref m) if m.is_dir() => fs::remove_dir_all(item)?,
+        Ok(_) => fs::remove_file(item)?,
+        // Already gone; still drop a dangling info file below.
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(info) = info_path(item)
+        && let Err(e) = fs::remove_file(info)
+        && e.kind() != io::ErrorKind::NotFound
+    {
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Items of the given trash directories, skipping info files whose entry is
+/// gone and entries without a readable info file.
+pub fn list(dirs: &[PathBuf]) -> Vec<TrashItem> {
+    let mut items = Vec::new();
+    for dir in dirs {
+        let top = mount_top(&dir.join("files").join("x"));
+        let Ok(read) = fs::read_dir(dir.join("info")) else {
+            continue;
+        };
+        for entry in read.flatten() {
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str().and_then(|n| n.strip_suffix(INFO_EXT)) else {
+                continue;
+            };
+            let path = dir.join("files").join(name);
+            if path.symlink_metadata().is_err() {
+                continue;
+            }
+            let Some((original, deleted)) = fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|text| parse_info(&text, &top))
+            else {
+                continue;
+            };
+            items.push(TrashItem {
+                path,
+                original,
+                deleted,
+            });
+        }
+    }
+    items
+}
+
+/// `<trash>/info/NAME.trashinfo` for `<trash>/files/NAME`.
+fn info_path(item: &Path) -> Option<PathBuf> {
+    let files = item.parent()?;
+    if files.file_name()? != "files" {
+        return None;
+    }
+    let mut name = item.file_name()?.to_os_string();
+    name.push(INFO_EXT);
+    Some(files.parent()?.join("info").join(name))
+}
+
+/// The directory relative `Path=` keys are resolved against: the mount top
+/// for a per-mount trash, `/` for the home trash (which stores absolute paths).
+fn mount_top(item: &Path) -> PathBuf {
+    let trash = item.parent().and_then(Path::parent);
+    let top = trash.and_then(|trash| {
+        let name = trash.file_name()?.to_str()?;
+        if name.starts_with(".Trash-") {
+            return trash.parent();
+        }
+        let admin = trash.parent()?;
+        if admin.file_name()? == ".Trash" {
+            return admin.parent();
+        }
+        None
+    });
+    top.map_or_else(|| PathBuf::from("/"), Path::to_path_buf)
+}
+
+/// Pick the trash for `path`: the home trash when it is on the same
+/// filesystem, otherwise one at the top of the path's mount. Also returns the
+/// mount top that info paths are written relative to.
+#[cfg(unix)]
+fn trash_dir_for(path: &Path) -> Result<(PathBuf, Option<PathBuf>), String> {
+    use std::os::unix::fs::{MetadataExt as _, PermissionsExt as _};
+
+    let home = home_trash().ok_or("No home directory to keep the trash in")?;
+    let dev = path
+        .symlink_metadata()
+        .map_err(|e| format!("{}: {e}", path.display()))?
+        .dev();
+    let home_dev = home
+        .ancestors()
+        .find_map(|p| p.metadata().ok())
+        .map(|m| m.dev());
+    if home_dev == Some(dev) {
+        return Ok((home, None));
+    }
+    let mut top = path.to_path_buf();
+    while let Some(parent) = top.parent()
+        && parent.metadata().is_ok_and(|m| m.dev() == dev)
+    {
+        top = parent.to_path_buf();
+    }
+    let uid = unsafe { libc::getuid() };
+    // An admin-provided `.Trash` must be a real sticky directory; otherwise
+    // the spec says to skip it and use `.Trash-$uid`.
+    let admin = top.join(".Trash");
+    if admin
+        .symlink_metadata()
+        .is_ok_and(|m| m.is_dir() && m.permissions().mode() & 0o1000 != 0)
+    {
+        let dir = admin.join(uid.to_string());
+        if create_private_dir(&dir).is_ok() {
+            return Ok((dir, Some(top)));
+        }
+    }
+    Ok((top.join(format!(".Trash-{uid}")), Some(top)))
+}
+
+#[cfg(not(unix))]
+fn trash_dir_for(_path: &Path) -> Result<(PathBuf, Option<PathBuf>), String> {
+    home_trash()
+        .map(|home| (home, None))
+        .ok_or_else(|| "No home directory to keep the trash in".to_string())
+}
+
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Rename `path` into `trash`, claiming a unique name by creating its info
+/// file first, as the spec requires.
+fn trash_into(trash: &Path, top: Option<&Path>, path: &Path, now: u64) -> Result<PathBuf, String> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    for dir in [&files, &info] {
+        create_private_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+    let base = path
+        .file_name()
+        .ok_or_else(|| format!("Cannot trash {}", path.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let recorded = top
+        .and_then(|top| path.strip_prefix(top).ok())
+        .unwrap_or(path);
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode_path(&recorded.to_string_lossy()),
+        local_date(now)
+    );
+    for n in 1u32.. {
+        let name = if n == 1 {
+            base.clone()
+        } else {
+            format!("{base}.{n}")
+        };
+        let target = files.join(&name);
+        let info_file = info.join(format!("{name}{INFO_EXT}"));
+        if target.symlink_metadata().is_ok() {
+            continue;
+        }
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_file)
+        {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("{}: {e}", info_file.display())),
+        };
+        let written = io::Write::write_all(&mut file, contents.as_bytes())
+            .and_then(|()| fs::rename(path, &target));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&info_file);
+            return Err(format!("Cannot move {} to the trash: {e}", path.display()));
+        }
+        return Ok(target);
+    }
+    unreachable!("ran out of trash names")
+}
+
+/// `(original path, deletion date)` from a `.trashinfo` body.
+fn parse_info(text: &str, top: &Path) -> Option<(PathBuf, Option<u64>)> {
+    let mut original = None;
+    let mut deleted = None;
+    let mut in_section = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line == "[Trash Info]";
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Path=") {
+            original = Some(top.join(percent_decode(value)));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deleted = parse_date(value);
+        }
+    }
+    Some((original?, deleted))
+}
+
+/// `YYYY-MM-DDThh:mm:ss` to seconds, without any zone conversion.
+fn parse_date(value: &str) -> Option<u64> {
+    let (date, time) = value.split_once('T')?;
+    let mut d = date.splitn(3, '-').map(str::parse::<u32>);
+    let mut t = time
+        .splitn(3, ':')
+        .map(|s| s.get(..2).unwrap_or(s).parse::<u32>());
+    crate::core::epoch_from_civil(
+        i64::from(d.next()?.ok()?),
+        d.next()?.ok()?,
+        d.next()?.ok()?,
+        t.next()?.ok()?,
+        t.next()?.ok()?,
+        t.next()?.ok()?,
+    )
+}
+
+/// `YYYY-MM-DDThh:mm:ss` in local time, as the spec asks for.
+fn local_date(epoch: u64) -> String {
+    #[cfg(unix)]
+    {
+        let secs = epoch as libc::time_t;
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if !unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+            return format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                tm.tm_year + 1900,
+                tm.tm_mon + 1,
+                tm.tm_mday,
+                tm.tm_hour,
+                tm.tm_min,
+                tm.tm_sec
+            );
+        }
+    }
+    let (y, m, d, hours, minutes, seconds) = crate::core::civil_from_epoch(epoch);
+    format!("{y:04}-{m:02}-{d:02}T{hours:02}:{minutes:02}:{seconds:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trash_restore_and_purge_round_trip() {
+        let root = std::env::temp_dir().join(format!("fileman_trash_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let trash = root.join("Trash");
+        let work = root.join("work dir");
+        fs::create_dir_all(work.join("sub")).unwrap();
+        fs::write(work.join("notes.txt"), "first").unwrap();
+
+        let trashed = trash_into(&trash, None, &work.join("notes.txt"), 0).unwrap();
+        assert_eq!(trashed, trash.join("files/notes.txt"));
+        let info = fs::read_to_string(trash.join("info/notes.txt.trashinfo")).unwrap();
+        assert!(info.contains("/work%20dir/notes.txt\n"), "{info}");
+
+        // A second file of the same name gets its own slot.
+        fs::write(work.join("notes.txt"), "second").unwrap();
+        let again = trash_into(&trash, None, &work.join("notes.txt"), 0).unwrap();
+        assert_eq!(again, trash.join("files/notes.txt.2"));
+        let dir = trash_into(&trash, None, &work.join("sub"), 0).unwrap();
+
+        let mut items = list(std::slice::from_ref(&trash));
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].original, work.join("notes.txt"));
+        assert_eq!(items[1].original, work.join("notes.txt"));
+
+        assert_eq!(restore(&trashed).unwrap(), work.join("notes.txt"));
+        assert_eq!(fs::read_to_string(work.join("notes.txt")).unwrap(), "first");
+        // The original path is taken now, so the other copy stays put.
+        assert!(restore(&again).is_err());
+        assert!(again.exists());
+
+        purge(&again).unwrap();
+        purge(&dir).unwrap();
+        assert!(list(std::slice::from_ref(&trash)).is_empty());
+        assert_eq!(fs::read_dir(trash.join("info")).unwrap().count(), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn info_paths_resolve_against_the_mount_top() {
+        let text = "[Trash Info]\nPath=photos/caf%C3%A9.jpg\nDeletionDate=2024-03-05T14:07:09\n";
+        let (original, deleted) = parse_info(text, Path::new("/mnt/usb")).unwrap();
+        assert_eq!(original, Path::new("/mnt/usb/photos/café.jpg"));
+        assert_eq!(deleted, crate::core::epoch_from_civil(2024, 3, 5, 14, 7, 9));
+        assert_eq!(
+            mount_top(Path::new("/mnt/usb/.Trash-1000/files/a")),
+            Path::new("/mnt/usb")
+        );
+        assert_eq!(
+            mount_top(Path::new("/mnt/usb/.Trash/1000/files/a")),
+            Path::new("/mnt/usb")
+        );
+        assert_eq!(
+            mount_top(Path::new("/home/me/.local/share/Trash/files/a")),
+            Path::new("/")
+        );
+        assert!(parse_info("[Other]\nPath=/x\n", Path::new("/")).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use fileman::{app_state, theme};
+use fileman::{app_state, core, theme};
 
 use crate::color32;
 
@@ -26,13 +26,24 @@ pub fn draw_command_bar(ui: &mut egui::Ui, app: &app_state::AppState, colors: &t
                         }
                         draw_key_cap(ui, k!("F1", "^H"), "Help", colors);
                         draw_key_cap(ui, k!("F2", "^R"), "Refresh", colors);
-                        let (mut f3, f4, mut f5, mut f6, f7, f8) = if modifiers.alt {
+                        let browser_mode = &app.get_active_panel().browser().browser_mode;
+                        let (mut f3, f4, mut f5, mut f6, f7, mut f8) = if modifiers.alt {
                             ("", "", "Pack", "Unpack", "Search", "")
                         } else if modifiers.shift {
-                            ("", "New", "Copy", "Rename", "", "")
+                            ("", "New", "Copy", "Rename", "", "Delete")
                         } else {
-                            ("View", "Edit", "Copy", "Move", "Mkdir", "Delete")
+                            ("View", "Edit", "Copy", "Move", "Mkdir", "Trash")
                         };
+                        // Remote files have no trash; in the Trash view F6/F8
+                        // act on the trashed items.
+                        match *browser_mode {
+                            core::BrowserMode::Remote { .. } if !modifiers.alt => f8 = "Delete",
+                            core::BrowserMode::Trash if !modifiers.alt => {
+                                f6 = if modifiers.shift { "" } else { "Restore" };
+                                f8 = "Purge";
+                            }
+                            _ => {}
+                        }
                         if preview_side.is_some() && !modifiers.alt && !modifiers.shift {
                             f3 = "Exit";
                         }
@@ -0,0 +1,112 @@
+use fileman::{app_state, core};
+
+use crate::color32;
+
+pub enum ConnectionAction {
+    Reconnect(String),
+    Disconnect(String),
+}
+
+/// Compact uptime: "45s", "12m", "3h 05m", "2d 04h".
+fn format_uptime(uptime: std::time::Duration) -> String {
+    let secs = uptime.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h {:02}m", secs / 3600, secs / 60 % 60)
+    } else {
+        format!("{}d {:02}h", secs / 86400, secs / 3600 % 24)
+    }
+}
+
+pub fn draw_connections(
+    ctx: &egui::Context,
+    app: &mut app_state::AppState,
+) -> Option<ConnectionAction> {
+    let selected = app.connections_selected()?;
+    let colors = app.theme.colors();
+    let infos = app.connection_infos();
+
+    let screen = ctx.content_rect();
+    let overlay_layer = egui::LayerId::new(egui::Order::Foreground, "connections_overlay".into());
+    ctx.layer_painter(overlay_layer).rect_filled(
+        screen,
+        egui::CornerRadius::ZERO,
+        egui::Color32::from_black_alpha(160),
+    );
+
+    let mut action = None;
+    egui::Window::new("Connections")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            if infos.is_empty() {
+                ui.colored_label(color32(colors.row_fg_inactive), "No open SFTP sessions");
+                return;
+            }
+            egui::Grid::new("connections_grid")
+                .num_columns(5)
+                .spacing(egui::vec2(16.0, 4.0))
+                .show(ui, |ui| {
+                    for header in ["Host", "User", "Uptime", "Transferred", "Server"] {
+                        ui.colored_label(color32(colors.preview_header_fg), header);
+                    }
+                    ui.end_row();
+                    for (i, info) in infos.iter().enumerate() {
+                        let fg = if i == selected {
+                            color32(colors.row_fg_selected)
+                        } else {
+                            color32(colors.row_fg_inactive)
+                        };
+                        if ui
+                            .selectable_label(
+                                i == selected,
+                                egui::RichText::new(&info.host).color(fg).strong(),
+                            )
+                            .clicked()
+                        {
+                            app.select_connection(i);
+                        }
+                        ui.colored_label(fg, &info.user);
+                        let uptime = if info.reconnects > 0 {
+                            format!(
+                                "{} ({} reconnects)",
+                                format_uptime(info.uptime),
+                                info.reconnects
+                            )
+                        } else {
+                            format_uptime(info.uptime)
+                        };
+                        ui.colored_label(fg, uptime);
+                        ui.colored_label(fg, core::format_size(info.bytes));
+                        ui.colored_label(fg, info.banner.as_deref().unwrap_or("-"));
+                        ui.end_row();
+                    }
+                });
+            ui.add_space(8.0);
+            let host = infos.get(selected).map(|info| info.host.clone());
+            ui.horizontal(|ui| {
+                if ui
+                    .add(egui::Button::new("Reconnect (R)").min_size(egui::vec2(120.0, 0.0)))
+                    .clicked()
+                    && let Some(ref host) = host
+                {
+                    action = Some(ConnectionAction::Reconnect(host.clone()));
+                }
+                if ui
+                    .add(egui::Button::new("Disconnect (Del)").min_size(egui::vec2(120.0, 0.0)))
+                    .clicked()
+                    && let Some(ref host) = host
+                {
+                    action = Some(ConnectionAction::Disconnect(host.clone()));
+                }
+            });
+        });
+    // Keep the uptime column ticking.
+    ctx.request_repaint_after(std::time::Duration::from_secs(1));
+    action
+}
@@ -1,5 +1,8 @@
-use fileman::app_state::{AsyncStatus, ErrorLogEntry, SearchStatus, UpdateStatus};
-use fileman::theme;
+use fileman::{
+    app_state::{AsyncStatus, ErrorLogEntry, SearchStatus, UpdateStatus},
+    core::{format_duration, format_size},
+    theme,
+};
 
 use crate::color32;
 
@@ -32,20 +35,30 @@ pub fn draw_help(
             ("Shift+F4 / Ctrl+N", "New file"),
             ("F7 / Ctrl+D", "New directory"),
             ("Insert / Ctrl+I", "Mark / unmark"),
-            ("Shift+F6 / Ctrl+Shift+M", "Rename"),
+            ("Shift+F6 / Ctrl+Shift+M", "Rename (marked: batch rename)"),
             ("F5 / Ctrl+C", "Copy"),
             ("Ctrl+Shift+C", "Copy path to clipboard"),
             ("Ctrl+,", "Settings"),
             ("Alt+F5 / Ctrl+A", "Pack (create archive)"),
             ("F6 / Ctrl+M", "Move"),
-            ("F8 / Ctrl+X", "Delete"),
+            ("Alt+F6", "Create link (H: kind, R: relative)"),
+            ("F8 / Ctrl+X", "Move to Trash (remote: delete)"),
+            ("Shift+F8", "Delete permanently"),
+            ("F6 / F8 in Trash view", "Restore / purge"),
             ("Space", "Compute folder size"),
             ("Alt+F7", "Search by name"),
             ("Shift+Alt+F7", "Search by content"),
             ("Ctrl+F", "Find in preview"),
             ("Alt+Enter", "Properties"),
             ("F2 / Ctrl+R", "Refresh"),
+            ("Shift+F2", "Compare directories (R: recursive, H: hash)"),
+            ("Ctrl+Shift+S", "Sync directories"),
             ("Ctrl+G", "Quick jump"),
+            ("Ctrl+K", "SFTP connections"),
+            ("Ctrl+O", "Run command"),
+            ("Ctrl+J", "Operation queue"),
+            ("Ctrl+Z", "Undo last file operation"),
+            ("Ctrl+Shift+Z", "Operation history"),
             ("F9", "Toggle theme"),
             ("F10", "Theme picker"),
             ("F1 / Ctrl+H", "Help"),
@@ -131,7 +144,7 @@ pub fn draw_help(
 }
 
 /// Compact relative-time format for the error log: "3s", "12m", "2h", "5d".
-fn relative_time(now: std::time::Instant, when: std::time::Instant) -> String {
+pub(crate) fn relative_time(now: std::time::Instant, when: std::time::Instant) -> String {
     let elapsed = now.saturating_duration_since(when).as_secs();
     if elapsed < 60 {
         format!("{elapsed:>3}s")
@@ -216,7 +229,28 @@ fn draw_async_status(ui: &mut egui::Ui, colors: &theme::ThemeColors, status: &As
     } else if status.io_cancel_requested {
         format!("{} tasks (cancelling)", status.io_in_flight)
     } else {
-        format!("{} tasks in flight", status.io_in_flight)
+        let mut label = if status.io_batch_total > 1 {
+            let completed = status.io_batch_total.saturating_sub(status.io_in_flight);
+            format!(
+                "{} tasks in flight ({completed} of {} done)",
+                status.io_in_flight, status.io_batch_total
+            )
+        } else {
+            format!("{} tasks in flight", status.io_in_flight)
+        };
+        if let Some(rate) = status.io_rate {
+            label.push_str(&format!(", {}/s", format_size(rate as u64)));
+        }
+        if let Some(elapsed) = status.io_elapsed {
+            label.push_str(&format!(", {} elapsed", format_duration(elapsed)));
+        }
+        if let Some(eta) = status.io_eta {
+            label.push_str(&format!(", {} left", format_duration(eta)));
+        }
+        if let Some(method) = status.io_copy_method {
+            label.push_str(&format!(", {}", method.label()));
+        }
+        label
     };
     draw_worker_row(ui, colors, "IO", &io_label, status.io_in_flight > 0);
 
@@ -1,10 +1,13 @@
 pub mod command_bar;
+pub mod connections;
 pub mod editor;
 pub mod help;
 pub mod modals;
+pub mod multi_rename;
 pub mod panel;
 pub mod preview;
 pub mod props_dialog;
 pub mod quick_jump;
 pub mod settings;
+pub mod sync;
 pub mod theme_picker;
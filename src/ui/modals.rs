@@ -26,6 +26,9 @@ pub fn draw_confirmation(ctx: &egui::Context, app: &mut app_state::AppState) {
         op,
         app_state::PendingOp::Rename { .. } | app_state::PendingOp::Pack { .. }
     );
+    let meets_symlinks = app.pending_op_meets_symlinks();
+    let is_link = matches!(op, app_state::PendingOp::Link { .. });
+    let is_compare = matches!(op, app_state::PendingOp::Compare { .. });
     let collisions = &app.pending_collisions;
     let overwrite = !collisions.is_empty();
 
@@ -40,10 +43,10 @@ pub fn draw_confirmation(ctx: &egui::Context, app: &mut app_state::AppState) {
                 ui.add_space(8.0);
                 let warn = egui::Color32::from_rgb(230, 170, 70);
                 let header = if collisions.len() == 1 {
-                    "1 item already exists and will be overwritten:".to_string()
+                    "1 item already exists; you will be asked what to do:".to_string()
                 } else {
                     format!(
-                        "{} items already exist and will be overwritten:",
+                        "{} items already exist; you will be asked about each:",
                         collisions.len()
                     )
                 };
@@ -55,6 +58,75 @@ pub fn draw_confirmation(ctx: &egui::Context, app: &mut app_state::AppState) {
                     ui.colored_label(warn, format!("  … and {} more", collisions.len() - 6));
                 }
             }
+            if meets_symlinks {
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    ui.colored_label(color32(colors.row_fg_inactive), "Symlinks (L):");
+                    for policy in fileman::core::SymlinkPolicy::ALL {
+                        if ui
+                            .selectable_label(app.symlink_policy == policy, policy.label())
+                            .clicked()
+                        {
+                            app.symlink_policy = policy;
+                        }
+                    }
+                });
+            }
+            if is_link {
+                ui.add_space(8.0);
+                let label_color = color32(colors.row_fg_inactive);
+                ui.horizontal(|ui| {
+                    ui.colored_label(label_color, "Kind (H):");
+                    for kind in fileman::core::LinkKind::ALL {
+                        if ui
+                            .selectable_label(app.link_kind == kind, kind.label())
+                            .clicked()
+                        {
+                            app.link_kind = kind;
+                        }
+                    }
+                });
+                // Hard links have no target path to spell out.
+                if app.link_kind == fileman::core::LinkKind::Symbolic {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(label_color, "Target (R):");
+                        for target in fileman::core::LinkTarget::ALL {
+                            if ui
+                                .selectable_label(app.link_target == target, target.label())
+                                .clicked()
+                            {
+                                app.link_target = target;
+                            }
+                        }
+                    });
+                }
+            }
+            if is_compare {
+                ui.add_space(8.0);
+                let label_color = color32(colors.row_fg_inactive);
+                ui.horizontal(|ui| {
+                    ui.colored_label(label_color, "Depth (R):");
+                    for depth in fileman::compare::CompareDepth::ALL {
+                        if ui
+                            .selectable_label(app.compare_depth == depth, depth.label())
+                            .clicked()
+                        {
+                            app.compare_depth = depth;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.colored_label(label_color, "Files (H):");
+                    for content in fileman::compare::CompareContent::ALL {
+                        if ui
+                            .selectable_label(app.compare_content == content, content.label())
+                            .clicked()
+                        {
+                            app.compare_content = content;
+                        }
+                    }
+                });
+            }
             if is_rename {
                 ui.add_space(8.0);
                 let mut name = app.rename_input.clone().unwrap_or_default();
@@ -115,8 +187,8 @@ pub fn draw_confirmation(ctx: &egui::Context, app: &mut app_state::AppState) {
     }
 }
 
-pub fn draw_progress_modal(ctx: &egui::Context, app: &app_state::AppState) {
-    if app.io_in_flight == 0 {
+pub fn draw_progress_modal(ctx: &egui::Context, app: &mut app_state::AppState) {
+    if !app.io_modal_visible() {
         return;
     }
 
@@ -136,10 +208,26 @@ pub fn draw_progress_modal(ctx: &egui::Context, app: &app_state::AppState) {
         .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
         .show(ctx, |ui| {
             ui.add_space(6.0);
+            let running: Vec<_> = app
+                .io_queue
+                .snapshot()
+                .0
+                .into_iter()
+                .filter_map(|item| item.progress)
+                .collect();
+            let completed = app.io_batch_total.saturating_sub(app.io_in_flight);
             let header = if app.io_cancel_requested {
                 "Cancelling…".to_string()
+            } else if app.io_queue.is_paused() && running.is_empty() {
+                "Queue paused".to_string()
+            } else if running.len() > 1 {
+                format!(
+                    "Working — {} tasks at once, {} of {} done",
+                    running.len(),
+                    completed,
+                    app.io_batch_total
+                )
             } else if app.io_batch_total > 1 {
-                let completed = app.io_batch_total.saturating_sub(app.io_in_flight);
                 let current = completed + 1;
                 format!(
                     "Working — file {} of {}",
@@ -151,65 +239,318 @@ pub fn draw_progress_modal(ctx: &egui::Context, app: &app_state::AppState) {
             };
             ui.colored_label(color32(colors.row_fg_active), header);
 
-            // Current file name. Mid-truncate so the leading dir context
-            // and the filename suffix both stay visible.
-            if let Some(name) = app.transfer_progress.current_name() {
-                let display = if name.chars().count() > 64 {
-                    let head: String = name.chars().take(28).collect();
-                    let tail: String = name
-                        .chars()
-                        .rev()
-                        .take(28)
-                        .collect::<Vec<_>>()
-                        .into_iter()
-                        .rev()
-                        .collect();
-                    format!("{head}…{tail}")
-                } else {
-                    name
-                };
-                ui.colored_label(
-                    color32(colors.row_fg_inactive),
-                    egui::RichText::new(display).monospace(),
-                );
+            // One row per running task, in the order they started.
+            for progress in &running {
+                ui.add_space(6.0);
+                draw_task_progress(ui, &colors, progress);
+            }
+            if running
+                .iter()
+                .any(|progress| progress.held.load(std::sync::atomic::Ordering::Relaxed))
+            {
+                ui.add_space(6.0);
+                if ui
+                    .add(egui::Button::new("Start (Enter)").min_size(egui::vec2(120.0, 0.0)))
+                    .clicked()
+                {
+                    app.release_held();
+                }
+            }
+            if let Some(conflict) = app
+                .conflicting_task()
+                .and_then(|progress| progress.pending_conflict())
+            {
+                ui.add_space(6.0);
+                draw_conflict(ui, app, &conflict);
             }
-
             ui.add_space(8.0);
-            let (done, total) = app.transfer_progress.snapshot();
-            let items = app
-                .transfer_progress
-                .items_done
-                .load(std::sync::atomic::Ordering::Relaxed);
-            if total > 0 {
-                let fraction = (done as f32 / total as f32).clamp(0.0, 1.0);
-                let done_fmt = fileman::core::format_size(done);
-                let total_fmt = fileman::core::format_size(total);
+            // Whole batch, by tasks finished; the bars above are per task.
+            if app.io_batch_total > 1 {
                 ui.add(
-                    egui::ProgressBar::new(fraction)
-                        .text(format!("{done_fmt} / {total_fmt}"))
+                    egui::ProgressBar::new(completed as f32 / app.io_batch_total as f32)
+                        .text(format!("{completed} of {} done", app.io_batch_total))
                         .animate(false),
                 );
-            } else if done > 0 {
-                let done_fmt = fileman::core::format_size(done);
-                ui.add(
-                    egui::ProgressBar::new(0.0)
-                        .text(format!("{done_fmt}…"))
-                        .animate(true),
-                );
-            } else if items > 0 {
-                ui.colored_label(
-                    color32(colors.row_fg_active),
-                    format!("Deleted {items} items…"),
-                );
-            } else {
-                ui.add(egui::ProgressBar::new(0.0).animate(true));
+            }
+            let status = app.async_status();
+            let mut stats = Vec::new();
+            if let Some(rate) = status.io_rate {
+                stats.push(format!("{}/s", fileman::core::format_size(rate as u64)));
+            }
+            if let Some(elapsed) = status.io_elapsed {
+                stats.push(format!(
+                    "{} elapsed",
+                    fileman::core::format_duration(elapsed)
+                ));
+            }
+            if let Some(eta) = status.io_eta {
+                stats.push(format!("{} left", fileman::core::format_duration(eta)));
+            }
+            if let Some(method) = status.io_copy_method {
+                stats.push(method.label().to_string());
+            }
+            if !stats.is_empty() {
+                ui.colored_label(color32(colors.row_fg_inactive), stats.join(" · "));
             }
             ctx.request_repaint_after(std::time::Duration::from_millis(120));
             ui.add_space(6.0);
-            ui.colored_label(color32(colors.row_fg_inactive), "Esc: cancel");
+            // Changes here last for the session; Settings saves them.
+            ui.horizontal(|ui| {
+                ui.colored_label(color32(colors.row_fg_inactive), "Speed limit");
+                let limit = crate::ui::settings::bandwidth_limit_value(
+                    &mut app.settings.bandwidth_limit_kib,
+                );
+                if ui.add(limit).changed() {
+                    app.apply_transfer_settings();
+                }
+                ui.colored_label(color32(colors.row_fg_inactive), "0 = unlimited");
+            });
+            ui.colored_label(
+                color32(colors.row_fg_inactive),
+                "B: keep browsing · Ctrl+J: queue · Esc: cancel all",
+            );
         });
 }
 
+/// Name and bar of one running IO task.
+fn draw_task_progress(
+    ui: &mut egui::Ui,
+    colors: &fileman::theme::ThemeColors,
+    progress: &fileman::core::TransferProgress,
+) {
+    // Mid-truncate so the leading dir context and the filename suffix both
+    // stay visible.
+    if let Some(name) = progress.current_name() {
+        let display = if name.chars().count() > 64 {
+            let head: String = name.chars().take(28).collect();
+            let tail: String = name
+                .chars()
+                .rev()
+                .take(28)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect();
+            format!("{head}…{tail}")
+        } else {
+            name
+        };
+        ui.colored_label(
+            color32(colors.row_fg_inactive),
+            egui::RichText::new(display).monospace(),
+        );
+    }
+    let (done, total) = progress.snapshot();
+    let items = progress
+        .items_done
+        .load(std::sync::atomic::Ordering::Relaxed);
+    if progress.held.load(std::sync::atomic::Ordering::Relaxed) {
+        ui.colored_label(
+            color32(colors.row_fg_active),
+            format!(
+                "Held: {} is over the hold size in Settings.",
+                fileman::core::format_size(total)
+            ),
+        );
+    } else if progress.pending_conflict().is_some() {
+        ui.colored_label(
+            color32(colors.row_fg_active),
+            "Waiting for an answer below.",
+        );
+    } else if total > 0 {
+        let fraction = (done as f32 / total as f32).clamp(0.0, 1.0);
+        let done_fmt = fileman::core::format_size(done);
+        let total_fmt = fileman::core::format_size(total);
+        let mut text = format!("{done_fmt} / {total_fmt}");
+        if let Some(eta) = progress.eta() {
+            text.push_str(&format!(" · {} left", fileman::core::format_duration(eta)));
+        }
+        ui.add(egui::ProgressBar::new(fraction).text(text).animate(false));
+    } else if done > 0 {
+        let done_fmt = fileman::core::format_size(done);
+        ui.add(
+            egui::ProgressBar::new(0.0)
+                .text(format!("{done_fmt}…"))
+                .animate(true),
+        );
+    } else if items > 0 {
+        ui.colored_label(
+            color32(colors.row_fg_active),
+            format!("Deleted {items} items…"),
+        );
+    } else {
+        ui.add(egui::ProgressBar::new(0.0).animate(true));
+    }
+}
+
+/// The IO queue (Ctrl+J): the running tasks, then pending ones in order.
+/// Closing it leaves the queue running in the background.
+pub fn draw_queue(ctx: &egui::Context, app: &mut app_state::AppState) {
+    let Some(selected) = app.queue_selected() else {
+        return;
+    };
+    let colors = app.theme.colors();
+    let screen = ctx.content_rect();
+    let overlay_layer = egui::LayerId::new(egui::Order::Foreground, "queue_overlay".into());
+    ctx.layer_painter(overlay_layer).rect_filled(
+        screen,
+        egui::CornerRadius::ZERO,
+        egui::Color32::from_black_alpha(160),
+    );
+
+    let rows = app.queue_rows();
+    let paused = app.io_queue.is_paused();
+    egui::Window::new("Queue")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let state = if paused { "Paused" } else { "Running" };
+                ui.colored_label(color32(colors.row_fg_active), state);
+                let toggle = if paused {
+                    "Resume (Space)"
+                } else {
+                    "Pause (Space)"
+                };
+                if ui.button(toggle).clicked() {
+                    app.toggle_queue_pause();
+                }
+            });
+            ui.add_space(6.0);
+            if rows.is_empty() {
+                ui.colored_label(color32(colors.row_fg_inactive), "Nothing queued");
+            }
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    for (i, row) in rows.iter().enumerate() {
+                        let marker = match row.1 {
+                            app_state::QueueRowState::Running => "▶",
+                            app_state::QueueRowState::Pending => " ",
+                        };
+                        let fg = if i == selected {
+                            color32(colors.row_fg_selected)
+                        } else {
+                            color32(colors.row_fg_inactive)
+                        };
+                        let response = ui.selectable_label(
+                            i == selected,
+                            egui::RichText::new(format!("{marker} {}", row.0.name))
+                                .color(fg)
+                                .monospace(),
+                        );
+                        if i == selected {
+                            response.scroll_to_me(None);
+                        }
+                        if response.clicked() {
+                            app.select_queue_row(i);
+                        }
+                    }
+                });
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button("Earlier (Shift+↑)").clicked() {
+                    app.shift_queue_row(fileman::core::QueueShift::Earlier);
+                }
+                if ui.button("Later (Shift+↓)").clicked() {
+                    app.shift_queue_row(fileman::core::QueueShift::Later);
+                }
+                if ui.button("Cancel (Del)").clicked() {
+                    app.cancel_queue_row();
+                }
+            });
+            ui.colored_label(
+                color32(colors.row_fg_inactive),
+                "Esc: close, the queue keeps running",
+            );
+        });
+    ctx.request_repaint_after(std::time::Duration::from_millis(250));
+}
+
+/// Answers to a name collision, with the keys that pick them.
+pub const CONFLICT_CHOICES: [(&str, egui::Key, fileman::core::ConflictAction); 5] = [
+    (
+        "Overwrite (O)",
+        egui::Key::O,
+        fileman::core::ConflictAction::Overwrite,
+    ),
+    (
+        "Skip (S)",
+        egui::Key::S,
+        fileman::core::ConflictAction::Skip,
+    ),
+    (
+        "Rename (R)",
+        egui::Key::R,
+        fileman::core::ConflictAction::Rename,
+    ),
+    (
+        "If newer (N)",
+        egui::Key::N,
+        fileman::core::ConflictAction::OverwriteIfNewer,
+    ),
+    (
+        "If size differs (D)",
+        egui::Key::D,
+        fileman::core::ConflictAction::OverwriteIfSizeDiffers,
+    ),
+];
+
+/// The name-collision prompt inside the progress window: both sides next
+/// to each other and the choices, with their keys.
+fn draw_conflict(
+    ui: &mut egui::Ui,
+    app: &mut app_state::AppState,
+    conflict: &fileman::core::Conflict,
+) {
+    let colors = app.theme.colors();
+    let warn = egui::Color32::from_rgb(230, 170, 70);
+    ui.colored_label(warn, format!("{} already exists", conflict.name));
+    let size = |side: &fileman::core::ConflictSide| match (side.is_dir, side.size) {
+        (true, _) => "folder".to_string(),
+        (false, Some(size)) => fileman::core::format_size(size),
+        (false, None) => "?".to_string(),
+    };
+    let date = |side: &fileman::core::ConflictSide| {
+        side.mtime
+            .map_or_else(|| "?".to_string(), fileman::core::format_date)
+    };
+    egui::Grid::new("conflict_sides")
+        .num_columns(3)
+        .spacing(egui::vec2(16.0, 2.0))
+        .show(ui, |ui| {
+            let dim = color32(colors.row_fg_inactive);
+            let fg = color32(colors.row_fg_active);
+            ui.label("");
+            ui.colored_label(dim, "New");
+            ui.colored_label(dim, "Existing");
+            ui.end_row();
+            ui.colored_label(dim, "Size");
+            ui.colored_label(fg, size(&conflict.source));
+            ui.colored_label(fg, size(&conflict.target));
+            ui.end_row();
+            ui.colored_label(dim, "Modified");
+            ui.colored_label(fg, date(&conflict.source));
+            ui.colored_label(fg, date(&conflict.target));
+            ui.end_row();
+        });
+    ui.add_space(4.0);
+    let mut answer = None;
+    ui.horizontal_wrapped(|ui| {
+        for (label, _, action) in CONFLICT_CHOICES {
+            if ui.button(label).clicked() {
+                answer = Some(action);
+            }
+        }
+    });
+    ui.checkbox(&mut app.conflict_apply_all, "Apply to all (A)");
+    if let Some(action) = answer {
+        app.answer_conflict(action);
+    }
+}
+
 pub fn draw_discard_modal(ctx: &egui::Context, app: &mut app_state::AppState) {
     let colors = app.theme.colors();
     let Some(side) = app.edit_panel_side() else {
@@ -264,6 +605,21 @@ pub fn draw_discard_modal(ctx: &egui::Context, app: &mut app_state::AppState) {
     }
 }
 
+/// "\"name\"" for a single target, "N items" otherwise.
+fn describe_targets(targets: &[std::path::PathBuf]) -> String {
+    if targets.len() == 1 {
+        format!(
+            "\"{}\"",
+            targets[0]
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("<unknown>")
+        )
+    } else {
+        format!("{} items", targets.len())
+    }
+}
+
 fn pending_op_text(op: &app_state::PendingOp) -> (&'static str, String) {
     match op {
         app_state::PendingOp::Copy { items, dst } => {
@@ -298,20 +654,27 @@ fn pending_op_text(op: &app_state::PendingOp) -> (&'static str, String) {
             };
             ("Confirm Move", body)
         }
-        app_state::PendingOp::Delete { targets } => {
-            let body = if targets.len() == 1 {
-                format!(
-                    "Delete \"{}\"?",
-                    targets[0]
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("<unknown>")
-                )
-            } else {
-                format!("Delete {} items?", targets.len())
-            };
-            ("Confirm Delete", body)
-        }
+        app_state::PendingOp::Delete { targets, mode } => match *mode {
+            app_state::DeleteMode::Trash => (
+                "Move to Trash",
+                format!("Move {} to Trash?", describe_targets(targets)),
+            ),
+            app_state::DeleteMode::Permanent => (
+                "Confirm Delete",
+                format!("Delete {} permanently?", describe_targets(targets)),
+            ),
+        },
+        app_state::PendingOp::Restore { targets } => (
+            "Restore",
+            format!("Restore {} from the Trash?", describe_targets(targets)),
+        ),
+        app_state::PendingOp::Purge { targets } => (
+            "Confirm Purge",
+            format!(
+                "Delete {} from the Trash permanently?",
+                describe_targets(targets)
+            ),
+        ),
         app_state::PendingOp::Rename { src } => (
             "Rename",
             format!(
@@ -340,5 +703,96 @@ fn pending_op_text(op: &app_state::PendingOp) -> (&'static str, String) {
             };
             ("Pack", body)
         }
+        app_state::PendingOp::Link { items, dst } => {
+            let dst_display = match dst {
+                app_state::CopyDest::Local(dir) => dir.to_string_lossy().into_owned(),
+                app_state::CopyDest::Remote { host, path } => format!("{host}:{path}"),
+            };
+            let body = if items.len() == 1 {
+                format!(
+                    "Link to \"{}\" from\n{}?",
+                    items[0].src.display_name(),
+                    dst_display
+                )
+            } else {
+                format!("Link to {} items from\n{}?", items.len(), dst_display)
+            };
+            ("Create Link", body)
+        }
+        app_state::PendingOp::Compare { left, right } => (
+            "Compare Directories",
+            format!(
+                "Compare\n{}\nwith\n{}?",
+                fileman::compare::describe(left),
+                fileman::compare::describe(right)
+            ),
+        ),
     }
 }
+
+/// Operation history (Ctrl+Shift+Z), newest first. Returns the row to undo
+/// when its button was clicked; Enter is handled in `input`.
+pub fn draw_undo_history(ctx: &egui::Context, app: &mut app_state::AppState) -> Option<usize> {
+    let selected = app.undo_history_selected()?;
+    let colors = app.theme.colors();
+
+    let screen = ctx.content_rect();
+    let overlay_layer = egui::LayerId::new(egui::Order::Foreground, "undo_history_overlay".into());
+    ctx.layer_painter(overlay_layer).rect_filled(
+        screen,
+        egui::CornerRadius::ZERO,
+        egui::Color32::from_black_alpha(160),
+    );
+
+    let mut undo = None;
+    let now = std::time::Instant::now();
+    egui::Window::new("Operation History")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            if app.undo_journal.is_empty() {
+                ui.colored_label(color32(colors.row_fg_inactive), "Nothing to undo");
+                return;
+            }
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    for (i, entry) in app.undo_journal.iter().rev().enumerate() {
+                        let fg = if i == selected {
+                            color32(colors.row_fg_selected)
+                        } else {
+                            color32(colors.row_fg_inactive)
+                        };
+                        let text = format!(
+                            "{}  {}",
+                            crate::ui::help::relative_time(now, entry.when),
+                            entry.label
+                        );
+                        let response = ui.selectable_label(
+                            i == selected,
+                            egui::RichText::new(text).color(fg).monospace(),
+                        );
+                        if i == selected {
+                            response.scroll_to_me(None);
+                        }
+                        if response.clicked() {
+                            app.select_undo_entry(i);
+                        }
+                    }
+                });
+            ui.add_space(8.0);
+            ui.colored_label(
+                color32(colors.row_fg_inactive),
+                "Undo refuses if the files changed since.",
+            );
+            if ui
+                .add(egui::Button::new("Undo (Enter)").min_size(egui::vec2(120.0, 0.0)))
+                .clicked()
+            {
+                undo = Some(selected);
+            }
+        });
+    undo
+}
@@ -0,0 +1,163 @@
+use egui;
+
+use fileman::app_state;
+use fileman::rename::{Case, FIELDS, Status};
+
+use crate::{color32, refresh_active_panel};
+
+/// Batch rename: the rule on top, every new name previewed below. Enter
+/// applies once no new name is a problem; Escape cancels.
+pub fn draw_multi_rename(ctx: &egui::Context, app: &mut app_state::AppState) {
+    // Read theme colors before taking the mutable dialog borrow of `app`.
+    let colors = app.theme.colors();
+    let Some(dialog) = app.multi_rename_mut() else {
+        return;
+    };
+    dialog.poll_taken();
+    if dialog.reading_taken() {
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    }
+    let screen = ctx.content_rect();
+    let overlay_layer = egui::LayerId::new(egui::Order::Foreground, "multi_rename_overlay".into());
+    ctx.layer_painter(overlay_layer).rect_filled(
+        screen,
+        egui::CornerRadius::ZERO,
+        egui::Color32::from_black_alpha(160),
+    );
+
+    let normal = color32(colors.row_fg_active);
+    let dim = color32(colors.row_fg_inactive);
+    let warn = egui::Color32::from_rgb(230, 170, 70);
+    let mut apply = ctx.input(|i| i.key_pressed(egui::Key::Enter));
+    let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+    egui::Window::new(format!("Rename {} items", dialog.items.len()))
+        .collapsible(false)
+        .resizable(false)
+        .default_width(560.0)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("multi_rename_rule")
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    let rule = &mut dialog.rule;
+                    ui.colored_label(dim, "Name");
+                    let name =
+                        ui.add(egui::TextEdit::singleline(&mut rule.name).desired_width(360.0));
+                    if dialog.focus {
+                        name.request_focus();
+                        dialog.focus = false;
+                    }
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Extension");
+                    ui.add(egui::TextEdit::singleline(&mut rule.ext).desired_width(360.0));
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Search");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut rule.search).desired_width(260.0));
+                        ui.checkbox(&mut rule.regex, "Regex");
+                    });
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Replace");
+                    ui.add(egui::TextEdit::singleline(&mut rule.replace).desired_width(360.0));
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Case");
+                    ui.horizontal(|ui| {
+                        for case in Case::ALL {
+                            if ui
+                                .selectable_label(rule.case == case, case.label())
+                                .clicked()
+                            {
+                                rule.case = case;
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Counter [C]");
+                    ui.horizontal(|ui| {
+                        ui.label("start");
+                        ui.add(egui::DragValue::new(&mut rule.counter_start));
+                        ui.label("step");
+                        ui.add(egui::DragValue::new(&mut rule.counter_step).range(1..=1000));
+                        ui.label("digits");
+                        ui.add(egui::DragValue::new(&mut rule.counter_digits).range(1..=10));
+                    });
+                    ui.end_row();
+                });
+            ui.add_space(4.0);
+            ui.colored_label(dim, FIELDS);
+            if dialog.reading_taken() {
+                ui.colored_label(dim, "Reading photo dates…");
+            }
+            ui.add_space(8.0);
+
+            let mut ready = false;
+            match dialog.preview() {
+                Err(e) => {
+                    ui.colored_label(warn, e);
+                }
+                Ok((names, status)) => {
+                    let problems = status.iter().filter(|s| s.is_problem()).count();
+                    let renamed = status.iter().filter(|s| **s == Status::Renamed).count();
+                    ready = problems == 0 && renamed > 0;
+                    if problems > 0 {
+                        ui.colored_label(warn, format!("{problems} names need fixing"));
+                    } else {
+                        ui.colored_label(normal, format!("{renamed} to rename"));
+                    }
+                    egui::ScrollArea::vertical()
+                        .max_height(320.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("multi_rename_preview")
+                                .num_columns(3)
+                                .striped(true)
+                                .spacing([16.0, 4.0])
+                                .show(ui, |ui| {
+                                    let rows = dialog.sources.iter().zip(&names).zip(&status);
+                                    for ((source, name), status) in rows {
+                                        let color = if status.is_problem() {
+                                            warn
+                                        } else if *status == Status::Unchanged {
+                                            dim
+                                        } else {
+                                            normal
+                                        };
+                                        ui.colored_label(dim, &source.name);
+                                        ui.colored_label(color, name);
+                                        ui.colored_label(color, status.label());
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+            }
+
+            ui.add_space(12.0);
+            ui.horizontal(|ui| {
+                let ok = ui.add_enabled(
+                    ready,
+                    egui::Button::new("Rename").min_size(egui::vec2(80.0, 0.0)),
+                );
+                let no = ui.add(egui::Button::new("Cancel").min_size(egui::vec2(80.0, 0.0)));
+                if ok.clicked() {
+                    apply = true;
+                }
+                if no.clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if cancel {
+        app.close_modal();
+    } else if apply && app.apply_multi_rename() {
+        refresh_active_panel(app);
+    }
+}
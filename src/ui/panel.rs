@@ -1,12 +1,12 @@
 use std::sync::mpsc;
 
-use fileman::{app_state, archive, core, theme};
+use fileman::{app_state, archive, core, scp, theme};
 
 use crate::input::open_selected;
 use crate::{
-    DATE_COL_WIDTH, ImageCache, ImageRequest, ROW_HEIGHT, SIZE_COL_WIDTH, ScrollMode, blend_color,
-    color32, fade_color, panel_path_segments, reload_panel, resort_browser_entries,
-    sort_mode_label, window_rows_for,
+    DATE_COL_WIDTH, DIFF_COL_WIDTH, ImageCache, ImageRequest, ROW_HEIGHT, SIZE_COL_WIDTH,
+    ScrollMode, blend_color, color32, fade_color, panel_path_segments, reload_panel,
+    resort_browser_entries, sort_mode_label, window_rows_for,
 };
 
 /// Type-aware foreground tint blended into the row fg. Returns None for
@@ -50,7 +50,8 @@ fn entry_tint(entry: &core::DirEntry) -> Option<theme::Color> {
 }
 
 /// Compact one-line panel footer status: marked-selection size, free space
-/// (for FS panels), and async-worker activity dots.
+/// (for FS and remote panels), the SCP-only mode of a remote host, and
+/// async-worker activity dots.
 fn draw_status_line(
     ui: &mut egui::Ui,
     app: &app_state::AppState,
@@ -82,11 +83,22 @@ fn draw_status_line(
             ui.colored_label(dim, "·");
         }
 
-        // FS panels: free space on the panel's drive. Cached per-frame —
-        // statvfs is cheap on Linux/macOS.
-        if let core::BrowserMode::Fs = browser.browser_mode
-            && let Some(free) = free_space_bytes(&browser.current_path)
-        {
+        // Free space on the panel's volume. Local panels call statvfs every
+        // frame — it is cheap on Linux/macOS.
+        let free = match browser.browser_mode {
+            core::BrowserMode::Fs => free_space_bytes(&browser.current_path),
+            // Remote panels: the last reading from the free-space worker,
+            // re-measured every REMOTE_FREE_SPACE_REFRESH.
+            core::BrowserMode::Remote { ref host, ref path } => {
+                ui.ctx()
+                    .request_repaint_after(app_state::REMOTE_FREE_SPACE_REFRESH);
+                app.remote_free_space
+                    .get(&(host.clone(), path.clone()))
+                    .and_then(|entry| entry.0)
+            }
+            _ => None,
+        };
+        if let Some(free) = free {
             ui.colored_label(
                 fg,
                 egui::RichText::new(format!("free: {}", core::format_size(free))).monospace(),
@@ -94,10 +106,22 @@ fn draw_status_line(
             ui.colored_label(dim, "·");
         }
 
+        // SSH hosts without SFTP browse in a degraded mode.
+        if let core::BrowserMode::Remote { ref host, .. } = browser.browser_mode
+            && app.sftp_stats.get(host).is_some_and(|stats| stats.scp_only)
+        {
+            ui.colored_label(fg, scp::MODE_NOTE);
+            ui.colored_label(dim, "·");
+        }
+
         // Async worker dots: io (copy/move/delete), dir (sized), search
         let async_status = app.async_status();
         ui.colored_label(dim, "io");
         worker_dot(ui, async_status.io_in_flight > 0, colors);
+        // How the last local copy moved its data: "reflink" and the like.
+        if let Some(method) = async_status.io_copy_method {
+            ui.colored_label(dim, method.label());
+        }
         ui.colored_label(dim, "dir");
         worker_dot(ui, async_status.dir_size_pending > 0, colors);
         ui.colored_label(dim, "search");
@@ -106,6 +130,8 @@ fn draw_status_line(
             matches!(async_status.search, app_state::SearchStatus::Running(_)),
             colors,
         );
+        ui.colored_label(dim, "compare");
+        worker_dot(ui, async_status.compare_running, colors);
     });
 }
 
@@ -743,7 +769,7 @@ pub fn draw_panel(
                                 scroll.show_rows(ui, ROW_HEIGHT, entries_len, |ui, row_range| {
                                     visible_range = row_range.clone();
                                     for idx in row_range {
-                                        let (entry, rename_active, is_marked) = {
+                                        let (entry, rename_active, is_marked, difference) = {
                                             let browser =
                                                 app.panel(panel_side_for_closure).browser();
                                             let entry = browser.entries[idx].clone();
@@ -752,7 +778,13 @@ pub fn draw_panel(
                                                 .as_ref()
                                                 .is_some_and(|rename| rename.index == idx);
                                             let is_marked = browser.marked.contains(&entry.name);
-                                            (entry, rename_active, is_marked)
+                                            let difference = match browser.browser_mode {
+                                                core::BrowserMode::Compare { .. } => {
+                                                    app.compare_difference(&entry.name)
+                                                }
+                                                _ => None,
+                                            };
+                                            (entry, rename_active, is_marked, difference)
                                         };
                                         let is_selected = selected_index == idx;
                                         let stripe = row_striping && idx % 2 == 0;
@@ -867,7 +899,20 @@ pub fn draw_panel(
                                                 color32(fade_color(fg, 0.7)),
                                             );
                                         }
-                                        let right_cols = SIZE_COL_WIDTH + DATE_COL_WIDTH;
+                                        let mut right_cols = SIZE_COL_WIDTH + DATE_COL_WIDTH;
+                                        if let Some(difference) = difference {
+                                            ui.painter().text(
+                                                egui::pos2(
+                                                    rect.right() - right_cols - 4.0,
+                                                    rect.center().y,
+                                                ),
+                                                egui::Align2::RIGHT_CENTER,
+                                                difference.label(),
+                                                font_id.clone(),
+                                                color32(colors.panel_border_active),
+                                            );
+                                            right_cols += DIFF_COL_WIDTH;
+                                        }
                                         let name_min =
                                             rect.left_center() + egui::Vec2::new(22.0, 0.0);
                                         let name_rect = egui::Rect::from_min_max(
@@ -1066,7 +1111,8 @@ pub fn draw_panel(
                                     .show(ui, |ui| {
                                         let search_open = is_active
                                             && app.search_ui == app_state::SearchUiState::Open;
-                                        if !search_open {
+                                        let command_open = is_active && app.command_input.is_some();
+                                        if !search_open && !command_open {
                                             draw_status_line(
                                                 ui,
                                                 app,
@@ -1106,6 +1152,21 @@ pub fn draw_panel(
                                                 }
                                             });
                                         }
+                                        if command_open
+                                            && let Some(ref mut command) = app.command_input
+                                        {
+                                            ui.horizontal(|ui| {
+                                                ui.colored_label(color32(colors.footer_fg), "$");
+                                                let response = egui::TextEdit::singleline(command)
+                                                    .id(ui.make_persistent_id("command_edit"))
+                                                    .desired_width(f32::INFINITY)
+                                                    .show(ui);
+                                                if app.command_focus {
+                                                    response.response.request_focus();
+                                                    app.command_focus = false;
+                                                }
+                                            });
+                                        }
                                     });
                             },
                         );
@@ -307,7 +307,7 @@ fn perms_row(
 #[cfg(unix)]
 fn apply_props_dialog(app: &mut app_state::AppState, recursive: bool) {
     // Compute the update while the dialog is borrowed, then release the borrow
-    // before touching other AppState fields (io_tx, counters, panels).
+    // before touching other AppState fields (IO queue, counters, panels).
     let (target, new_mode, uid, gid, changed) = {
         let Some(dialog) = app.props_dialog_mut() else {
             return;
@@ -337,19 +337,13 @@ fn apply_props_dialog(app: &mut app_state::AppState, recursive: bool) {
         app.close_modal();
         return;
     }
-    if let Err(e) = app.io_tx.send(core::IOTask::SetProps {
+    app.enqueue_io(core::IOTask::SetProps {
         path: target,
         mode: new_mode,
         uid,
         gid,
         recursive,
-    }) {
-        if let Some(dialog) = app.props_dialog_mut() {
-            dialog.error = Some(format!("Failed to queue update: {e}"));
-        }
-        return;
-    }
-    app.io_in_flight = app.io_in_flight.saturating_add(1);
+    });
     app.close_modal();
     app.store_selection_memory_for(app.active_panel);
     refresh_active_panel(app);
@@ -12,8 +12,10 @@ pub struct QuickJumpResult {
 fn category_group(cat: app_state::QuickJumpCategory) -> u8 {
     match cat {
         app_state::QuickJumpCategory::Remote => 0,
-        app_state::QuickJumpCategory::Home | app_state::QuickJumpCategory::Mount => 1,
-        app_state::QuickJumpCategory::Ssh => 2,
+        app_state::QuickJumpCategory::Home
+        | app_state::QuickJumpCategory::Mount
+        | app_state::QuickJumpCategory::Trash => 1,
+        app_state::QuickJumpCategory::Ssh | app_state::QuickJumpCategory::Bookmark => 2,
     }
 }
 
@@ -1,4 +1,5 @@
 use fileman::app_state;
+use fileman::core::SymlinkPolicy;
 use fileman::settings::{Bookmark, Settings, ThemePref};
 use fileman::theme;
 
@@ -76,9 +77,54 @@ pub fn draw_settings(
                 &mut draft.auto_refresh,
                 "Auto-refresh SFTP directories on back-navigation",
             );
+            ui.checkbox(
+                &mut draft.verify_transfers,
+                "Verify SHA-256 checksums after remote transfers",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Remote transfer speed limit");
+                ui.add(bandwidth_limit_value(&mut draft.bandwidth_limit_kib));
+                ui.colored_label(color32(colors.row_fg_inactive), "0 = unlimited");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Hold remote transfers of at least");
+                ui.add(
+                    egui::DragValue::new(&mut draft.hold_transfers_mib)
+                        .speed(16.0)
+                        .suffix(" MiB"),
+                );
+                ui.colored_label(
+                    color32(colors.row_fg_inactive),
+                    "until started by hand (0 = off)",
+                );
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Local copies keep");
+                ui.checkbox(&mut draft.preserve_timestamps, "timestamps");
+                ui.checkbox(&mut draft.preserve_mode, "permissions");
+                ui.checkbox(&mut draft.preserve_ownership, "owner (as root)");
+                ui.checkbox(&mut draft.preserve_xattrs, "xattrs and ACLs");
+                ui.checkbox(&mut draft.preserve_sparse, "sparse files");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Folder sizes count links as");
+                for policy in SymlinkPolicy::ALL {
+                    let label = match policy {
+                        SymlinkPolicy::Links => "their own size",
+                        SymlinkPolicy::Follow => "their targets",
+                        SymlinkPolicy::Skip => "nothing",
+                    };
+                    if ui
+                        .selectable_label(draft.dir_size_symlinks == policy, label)
+                        .clicked()
+                    {
+                        draft.dir_size_symlinks = policy;
+                    }
+                }
+            });
 
             ui.add_space(10.0);
-            section_header(ui, &colors, "SFTP Bookmarks");
+            section_header(ui, &colors, "Remote Bookmarks");
             bookmark_editor(ui, &colors, &mut draft.bookmarks);
 
             ui.add_space(14.0);
@@ -113,6 +159,12 @@ pub fn draw_settings(
     outcome
 }
 
+/// Drag value for a speed limit in KiB/s, 0 meaning unlimited. Also used
+/// by the progress window to adjust a running transfer.
+pub fn bandwidth_limit_value(kib: &mut u64) -> egui::DragValue<'_> {
+    egui::DragValue::new(kib).speed(16.0).suffix(" KiB/s")
+}
+
 fn section_header(ui: &mut egui::Ui, colors: &theme::ThemeColors, text: &str) {
     ui.colored_label(
         color32(colors.preview_text),
@@ -133,7 +185,7 @@ fn bookmark_editor(ui: &mut egui::Ui, colors: &theme::ThemeColors, bookmarks: &m
             ui.add(
                 egui::TextEdit::singleline(&mut bm.host)
                     .desired_width(140.0)
-                    .hint_text("host"),
+                    .hint_text("host or davs://server"),
             );
             ui.add(
                 egui::TextEdit::singleline(&mut bm.path)
@@ -180,10 +232,18 @@ pub fn cancel(app: &mut app_state::AppState) {
 /// Apply the draft as the new settings, persist to disk, and close. The
 /// theme preference is also applied to the live theme.
 pub fn save(app: &mut app_state::AppState) {
-    if let Some(draft) = app.settings_draft_mut().map(|d| d.clone()) {
+    if let Some(mut draft) = app.settings_draft_mut().map(|d| d.clone()) {
         app.close_modal();
+        for bm in &mut draft.bookmarks {
+            bm.normalize();
+        }
+        // Sizes measured under the old choice would be mixed with new ones.
+        if draft.dir_size_symlinks != app.settings.dir_size_symlinks {
+            app.dir_sizes.clear();
+        }
         app.settings = draft;
         crate::apply_theme_preference(&mut app.theme, &app.settings.theme);
+        app.apply_transfer_settings();
         if let Err(e) = fileman::settings::save(&app.settings) {
             app.record_error("settings", format!("save failed: {e}"));
         }
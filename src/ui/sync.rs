@@ -0,0 +1,239 @@
+use egui;
+
+use fileman::app_state;
+use fileman::compare::{CompareContent, describe};
+use fileman::core::{ActivePanel, format_size};
+use fileman::sync::{Action, SyncDeletes, SyncDirection};
+
+use crate::color32;
+
+/// Sync: the options on top, the plan from comparing both trees below.
+/// Enter compares again after an option changed, otherwise queues the
+/// plan; Escape cancels.
+pub fn draw_sync(ctx: &egui::Context, app: &mut app_state::AppState) {
+    // Read theme colors and the comparison state before taking the mutable
+    // dialog borrow of `app`.
+    let colors = app.theme.colors();
+    let comparing = app.sync_comparing();
+    let Some(dialog) = app.sync_dialog_mut() else {
+        return;
+    };
+    let screen = ctx.content_rect();
+    let overlay_layer = egui::LayerId::new(egui::Order::Foreground, "sync_overlay".into());
+    ctx.layer_painter(overlay_layer).rect_filled(
+        screen,
+        egui::CornerRadius::ZERO,
+        egui::Color32::from_black_alpha(160),
+    );
+
+    let normal = color32(colors.row_fg_active);
+    let dim = color32(colors.row_fg_inactive);
+    let warn = egui::Color32::from_rgb(230, 170, 70);
+    let enter = ctx.input(|i| i.key_pressed(egui::Key::Enter));
+    let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+    let mut compare = false;
+    let mut apply = false;
+    let mut stale = false;
+    let plan = dialog.plan();
+    let ready = plan.as_ref().is_some_and(|p| !p.steps.is_empty());
+
+    egui::Window::new("Sync directories")
+        .collapsible(false)
+        .resizable(false)
+        .default_width(600.0)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("sync_options")
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    ui.colored_label(dim, "Left");
+                    ui.colored_label(normal, describe(&dialog.left));
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Right");
+                    ui.colored_label(normal, describe(&dialog.right));
+                    ui.end_row();
+
+                    let options = &mut dialog.options;
+                    ui.colored_label(dim, "Direction");
+                    ui.horizontal(|ui| {
+                        for direction in SyncDirection::ALL {
+                            if ui
+                                .selectable_label(options.direction == direction, direction.label())
+                                .clicked()
+                            {
+                                options.direction = direction;
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Extra entries");
+                    ui.add_enabled_ui(options.direction != SyncDirection::Both, |ui| {
+                        ui.horizontal(|ui| {
+                            for deletes in SyncDeletes::ALL {
+                                if ui
+                                    .selectable_label(options.deletes == deletes, deletes.label())
+                                    .clicked()
+                                {
+                                    options.deletes = deletes;
+                                }
+                            }
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Files");
+                    ui.horizontal(|ui| {
+                        for content in CompareContent::ALL {
+                            if ui
+                                .selectable_label(options.content == content, content.label())
+                                .clicked()
+                                && options.content != content
+                            {
+                                options.content = content;
+                                stale = true;
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.colored_label(dim, "Exclude");
+                    let excludes = ui.add(
+                        egui::TextEdit::singleline(&mut options.excludes)
+                            .hint_text(".git target *.tmp")
+                            .desired_width(400.0),
+                    );
+                    if dialog.focus {
+                        excludes.request_focus();
+                        dialog.focus = false;
+                    }
+                    if excludes.changed() {
+                        stale = true;
+                    }
+                    ui.end_row();
+                });
+            ui.add_space(8.0);
+
+            if let Some(ref error) = dialog.error {
+                ui.colored_label(warn, error);
+            }
+            match plan {
+                None if comparing => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.colored_label(
+                            dim,
+                            format!("Comparing… {} entries looked at", dialog.scanned),
+                        );
+                    });
+                }
+                // The error above says why there is no plan.
+                None if dialog.error.is_some() => {}
+                None => {
+                    ui.colored_label(dim, "Options changed: press Enter to compare again.");
+                }
+                Some(ref plan) if plan.steps.is_empty() && plan.skipped.is_empty() => {
+                    ui.colored_label(normal, "Nothing to do: the directories match.");
+                }
+                Some(ref plan) => {
+                    ui.colored_label(
+                        normal,
+                        format!(
+                            "{} to copy, {} to overwrite, {} to delete, {} skipped",
+                            plan.count(Action::Copy) + plan.count(Action::Create),
+                            plan.count(Action::Overwrite),
+                            plan.count(Action::Delete),
+                            plan.skipped.len()
+                        ),
+                    );
+                    egui::ScrollArea::vertical()
+                        .max_height(320.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("sync_plan")
+                                .num_columns(4)
+                                .striped(true)
+                                .spacing([16.0, 4.0])
+                                .show(ui, |ui| {
+                                    for step in &plan.steps {
+                                        let color = match step.action {
+                                            Action::Delete => warn,
+                                            Action::Copy | Action::Overwrite | Action::Create => {
+                                                normal
+                                            }
+                                        };
+                                        let side = match step.to {
+                                            ActivePanel::Left => "left",
+                                            ActivePanel::Right => "right",
+                                        };
+                                        let suffix = if step.entry.is_dir { "/" } else { "" };
+                                        let size = match step.entry.size {
+                                            Some(size) if !step.entry.is_dir => format_size(size),
+                                            _ => String::new(),
+                                        };
+                                        ui.colored_label(color, step.action.label());
+                                        ui.colored_label(dim, side);
+                                        ui.colored_label(color, format!("{}{suffix}", step.path));
+                                        ui.colored_label(dim, size);
+                                        ui.end_row();
+                                    }
+                                    for skipped in &plan.skipped {
+                                        ui.colored_label(dim, "skip");
+                                        ui.colored_label(dim, "");
+                                        ui.colored_label(dim, &skipped.path);
+                                        ui.colored_label(dim, skipped.reason);
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+            }
+
+            ui.add_space(12.0);
+            ui.horizontal(|ui| {
+                let ok = ui.add_enabled(
+                    ready && !stale,
+                    egui::Button::new("Sync").min_size(egui::vec2(80.0, 0.0)),
+                );
+                let again = ui.add_enabled(
+                    !comparing,
+                    egui::Button::new("Compare").min_size(egui::vec2(80.0, 0.0)),
+                );
+                let no = ui.add(egui::Button::new("Cancel").min_size(egui::vec2(80.0, 0.0)));
+                if ok.clicked() {
+                    apply = true;
+                }
+                if again.clicked() {
+                    compare = true;
+                }
+                if no.clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if enter && !stale {
+        if ready {
+            apply = true;
+        } else if dialog.diffs.is_none() && !comparing {
+            compare = true;
+        }
+    }
+    if stale {
+        if comparing {
+            app.cancel_compare();
+        }
+        if let Some(dialog) = app.sync_dialog_mut() {
+            dialog.diffs = None;
+        }
+    }
+    if cancel {
+        app.close_sync();
+    } else if compare {
+        app.start_sync_compare();
+    } else if apply {
+        app.apply_sync();
+    }
+}
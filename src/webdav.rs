@@ -0,0 +1,629 @@
+//! WebDAV backend for `dav://` and `davs://` host keys.
+//!
+//! Host keys look like `davs://[user@]host[:port]`; the path on the server,
+//! e.g. `/remote.php/dav/files/alice` on Nextcloud, is part of the location
+//! rather than the key. As with FTP, passwords come from `~/.netrc` and are
+//! sent with HTTP basic auth. Listings are `PROPFIND` requests whose
+//! multistatus replies are parsed as they arrive, so large directories show
+//! up in batches. Downloads use ranged `GET`s, uploads stream a chunked
+//! `PUT`, and copies within the server are a single `COPY`.
+
+use std::{
+    io::{self, BufReader, Read, Write},
+    sync::{Arc, mpsc},
+    time::Duration,
+};
+
+use reqwest::{
+    StatusCode,
+    blocking::{Body, Client, RequestBuilder, Response},
+    header,
+};
+
+use crate::{
+    core::{DirEntry, EntryLocation, percent_decode, percent_encode_path},
+    remote::{self, RemoteStat, SessionStats},
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Entries handed to the listing callback at a time.
+const BATCH_SIZE: usize = 64;
+/// Upload bytes collected before they are handed to the request body.
+const UPLOAD_CHUNK: usize = 64 * 1024;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop>
+</d:propfind>"#;
+
+/// Failure of a request. `Connection` errors mean the server could not be
+/// reached; a `Server` error is an HTTP status it answered with.
+enum Error {
+    Connection(String),
+    Server(String),
+}
+
+impl Error {
+    fn into_message(self) -> String {
+        match self {
+            Error::Connection(msg) | Error::Server(msg) => msg,
+        }
+    }
+
+    fn into_listing_error(self) -> (String, bool) {
+        match self {
+            Error::Connection(msg) => (msg, true),
+            Error::Server(msg) => (msg, false),
+        }
+    }
+
+    fn into_io(self) -> io::Error {
+        io::Error::other(self.into_message())
+    }
+}
+
+fn build_client() -> Result<Client, String> {
+    Client::builder()
+        .user_agent(concat!("fileman/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(CONNECT_TIMEOUT)
+        // Transfers of large files take as long as they take.
+        .timeout(None)
+        .build()
+        .map_err(|e| format!("HTTP client setup: {e}"))
+}
+
+pub struct WebDavSession {
+    pub host: String,
+    pub stats: Arc<SessionStats>,
+    client: Client,
+    /// `http[s]://host[:port]`, without a trailing slash.
+    base: String,
+    credentials: Option<(String, String)>,
+}
+
+/// Connect to a `dav://` or `davs://` host key.
+pub fn connect(host: &str) -> Result<WebDavSession, String> {
+    let (scheme, authority) = remote::split_scheme(host);
+    let protocol = match scheme {
+        "davs" => "https",
+        _ => "http",
+    };
+    let (user, server) = match authority.rsplit_once('@') {
+        Some((user, server)) => (Some(user), server),
+        None => (None, authority),
+    };
+    let (domain, _) = remote::split_port(server, 0);
+    let credentials = remote::netrc_credentials(&remote::load_netrc(), domain, user)
+        .or_else(|| user.map(|user| (user.to_string(), String::new())));
+    let mut session = WebDavSession {
+        host: host.to_string(),
+        stats: SessionStats::new(
+            credentials.as_ref().map_or("", |pair| pair.0.as_str()),
+            None,
+        ),
+        client: build_client()?,
+        base: format!("{protocol}://{server}"),
+        credentials,
+    };
+    let response = session
+        .send(session.request("OPTIONS", "/"), "OPTIONS /")
+        .map_err(Error::into_message)?;
+    // Only authentication matters here: plenty of servers answer OPTIONS on
+    // the root with an error while serving WebDAV further down.
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return Err(login_error(&session, "OPTIONS /"));
+    }
+    let server_header = response
+        .headers()
+        .get(header::SERVER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    session.stats = SessionStats::new(&session.stats.user, server_header);
+    Ok(session)
+}
+
+fn login_error(session: &WebDavSession, what: &str) -> String {
+    match session.credentials {
+        Some((ref user, _)) => format!(
+            "{what}: login failed for {user}@{}. Add credentials to ~/.netrc.",
+            session.base
+        ),
+        None => format!(
+            "{what}: {} requires a login. Add credentials to ~/.netrc.",
+            session.base
+        ),
+    }
+}
+
+impl WebDavSession {
+    fn url(&self, path: &str) -> String {
+        let path = if path.is_empty() { "/" } else { path };
+        format!("{}{}", self.base, percent_encode_path(path))
+    }
+
+    fn request(&self, method: &str, path: &str) -> RequestBuilder {
+        let method = reqwest::Method::from_bytes(method.as_bytes()).unwrap_or_default();
+        let request = self.client.request(method, self.url(path));
+        match self.credentials {
+            Some((ref user, ref password)) => request.basic_auth(user, Some(password)),
+            None => request,
+        }
+    }
+
+    fn send(&self, request: RequestBuilder, what: &str) -> Result<Response, Error> {
+        request
+            .send()
+            .map_err(|e| Error::Connection(format!("{what}: {e}")))
+    }
+
+    /// Send and require a success status.
+    fn expect(&self, request: RequestBuilder, what: &str) -> Result<Response, Error> {
+        let response = self.send(request, what)?;
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::UNAUTHORIZED => Err(Error::Server(login_error(self, what))),
+            status => Err(Error::Server(format!("{what}: {status}"))),
+        }
+    }
+
+    fn simple(&self, method: &str, path: &str) -> Result<(), String> {
+        self.expect(self.request(method, path), &format!("{method} {path}"))
+            .map(|_| ())
+            .map_err(Error::into_message)
+    }
+
+    fn propfind(&self, path: &str, depth: &str) -> Result<Response, Error> {
+        let request = self
+            .request("PROPFIND", path)
+            .header("Depth", depth)
+            .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY);
+        self.expect(request, &format!("PROPFIND {path}"))
+    }
+
+    /// `MOVE` or `COPY` to another path on the same server.
+    fn transfer(&self, method: &str, src: &str, dst: &str) -> Result<(), String> {
+        let request = self
+            .request(method, src)
+            .header("Destination", self.url(dst));
+        self.expect(request, &format!("{method} {src}"))
+            .map(|_| ())
+            .map_err(Error::into_message)
+    }
+
+    /// Ranged `GET` starting at `offset`, or `None` when nothing is left
+    /// to read.
+    fn get_from(&self, path: &str, offset: u64) -> Result<Option<Response>, Error> {
+        let what = format!("GET {path}");
+        let mut request = self.request("GET", path);
+        if offset > 0 {
+            request = request.header(header::RANGE, format!("bytes={offset}-"));
+        }
+        let response = self.send(request, &what)?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(None);
+        }
+        let mut response = match response.status() {
+            status if status.is_success() => response,
+            StatusCode::UNAUTHORIZED => return Err(Error::Server(login_error(self, &what))),
+            status => return Err(Error::Server(format!("{what}: {status}"))),
+        };
+        if offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            // The server ignored the range; skip to the offset ourselves.
+            let skipped = io::copy(&mut (&mut response).take(offset), &mut io::sink())
+                .map_err(|e| Error::Connection(format!("{what}: {e}")))?;
+            if skipped < offset {
+                return Ok(None);
+            }
+        }
+        Ok(Some(response))
+    }
+}
+
+impl remote::RemoteFs for WebDavSession {
+    fn host(&self) -> &str {
+        &self.host
+    }
+
+    fn home_dir(&self) -> Option<&str> {
+        None
+    }
+
+    fn stats(&self) -> &Arc<SessionStats> {
+        &self.stats
+    }
+
+    fn is_alive(&self) -> bool {
+        self.send(self.request("OPTIONS", "/"), "OPTIONS /").is_ok()
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.client = build_client()?;
+        self.send(self.request("OPTIONS", "/"), "OPTIONS /")
+            .map_err(Error::into_message)?;
+        self.stats.mark_reconnected();
+        Ok(())
+    }
+
+    fn list(
+        &self,
+        path: &str,
+        on_batch: &mut dyn FnMut(Vec<DirEntry>),
+    ) -> Result<(), (String, bool)> {
+        let dir = if path.is_empty() { "/" } else { path };
+        let response = self.propfind(dir, "1").map_err(Error::into_listing_error)?;
+        if let Some(parent) = remote::parent_entry(&self.host, dir) {
+            on_batch(vec![parent]);
+        }
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        parse_multistatus(BufReader::new(response), |resource| {
+            if same_path(&resource.path, dir) {
+                return;
+            }
+            let Some(name) = resource.name() else {
+                return;
+            };
+            batch.push(DirEntry {
+                location: EntryLocation::Remote {
+                    host: self.host.clone(),
+                    path: remote::join_path(dir, name),
+                },
+                name: name.to_string(),
+                is_dir: resource.is_dir,
+                is_symlink: false,
+                link_target: None,
+                size: if resource.is_dir { None } else { resource.size },
+                modified: resource.mtime,
+            });
+            if batch.len() >= BATCH_SIZE {
+                on_batch(std::mem::replace(
+                    &mut batch,
+                    Vec::with_capacity(BATCH_SIZE),
+                ));
+            }
+        })
+        .map_err(|e| (format!("PROPFIND {dir}: {e}"), false))?;
+        if !batch.is_empty() {
+            on_batch(batch);
+        }
+        Ok(())
+    }
+
+    fn stat(&self, path: &str) -> Result<RemoteStat, String> {
+        let response = self.propfind(path, "0").map_err(Error::into_message)?;
+        let mut found = None;
+        parse_multistatus(BufReader::new(response), |resource| {
+            found.get_or_insert(resource);
+        })
+        .map_err(|e| format!("PROPFIND {path}: {e}"))?;
+        let resource = found.ok_or_else(|| format!("PROPFIND {path}: empty response"))?;
+        Ok(RemoteStat {
+            is_dir: resource.is_dir,
+            size: if resource.is_dir { None } else { resource.size },
+            mtime: resource.mtime,
+        })
+    }
+
+    fn open_read(&self, path: &str) -> Result<Box<dyn crate::archive::ReadSeek + '_>, String> {
+        let stat = remote::RemoteFs::stat(self, path)?;
+        if stat.is_dir {
+            return Err(format!("open {path}: is a directory"));
+        }
+        let path = path.to_string();
+        Ok(Box::new(remote::RangedReader::new(
+            stat.size,
+            move |offset| self.get_from(&path, offset).map_err(Error::into_io),
+        )))
+    }
+
+    fn create(&self, path: &str) -> Result<Box<dyn Write + '_>, String> {
+        let (sender, receiver) = mpsc::sync_channel(4);
+        let request = self.request("PUT", path).body(Body::new(ChannelReader {
+            receiver,
+            chunk: Vec::new(),
+            offset: 0,
+        }));
+        let what = format!("PUT {path}");
+        let base = self.base.clone();
+        let upload = std::thread::spawn(move || match request.send() {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) if response.status() == StatusCode::UNAUTHORIZED => Err(format!(
+                "{what}: login to {base} failed. Add credentials to ~/.netrc."
+            )),
+            Ok(response) => Err(format!("{what}: {}", response.status())),
+            Err(e) => Err(format!("{what}: {e}")),
+        });
+        Ok(Box::new(DavWriter {
+            session: self,
+            buffer: Vec::with_capacity(UPLOAD_CHUNK),
+            sender: Some(sender),
+            upload: Some(upload),
+        }))
+    }
+
+    fn rename(&self, src: &str, dst: &str) -> Result<(), String> {
+        self.transfer("MOVE", src, dst)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        self.simple("DELETE", path)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<(), String> {
+        self.simple("DELETE", path)
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        self.simple("MKCOL", path)
+    }
+
+    fn copy(&self, src: &str, dst: &str) -> Option<Result<(), String>> {
+        Some(self.transfer("COPY", src, dst))
+    }
+}
+
+/// Request body fed from the writer through a channel.
+struct ChannelReader {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    offset: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset >= self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+                // The writer is done.
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.offset);
+        buf[..n].copy_from_slice(&self.chunk[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// Upload in progress. The request runs on its own thread and is finished
+/// on drop, so a failed upload shows up as a size mismatch in the caller's
+/// check.
+struct DavWriter<'a> {
+    session: &'a WebDavSession,
+    buffer: Vec<u8>,
+    sender: Option<mpsc::SyncSender<Vec<u8>>>,
+    upload: Option<std::thread::JoinHandle<Result<(), String>>>,
+}
+
+impl DavWriter<'_> {
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(UPLOAD_CHUNK));
+        let sent = self
+            .sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(chunk).is_ok());
+        if sent {
+            return Ok(());
+        }
+        // The request ended early; report why.
+        self.sender = None;
+        match self.finish() {
+            Err(msg) => Err(io::Error::other(msg)),
+            Ok(()) => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.sender = None;
+        match self.upload.take() {
+            Some(upload) => upload
+                .join()
+                .unwrap_or_else(|_| Err("upload thread panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Write for DavWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.sender.is_none() {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+        }
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= UPLOAD_CHUNK {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+impl Drop for DavWriter<'_> {
+    fn drop(&mut self) {
+        let result = self
+            .send_buffer()
+            .map_err(|e| e.to_string())
+            .and_then(|()| self.finish());
+        if let Err(e) = result {
+            log::warn!("WebDAV upload to {}: {e}", self.session.host);
+        }
+    }
+}
+
+/// One `<response>` of a multistatus reply.
+#[derive(Debug, Default, PartialEq)]
+struct Resource {
+    /// Decoded path from the `href`, without scheme and authority.
+    path: String,
+    is_dir: bool,
+    size: Option<u64>,
+    mtime: Option<u64>,
+}
+
+impl Resource {
+    fn name(&self) -> Option<&str> {
+        let name = self.path.trim_end_matches('/').rsplit('/').next()?;
+        (!name.is_empty()).then_some(name)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Href,
+    Length,
+    Modified,
+}
+
+/// Parse a `207 Multi-Status` body, calling `on_resource` for each
+/// `<response>` as soon as it is complete. Namespace prefixes are ignored;
+/// every property of interest lives in `DAV:`.
+fn parse_multistatus(
+    source: impl io::BufRead,
+    mut on_resource: impl FnMut(Resource),
+) -> Result<(), String> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_reader(source);
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut current: Option<Resource> = None;
+    let mut field = None;
+    loop {
+        match reader
+            .read_event_into(&mut buf)
+            .map_err(|e| e.to_string())?
+        {
+            Event::Start(ref e) => match e.local_name().as_ref() {
+                b"response" => current = Some(Resource::default()),
+                b"href" => field = Some(Field::Href),
+                b"getcontentlength" => field = Some(Field::Length),
+                b"getlastmodified" => field = Some(Field::Modified),
+                b"collection" => {
+                    if let Some(ref mut resource) = current {
+                        resource.is_dir = true;
+                    }
+                }
+                _ => field = None,
+            },
+            Event::Empty(ref e) => {
+                if e.local_name().as_ref() == b"collection"
+                    && let Some(ref mut resource) = current
+                {
+                    resource.is_dir = true;
+                }
+            }
+            Event::Text(ref e) => {
+                if let (Some(field), Some(resource)) = (field, current.as_mut()) {
+                    let text = e.unescape().map_err(|e| e.to_string())?;
+                    let text = text.trim();
+                    match field {
+                        // Only the first href of a response names it.
+                        Field::Href if resource.path.is_empty() => resource.path = href_path(text),
+                        Field::Href => {}
+                        Field::Length => resource.size = text.parse().ok(),
+                        Field::Modified => resource.mtime = crate::core::epoch_from_http_date(text),
+                    }
+                }
+            }
+            Event::End(ref e) => {
+                field = None;
+                if e.local_name().as_ref() == b"response"
+                    && let Some(resource) = current.take()
+                {
+                    on_resource(resource);
+                }
+            }
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Path of an `href`, which servers send either absolute (`/dav/a%20b`) or
+/// as a full URL.
+fn href_path(href: &str) -> String {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+        None => href,
+    };
+    percent_decode(path)
+}
+
+/// Whether two paths name the same resource, ignoring a trailing slash.
+fn same_path(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multistatus_streams_resources() {
+        let body = r#"<?xml version="1.0"?>
+<D:multistatus xmlns:D="DAV:">
+  <D:response>
+    <D:href>/dav/photos/</D:href>
+    <D:propstat><D:prop>
+      <D:resourcetype><D:collection/></D:resourcetype>
+      <D:getlastmodified>Tue, 15 Nov 1994 12:45:26 GMT</D:getlastmodified>
+    </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>https://cloud.example.com/dav/photos/caf%C3%A9%20&amp;%20bar.jpg</D:href>
+    <D:propstat><D:prop>
+      <D:resourcetype/>
+      <D:getcontentlength>1234</D:getcontentlength>
+    </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat>
+    <D:propstat><D:prop><D:getlastmodified/></D:prop>
+      <D:status>HTTP/1.1 404 Not Found</D:status></D:propstat>
+  </D:response>
+</D:multistatus>"#;
+        let mut resources = Vec::new();
+        parse_multistatus(body.as_bytes(), |r| resources.push(r)).unwrap();
+        assert_eq!(
+            resources,
+            [
+                Resource {
+                    path: "/dav/photos/".to_string(),
+                    is_dir: true,
+                    size: None,
+                    mtime: Some(784_903_526),
+                },
+                Resource {
+                    path: "/dav/photos/café & bar.jpg".to_string(),
+                    is_dir: false,
+                    size: Some(1234),
+                    mtime: None,
+                },
+            ]
+        );
+        assert_eq!(resources[0].name(), Some("photos"));
+        assert_eq!(resources[1].name(), Some("café & bar.jpg"));
+        assert!(same_path(&resources[0].path, "/dav/photos"));
+    }
+
+    #[test]
+    fn paths_encode_and_decode() {
+        assert_eq!(
+            percent_encode_path("/a b/café#1.txt"),
+            "/a%20b/caf%C3%A9%231.txt"
+        );
+        assert_eq!(
+            percent_decode("/a%20b/caf%C3%A9%231.txt"),
+            "/a b/café#1.txt"
+        );
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(href_path("http://host:8080"), "/");
+    }
+}
@@ -15,19 +15,30 @@ use std::sync::{Arc, Mutex};
 
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::compare::{CompareEvent, CompareRequest};
 use crate::core::{
-    EntryLocation, IOResult, IOTask, PreviewContent, PreviewRequest, SearchCase, SearchEvent,
-    SearchMode, SearchProgress, SearchRequest, SearchResult, copy_container_dir,
-    copy_container_entry, copy_recursively, create_archive, format_container_listing,
-    is_probably_text, is_text_name, is_text_path, read_container_directory,
+    CommandCwd, CommandEvent, CommandRequest, Conflict, ConflictSide, EntryLocation, IOResult,
+    IOTask, Preserve, PreviewContent, PreviewRequest, Resolution, SearchCase, SearchEvent,
+    SearchMode, SearchProgress, SearchRequest, SearchResult, SymlinkPolicy, UndoAction, UndoRecord,
+    copy_as, copy_container_dir, copy_container_entry, copy_recursively, create_archive,
+    create_link, format_container_listing, free_name, is_probably_text, is_text_name, is_text_path,
+    read_container_directory, read_container_metadata,
 };
-use crate::sftp::SftpSession;
+use crate::remote::{RemoteFs, SharedRemote};
 
-type SftpSessions = Arc<Mutex<std::collections::HashMap<String, Arc<Mutex<SftpSession>>>>>;
+type SftpSessions = Arc<Mutex<std::collections::HashMap<String, SharedRemote>>>;
+type DirSizeChannels = (
+    mpsc::Sender<(PathBuf, SymlinkPolicy)>,
+    mpsc::Receiver<(PathBuf, u64)>,
+);
 type RemoteDirSizeChannels = (
     mpsc::Sender<(String, String)>,
     mpsc::Receiver<(String, String, u64)>,
 );
+type RemoteFreeSpaceChannels = (
+    mpsc::Sender<(String, String)>,
+    mpsc::Receiver<(String, String, Option<u64>)>,
+);
 
 fn lock_or_recover<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
     match mutex.lock() {
@@ -36,775 +47,1407 @@ fn lock_or_recover<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
     }
 }
 
-fn with_sftp<F, R>(sessions: &SftpSessions, host: &str, f: F) -> Result<R, String>
-where
-    F: FnOnce(&ssh2::Sftp) -> Result<R, String>,
-{
-    with_sftp_session(sessions, host, |s| f(&s.sftp))
-}
-
-fn with_sftp_session<F, R>(sessions: &SftpSessions, host: &str, f: F) -> Result<R, String>
+fn with_remote<F, R>(sessions: &SftpSessions, host: &str, f: F) -> Result<R, String>
 where
-    F: FnOnce(&SftpSession) -> Result<R, String>,
+    F: FnOnce(&dyn RemoteFs) -> Result<R, String>,
 {
     let session_arc = lock_or_recover(sessions)
         .get(host)
         .cloned()
-        .ok_or_else(|| format!("No SFTP session for host: {host}"))?;
+        .ok_or_else(|| format!("No remote session for host: {host}"))?;
     let locked = lock_or_recover(&session_arc);
-    f(&locked)
+    f(&**locked)
+}
+
+/// Hold a remote transfer at or over the hold threshold until the user
+/// releases it. `size` is only measured when holding is enabled. Returns
+/// false if cancelled while waiting. Call it without holding a session
+/// lock, so the host can be browsed while the transfer waits.
+fn wait_for_release(
+    progress: &crate::core::TransferProgress,
+    cancel: &AtomicBool,
+    size: impl FnOnce() -> u64,
+) -> bool {
+    !progress.holds_large() || progress.hold_if_large(size(), cancel)
+}
+
+/// Settle a name collision before writing `name` into a destination.
+/// `target` describes what already has that name (`None`: no collision) and
+/// `taken` tells whether a candidate for a rename is in use. Returns the
+/// name to write under (the same one to overwrite), or `None` to skip the
+/// item, as also when cancelled while asking.
+fn settle_conflict(
+    progress: &crate::core::TransferProgress,
+    cancel: &AtomicBool,
+    name: &str,
+    source: Option<ConflictSide>,
+    target: Option<ConflictSide>,
+    taken: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let Some(target) = target else {
+        return Some(name.to_string());
+    };
+    let conflict = Conflict {
+        name: name.to_string(),
+        source: source.unwrap_or(ConflictSide::UNKNOWN),
+        target,
+    };
+    match progress.ask_conflict(conflict, cancel)? {
+        Resolution::Overwrite => Some(name.to_string()),
+        Resolution::Skip => None,
+        Resolution::Rename => Some(free_name(name, taken)),
+    }
+}
+
+/// [`settle_conflict`] for a local destination path. A chosen new name
+/// replaces the last component; otherwise `target` is returned as is.
+fn settle_local(
+    target: &Path,
+    source: Option<ConflictSide>,
+    progress: &crate::core::TransferProgress,
+    cancel: &AtomicBool,
+) -> Option<PathBuf> {
+    let name = fs_name(target);
+    let dir = target.parent().unwrap_or(target);
+    let chosen = settle_conflict(
+        progress,
+        cancel,
+        &name,
+        source,
+        ConflictSide::local(target),
+        |n| dir.join(n).symlink_metadata().is_ok(),
+    )?;
+    Some(if chosen == name {
+        target.to_path_buf()
+    } else {
+        dir.join(chosen)
+    })
+}
+
+/// [`settle_conflict`] for `name` in a remote directory. The session is
+/// locked only to look at the directory, not while the user is asked, so
+/// listings of the host go on meanwhile.
+fn settle_remote(
+    session: &SharedRemote,
+    dir: &str,
+    name: &str,
+    source: Option<ConflictSide>,
+    progress: &crate::core::TransferProgress,
+    cancel: &AtomicBool,
+) -> Option<String> {
+    let target = remote_side(
+        &**lock_or_recover(session),
+        &crate::remote::join_path(dir, name),
+    );
+    settle_conflict(progress, cancel, name, source, target, |n| {
+        lock_or_recover(session)
+            .stat(&crate::remote::join_path(dir, n))
+            .is_ok()
+    })
+}
+
+fn remote_side(fs: &dyn RemoteFs, path: &str) -> Option<ConflictSide> {
+    fs.stat(path).ok().map(|stat| ConflictSide {
+        is_dir: stat.is_dir,
+        size: stat.size,
+        mtime: stat.mtime,
+    })
 }
 
 const PREVIEW_CHUNK_BYTES: usize = 16 * 1024;
 
+/// Start the IO workers. Tasks pushed into `queue` wait there, where the UI
+/// can pause, reorder or drop them before they run;
+/// [`crate::core::IO_WORKERS`] threads take them from there, each task
+/// reporting into its own child of `transfer_progress`, until the queue is
+/// closed.
 pub fn start_io_worker(
     sftp_sessions: SftpSessions,
     transfer_progress: Arc<crate::core::TransferProgress>,
     wake: Option<std::sync::Arc<dyn Fn() + Send + Sync>>,
-    cancel_flag: Arc<AtomicBool>,
-) -> (
-    mpsc::Sender<IOTask>,
-    mpsc::Receiver<IOResult>,
-    mpsc::Sender<()>,
-) {
-    let (tx, rx) = mpsc::channel::<IOTask>();
+    queue: Arc<crate::core::IoQueue>,
+) -> mpsc::Receiver<IOResult> {
     let (result_tx, result_rx) = mpsc::channel::<IOResult>();
-    let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
-    thread::spawn(move || {
-        let mut cancel_requested = false;
-        while let Ok(task) = rx.recv() {
-            while cancel_rx.try_recv().is_ok() {
-                cancel_requested = true;
-                cancel_flag.store(true, Ordering::Relaxed);
-            }
-            if cancel_requested {
-                let _ = result_tx.send(IOResult::Completed);
-                while let Ok(_dropped) = rx.try_recv() {
-                    let _ = result_tx.send(IOResult::Completed);
+    for _ in 0..crate::core::IO_WORKERS {
+        let sftp_sessions = sftp_sessions.clone();
+        let transfer_progress = transfer_progress.clone();
+        let wake = wake.clone();
+        let queue = queue.clone();
+        let result_tx = result_tx.clone();
+        thread::spawn(move || {
+            while let Some(started) = queue.next(&transfer_progress) {
+                let io_result = run_started(
+                    started.task,
+                    &sftp_sessions,
+                    &started.progress,
+                    &started.cancel,
+                );
+                queue.finish(started.id);
+                let _ = result_tx.send(io_result);
+                if let Some(ref wake) = wake {
+                    wake();
                 }
-                cancel_requested = false;
-                cancel_flag.store(false, Ordering::Relaxed);
-                continue;
             }
-            cancel_flag.store(false, Ordering::Relaxed);
-            transfer_progress.reset(0);
-            transfer_progress.set_current_name(Some(task.display_name()));
-            // Default: refresh local Fs panels. Remote/silent ops override below.
-            let mut io_result = IOResult::Completed;
-            let task_clone = task.clone();
-            match task {
-                IOTask::Copy { src, dst_dir } => {
-                    if let Err(e) = copy_recursively(&src, &dst_dir) {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!(
-                                "Permission denied: copy {} → {}",
-                                src.display(),
-                                dst_dir.display()
-                            );
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else {
-                            let msg = format!("Copy error: {e}");
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        }
-                    }
+        });
+    }
+    result_rx
+}
+
+/// Run one task from the queue, retrying it once over revived connections,
+/// and count its bytes towards the sessions it used.
+fn run_started(
+    task: IOTask,
+    sftp_sessions: &SftpSessions,
+    progress: &Arc<crate::core::TransferProgress>,
+    cancel: &Arc<AtomicBool>,
+) -> IOResult {
+    progress.set_current_name(Some(task.display_name()));
+    let retry = task.is_idempotent().then(|| task.clone());
+    let hosts: Vec<String> = task.remote_hosts().into_iter().map(String::from).collect();
+    let mut io_result = run_io_task(task, sftp_sessions, progress, cancel);
+    // A dead connection (e.g. after the machine slept) is re-established
+    // once and the task retried, instead of surfacing the error.
+    if let Some(task) = retry
+        && matches!(io_result, IOResult::Error(_) | IOResult::ErrorRemote(..))
+        && !cancel.load(Ordering::Relaxed)
+        && revive_sessions(sftp_sessions, &hosts)
+    {
+        progress.reset(0);
+        io_result = run_io_task(task, sftp_sessions, progress, cancel);
+    }
+    let (bytes, _) = progress.snapshot();
+    for host in &hosts {
+        if let Some(session) = lock_or_recover(sftp_sessions).get(host).cloned() {
+            lock_or_recover(&session)
+                .stats()
+                .bytes
+                .fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+    io_result
+}
+
+/// Reconnect any of `hosts` whose session no longer answers. Returns true if
+/// at least one session was re-established.
+fn revive_sessions(sftp_sessions: &SftpSessions, hosts: &[String]) -> bool {
+    let mut revived = false;
+    for host in hosts {
+        let Some(session) = lock_or_recover(sftp_sessions).get(host).cloned() else {
+            continue;
+        };
+        let mut locked = lock_or_recover(&session);
+        if locked.is_alive() {
+            continue;
+        }
+        match locked.reconnect() {
+            Ok(()) => revived = true,
+            Err(e) => eprintln!("Reconnect to {host} failed: {e}"),
+        }
+    }
+    revived
+}
+
+/// Hash both ends of a finished copy and fail on any difference.
+fn verify_checksums(
+    name: &str,
+    transfer_progress: &crate::core::TransferProgress,
+    src: impl FnOnce() -> Result<crate::core::Checksums, String>,
+    dst: impl FnOnce() -> Result<crate::core::Checksums, String>,
+) -> Result<(), String> {
+    transfer_progress.set_current_name(Some(format!("Verifying {name}")));
+    crate::core::compare_checksums(&src()?, &dst()?)
+}
+
+fn run_io_task(
+    task: IOTask,
+    sftp_sessions: &SftpSessions,
+    transfer_progress: &Arc<crate::core::TransferProgress>,
+    cancel_flag: &Arc<AtomicBool>,
+) -> IOResult {
+    // Default: refresh local Fs panels. Remote/silent ops override below.
+    let mut io_result = IOResult::Completed;
+    let task_clone = task.clone();
+    match task {
+        IOTask::Copy {
+            src,
+            dst_dir,
+            preserve,
+            symlinks,
+        } => {
+            let target = match src.file_name() {
+                Some(name) => match settle_local(
+                    &dst_dir.join(name),
+                    ConflictSide::local(&src),
+                    transfer_progress,
+                    cancel_flag,
+                ) {
+                    Some(target) => Some(target),
+                    None => return io_result,
+                },
+                None => None,
+            };
+            let replaced = target
+                .as_ref()
+                .is_some_and(|t| t.symlink_metadata().is_ok());
+            let copied = match target {
+                Some(ref target) => copy_as(&src, target, preserve, symlinks),
+                None => copy_recursively(&src, &dst_dir, preserve, symlinks),
+            };
+            match copied {
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    let msg = format!(
+                        "Permission denied: copy {} → {}",
+                        src.display(),
+                        dst_dir.display()
+                    );
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
+                    };
                 }
-                IOTask::CopyContainer {
-                    kind,
-                    archive_path,
-                    inner_path,
-                    dst_dir,
-                    display_name,
-                } => {
-                    if let Err(e) = copy_container_entry(
-                        kind,
-                        &archive_path,
-                        &inner_path,
-                        &dst_dir,
-                        &display_name,
-                    ) {
-                        let msg = format!("Copy container error: {e}");
+                Err(e) => {
+                    let msg = format!("Copy error: {e}");
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
+                }
+                Ok(method) => {
+                    let label = format!(
+                        "Copy {} → {}{}",
+                        fs_name(&src),
+                        dst_dir.display(),
+                        method_suffix(method)
+                    );
+                    log::info!("{label}");
+                    let undo = target.filter(|_| !replaced).map(|target| {
+                        UndoRecord::new(label, UndoAction::TrashCreated { path: target })
+                    });
+                    io_result = copy_result(method, undo);
+                }
+            }
+        }
+        IOTask::CopyContainer {
+            kind,
+            archive_path,
+            inner_path,
+            dst_dir,
+            display_name,
+        } => {
+            let source = ConflictSide {
+                size: read_container_metadata(kind, &archive_path, &inner_path)
+                    .ok()
+                    .flatten()
+                    .map(|(size, _)| size),
+                ..ConflictSide::UNKNOWN
+            };
+            let Some(target) = settle_local(
+                &dst_dir.join(&display_name),
+                Some(source),
+                transfer_progress,
+                cancel_flag,
+            ) else {
+                return io_result;
+            };
+            if let Err(e) = copy_container_entry(
+                kind,
+                &archive_path,
+                &inner_path,
+                &dst_dir,
+                &fs_name(&target),
+            ) {
+                let msg = format!("Copy container error: {e}");
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            }
+        }
+        IOTask::CopyContainerDir {
+            kind,
+            archive_path,
+            inner_path,
+            dst_dir,
+            display_name,
+        } => {
+            let source = ConflictSide {
+                is_dir: true,
+                ..ConflictSide::UNKNOWN
+            };
+            let Some(target) = settle_local(
+                &dst_dir.join(&display_name),
+                Some(source),
+                transfer_progress,
+                cancel_flag,
+            ) else {
+                return io_result;
+            };
+            if let Err(e) = copy_container_dir(
+                kind,
+                &archive_path,
+                &inner_path,
+                &dst_dir,
+                &fs_name(&target),
+            ) {
+                let msg = format!("Copy container dir error: {e}");
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            }
+        }
+        IOTask::Move {
+            src,
+            dst_dir,
+            symlinks,
+        } => {
+            let target = dst_dir.join(src.file_name().unwrap_or(std::ffi::OsStr::new("moved")));
+            let Some(target) = settle_local(
+                &target,
+                ConflictSide::local(&src),
+                transfer_progress,
+                cancel_flag,
+            ) else {
+                return io_result;
+            };
+            // A move that replaced something can't be taken back.
+            let replaced = target.symlink_metadata().is_ok();
+            let mut method = None;
+            // A rename carries links over as they are; when the choice is
+            // otherwise, each link needs a look: copy, then delete.
+            let renamed = if symlinks == SymlinkPolicy::Links || !has_symlinks(&src) {
+                std::fs::rename(&src, &target)
+            } else {
+                Err(std::io::ErrorKind::CrossesDevices.into())
+            };
+            if let Err(e) = renamed {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    let msg = format!(
+                        "Permission denied: move {} → {}",
+                        src.display(),
+                        dst_dir.display()
+                    );
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
+                    };
+                } else if let Err(copy_err) =
+                    copy_as(&src, &target, Preserve::ALL, symlinks).map(|copied| method = copied)
+                {
+                    if copy_err.kind() == std::io::ErrorKind::PermissionDenied {
+                        let msg = format!(
+                            "Permission denied: move {} → {}",
+                            src.display(),
+                            dst_dir.display()
+                        );
+                        eprintln!("{msg}");
+                        io_result = IOResult::PermissionError {
+                            message: msg,
+                            task: task_clone,
+                        };
+                    } else {
+                        let msg = format!("Move error: {copy_err}");
                         eprintln!("{msg}");
                         io_result = IOResult::Error(msg);
                     }
-                }
-                IOTask::CopyContainerDir {
-                    kind,
-                    archive_path,
-                    inner_path,
-                    dst_dir,
-                    display_name,
-                } => {
-                    if let Err(e) = copy_container_dir(
-                        kind,
-                        &archive_path,
-                        &inner_path,
-                        &dst_dir,
-                        &display_name,
-                    ) {
-                        let msg = format!("Copy container dir error: {e}");
+                } else if let Err(remove_err) = remove_moved(&src, symlinks) {
+                    if remove_err.kind() == std::io::ErrorKind::PermissionDenied {
+                        let msg = format!("Permission denied: move cleanup {}", src.display());
+                        eprintln!("{msg}");
+                        io_result = IOResult::PermissionError {
+                            message: msg,
+                            task: task_clone,
+                        };
+                    } else {
+                        let msg = format!("Move cleanup error: {remove_err}");
                         eprintln!("{msg}");
                         io_result = IOResult::Error(msg);
                     }
                 }
-                IOTask::Move { src, dst_dir } => {
-                    let target = dst_dir.join(
-                        src.file_name()
-                            .map(|s| s.to_string_lossy().into_owned())
-                            .unwrap_or_else(|| "moved".to_string()),
-                    );
-                    if let Err(e) = std::fs::rename(&src, &target) {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!(
-                                "Permission denied: move {} → {}",
-                                src.display(),
-                                dst_dir.display()
-                            );
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else if let Err(copy_err) = copy_recursively(&src, &dst_dir) {
-                            if copy_err.kind() == std::io::ErrorKind::PermissionDenied {
-                                let msg = format!(
-                                    "Permission denied: move {} → {}",
-                                    src.display(),
-                                    dst_dir.display()
-                                );
-                                eprintln!("{msg}");
-                                io_result = IOResult::PermissionError {
-                                    message: msg,
-                                    task: task_clone,
-                                };
-                            } else {
-                                let msg = format!("Move error: {copy_err}");
-                                eprintln!("{msg}");
-                                io_result = IOResult::Error(msg);
-                            }
-                        } else if let Err(remove_err) = match std::fs::symlink_metadata(&src) {
-                            Ok(ref m) if m.is_dir() && !m.file_type().is_symlink() => {
-                                std::fs::remove_dir_all(&src)
-                            }
-                            _ => std::fs::remove_file(&src),
-                        } {
-                            if remove_err.kind() == std::io::ErrorKind::PermissionDenied {
-                                let msg =
-                                    format!("Permission denied: move cleanup {}", src.display());
-                                eprintln!("{msg}");
-                                io_result = IOResult::PermissionError {
-                                    message: msg,
-                                    task: task_clone,
-                                };
-                            } else {
-                                let msg = format!("Move cleanup error: {remove_err}");
-                                eprintln!("{msg}");
-                                io_result = IOResult::Error(msg);
-                            }
-                        }
-                    }
+            }
+            if matches!(io_result, IOResult::Completed) {
+                let undo = (!replaced && target.symlink_metadata().is_ok()).then(|| {
+                    UndoRecord::new(
+                        format!(
+                            "Move {} → {}{}",
+                            fs_name(&src),
+                            dst_dir.display(),
+                            method_suffix(method)
+                        ),
+                        UndoAction::MoveBack {
+                            from: target,
+                            to: src,
+                        },
+                    )
+                });
+                io_result = copy_result(method, undo);
+            }
+        }
+        IOTask::Delete { target } => {
+            let res = match std::fs::symlink_metadata(&target) {
+                Ok(ref m) if m.is_dir() && !m.file_type().is_symlink() => {
+                    std::fs::remove_dir_all(&target)
                 }
-                IOTask::Delete { target } => {
-                    let res = match std::fs::symlink_metadata(&target) {
-                        Ok(ref m) if m.is_dir() && !m.file_type().is_symlink() => {
-                            std::fs::remove_dir_all(&target)
-                        }
-                        _ => std::fs::remove_file(&target),
+                _ => std::fs::remove_file(&target),
+            };
+            if let Err(e) = res {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    let msg = format!("Permission denied: {}", target.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
                     };
-                    if let Err(e) = res {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!("Permission denied: {}", target.display());
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else {
-                            let msg = format!("Delete error: {}\n{e}", target.to_string_lossy());
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        }
-                    }
+                } else {
+                    let msg = format!("Delete error: {}\n{e}", target.to_string_lossy());
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
                 }
-                IOTask::Rename { src, new_name } => {
-                    let target = src.with_file_name(&new_name);
-                    // Refuse to rename onto an existing different entry, which
-                    // std::fs::rename would silently replace. A no-op rename and
-                    // a case-only rename on a case-insensitive filesystem (where
-                    // target resolves to the same file as src) are allowed.
-                    let onto_existing = target != src
-                        && target.symlink_metadata().is_ok()
-                        && target.canonicalize().ok() != src.canonicalize().ok();
-                    if onto_existing {
-                        let msg = format!("Already exists: {}", target.display());
-                        eprintln!("{msg}");
-                        io_result = IOResult::Error(msg);
-                    } else if let Err(e) = std::fs::rename(&src, &target) {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!("Permission denied: rename {}", src.display());
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else {
-                            let msg = format!("Rename error: {e}");
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        }
-                    }
+            }
+        }
+        IOTask::Trash { target } => match crate::trash::move_to_trash(&target) {
+            Ok(item) => {
+                io_result = undoable(
+                    format!("Trash {}", target.display()),
+                    UndoAction::RestoreTrash { item },
+                );
+            }
+            Err(e) => {
+                let msg = format!(
+                    "Trash error: {}\n{e}\nShift+F8 deletes permanently.",
+                    target.to_string_lossy()
+                );
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            }
+        },
+        IOTask::RestoreTrash { item } => {
+            if let Err(e) = crate::trash::restore(&item) {
+                let msg = format!("Restore error: {e}");
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            }
+        }
+        IOTask::PurgeTrash { item } => {
+            if let Err(e) = crate::trash::purge(&item) {
+                let msg = format!("Purge error: {}\n{e}", item.to_string_lossy());
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            }
+        }
+        IOTask::Rename { src, new_name } => {
+            let target = src.with_file_name(&new_name);
+            // Refuse to rename onto an existing different entry, which
+            // std::fs::rename would silently replace. A no-op rename and
+            // a case-only rename on a case-insensitive filesystem (where
+            // target resolves to the same file as src) are allowed.
+            let onto_existing = target != src
+                && target.symlink_metadata().is_ok()
+                && target.canonicalize().ok() != src.canonicalize().ok();
+            if onto_existing {
+                let msg = format!("Already exists: {}", target.display());
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            } else if let Err(e) = std::fs::rename(&src, &target) {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    let msg = format!("Permission denied: rename {}", src.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
+                    };
+                } else {
+                    let msg = format!("Rename error: {e}");
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
                 }
-                IOTask::WriteFile {
-                    path,
-                    contents,
-                    exclusive,
-                } => {
-                    let write_result = (|| -> std::io::Result<()> {
-                        use std::io::Write as _;
-                        if exclusive {
-                            // New file: create_new fails if the path exists,
-                            // rather than truncating an existing file.
-                            let mut f = std::fs::OpenOptions::new()
-                                .write(true)
-                                .create_new(true)
-                                .open(&path)?;
-                            f.write_all(&contents)?;
-                            f.sync_all()?;
-                        } else {
-                            // Overwrite (editor save): write a sibling temp file,
-                            // fsync it, then rename over the target so a crash or
-                            // ENOSPC can never leave the original truncated.
-                            let dir = path.parent().unwrap_or_else(|| Path::new("."));
-                            let fname = path
-                                .file_name()
-                                .map(|s| s.to_string_lossy().into_owned())
-                                .unwrap_or_else(|| "file".to_string());
-                            let tmp =
-                                dir.join(format!(".{fname}.fileman-tmp.{}", std::process::id()));
-                            {
-                                let mut f = std::fs::File::create(&tmp)?;
-                                f.write_all(&contents)?;
-                                f.sync_all()?;
-                            }
-                            if let Err(e) = std::fs::rename(&tmp, &path) {
-                                let _ = std::fs::remove_file(&tmp);
-                                return Err(e);
-                            }
-                        }
-                        Ok(())
-                    })();
-                    if let Err(e) = write_result {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!("Permission denied: write {}", path.display());
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else if exclusive && e.kind() == std::io::ErrorKind::AlreadyExists {
-                            let msg = format!("File already exists: {}", path.display());
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        } else {
-                            let msg = format!("Write error: {e}");
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        }
+            } else if target != src {
+                io_result = undoable(
+                    format!("Rename {} → {new_name}", fs_name(&src)),
+                    UndoAction::MoveBack {
+                        from: target,
+                        to: src,
+                    },
+                );
+            }
+        }
+        IOTask::WriteFile {
+            path,
+            contents,
+            exclusive,
+        } => {
+            let write_result = (|| -> std::io::Result<()> {
+                use std::io::Write as _;
+                if exclusive {
+                    // New file: create_new fails if the path exists,
+                    // rather than truncating an existing file.
+                    let mut f = std::fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)?;
+                    f.write_all(&contents)?;
+                    f.sync_all()?;
+                } else {
+                    // Overwrite (editor save): write a sibling temp file,
+                    // fsync it, then rename over the target so a crash or
+                    // ENOSPC can never leave the original truncated.
+                    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+                    let fname = path
+                        .file_name()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "file".to_string());
+                    let tmp = dir.join(format!(".{fname}.fileman-tmp.{}", std::process::id()));
+                    {
+                        let mut f = std::fs::File::create(&tmp)?;
+                        f.write_all(&contents)?;
+                        f.sync_all()?;
                     }
-                }
-                IOTask::Mkdir { path } => {
-                    if let Err(e) = std::fs::create_dir(&path) {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!("Permission denied: mkdir {}", path.display());
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else {
-                            let msg = format!("Mkdir error: {e}");
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        }
+                    if let Err(e) = std::fs::rename(&tmp, &path) {
+                        let _ = std::fs::remove_file(&tmp);
+                        return Err(e);
                     }
                 }
-                IOTask::Pack {
-                    sources,
-                    archive_path,
-                    kind,
-                } => {
-                    if let Err(e) = create_archive(&sources, &archive_path, kind) {
-                        let msg = format!("Pack error: {e}");
-                        eprintln!("{msg}");
-                        io_result = IOResult::Error(msg);
-                    }
+                Ok(())
+            })();
+            if let Err(e) = write_result {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    let msg = format!("Permission denied: write {}", path.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
+                    };
+                } else if exclusive && e.kind() == std::io::ErrorKind::AlreadyExists {
+                    let msg = format!("File already exists: {}", path.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
+                } else {
+                    let msg = format!("Write error: {e}");
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
                 }
-                #[cfg(unix)]
-                IOTask::SetProps {
-                    path,
-                    mode,
-                    uid,
-                    gid,
-                    recursive,
-                } => {
-                    let res = if recursive {
-                        apply_props_recursive(&path, mode, uid, gid)
-                    } else {
-                        apply_props(&path, mode, uid, gid)
+            } else if exclusive {
+                io_result = undoable(
+                    format!("New file {}", path.display()),
+                    UndoAction::TrashCreated { path },
+                );
+            }
+        }
+        IOTask::Mkdir { path } => {
+            if let Err(e) = std::fs::create_dir(&path) {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    let msg = format!("Permission denied: mkdir {}", path.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
                     };
-                    if let Err(e) = res {
-                        if e.kind() == std::io::ErrorKind::PermissionDenied {
-                            let msg = format!("Permission denied: set props on {}", path.display());
-                            eprintln!("{msg}");
-                            io_result = IOResult::PermissionError {
-                                message: msg,
-                                task: task_clone,
-                            };
-                        } else {
-                            let msg = format!("Props error: {e}");
-                            eprintln!("{msg}");
-                            io_result = IOResult::Error(msg);
-                        }
-                    }
+                } else {
+                    let msg = format!("Mkdir error: {e}");
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
                 }
-                #[cfg(not(unix))]
-                IOTask::SetProps { .. } => {
-                    let msg = "SetProps is not supported on this platform".to_string();
+            } else {
+                io_result = undoable(
+                    format!("New directory {}", path.display()),
+                    UndoAction::TrashCreated { path },
+                );
+            }
+        }
+        IOTask::Link {
+            src,
+            dst_dir,
+            kind,
+            target,
+        } => {
+            let link = dst_dir.join(src.file_name().unwrap_or_default());
+            match create_link(&src, &link, kind, target) {
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    let msg = format!("Permission denied: link {}", link.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
+                    };
+                }
+                Err(e) => {
+                    let msg = format!("Link error: {e}");
                     eprintln!("{msg}");
                     io_result = IOResult::Error(msg);
                 }
-                IOTask::WriteRemoteFile {
-                    host,
-                    path,
-                    contents,
-                } => {
-                    let mut err_msg = None;
-                    if let Err(e) = with_sftp(&sftp_sessions, &host, |sftp| {
-                        crate::sftp::write_file(sftp, &path, &contents)
-                            .map_err(|e| format!("Remote write error: {e}"))
-                    }) {
-                        eprintln!("{e}");
-                        err_msg = Some(e);
-                    }
-                    io_result = if let Some(msg) = err_msg {
-                        IOResult::ErrorRemote(host, msg)
-                    } else {
-                        IOResult::CompletedRemote(host)
+                Ok(()) => {
+                    io_result = undoable(
+                        format!("Link {} → {}", link.display(), src.display()),
+                        UndoAction::TrashCreated { path: link },
+                    );
+                }
+            }
+        }
+        IOTask::Pack {
+            sources,
+            archive_path,
+            kind,
+        } => {
+            if let Err(e) = create_archive(&sources, &archive_path, kind) {
+                let msg = format!("Pack error: {e}");
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
+            }
+        }
+        #[cfg(unix)]
+        IOTask::SetProps {
+            path,
+            mode,
+            uid,
+            gid,
+            recursive,
+        } => {
+            let res = if recursive {
+                apply_props_recursive(&path, mode, uid, gid)
+            } else {
+                apply_props(&path, mode, uid, gid)
+            };
+            if let Err(e) = res {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    let msg = format!("Permission denied: set props on {}", path.display());
+                    eprintln!("{msg}");
+                    io_result = IOResult::PermissionError {
+                        message: msg,
+                        task: task_clone,
                     };
+                } else {
+                    let msg = format!("Props error: {e}");
+                    eprintln!("{msg}");
+                    io_result = IOResult::Error(msg);
                 }
-                IOTask::CopyRemoteToLocal {
-                    host,
-                    remote_path,
-                    dst_dir,
-                    name,
-                    is_dir,
-                    delete_source_on_success,
-                } => {
-                    let mut err_msg: Option<String> = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        let result = if is_dir {
-                            let total =
-                                crate::sftp::count_bytes_via_exec(&locked.session, &remote_path);
-                            transfer_progress.reset(total);
-                            crate::sftp::copy_remote_dir_to_local_via_tar(
-                                &locked.session,
-                                &remote_path,
-                                &dst_dir,
-                                &name,
-                                &cancel_flag,
-                                Some(&transfer_progress),
-                            )
-                        } else {
-                            let local_path = dst_dir.join(&name);
-                            crate::sftp::copy_remote_to_local_progress(
-                                &locked.sftp,
+            }
+        }
+        #[cfg(not(unix))]
+        IOTask::SetProps { .. } => {
+            let msg = "SetProps is not supported on this platform".to_string();
+            eprintln!("{msg}");
+            io_result = IOResult::Error(msg);
+        }
+        IOTask::WriteRemoteFile {
+            host,
+            path,
+            contents,
+            expected_mtime,
+        } => {
+            let mut changed = false;
+            let written = with_remote(sftp_sessions, &host, |fs| {
+                if expected_mtime.is_some() && crate::remote::mtime(fs, &path) != expected_mtime {
+                    changed = true;
+                    return Ok(None);
+                }
+                crate::remote::write_file(fs, &path, &contents)
+                    .map_err(|e| format!("Remote write error: {e}"))?;
+                Ok(crate::remote::mtime(fs, &path))
+            });
+            io_result = match written {
+                Ok(_) if changed => IOResult::RemoteChanged {
+                    message: format!("{path} on {host} changed since it was opened"),
+                    task: IOTask::WriteRemoteFile {
+                        host,
+                        path,
+                        contents,
+                        expected_mtime: None,
+                    },
+                },
+                Ok(mtime) => IOResult::RemoteFileWritten { host, path, mtime },
+                Err(e) => {
+                    eprintln!("{e}");
+                    IOResult::ErrorRemote(host, e)
+                }
+            };
+        }
+        IOTask::CopyRemoteToLocal {
+            host,
+            remote_path,
+            dst_dir,
+            name,
+            is_dir,
+            delete_source_on_success,
+            verify,
+        } => {
+            let mut err_msg: Option<String> = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let source = remote_side(&**lock_or_recover(&session), &remote_path);
+                let Some(target) =
+                    settle_local(&dst_dir.join(&name), source, transfer_progress, cancel_flag)
+                else {
+                    return io_result;
+                };
+                let released = wait_for_release(transfer_progress, cancel_flag, || {
+                    crate::remote::count_bytes(&**lock_or_recover(&session), &remote_path)
+                });
+                let locked = lock_or_recover(&session);
+                let fs = &**locked;
+                // tar unpacks under the source name first, which is taken.
+                let renamed = target.file_name() != Some(name.as_ref());
+                let name = fs_name(&target);
+                let result = if !released {
+                    Err("Cancelled".to_string())
+                } else if is_dir {
+                    let total = crate::remote::count_bytes(fs, &remote_path);
+                    transfer_progress.reset(total);
+                    match fs.as_sftp() {
+                        Some(sftp) if !renamed => crate::sftp::copy_remote_dir_to_local_via_tar(
+                            &sftp.session,
+                            &remote_path,
+                            &dst_dir,
+                            &name,
+                            cancel_flag,
+                            Some(transfer_progress),
+                        ),
+                        _ => crate::remote::copy_dir_to_local(
+                            fs,
+                            &remote_path,
+                            &dst_dir,
+                            &name,
+                            cancel_flag,
+                            Some(transfer_progress),
+                        ),
+                    }
+                } else {
+                    let local_path = dst_dir.join(&name);
+                    crate::remote::copy_to_local(
+                        fs,
+                        &remote_path,
+                        &local_path,
+                        Some(cancel_flag),
+                        Some(transfer_progress),
+                    )
+                };
+                let result = match result {
+                    Ok(()) if verify => verify_checksums(
+                        &name,
+                        transfer_progress,
+                        || crate::remote::sha256(fs, &remote_path, is_dir),
+                        || {
+                            crate::core::sha256_local(&dst_dir.join(&name))
+                                .map_err(|e| e.to_string())
+                        },
+                    ),
+                    other => other,
+                };
+                match result {
+                    // Only a fully successful copy may delete the source
+                    // (this is the move half). A cancel returns Err and
+                    // therefore leaves the source untouched.
+                    Ok(()) => {
+                        if delete_source_on_success
+                            && let Err(e) = crate::remote::recursive_delete(
+                                fs,
                                 &remote_path,
-                                &local_path,
-                                Some(&cancel_flag),
-                                Some(&transfer_progress),
+                                is_dir,
+                                Some(transfer_progress),
                             )
-                        };
-                        match result {
-                            // Only a fully successful copy may delete the source
-                            // (this is the move half). A cancel returns Err and
-                            // therefore leaves the source untouched.
-                            Ok(()) => {
-                                if delete_source_on_success
-                                    && let Err(e) = crate::sftp::recursive_delete(
-                                        &locked.sftp,
-                                        &remote_path,
-                                        is_dir,
-                                        Some(&transfer_progress),
-                                    )
-                                {
-                                    let msg = format!(
-                                        "Move {name}: copied but failed to remove source: {e}"
-                                    );
-                                    eprintln!("{msg}");
-                                    err_msg = Some(msg);
-                                }
-                            }
-                            Err(ref e) if e == "Cancelled" => {}
-                            Err(e) => {
-                                let msg = format!("Copy {name}: {e}");
-                                eprintln!("{msg}");
-                                err_msg = Some(msg);
-                            }
+                        {
+                            let msg =
+                                format!("Move {name}: copied but failed to remove source: {e}");
+                            eprintln!("{msg}");
+                            err_msg = Some(msg);
                         }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
+                    }
+                    Err(ref e) if e == "Cancelled" => {}
+                    Err(e) => {
+                        let msg = format!("Copy {name}: {e}");
                         eprintln!("{msg}");
                         err_msg = Some(msg);
                     }
-                    io_result = match err_msg {
-                        Some(msg) => IOResult::Error(msg),
-                        // A move refreshes both the local destination and the
-                        // remote source; a plain copy refreshes only the local
-                        // destination (the default `Completed`).
-                        None if delete_source_on_success => IOResult::CompletedMoved(host),
-                        None => IOResult::Completed,
-                    };
                 }
-                IOTask::CopyLocalToRemote {
-                    src,
-                    host,
-                    remote_dir,
-                    is_dir,
-                    delete_source_on_success,
-                } => {
-                    let mut err_msg: Option<String> = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        let result = if is_dir {
-                            let total = crate::sftp::count_bytes_local(&src);
-                            transfer_progress.reset(total);
-                            crate::sftp::copy_local_dir_to_remote_via_tar(
-                                &src,
-                                &locked.session,
-                                &remote_dir,
-                                &cancel_flag,
-                                Some(&transfer_progress),
-                            )
-                        } else {
-                            let name = src
-                                .file_name()
-                                .map(|s| s.to_string_lossy().to_string())
-                                .unwrap_or_else(|| "file".to_string());
-                            let remote_path = format!("{remote_dir}/{name}");
-                            crate::sftp::copy_local_to_remote_progress(
-                                &locked.sftp,
-                                &src,
-                                &remote_path,
-                                Some(&cancel_flag),
-                                Some(&transfer_progress),
-                            )
-                        };
-                        match result {
-                            // Only a fully successful upload may delete the local
-                            // source (the move half). A cancel returns Err and
-                            // therefore leaves the source untouched.
-                            Ok(()) => {
-                                if delete_source_on_success {
-                                    let del = match std::fs::symlink_metadata(&src) {
-                                        Ok(ref m) if m.is_dir() && !m.file_type().is_symlink() => {
-                                            std::fs::remove_dir_all(&src)
-                                        }
-                                        _ => std::fs::remove_file(&src),
-                                    };
-                                    if let Err(e) = del {
-                                        let msg = format!(
-                                            "Move: uploaded but failed to remove source {}: {e}",
-                                            src.display()
-                                        );
-                                        eprintln!("{msg}");
-                                        err_msg = Some(msg);
-                                    }
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = match err_msg {
+                Some(msg) => IOResult::Error(msg),
+                // A move refreshes both the local destination and the
+                // remote source; a plain copy refreshes only the local
+                // destination (the default `Completed`).
+                None if delete_source_on_success => IOResult::CompletedMoved(host),
+                None => IOResult::Completed,
+            };
+        }
+        IOTask::CopyLocalToRemote {
+            src,
+            host,
+            remote_dir,
+            is_dir,
+            delete_source_on_success,
+            verify,
+        } => {
+            let mut err_msg: Option<String> = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let name = src
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "file".to_string());
+                let Some(chosen) = settle_remote(
+                    &session,
+                    &remote_dir,
+                    &name,
+                    ConflictSide::local(&src),
+                    transfer_progress,
+                    cancel_flag,
+                ) else {
+                    return io_result;
+                };
+                let released = wait_for_release(transfer_progress, cancel_flag, || {
+                    crate::sftp::count_bytes_local(&src)
+                });
+                let locked = lock_or_recover(&session);
+                let fs = &**locked;
+                // tar lands under the source name, which is taken.
+                let renamed = chosen != name;
+                let name = chosen;
+                let remote_path = crate::remote::join_path(&remote_dir, &name);
+                let result = if !released {
+                    Err("Cancelled".to_string())
+                } else if is_dir {
+                    let total = crate::sftp::count_bytes_local(&src);
+                    transfer_progress.reset(total);
+                    match fs.as_sftp() {
+                        Some(sftp) if !renamed => crate::sftp::copy_local_dir_to_remote_via_tar(
+                            &src,
+                            &sftp.session,
+                            &remote_dir,
+                            cancel_flag,
+                            Some(transfer_progress),
+                        ),
+                        _ => crate::remote::copy_dir_from_local(
+                            fs,
+                            &src,
+                            &remote_dir,
+                            &name,
+                            cancel_flag,
+                            Some(transfer_progress),
+                        ),
+                    }
+                } else {
+                    crate::remote::copy_from_local(
+                        fs,
+                        &src,
+                        &remote_path,
+                        Some(cancel_flag),
+                        Some(transfer_progress),
+                    )
+                };
+                // The tar upload lands at `remote_dir/<src name>` as well.
+                let result = match result {
+                    Ok(()) if verify => verify_checksums(
+                        &name,
+                        transfer_progress,
+                        || crate::core::sha256_local(&src).map_err(|e| e.to_string()),
+                        || crate::remote::sha256(fs, &remote_path, is_dir),
+                    ),
+                    other => other,
+                };
+                match result {
+                    // Only a fully successful upload may delete the local
+                    // source (the move half). A cancel returns Err and
+                    // therefore leaves the source untouched.
+                    Ok(()) => {
+                        if delete_source_on_success {
+                            let del = match std::fs::symlink_metadata(&src) {
+                                Ok(ref m) if m.is_dir() && !m.file_type().is_symlink() => {
+                                    std::fs::remove_dir_all(&src)
                                 }
-                            }
-                            Err(ref e) if e == "Cancelled" => {}
-                            Err(e) => {
-                                let label = src
-                                    .file_name()
-                                    .map(|s| s.to_string_lossy().to_string())
-                                    .unwrap_or_else(|| src.display().to_string());
-                                let msg = format!("Copy {label}: {e}");
+                                _ => std::fs::remove_file(&src),
+                            };
+                            if let Err(e) = del {
+                                let msg = format!(
+                                    "Move: uploaded but failed to remove source {}: {e}",
+                                    src.display()
+                                );
                                 eprintln!("{msg}");
                                 err_msg = Some(msg);
                             }
                         }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
-                        eprintln!("{msg}");
-                        err_msg = Some(msg);
                     }
-                    io_result = match err_msg {
-                        Some(msg) => IOResult::ErrorRemote(host, msg),
-                        // A move refreshes both the remote destination and the
-                        // local source; a plain copy refreshes only the remote
-                        // destination.
-                        None if delete_source_on_success => IOResult::CompletedMoved(host),
-                        None => IOResult::CompletedRemote(host),
-                    };
-                }
-                IOTask::DeleteRemote { host, items } => {
-                    let mut err_msg = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        for item in items.iter() {
-                            if let Err(e) = crate::sftp::recursive_delete(
-                                &locked.sftp,
-                                item.0.as_str(),
-                                item.1,
-                                Some(&transfer_progress),
-                            ) {
-                                let msg = format!("Remote delete error: {e}");
-                                eprintln!("{msg}");
-                                err_msg = Some(msg);
-                            }
-                        }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
+                    Err(ref e) if e == "Cancelled" => {}
+                    Err(e) => {
+                        let label = src
+                            .file_name()
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or_else(|| src.display().to_string());
+                        let msg = format!("Copy {label}: {e}");
                         eprintln!("{msg}");
                         err_msg = Some(msg);
                     }
-                    io_result = if let Some(msg) = err_msg {
-                        IOResult::ErrorRemote(host, msg)
-                    } else {
-                        IOResult::CompletedRemote(host)
-                    };
                 }
-                IOTask::RenameRemote {
-                    host,
-                    src,
-                    new_name,
-                } => {
-                    let mut err_msg = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        let parent = if let Some(pos) = src.rfind('/') {
-                            &src[..pos]
-                        } else {
-                            ""
-                        };
-                        let dst = if parent.is_empty() {
-                            format!("/{new_name}")
-                        } else {
-                            format!("{parent}/{new_name}")
-                        };
-                        if let Err(e) = crate::sftp::rename(&locked.sftp, &src, &dst) {
-                            let msg = format!("Remote rename error: {e}");
-                            eprintln!("{msg}");
-                            err_msg = Some(msg);
-                        }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = match err_msg {
+                Some(msg) => IOResult::ErrorRemote(host, msg),
+                // A move refreshes both the remote destination and the
+                // local source; a plain copy refreshes only the remote
+                // destination.
+                None if delete_source_on_success => IOResult::CompletedMoved(host),
+                None => IOResult::CompletedRemote(host),
+            };
+        }
+        IOTask::DeleteRemote { host, items } => {
+            let mut err_msg = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let locked = lock_or_recover(&session);
+                for item in items.iter() {
+                    if let Err(e) = crate::remote::recursive_delete(
+                        &**locked,
+                        item.0.as_str(),
+                        item.1,
+                        Some(transfer_progress),
+                    ) {
+                        let msg = format!("Remote delete error: {e}");
                         eprintln!("{msg}");
                         err_msg = Some(msg);
                     }
-                    io_result = if let Some(msg) = err_msg {
-                        IOResult::ErrorRemote(host, msg)
-                    } else {
-                        IOResult::CompletedRemote(host)
-                    };
                 }
-                IOTask::MkdirRemote { host, path } => {
-                    let mut err_msg = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        if let Err(e) = crate::sftp::mkdir(&locked.sftp, &path) {
-                            let msg = format!("Remote mkdir error: {e}");
-                            eprintln!("{msg}");
-                            err_msg = Some(msg);
-                        }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = if let Some(msg) = err_msg {
+                IOResult::ErrorRemote(host, msg)
+            } else {
+                IOResult::CompletedRemote(host)
+            };
+        }
+        IOTask::RenameRemote {
+            host,
+            src,
+            new_name,
+        } => {
+            let mut err_msg = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let locked = lock_or_recover(&session);
+                let parent = if let Some(pos) = src.rfind('/') {
+                    &src[..pos]
+                } else {
+                    ""
+                };
+                let dst = if parent.is_empty() {
+                    format!("/{new_name}")
+                } else {
+                    format!("{parent}/{new_name}")
+                };
+                if let Err(e) = locked.rename(&src, &dst) {
+                    let msg = format!("Remote rename error: {e}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
+                }
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = if let Some(msg) = err_msg {
+                IOResult::ErrorRemote(host, msg)
+            } else {
+                IOResult::CompletedRemote(host)
+            };
+        }
+        IOTask::MkdirRemote { host, path } => {
+            let mut err_msg = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let locked = lock_or_recover(&session);
+                if let Err(e) = locked.mkdir(&path) {
+                    let msg = format!("Remote mkdir error: {e}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
+                }
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = if let Some(msg) = err_msg {
+                IOResult::ErrorRemote(host, msg)
+            } else {
+                IOResult::CompletedRemote(host)
+            };
+        }
+        IOTask::CopyRemoteToLocalAndOpen {
+            host,
+            remote_path,
+            local_path,
+        } => {
+            let mut err_msg: Option<String> = None;
+            let mut mtime = None;
+            let mut opened = false;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let locked = lock_or_recover(&session);
+                match crate::remote::copy_to_local(
+                    &**locked,
+                    &remote_path,
+                    &local_path,
+                    Some(cancel_flag),
+                    Some(transfer_progress),
+                ) {
+                    Ok(()) => {
+                        mtime = crate::remote::mtime(&**locked, &remote_path);
+                        opened = true;
+                        open_with_default_app_bg(&local_path);
+                    }
+                    Err(e) if e == "Cancelled" => {}
+                    Err(e) => {
+                        let msg = format!("Open {remote_path}: {e}");
                         eprintln!("{msg}");
                         err_msg = Some(msg);
                     }
-                    io_result = if let Some(msg) = err_msg {
-                        IOResult::ErrorRemote(host, msg)
-                    } else {
-                        IOResult::CompletedRemote(host)
-                    };
                 }
-                IOTask::CopyRemoteToLocalAndOpen {
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = match err_msg {
+                Some(msg) => IOResult::Error(msg),
+                None if opened => IOResult::OpenedRemote {
                     host,
                     remote_path,
                     local_path,
-                } => {
-                    let mut err_msg: Option<String> = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        match crate::sftp::copy_remote_to_local_progress(
-                            &locked.sftp,
-                            &remote_path,
-                            &local_path,
-                            Some(&cancel_flag),
-                            Some(&transfer_progress),
-                        ) {
-                            Ok(()) => open_with_default_app_bg(&local_path),
-                            Err(e) if e == "Cancelled" => {}
-                            Err(e) => {
-                                let msg = format!("Open {remote_path}: {e}");
-                                eprintln!("{msg}");
-                                err_msg = Some(msg);
-                            }
-                        }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
-                        eprintln!("{msg}");
-                        err_msg = Some(msg);
-                    }
-                    io_result = match err_msg {
-                        Some(msg) => IOResult::Error(msg),
-                        None => IOResult::CompletedSilent,
-                    };
+                    mtime,
+                },
+                None => IOResult::CompletedSilent,
+            };
+        }
+        IOTask::CopyRemoteSameHost {
+            host,
+            src_path,
+            dst_dir,
+            name,
+        } => {
+            let mut err_msg = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let source = remote_side(&**lock_or_recover(&session), &src_path);
+                let Some(name) = settle_remote(
+                    &session,
+                    &dst_dir,
+                    &name,
+                    source,
+                    transfer_progress,
+                    cancel_flag,
+                ) else {
+                    return io_result;
+                };
+                let locked = lock_or_recover(&session);
+                if let Err(e) = crate::remote::recursive_copy(&**locked, &src_path, &dst_dir, &name)
+                {
+                    let msg = format!("Remote copy error: {e}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
                 }
-                IOTask::CopyRemoteSameHost {
-                    host,
-                    src_path,
-                    dst_dir,
-                    name,
-                } => {
-                    let mut err_msg = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        if let Err(e) = crate::sftp::recursive_copy_remote(
-                            &locked.sftp,
-                            &src_path,
-                            &dst_dir,
-                            &name,
-                        ) {
-                            let msg = format!("Remote copy error: {e}");
-                            eprintln!("{msg}");
-                            err_msg = Some(msg);
-                        }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
-                        eprintln!("{msg}");
-                        err_msg = Some(msg);
-                    }
-                    io_result = if let Some(msg) = err_msg {
-                        IOResult::ErrorRemote(host, msg)
-                    } else {
-                        IOResult::CompletedRemote(host)
-                    };
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = if let Some(msg) = err_msg {
+                IOResult::ErrorRemote(host, msg)
+            } else {
+                IOResult::CompletedRemote(host)
+            };
+        }
+        IOTask::MoveRemoteSameHost {
+            host,
+            src_path,
+            dst_dir,
+            name,
+        } => {
+            let mut err_msg = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let source = remote_side(&**lock_or_recover(&session), &src_path);
+                let Some(name) = settle_remote(
+                    &session,
+                    &dst_dir,
+                    &name,
+                    source,
+                    transfer_progress,
+                    cancel_flag,
+                ) else {
+                    return io_result;
+                };
+                let locked = lock_or_recover(&session);
+                let dst_path = crate::remote::join_path(&dst_dir, &name);
+                if let Err(e) = locked.rename(&src_path, &dst_path) {
+                    let msg = format!("Remote move error: {e}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
                 }
-                IOTask::MoveRemoteSameHost {
-                    host,
-                    src_path,
-                    dst_dir,
-                    name,
-                } => {
-                    let mut err_msg = None;
-                    if let Some(session) = lock_or_recover(&sftp_sessions).get(&host).cloned() {
-                        let locked = lock_or_recover(&session);
-                        let dst_path = format!("{}/{}", dst_dir.trim_end_matches('/'), name);
-                        if let Err(e) = crate::sftp::rename(&locked.sftp, &src_path, &dst_path) {
-                            let msg = format!("Remote move error: {e}");
-                            eprintln!("{msg}");
-                            err_msg = Some(msg);
-                        }
-                    } else {
-                        let msg = format!("No SFTP session for host: {host}");
-                        eprintln!("{msg}");
-                        err_msg = Some(msg);
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = if let Some(msg) = err_msg {
+                IOResult::ErrorRemote(host, msg)
+            } else {
+                IOResult::CompletedRemote(host)
+            };
+        }
+        IOTask::LinkRemoteSameHost {
+            host,
+            src_path,
+            dst_dir,
+            name,
+            kind,
+            target,
+        } => {
+            let mut err_msg = None;
+            if let Some(session) = lock_or_recover(sftp_sessions).get(&host).cloned() {
+                let locked = lock_or_recover(&session);
+                let path = crate::remote::join_path(&dst_dir, &name);
+                let contents = match kind {
+                    crate::core::LinkKind::Symbolic => {
+                        crate::remote::link_contents(&src_path, &path, target)
                     }
-                    io_result = if let Some(msg) = err_msg {
-                        IOResult::ErrorRemote(host, msg)
-                    } else {
-                        IOResult::CompletedRemote(host)
-                    };
+                    crate::core::LinkKind::Hard => src_path,
+                };
+                let res = locked
+                    .link(&contents, &path, kind)
+                    .unwrap_or_else(|| Err("links are not supported here".to_string()));
+                if let Err(e) = res {
+                    let msg = format!("Remote link error: {e}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
                 }
-                IOTask::CopyContainerAndOpen {
-                    kind,
-                    archive_path,
-                    inner_path,
-                    dst_dir,
-                    display_name,
-                } => {
-                    match copy_container_entry(
-                        kind,
-                        &archive_path,
-                        &inner_path,
+            } else {
+                let msg = format!("No remote session for host: {host}");
+                eprintln!("{msg}");
+                err_msg = Some(msg);
+            }
+            io_result = if let Some(msg) = err_msg {
+                IOResult::ErrorRemote(host, msg)
+            } else {
+                IOResult::CompletedRemote(host)
+            };
+        }
+        IOTask::CopyContainerAndOpen {
+            kind,
+            archive_path,
+            inner_path,
+            dst_dir,
+            display_name,
+        } => {
+            match copy_container_entry(kind, &archive_path, &inner_path, &dst_dir, &display_name) {
+                Ok(()) => open_with_default_app_bg(&dst_dir.join(&display_name)),
+                Err(e) => eprintln!("Extract error: {e}"),
+            }
+            io_result = IOResult::CompletedSilent;
+        }
+        IOTask::CopyRemoteCrossHost {
+            src_host,
+            src_path,
+            dst_host,
+            dst_dir,
+            name,
+            is_dir,
+            verify,
+        } => {
+            let sessions = lock_or_recover(sftp_sessions);
+            let src_session = sessions.get(&src_host).cloned();
+            let dst_session = sessions.get(&dst_host).cloned();
+            drop(sessions);
+            let mut err_msg: Option<String> = None;
+            match (src_session, dst_session) {
+                (Some(src_arc), Some(dst_arc)) => {
+                    let source = remote_side(&**lock_or_recover(&src_arc), &src_path);
+                    let Some(chosen) = settle_remote(
+                        &dst_arc,
                         &dst_dir,
-                        &display_name,
-                    ) {
-                        Ok(()) => open_with_default_app_bg(&dst_dir.join(&display_name)),
-                        Err(e) => eprintln!("Extract error: {e}"),
-                    }
-                    io_result = IOResult::CompletedSilent;
-                }
-                IOTask::CopyRemoteCrossHost {
-                    src_host,
-                    src_path,
-                    dst_host,
-                    dst_dir,
-                    name,
-                    is_dir: _,
-                } => {
-                    let sessions = lock_or_recover(&sftp_sessions);
-                    let src_session = sessions.get(&src_host).cloned();
-                    let dst_session = sessions.get(&dst_host).cloned();
-                    drop(sessions);
-                    let mut err_msg: Option<String> = None;
-                    match (src_session, dst_session) {
-                        (Some(src_arc), Some(dst_arc)) => {
-                            let src_locked = lock_or_recover(&src_arc);
-                            let dst_locked = lock_or_recover(&dst_arc);
+                        &name,
+                        source,
+                        transfer_progress,
+                        cancel_flag,
+                    ) else {
+                        return io_result;
+                    };
+                    let released = wait_for_release(transfer_progress, cancel_flag, || {
+                        crate::remote::count_bytes(&**lock_or_recover(&src_arc), &src_path)
+                    });
+                    let src_locked = lock_or_recover(&src_arc);
+                    let dst_locked = lock_or_recover(&dst_arc);
+                    let (src_fs, dst_fs) = (&**src_locked, &**dst_locked);
+                    // tar lands under the source name, which is taken.
+                    let renamed = chosen != name;
+                    let name = chosen;
+                    let result = match (src_fs.as_sftp(), dst_fs.as_sftp()) {
+                        _ if !released => Err("Cancelled".to_string()),
+                        (Some(src_sftp), Some(dst_sftp)) if !renamed => {
                             transfer_progress.reset(0);
-                            if let Err(e) = crate::sftp::copy_cross_host_via_tar(
-                                &src_locked.session,
+                            crate::sftp::copy_cross_host_via_tar(
+                                &src_sftp.session,
                                 &src_path,
-                                &dst_locked.session,
+                                &dst_sftp.session,
                                 &dst_dir,
                                 &name,
-                                &cancel_flag,
-                                Some(&transfer_progress),
-                            ) && e != "Cancelled"
-                            {
-                                let msg = format!("Copy {name}: {e}");
-                                eprintln!("{msg}");
-                                err_msg = Some(msg);
-                            }
-                        }
-                        (None, _) => {
-                            let msg = format!("No SFTP session for host: {src_host}");
-                            eprintln!("{msg}");
-                            err_msg = Some(msg);
+                                cancel_flag,
+                                Some(transfer_progress),
+                            )
                         }
-                        (_, None) => {
-                            let msg = format!("No SFTP session for host: {dst_host}");
-                            eprintln!("{msg}");
-                            err_msg = Some(msg);
+                        _ => {
+                            transfer_progress.reset(crate::remote::count_bytes(src_fs, &src_path));
+                            crate::remote::copy_between(
+                                src_fs,
+                                &src_path,
+                                dst_fs,
+                                &dst_dir,
+                                &name,
+                                cancel_flag,
+                                Some(transfer_progress),
+                            )
                         }
-                    }
-                    io_result = match err_msg {
-                        Some(msg) => IOResult::ErrorRemote(dst_host, msg),
-                        None => IOResult::CompletedRemote(dst_host),
                     };
-                }
-                IOTask::Elevated(inner) => match crate::elevate::execute_elevated(&inner) {
-                    Ok(()) => {}
-                    Err(msg) => {
+                    let result = match result {
+                        Ok(()) if verify => verify_checksums(
+                            &name,
+                            transfer_progress,
+                            || crate::remote::sha256(src_fs, &src_path, is_dir),
+                            || {
+                                let dst_path = crate::remote::join_path(&dst_dir, &name);
+                                crate::remote::sha256(dst_fs, &dst_path, is_dir)
+                            },
+                        ),
+                        other => other,
+                    };
+                    if let Err(e) = result
+                        && e != "Cancelled"
+                    {
+                        let msg = format!("Copy {name}: {e}");
                         eprintln!("{msg}");
-                        io_result = IOResult::Error(msg);
+                        err_msg = Some(msg);
                     }
-                },
+                }
+                (None, _) => {
+                    let msg = format!("No remote session for host: {src_host}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
+                }
+                (_, None) => {
+                    let msg = format!("No remote session for host: {dst_host}");
+                    eprintln!("{msg}");
+                    err_msg = Some(msg);
+                }
             }
-            transfer_progress.set_current_name(None);
-            let _ = result_tx.send(io_result);
-            if let Some(ref wake) = wake {
-                wake();
+            io_result = match err_msg {
+                Some(msg) => IOResult::ErrorRemote(dst_host, msg),
+                None => IOResult::CompletedRemote(dst_host),
+            };
+        }
+        IOTask::Elevated(inner) => match crate::elevate::execute_elevated(&inner) {
+            Ok(()) => {}
+            Err(msg) => {
+                eprintln!("{msg}");
+                io_result = IOResult::Error(msg);
             }
+        },
+        IOTask::Answered { task, conflicts } => {
+            transfer_progress.answer_task_conflicts(conflicts);
+            return run_io_task(*task, sftp_sessions, transfer_progress, cancel_flag);
         }
-    });
-    (tx, result_rx, cancel_tx)
+        IOTask::Undo(record) => {
+            if let Err(e) = record.check().and_then(|()| undo(&record.action)) {
+                let message = format!("Cannot undo {}: {e}", record.label);
+                eprintln!("{message}");
+                io_result = IOResult::UndoFailed { message, record };
+            }
+        }
+    }
+    io_result
+}
+
+fn fs_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
+
+/// Delete the source of a move that went by copying. Links a move with
+/// [`SymlinkPolicy::Skip`] left out stay behind, with the directories
+/// holding them.
+fn remove_moved(src: &Path, symlinks: SymlinkPolicy) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(src)?;
+    if meta.file_type().is_symlink() {
+        return match symlinks {
+            SymlinkPolicy::Skip => Ok(()),
+            SymlinkPolicy::Links | SymlinkPolicy::Follow => std::fs::remove_file(src),
+        };
+    }
+    if !meta.is_dir() {
+        return std::fs::remove_file(src);
+    }
+    if symlinks != SymlinkPolicy::Skip {
+        return std::fs::remove_dir_all(src);
+    }
+    for entry in std::fs::read_dir(src)? {
+        remove_moved(&entry?.path(), symlinks)?;
+    }
+    match std::fs::remove_dir(src) {
+        Err(ref e) if e.kind() == std::io::ErrorKind::DirectoryNotEmpty => Ok(()),
+        other => other,
+    }
+}
+
+/// Whether `path` is a symbolic link or a directory tree holding any.
+fn has_symlinks(path: &Path) -> bool {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return false;
+    };
+    if meta.file_type().is_symlink() {
+        return true;
+    }
+    meta.is_dir()
+        && std::fs::read_dir(path)
+            .is_ok_and(|entries| entries.flatten().any(|entry| has_symlinks(&entry.path())))
+}
+
+/// " (reflink)" and the like, for labels of copies that moved file data.
+fn method_suffix(method: Option<crate::core::CopyMethod>) -> String {
+    method.map_or_else(String::new, |method| format!(" ({})", method.label()))
+}
+
+fn undoable(label: String, action: UndoAction) -> IOResult {
+    IOResult::CompletedUndoable(UndoRecord::new(label, action))
+}
+
+/// The result of a local copy or move: how its data moved, when it moved
+/// any, and the undo record, when it can be taken back.
+fn copy_result(method: Option<crate::core::CopyMethod>, undo: Option<UndoRecord>) -> IOResult {
+    match (method, undo) {
+        (Some(method), undo) => IOResult::Copied { method, undo },
+        (None, Some(record)) => IOResult::CompletedUndoable(record),
+        (None, None) => IOResult::Completed,
+    }
+}
+
+/// Carry out an undo whose record passed `UndoRecord::check`.
+fn undo(action: &UndoAction) -> Result<(), String> {
+    match *action {
+        UndoAction::MoveBack { ref from, ref to } => match std::fs::rename(from, to) {
+            Ok(()) => Ok(()),
+            // The move crossed filesystems (copy + delete); go back the same way.
+            Err(ref e)
+                if e.kind() == std::io::ErrorKind::CrossesDevices
+                    && from.file_name() == to.file_name() =>
+            {
+                let dst_dir = to.parent().unwrap_or_else(|| Path::new("/"));
+                copy_recursively(from, dst_dir, Preserve::ALL, SymlinkPolicy::Links)
+                    .map_err(|e| e.to_string())?;
+                match std::fs::symlink_metadata(from) {
+                    Ok(ref m) if m.is_dir() => std::fs::remove_dir_all(from),
+                    _ => std::fs::remove_file(from),
+                }
+                .map_err(|e| format!("{}: {e}", from.display()))
+            }
+            Err(e) => Err(e.to_string()),
+        },
+        UndoAction::TrashCreated { ref path } => crate::trash::move_to_trash(path).map(|_| ()),
+        UndoAction::RestoreTrash { ref item } => crate::trash::restore(item).map(|_| ()),
+    }
 }
 
 fn open_with_default_app_bg(path: &Path) {
@@ -970,7 +1613,7 @@ pub fn start_preview_worker(
                             let session = lock_or_recover(&sftp_sessions).get(&host).cloned();
                             if let Some(session) = session {
                                 let locked = lock_or_recover(&session);
-                                match crate::sftp::open_remote_reader(&locked.sftp, &path) {
+                                match locked.open_read(&path) {
                                     Ok(reader) => {
                                         if let Err(err) = send_streaming_preview(
                                             &result_tx,
@@ -1003,7 +1646,7 @@ pub fn start_preview_worker(
                                 let _ = result_tx.send((
                                     id,
                                     PreviewContent::Text(format!(
-                                        "No SFTP session for host: {host}"
+                                        "No remote session for host: {host}"
                                     )),
                                 ));
                             }
@@ -1041,15 +1684,13 @@ fn preview_request_id(request: &PreviewRequest) -> u64 {
     }
 }
 
-pub fn start_dir_size_worker(
-    wake: Option<Arc<dyn Fn() + Send + Sync>>,
-) -> (mpsc::Sender<PathBuf>, mpsc::Receiver<(PathBuf, u64)>) {
-    let (tx, rx) = mpsc::channel::<PathBuf>();
+pub fn start_dir_size_worker(wake: Option<Arc<dyn Fn() + Send + Sync>>) -> DirSizeChannels {
+    let (tx, rx) = mpsc::channel::<(PathBuf, SymlinkPolicy)>();
     let (result_tx, result_rx) = mpsc::channel::<(PathBuf, u64)>();
     let max_concurrent = 4usize;
     thread::spawn(move || {
         let mut active: Vec<thread::JoinHandle<()>> = Vec::new();
-        while let Ok(path) = rx.recv() {
+        while let Ok((path, symlinks)) = rx.recv() {
             active.retain(|h| !h.is_finished());
             if active.len() >= max_concurrent {
                 let _ = active.remove(0).join();
@@ -1057,7 +1698,7 @@ pub fn start_dir_size_worker(
             let result_tx = result_tx.clone();
             let wake = wake.clone();
             active.push(thread::spawn(move || {
-                let size = compute_dir_size(&path);
+                let size = compute_dir_size(&path, symlinks);
                 let _ = result_tx.send((path, size));
                 if let Some(ref w) = wake {
                     w();
@@ -1308,7 +1949,6 @@ pub fn start_search_worker(
             if request.case == SearchCase::Insensitive {
                 needle = needle.to_ascii_lowercase();
             }
-            let use_wildcard = needle.contains('*') || needle.contains('?');
             let mut tick = 0usize;
 
             loop {
@@ -1363,17 +2003,7 @@ pub fn start_search_worker(
                     match request.mode {
                         SearchMode::Name => {
                             let name = entry.file_name().to_string_lossy().to_string();
-                            let haystack = if request.case == SearchCase::Insensitive {
-                                name.to_ascii_lowercase()
-                            } else {
-                                name.clone()
-                            };
-                            let matched = if use_wildcard {
-                                wildcard_match(&haystack, &needle)
-                            } else {
-                                haystack.contains(&needle)
-                            };
-                            if matched {
+                            if name_matches(&name, &needle, request.case) {
                                 let size = if file_type.is_file() {
                                     metadata.as_ref().map(|m| m.len())
                                 } else {
@@ -1426,11 +2056,83 @@ pub fn start_search_worker(
     (tx, result_rx)
 }
 
+/// Compare two directories off the UI thread. A request arriving while one
+/// runs stops it; only the newest gets a `Done`.
+pub fn start_compare_worker(
+    wake: Option<Arc<dyn Fn() + Send + Sync>>,
+) -> (mpsc::Sender<CompareRequest>, mpsc::Receiver<CompareEvent>) {
+    let (tx, rx) = mpsc::channel::<CompareRequest>();
+    let (result_tx, result_rx) = mpsc::channel::<CompareEvent>();
+    thread::spawn(move || {
+        let mut pending: Option<CompareRequest> = None;
+        loop {
+            let request = match pending.take() {
+                Some(request) => request,
+                None => match rx.recv() {
+                    Ok(request) => request,
+                    Err(_) => break,
+                },
+            };
+            let id = request.id;
+            let result = crate::compare::compare(
+                &request.left,
+                &request.right,
+                request.depth,
+                request.content,
+                &request.excludes,
+                &mut |scanned| {
+                    if let Ok(newer) = rx.try_recv() {
+                        pending = Some(newer);
+                        return false;
+                    }
+                    if scanned % 200 == 0 {
+                        let _ = result_tx.send(CompareEvent::Progress { id, scanned });
+                        if let Some(w) = wake.as_ref() {
+                            w();
+                        }
+                    }
+                    true
+                },
+            );
+            if pending.is_some() {
+                continue;
+            }
+            let _ = result_tx.send(match result {
+                Ok(diffs) => CompareEvent::Done { id, diffs },
+                Err(message) => CompareEvent::Error { id, message },
+            });
+            if let Some(w) = wake.as_ref() {
+                w();
+            }
+        }
+    });
+    (tx, result_rx)
+}
+
+/// Whether a file or directory name matches the search needle. `needle` is
+/// already lowercased for case-insensitive searches; `*` and `?` make it a
+/// wildcard pattern over the whole name, otherwise it matches a substring.
+fn name_matches(name: &str, needle: &str, case: SearchCase) -> bool {
+    let haystack = if case == SearchCase::Insensitive {
+        name.to_ascii_lowercase()
+    } else {
+        name.to_string()
+    };
+    if needle.contains('*') || needle.contains('?') {
+        wildcard_match(&haystack, needle)
+    } else {
+        haystack.contains(needle)
+    }
+}
+
 fn file_contains(path: &PathBuf, needle: &str, case: SearchCase) -> std::io::Result<bool> {
+    reader_contains(&mut File::open(path)?, needle, case)
+}
+
+fn reader_contains(file: &mut dyn Read, needle: &str, case: SearchCase) -> std::io::Result<bool> {
     if needle.is_empty() {
         return Ok(false);
     }
-    let mut file = File::open(path)?;
     let mut buf = vec![0u8; 64 * 1024];
     let mut carry: Vec<u8> = Vec::new();
     let needle_bytes = needle.as_bytes();
@@ -1477,7 +2179,7 @@ fn file_contains(path: &PathBuf, needle: &str, case: SearchCase) -> std::io::Res
     Ok(false)
 }
 
-fn wildcard_match(text: &str, pattern: &str) -> bool {
+pub(crate) fn wildcard_match(text: &str, pattern: &str) -> bool {
     let mut t = 0usize;
     let mut p = 0usize;
     let mut star_idx: Option<usize> = None;
@@ -1595,7 +2297,7 @@ fn run_remote_search(
         None => {
             let _ = result_tx.send(SearchEvent::Error {
                 id: request.id,
-                message: format!("No SFTP session for host: {host}"),
+                message: format!("No remote session for host: {host}"),
             });
             emit_done(SearchProgress {
                 scanned: 0,
@@ -1607,8 +2309,14 @@ fn run_remote_search(
 
     let cmd = build_remote_search_cmd(remote_root, &request.needle, request.case, request.mode);
     let locked = lock_or_recover(&session_arc);
+    // Results stream line by line, so this needs a live SSH channel rather
+    // than the buffered `RemoteFs::exec`. Backends without one are walked
+    // through their listings.
+    let Some(sftp) = locked.as_sftp() else {
+        return walk_remote_search(request, &**locked, remote_root, result_tx, cancel_rx, wake);
+    };
 
-    let mut channel = match locked.session.channel_session() {
+    let mut channel = match sftp.session.channel_session() {
         Ok(ch) => ch,
         Err(e) => {
             let _ = result_tx.send(SearchEvent::Error {
@@ -1669,7 +2377,7 @@ fn run_remote_search(
         progress.scanned = progress.scanned.saturating_add(1);
         progress.matched = progress.matched.saturating_add(1);
 
-        let synthetic = std::path::PathBuf::from(format!("/sftp/{host}{remote_path}"));
+        let synthetic = crate::remote::synthetic_path(host, remote_path);
         let _ = result_tx.send(SearchEvent::Match {
             id: request.id,
             result: SearchResult {
@@ -1689,7 +2397,297 @@ fn run_remote_search(
     None
 }
 
-/// Worker that computes remote directory sizes via `du` over SSH exec.
+/// Search a remote tree by listing it directory by directory, for backends
+/// without a shell. Content searches read every file through the backend.
+/// Returns `Some(new_request)` on cancellation, like [`run_remote_search`].
+fn walk_remote_search(
+    request: &SearchRequest,
+    fs: &dyn RemoteFs,
+    remote_root: &str,
+    result_tx: &mpsc::Sender<SearchEvent>,
+    cancel_rx: &mpsc::Receiver<SearchRequest>,
+    wake: &Option<Arc<dyn Fn() + Send + Sync>>,
+) -> Option<SearchRequest> {
+    let notify = || {
+        if let Some(w) = wake.as_ref() {
+            w();
+        }
+    };
+    let needle = if request.case == SearchCase::Insensitive {
+        request.needle.to_ascii_lowercase()
+    } else {
+        request.needle.clone()
+    };
+    let mut progress = SearchProgress {
+        scanned: 0,
+        matched: 0,
+    };
+    let mut stack = vec![remote_root.to_string()];
+    let mut tick = 0usize;
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = crate::remote::read_directory(fs, &dir) else {
+            continue;
+        };
+        for entry in entries {
+            tick = tick.wrapping_add(1);
+            if tick.is_multiple_of(32) {
+                if let Ok(new_req) = cancel_rx.try_recv() {
+                    return Some(new_req);
+                }
+                let _ = result_tx.send(SearchEvent::Progress {
+                    id: request.id,
+                    progress,
+                });
+                notify();
+            }
+            progress.scanned = progress.scanned.saturating_add(1);
+            let EntryLocation::Remote { ref path, .. } = entry.location else {
+                continue;
+            };
+            if entry.is_dir {
+                stack.push(path.clone());
+            }
+            let matched = match request.mode {
+                SearchMode::Name => name_matches(&entry.name, &needle, request.case),
+                SearchMode::Content => {
+                    !entry.is_dir
+                        && fs.open_read(path).is_ok_and(|mut reader| {
+                            reader_contains(&mut reader, &request.needle, request.case)
+                                .unwrap_or(false)
+                        })
+                }
+            };
+            if !matched {
+                continue;
+            }
+            progress.matched = progress.matched.saturating_add(1);
+            let _ = result_tx.send(SearchEvent::Match {
+                id: request.id,
+                result: SearchResult {
+                    path: crate::remote::synthetic_path(fs.host(), path),
+                    is_dir: entry.is_dir,
+                    size: entry.size,
+                    modified: entry.modified,
+                    remote_path: Some(path.clone()),
+                },
+            });
+            notify();
+        }
+    }
+    let _ = result_tx.send(SearchEvent::Done {
+        id: request.id,
+        progress,
+    });
+    notify();
+    None
+}
+
+/// Worker that runs prompt commands one at a time, streaming their output.
+/// Setting `cancel` stops the running command; it is cleared when the next
+/// one starts.
+pub fn start_command_worker(
+    sftp_sessions: SftpSessions,
+    cancel: Arc<AtomicBool>,
+    wake: Option<Arc<dyn Fn() + Send + Sync>>,
+) -> (mpsc::Sender<CommandRequest>, mpsc::Receiver<CommandEvent>) {
+    let (tx, rx) = mpsc::channel::<CommandRequest>();
+    let (result_tx, result_rx) = mpsc::channel::<CommandEvent>();
+    thread::spawn(move || {
+        while let Ok(request) = rx.recv() {
+            cancel.store(false, Ordering::Relaxed);
+            let mut output = |text: String| {
+                let _ = result_tx.send(CommandEvent::Output {
+                    id: request.id,
+                    text,
+                });
+                if let Some(ref wake) = wake {
+                    wake();
+                }
+            };
+            let status = match request.cwd {
+                CommandCwd::Local(ref dir) => {
+                    run_local_command(&request.command, dir, &cancel, &mut output)
+                }
+                CommandCwd::Remote { ref host, ref path } => {
+                    let command = format!("cd {} && {}", sh_quote(path), request.command);
+                    with_remote(&sftp_sessions, host, |fs| {
+                        run_remote_command(fs, &command, &cancel, &mut output)
+                    })
+                }
+            };
+            let _ = result_tx.send(CommandEvent::Done {
+                id: request.id,
+                status,
+            });
+            if let Some(ref wake) = wake {
+                wake();
+            }
+        }
+    });
+    (tx, result_rx)
+}
+
+/// Decode the complete UTF-8 prefix of `pending`, leaving a character split
+/// across reads for the next call. Invalid bytes decode lossily.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let cut = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..cut]).into_owned();
+    pending.drain(..cut);
+    text
+}
+
+fn run_local_command(
+    command: &str,
+    dir: &Path,
+    cancel: &AtomicBool,
+    output: &mut dyn FnMut(String),
+) -> Result<i32, String> {
+    use std::process::{Command, Stdio};
+
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        use std::os::unix::process::CommandExt as _;
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        // Own process group, so cancelling also stops what the shell started.
+        cmd.process_group(0);
+        cmd
+    };
+    let mut child = cmd
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{command}: {e}"))?;
+
+    let (chunk_tx, chunk_rx) = mpsc::channel::<Vec<u8>>();
+    let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    ];
+    for mut pipe in pipes.into_iter().flatten() {
+        let chunk_tx = chunk_tx.clone();
+        thread::spawn(move || {
+            let mut buf = vec![0u8; 16 * 1024];
+            while let Ok(n) = pipe.read(&mut buf) {
+                if n == 0 || chunk_tx.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(chunk_tx);
+
+    let mut pending = Vec::new();
+    let mut killed = false;
+    loop {
+        match chunk_rx.recv_timeout(std::time::Duration::from_millis(50)) {
+            Ok(bytes) => {
+                pending.extend_from_slice(&bytes);
+                let text = take_utf8(&mut pending);
+                if !text.is_empty() {
+                    output(text);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if !killed && cancel.load(Ordering::Relaxed) {
+            killed = true;
+            #[cfg(unix)]
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGTERM);
+            }
+            #[cfg(not(unix))]
+            let _ = child.kill();
+        }
+    }
+    if !pending.is_empty() {
+        output(String::from_utf8_lossy(&pending).into_owned());
+    }
+    let status = child.wait().map_err(|e| format!("{command}: {e}"))?;
+    if killed {
+        return Err("Cancelled".to_string());
+    }
+    Ok(status.code().unwrap_or(-1))
+}
+
+fn run_remote_command(
+    fs: &dyn RemoteFs,
+    command: &str,
+    cancel: &AtomicBool,
+    output: &mut dyn FnMut(String),
+) -> Result<i32, String> {
+    let Some(sftp) = fs.as_sftp() else {
+        // No channel to stream from: wait for the whole output.
+        let result = fs
+            .exec(command)
+            .ok_or_else(|| format!("Commands are not supported on {}", fs.host()))??;
+        let mut text = String::from_utf8_lossy(&result.stdout).into_owned();
+        text.push_str(&result.stderr);
+        output(text);
+        return Ok(result.status);
+    };
+    let session = &sftp.session;
+    let mut channel = session
+        .channel_session()
+        .map_err(|e| format!("SSH channel: {e}"))?;
+    channel
+        .handle_extended_data(ssh2::ExtendedData::Merge)
+        .map_err(|e| format!("SSH channel: {e}"))?;
+    channel.exec(command).map_err(|e| format!("exec: {e}"))?;
+    // Short read timeouts keep a quiet command cancellable.
+    session.set_timeout(100);
+    let mut buf = vec![0u8; 16 * 1024];
+    let mut pending = Vec::new();
+    let read_result = loop {
+        if cancel.load(Ordering::Relaxed) {
+            break Err("Cancelled".to_string());
+        }
+        match channel.read(&mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(n) => {
+                pending.extend_from_slice(&buf[..n]);
+                let text = take_utf8(&mut pending);
+                if !text.is_empty() {
+                    output(text);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => break Err(format!("read: {e}")),
+        }
+    };
+    session.set_timeout(30_000);
+    if !pending.is_empty() {
+        output(String::from_utf8_lossy(&pending).into_owned());
+    }
+    // Closing the channel on cancel hangs up the remote command.
+    let _ = channel.close();
+    read_result?;
+    let _ = channel.wait_close();
+    channel
+        .exit_status()
+        .map_err(|e| format!("exit status: {e}"))
+}
+
+/// Worker that computes remote directory sizes (via `du` when the backend has a shell).
 /// Receives `(host, remote_path)` pairs, sends `(host, remote_path, byte_count)` back.
 pub fn start_remote_dir_size_worker(
     sftp_sessions: SftpSessions,
@@ -1713,7 +2711,7 @@ pub fn start_remote_dir_size_worker(
                     let session_arc = lock_or_recover(&sessions).get(&host).cloned();
                     if let Some(arc) = session_arc {
                         let locked = lock_or_recover(&arc);
-                        crate::sftp::count_bytes_via_exec(&locked.session, &path)
+                        crate::remote::count_bytes(&**locked, &path)
                     } else {
                         0
                     }
@@ -1731,10 +2729,52 @@ pub fn start_remote_dir_size_worker(
     (tx, result_rx)
 }
 
-fn compute_dir_size(root: &Path) -> u64 {
+/// Worker that measures free space on remote volumes. Receives
+/// `(host, remote_path)` pairs, sends `(host, remote_path, free_bytes)` back.
+/// Each query gets its own thread: a session busy with a long transfer holds
+/// its lock, and that must not delay the answer for other hosts.
+pub fn start_remote_free_space_worker(
+    sftp_sessions: SftpSessions,
+    wake: Option<Arc<dyn Fn() + Send + Sync>>,
+) -> RemoteFreeSpaceChannels {
+    let (tx, rx) = mpsc::channel::<(String, String)>();
+    let (result_tx, result_rx) = mpsc::channel::<(String, String, Option<u64>)>();
+    thread::spawn(move || {
+        while let Ok((host, path)) = rx.recv() {
+            let sessions = sftp_sessions.clone();
+            let result_tx = result_tx.clone();
+            let wake = wake.clone();
+            thread::spawn(move || {
+                let free = with_remote(&sessions, &host, |fs| {
+                    Ok(crate::remote::free_space(fs, &path))
+                })
+                .ok()
+                .flatten();
+                let _ = result_tx.send((host, path, free));
+                if let Some(ref w) = wake {
+                    w();
+                }
+            });
+        }
+    });
+    (tx, result_rx)
+}
+
+/// Bytes of the regular files under `root`. `symlinks` decides what links
+/// add: their own size, their targets' (each directory counted once, so
+/// loops end), or nothing.
+fn compute_dir_size(root: &Path, symlinks: SymlinkPolicy) -> u64 {
     let mut total = 0u64;
+    let mut seen: std::collections::HashSet<PathBuf> = std::collections::HashSet::new();
     let mut stack = vec![root.to_path_buf()];
     while let Some(dir) = stack.pop() {
+        if symlinks == SymlinkPolicy::Follow
+            && !dir
+                .canonicalize()
+                .is_ok_and(|canonical| seen.insert(canonical))
+        {
+            continue;
+        }
         let read_dir = match std::fs::read_dir(&dir) {
             Ok(rd) => rd,
             Err(_) => continue,
@@ -1745,14 +2785,24 @@ fn compute_dir_size(root: &Path) -> u64 {
                 Ok(ft) => ft,
                 Err(_) => continue,
             };
-            if file_type.is_symlink() {
+            let meta = if file_type.is_symlink() {
+                match symlinks {
+                    SymlinkPolicy::Skip => continue,
+                    SymlinkPolicy::Links => entry.metadata(),
+                    SymlinkPolicy::Follow => std::fs::metadata(&path),
+                }
+            } else if file_type.is_dir() {
+                stack.push(path);
                 continue;
-            }
-            if file_type.is_dir() {
+            } else {
+                entry.metadata()
+            };
+            let Ok(meta) = meta else {
+                continue;
+            };
+            if meta.is_dir() {
                 stack.push(path);
-            } else if file_type.is_file()
-                && let Ok(meta) = entry.metadata()
-            {
+            } else if meta.is_file() || meta.file_type().is_symlink() {
                 total = total.saturating_add(meta.len());
             }
         }
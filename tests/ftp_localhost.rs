@@ -0,0 +1,382 @@
+//! Integration tests for the FTP backend against a minimal in-process FTP
+//! server (std only) serving a temporary directory on 127.0.0.1.
+//!
+//! The server speaks just enough of RFC 959/3659 for the client: login,
+//! EPSV/PASV, MLSD/MLST or LIST/SIZE/MDTM, RETR with REST, STOR and the
+//! namespace commands. It can hide MLST and EPSV to exercise the fallbacks,
+//! and cut the first download short to exercise resume.
+
+use std::{
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use fileman::{
+    ftp,
+    remote::{self, RemoteFs},
+};
+
+#[derive(Clone, Copy)]
+struct ServerOptions {
+    mlst: bool,
+    epsv: bool,
+    /// Close the data connection of the first RETR after this many bytes.
+    cut_first_download_at: Option<usize>,
+}
+
+const MODERN: ServerOptions = ServerOptions {
+    mlst: true,
+    epsv: true,
+    cut_first_download_at: None,
+};
+
+struct TmpDir(PathBuf);
+
+impl TmpDir {
+    fn new(tag: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("fileman-ftp-{tag}-{}-{n}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        TmpDir(path)
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Serve `root` on an ephemeral port; returns the `ftp://` host key.
+fn start_server(root: &Path, options: ServerOptions) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let root = root.to_path_buf();
+    let cut_pending = Arc::new(AtomicBool::new(options.cut_first_download_at.is_some()));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let root = root.clone();
+            let cut_pending = cut_pending.clone();
+            std::thread::spawn(move || {
+                let _ = serve(stream, &root, options, &cut_pending);
+            });
+        }
+    });
+    format!("ftp://127.0.0.1:{port}")
+}
+
+fn reply(out: &mut TcpStream, text: &str) -> std::io::Result<()> {
+    out.write_all(format!("{text}\r\n").as_bytes())
+}
+
+fn resolve(root: &Path, cwd: &str, arg: &str) -> (String, PathBuf) {
+    let virtual_path = if arg.starts_with('/') {
+        arg.to_string()
+    } else {
+        format!("{}/{arg}", cwd.trim_end_matches('/'))
+    };
+    let local = root.join(virtual_path.trim_start_matches('/'));
+    (virtual_path, local)
+}
+
+fn facts(path: &Path) -> String {
+    let meta = std::fs::metadata(path).unwrap();
+    let kind = if meta.is_dir() { "dir" } else { "file" };
+    format!("type={kind};size={};modify=20200102030405;", meta.len())
+}
+
+fn serve(
+    mut out: TcpStream,
+    root: &Path,
+    options: ServerOptions,
+    cut_pending: &AtomicBool,
+) -> std::io::Result<()> {
+    let mut input = BufReader::new(out.try_clone()?);
+    let mut cwd = "/".to_string();
+    let mut passive: Option<TcpListener> = None;
+    let mut rest = 0u64;
+    let mut rename_from: Option<PathBuf> = None;
+    reply(&mut out, "220 test server ready")?;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let line = line.trim_end();
+        let (verb, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let (virtual_path, local) = resolve(root, &cwd, arg);
+        match verb.to_ascii_uppercase().as_str() {
+            "USER" => reply(&mut out, "331 password please")?,
+            "PASS" => reply(&mut out, "230 logged in")?,
+            "TYPE" | "NOOP" => reply(&mut out, "200 ok")?,
+            "FEAT" if options.mlst => reply(
+                &mut out,
+                "211-Features:\r\n MLST type*;size*;modify*;\r\n EPSV\r\n211 End",
+            )?,
+            "FEAT" => reply(&mut out, "211 No features")?,
+            "PWD" => reply(&mut out, &format!("257 \"{cwd}\" is current"))?,
+            "CWD" if local.is_dir() => {
+                cwd = virtual_path;
+                reply(&mut out, "250 ok")?
+            }
+            "EPSV" if options.epsv => {
+                let listener = TcpListener::bind("127.0.0.1:0")?;
+                let port = listener.local_addr()?.port();
+                passive = Some(listener);
+                reply(
+                    &mut out,
+                    &format!("229 Entering Extended Passive Mode (|||{port}|)"),
+                )?
+            }
+            "PASV" => {
+                let listener = TcpListener::bind("127.0.0.1:0")?;
+                let port = listener.local_addr()?.port();
+                passive = Some(listener);
+                reply(
+                    &mut out,
+                    &format!(
+                        "227 Entering Passive Mode (127,0,0,1,{},{})",
+                        port / 256,
+                        port % 256
+                    ),
+                )?
+            }
+            "REST" => {
+                rest = arg.parse().unwrap_or(0);
+                reply(&mut out, "350 restarting")?
+            }
+            "MLST" if local.exists() => reply(
+                &mut out,
+                &format!(
+                    "250-Listing\r\n {} {virtual_path}\r\n250 End",
+                    facts(&local)
+                ),
+            )?,
+            "SIZE" if local.is_file() => {
+                let len = std::fs::metadata(&local)?.len();
+                reply(&mut out, &format!("213 {len}"))?
+            }
+            "MDTM" if local.exists() => reply(&mut out, "213 20200102030405")?,
+            "MLSD" | "LIST" | "RETR" | "STOR" => {
+                let Some(listener) = passive.take() else {
+                    reply(&mut out, "425 use PASV first")?;
+                    continue;
+                };
+                let dir = if arg.is_empty() {
+                    resolve(root, &cwd, "").1
+                } else {
+                    local.clone()
+                };
+                let verb = verb.to_ascii_uppercase();
+                if verb == "RETR" && !local.is_file() || verb != "STOR" && !dir.exists() {
+                    reply(&mut out, "550 no such file")?;
+                    continue;
+                }
+                let (mut data, _) = listener.accept()?;
+                reply(&mut out, "150 opening data connection")?;
+                let mut complete = true;
+                match verb.as_str() {
+                    "MLSD" | "LIST" => {
+                        let mut listing = String::new();
+                        for entry in std::fs::read_dir(&dir)? {
+                            let entry = entry?;
+                            let name = entry.file_name().to_string_lossy().into_owned();
+                            let path = entry.path();
+                            if verb == "MLSD" {
+                                listing.push_str(&format!("{} {name}\r\n", facts(&path)));
+                            } else {
+                                let meta = entry.metadata()?;
+                                let kind = if meta.is_dir() { 'd' } else { '-' };
+                                listing.push_str(&format!(
+                                    "{kind}rw-r--r--   1 ftp  ftp  {:>8} Jan  2  2020 {name}\r\n",
+                                    meta.len()
+                                ));
+                            }
+                        }
+                        data.write_all(listing.as_bytes())?;
+                    }
+                    "RETR" => {
+                        let mut file = std::fs::File::open(&local)?;
+                        file.seek(SeekFrom::Start(std::mem::take(&mut rest)))?;
+                        let mut bytes = Vec::new();
+                        file.read_to_end(&mut bytes)?;
+                        if let Some(cut) = options.cut_first_download_at
+                            && cut_pending.swap(false, Ordering::SeqCst)
+                        {
+                            bytes.truncate(cut);
+                            complete = false;
+                        }
+                        data.write_all(&bytes)?;
+                    }
+                    _ => {
+                        let mut file = std::fs::File::create(&local)?;
+                        std::io::copy(&mut data, &mut file)?;
+                    }
+                }
+                drop(data);
+                if complete {
+                    reply(&mut out, "226 transfer complete")?
+                } else {
+                    reply(&mut out, "426 connection closed; transfer aborted")?
+                }
+            }
+            "DELE" if local.is_file() => {
+                std::fs::remove_file(&local)?;
+                reply(&mut out, "250 deleted")?
+            }
+            "RMD" if local.is_dir() => {
+                std::fs::remove_dir(&local)?;
+                reply(&mut out, "250 removed")?
+            }
+            "MKD" if !local.exists() => {
+                std::fs::create_dir(&local)?;
+                reply(&mut out, &format!("257 \"{virtual_path}\" created"))?
+            }
+            "RNFR" if local.exists() => {
+                rename_from = Some(local);
+                reply(&mut out, "350 ready for RNTO")?
+            }
+            "RNTO" => match rename_from.take() {
+                Some(from) => {
+                    std::fs::rename(from, &local)?;
+                    reply(&mut out, "250 renamed")?
+                }
+                None => reply(&mut out, "503 RNFR first")?,
+            },
+            "QUIT" => {
+                reply(&mut out, "221 bye")?;
+                return Ok(());
+            }
+            "EPSV" | "MLST" => reply(&mut out, "502 not implemented")?,
+            _ => reply(&mut out, "550 failed")?,
+        }
+    }
+}
+
+fn names(fs: &dyn RemoteFs, path: &str) -> Vec<String> {
+    let mut names: Vec<String> = remote::read_directory(fs, path)
+        .expect("list")
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn ftp_round_trip_with_mlsd() {
+    let root = TmpDir::new("mlsd");
+    let session = ftp::connect(&start_server(&root.0, MODERN)).expect("connect");
+    assert_eq!(session.home_dir(), Some("/"));
+
+    remote::write_file(&session, "/a.txt", b"hello ftp").expect("write");
+    session.mkdir("/dir").expect("mkdir");
+    assert_eq!(names(&session, "/"), ["a.txt", "dir"]);
+    let listing = remote::read_directory(&session, "/").unwrap();
+    let file = listing.iter().find(|e| e.name == "a.txt").unwrap();
+    assert_eq!(file.size, Some(9));
+    assert!(file.modified.is_some());
+
+    session.rename("/a.txt", "/dir/b.txt").expect("rename");
+    let stat = session.stat("/dir/b.txt").expect("stat");
+    assert!(!stat.is_dir);
+    assert_eq!(stat.size, Some(9));
+    assert!(session.stat("/dir").unwrap().is_dir);
+    assert_eq!(
+        remote::read_file_full(&session, "/dir/b.txt", None).unwrap(),
+        b"hello ftp"
+    );
+
+    // Listings below the root start with "..".
+    let mut batches = Vec::new();
+    session
+        .list("/dir", &mut |batch| batches.extend(batch))
+        .unwrap();
+    assert_eq!(batches[0].name, "..");
+
+    remote::recursive_delete(&session, "/dir", true, None).expect("delete");
+    assert!(names(&session, "/").is_empty());
+}
+
+#[test]
+fn ftp_list_fallback_and_pasv() {
+    let root = TmpDir::new("list");
+    let options = ServerOptions {
+        mlst: false,
+        epsv: false,
+        cut_first_download_at: None,
+    };
+    let session = ftp::connect(&start_server(&root.0, options)).expect("connect");
+    std::fs::create_dir(root.0.join("logs")).unwrap();
+    std::fs::write(root.0.join("logs").join("boot log.txt"), b"0123456789").unwrap();
+
+    let listing = remote::read_directory(&session, "/logs").unwrap();
+    assert_eq!(listing.len(), 1);
+    assert_eq!(listing[0].name, "boot log.txt");
+    assert_eq!(listing[0].size, Some(10));
+    let stat = session.stat("/logs/boot log.txt").unwrap();
+    assert_eq!(stat.size, Some(10));
+    assert!(stat.mtime.is_some());
+    assert!(session.stat("/logs").unwrap().is_dir);
+    assert!(session.is_alive());
+}
+
+#[test]
+fn ftp_download_resumes_after_dropped_connection() {
+    let root = TmpDir::new("resume");
+    let options = ServerOptions {
+        cut_first_download_at: Some(1000),
+        ..MODERN
+    };
+    let session = ftp::connect(&start_server(&root.0, options)).expect("connect");
+    let contents: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(root.0.join("firmware.bin"), &contents).unwrap();
+
+    let local = TmpDir::new("resume-out");
+    let dst = local.0.join("firmware.bin");
+    remote::copy_to_local(&session, "/firmware.bin", &dst, None, None).expect("download");
+    assert_eq!(std::fs::read(&dst).unwrap(), contents);
+}
+
+#[test]
+fn ftp_reader_seeks_with_rest() {
+    let root = TmpDir::new("seek");
+    let session = ftp::connect(&start_server(&root.0, MODERN)).expect("connect");
+    let contents: Vec<u8> = (0..50_000u32).map(|i| (i % 253) as u8).collect();
+    std::fs::write(root.0.join("data.bin"), &contents).unwrap();
+
+    let mut reader = session.open_read("/data.bin").expect("open");
+    let mut buf = [0u8; 16];
+    reader.seek(SeekFrom::Start(40_000)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], contents[40_000..40_016]);
+    reader.seek(SeekFrom::End(-16)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], contents[contents.len() - 16..]);
+    reader.seek(SeekFrom::Start(100)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], contents[100..116]);
+}
+
+#[test]
+fn ftp_copy_within_server_reads_and_writes_at_once() {
+    let root = TmpDir::new("copy");
+    let session = ftp::connect(&start_server(&root.0, MODERN)).expect("connect");
+    std::fs::create_dir(root.0.join("src")).unwrap();
+    std::fs::write(root.0.join("src").join("one.txt"), b"one").unwrap();
+    std::fs::write(root.0.join("src").join("two.txt"), b"two").unwrap();
+
+    remote::recursive_copy(&session, "/src", "/", "dst").expect("copy");
+    assert_eq!(names(&session, "/dst"), ["one.txt", "two.txt"]);
+    assert_eq!(
+        std::fs::read(root.0.join("dst").join("two.txt")).unwrap(),
+        b"two"
+    );
+}
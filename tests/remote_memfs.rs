@@ -0,0 +1,270 @@
+//! Remote file operations end to end against the in-memory `mem://` backend:
+//! the IO, search and dir-size workers run exactly as the app drives them,
+//! without a server. Each test uses its own tree, since trees are global.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use fileman::{
+    core::{
+        IOResult, IOTask, IoQueue, SearchCase, SearchEvent, SearchMode, SearchRequest,
+        TransferProgress,
+    },
+    memfs,
+    remote::{self, SharedRemote},
+    workers,
+};
+
+type Sessions = Arc<Mutex<HashMap<String, SharedRemote>>>;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct TmpDir(PathBuf);
+
+impl TmpDir {
+    fn new(tag: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("fileman-mem-{tag}-{}-{n}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        TmpDir(path)
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Connect to each host through `remote::connect` and register the sessions
+/// the way the app does after a successful connect.
+fn sessions(hosts: &[&str]) -> Sessions {
+    memfs::register();
+    let mut map = HashMap::new();
+    for host in hosts {
+        let session = remote::connect(host).unwrap();
+        map.insert(host.to_string(), Arc::new(Mutex::new(session)));
+    }
+    Arc::new(Mutex::new(map))
+}
+
+/// Write `files` (relative path, contents) into a fresh tree `name`.
+fn seed(name: &str, files: &[(&str, &str)]) {
+    let dir = TmpDir::new(name);
+    for &(path, contents) in files {
+        let path = dir.0.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    memfs::seed_from_dir(name, &dir.0).unwrap();
+}
+
+/// Run tasks through the IO worker one at a time, failing on any error.
+fn run(sessions: &Sessions, tasks: Vec<IOTask>) {
+    let queue = Arc::new(IoQueue::new());
+    let rx = workers::start_io_worker(
+        sessions.clone(),
+        Arc::new(TransferProgress::new()),
+        None,
+        queue.clone(),
+    );
+    for task in tasks {
+        queue.push(task);
+        match rx.recv_timeout(TIMEOUT).unwrap() {
+            IOResult::Error(msg) | IOResult::ErrorRemote(_, msg) => panic!("{msg}"),
+            _ => {}
+        }
+    }
+    queue.close();
+}
+
+fn paths(name: &str) -> Vec<String> {
+    memfs::paths(name).into_iter().map(|p| p.0).collect()
+}
+
+#[test]
+fn mem_copy_local_to_remote_and_back_verified() {
+    let host = "mem://upload";
+    seed("upload", &[]);
+    let local = TmpDir::new("upload-src");
+    std::fs::create_dir_all(local.0.join("docs/sub")).unwrap();
+    std::fs::write(local.0.join("docs/a.txt"), "alpha").unwrap();
+    std::fs::write(local.0.join("docs/sub/b.txt"), "beta").unwrap();
+    let back = TmpDir::new("upload-back");
+    let sessions = sessions(&[host]);
+    run(
+        &sessions,
+        vec![
+            IOTask::CopyLocalToRemote {
+                src: local.0.join("docs"),
+                host: host.to_string(),
+                remote_dir: "/".to_string(),
+                is_dir: true,
+                delete_source_on_success: false,
+                verify: true,
+            },
+            IOTask::CopyRemoteToLocal {
+                host: host.to_string(),
+                remote_path: "/docs".to_string(),
+                dst_dir: back.0.clone(),
+                name: "docs".to_string(),
+                is_dir: true,
+                delete_source_on_success: true,
+                verify: true,
+            },
+        ],
+    );
+    assert_eq!(
+        std::fs::read_to_string(back.0.join("docs/sub/b.txt")).unwrap(),
+        "beta"
+    );
+    assert_eq!(
+        std::fs::read_to_string(back.0.join("docs/a.txt")).unwrap(),
+        "alpha"
+    );
+    // The download was a move, so the remote source is gone.
+    assert!(paths("upload").is_empty());
+}
+
+#[test]
+fn mem_mkdir_copy_move_rename_delete_on_one_host() {
+    let host = "mem://ops";
+    seed("ops", &[("src/one.txt", "1"), ("src/nested/two.txt", "22")]);
+    let sessions = sessions(&[host]);
+    run(
+        &sessions,
+        vec![
+            IOTask::MkdirRemote {
+                host: host.to_string(),
+                path: "/dst".to_string(),
+            },
+            IOTask::CopyRemoteSameHost {
+                host: host.to_string(),
+                src_path: "/src".to_string(),
+                dst_dir: "/dst".to_string(),
+                name: "src".to_string(),
+            },
+            IOTask::MoveRemoteSameHost {
+                host: host.to_string(),
+                src_path: "/src/one.txt".to_string(),
+                dst_dir: "/dst".to_string(),
+                name: "one.txt".to_string(),
+            },
+            IOTask::RenameRemote {
+                host: host.to_string(),
+                src: "/dst/src".to_string(),
+                new_name: "copy".to_string(),
+            },
+            IOTask::DeleteRemote {
+                host: host.to_string(),
+                items: vec![("/src".to_string(), true)],
+            },
+        ],
+    );
+    assert_eq!(
+        paths("ops"),
+        [
+            "/dst",
+            "/dst/copy",
+            "/dst/copy/nested",
+            "/dst/copy/nested/two.txt",
+            "/dst/copy/one.txt",
+            "/dst/one.txt",
+        ]
+    );
+    assert_eq!(
+        memfs::read("ops", "/dst/copy/nested/two.txt").unwrap(),
+        b"22"
+    );
+}
+
+#[test]
+fn mem_cross_host_copy() {
+    seed("left", &[("tree/x.bin", "xyz"), ("tree/deep/y.bin", "")]);
+    seed("right", &[]);
+    let sessions = sessions(&["mem://left", "mem://right"]);
+    run(
+        &sessions,
+        vec![IOTask::CopyRemoteCrossHost {
+            src_host: "mem://left".to_string(),
+            src_path: "/tree".to_string(),
+            dst_host: "mem://right".to_string(),
+            dst_dir: "/".to_string(),
+            name: "tree".to_string(),
+            is_dir: true,
+            verify: true,
+        }],
+    );
+    assert_eq!(
+        paths("right"),
+        ["/tree", "/tree/deep", "/tree/deep/y.bin", "/tree/x.bin"]
+    );
+    assert_eq!(memfs::read("right", "/tree/x.bin").unwrap(), b"xyz");
+}
+
+fn search(sessions: &Sessions, host: &str, needle: &str, mode: SearchMode) -> Vec<String> {
+    let (tx, rx) = workers::start_search_worker(None, sessions.clone());
+    tx.send(SearchRequest {
+        id: 1,
+        root: remote::synthetic_path(host, "/"),
+        needle: needle.to_string(),
+        case: SearchCase::Insensitive,
+        mode,
+        remote: Some((host.to_string(), "/".to_string())),
+    })
+    .unwrap();
+    let mut found = Vec::new();
+    loop {
+        match rx.recv_timeout(TIMEOUT).unwrap() {
+            SearchEvent::Match { result, .. } => found.push(result.remote_path.unwrap()),
+            SearchEvent::Done { .. } => break,
+            SearchEvent::Error { message, .. } => panic!("{message}"),
+            SearchEvent::Progress { .. } => {}
+        }
+    }
+    found.sort();
+    found
+}
+
+#[test]
+fn mem_search_by_name_and_content() {
+    let host = "mem://search";
+    seed(
+        "search",
+        &[
+            ("notes/Todo.md", "buy milk"),
+            ("notes/done.md", "Shipped the RELEASE"),
+            ("src/main.rs", "fn main() { release(); }"),
+        ],
+    );
+    let sessions = sessions(&[host]);
+    assert_eq!(
+        search(&sessions, host, "*.MD", SearchMode::Name),
+        ["/notes/Todo.md", "/notes/done.md"]
+    );
+    assert_eq!(search(&sessions, host, "not", SearchMode::Name), ["/notes"]);
+    assert_eq!(
+        search(&sessions, host, "release", SearchMode::Content),
+        ["/notes/done.md", "/src/main.rs"]
+    );
+}
+
+#[test]
+fn mem_dir_size_walks_the_tree() {
+    let host = "mem://sizes";
+    seed("sizes", &[("d/a", "12345"), ("d/e/b", "678"), ("c", "9")]);
+    let sessions = sessions(&[host]);
+    let (tx, rx) = workers::start_remote_dir_size_worker(sessions, None);
+    tx.send((host.to_string(), "/d".to_string())).unwrap();
+    let (reply_host, path, size) = rx.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!((reply_host.as_str(), path.as_str(), size), (host, "/d", 8));
+}
@@ -0,0 +1,567 @@
+//! Integration tests for the S3 backend against a minimal in-process,
+//! MinIO-style stand-in (std only) that keeps one bucket in memory and
+//! serves it path-style on 127.0.0.1.
+//!
+//! The server handles one request per connection and implements the calls
+//! the client makes: HEAD on the bucket and objects, ListObjectsV2 with
+//! delimiter and pagination, ranged GET, PUT (plain and copy), multipart
+//! uploads, and DELETE. It checks that every request carries a SigV4
+//! `Authorization` for the test key and that payload hashes match.
+
+#![cfg(feature = "s3")]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use fileman::{
+    remote::{self, RemoteFs},
+    s3,
+};
+
+const BUCKET: &str = "bucket";
+const ACCESS_KEY: &str = "TESTKEY";
+const MIN_PART: usize = 5 * 1024 * 1024;
+
+#[derive(Default)]
+struct Bucket {
+    objects: BTreeMap<String, Vec<u8>>,
+    uploads: HashMap<String, BTreeMap<u32, Vec<u8>>>,
+}
+
+struct Server {
+    host: String,
+    endpoint: String,
+    bucket: Arc<Mutex<Bucket>>,
+    parts_uploaded: Arc<AtomicUsize>,
+}
+
+impl Server {
+    fn profile(&self, access_key_id: &str) -> s3::Profile {
+        s3::Profile {
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+            region: "us-east-1".to_string(),
+            endpoint: Some(self.endpoint.clone()),
+        }
+    }
+
+    fn connect(&self) -> s3::S3Session {
+        s3::connect_with(&self.host, self.profile(ACCESS_KEY)).expect("connect")
+    }
+
+    fn object(&self, key: &str) -> Option<Vec<u8>> {
+        self.bucket.lock().unwrap().objects.get(key).cloned()
+    }
+}
+
+/// Serve an empty bucket on an ephemeral port, `page_size` keys per
+/// listing page.
+fn start_server(page_size: usize) -> Server {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let bucket = Arc::new(Mutex::new(Bucket::default()));
+    let parts_uploaded = Arc::new(AtomicUsize::new(0));
+    let state = (bucket.clone(), parts_uploaded.clone());
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (bucket, parts) = (state.0.clone(), state.1.clone());
+            std::thread::spawn(move || {
+                let _ = serve(stream, &bucket, &parts, page_size);
+            });
+        }
+    });
+    Server {
+        host: format!("s3://test@{BUCKET}"),
+        endpoint: format!("http://127.0.0.1:{port}"),
+        bucket,
+        parts_uploaded,
+    }
+}
+
+struct Request {
+    method: String,
+    key: Option<String>,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn decode(s: &str) -> String {
+    let mut out = Vec::new();
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex: String = bytes.by_ref().take(2).map(char::from).collect();
+            out.push(u8::from_str_radix(&hex, 16).unwrap());
+        } else {
+            out.push(b);
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::Digest as _;
+    sha2::Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> std::io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (decode(k), decode(v))
+        })
+        .collect();
+    // Path-style: `/bucket` or `/bucket/key`.
+    let path = decode(path);
+    let key = match path.trim_start_matches('/').split_once('/') {
+        Some((_, "")) | None => None,
+        Some((_, key)) => Some(key.to_string()),
+    };
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let mut body = vec![
+        0;
+        headers
+            .get("content-length")
+            .map_or(0, |l| l.parse().unwrap())
+    ];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        key,
+        query,
+        headers,
+        body,
+    })
+}
+
+fn respond(
+    out: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+    if !headers.iter().any(|h| h.0 == "Content-Length") {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    for &(name, ref value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    out.write_all(head.as_bytes())?;
+    out.write_all(body)
+}
+
+fn error(out: &mut TcpStream, status: &str, code: &str) -> std::io::Result<()> {
+    let body = format!("<Error><Code>{code}</Code><Message>{status}</Message></Error>");
+    respond(out, status, &[], body.as_bytes())
+}
+
+fn list(bucket: &Bucket, request: &Request, page_size: usize) -> String {
+    let prefix = request.query.get("prefix").map_or("", String::as_str);
+    let delimiter = request.query.contains_key("delimiter");
+    let max_keys = request
+        .query
+        .get("max-keys")
+        .map_or(page_size, |m| m.parse::<usize>().unwrap().min(page_size));
+    let token = request.query.get("continuation-token");
+    let mut contents = String::new();
+    let mut prefixes: Vec<String> = Vec::new();
+    let mut count = 0;
+    let mut last = None;
+    let mut truncated = false;
+    for (key, data) in bucket.objects.range(prefix.to_string()..) {
+        if !key.starts_with(prefix) {
+            break;
+        }
+        if let Some(token) = token
+            && (key <= token || (token.ends_with('/') && key.starts_with(token.as_str())))
+        {
+            continue;
+        }
+        let common = delimiter
+            .then(|| key[prefix.len()..].find('/'))
+            .flatten()
+            .map(|slash| key[..prefix.len() + slash + 1].to_string());
+        if let Some(ref common) = common
+            && prefixes.last() == Some(common)
+        {
+            continue;
+        }
+        if count == max_keys {
+            truncated = true;
+            break;
+        }
+        count += 1;
+        match common {
+            Some(common) => {
+                last = Some(common.clone());
+                prefixes.push(common);
+            }
+            None => {
+                last = Some(key.clone());
+                contents.push_str(&format!(
+                    "<Contents><Key>{}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified>\
+                     <Size>{}</Size></Contents>",
+                    xml_escape(key),
+                    data.len()
+                ));
+            }
+        }
+    }
+    let mut xml = format!(
+        "<?xml version=\"1.0\"?><ListBucketResult><Name>{BUCKET}</Name>\
+         <Prefix>{}</Prefix><KeyCount>{count}</KeyCount><IsTruncated>{truncated}</IsTruncated>",
+        xml_escape(prefix)
+    );
+    if truncated && let Some(last) = last {
+        xml.push_str(&format!(
+            "<NextContinuationToken>{}</NextContinuationToken>",
+            xml_escape(&last)
+        ));
+    }
+    xml.push_str(&contents);
+    for p in prefixes {
+        xml.push_str(&format!(
+            "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+            xml_escape(&p)
+        ));
+    }
+    xml.push_str("</ListBucketResult>");
+    xml
+}
+
+fn serve(
+    stream: TcpStream,
+    bucket: &Mutex<Bucket>,
+    parts_uploaded: &AtomicUsize,
+    page_size: usize,
+) -> std::io::Result<()> {
+    let mut out = stream.try_clone()?;
+    let request = read_request(&mut BufReader::new(stream))?;
+    let authorized = request.headers.get("authorization").is_some_and(|auth| {
+        auth.starts_with(&format!("AWS4-HMAC-SHA256 Credential={ACCESS_KEY}/"))
+    }) && request.headers.contains_key("x-amz-date");
+    if !authorized {
+        return error(&mut out, "403 Forbidden", "InvalidAccessKeyId");
+    }
+    if request.headers.get("x-amz-content-sha256") != Some(&sha256_hex(&request.body)) {
+        return error(&mut out, "400 Bad Request", "XAmzContentSHA256Mismatch");
+    }
+    let mut bucket = bucket.lock().unwrap();
+    let Some(key) = request.key.clone() else {
+        return match request.method.as_str() {
+            "HEAD" => respond(
+                &mut out,
+                "200 OK",
+                &[("Server", "test-s3".to_string())],
+                b"",
+            ),
+            "GET" if request.query.get("list-type").is_some_and(|t| t == "2") => {
+                let xml = list(&bucket, &request, page_size);
+                respond(&mut out, "200 OK", &[], xml.as_bytes())
+            }
+            _ => error(&mut out, "405 Method Not Allowed", "MethodNotAllowed"),
+        };
+    };
+    let upload_id = request.query.get("uploadId").cloned();
+    match (request.method.as_str(), upload_id) {
+        ("HEAD", _) => match bucket.objects.get(&key) {
+            Some(data) => respond(
+                &mut out,
+                "200 OK",
+                &[
+                    ("Content-Length", data.len().to_string()),
+                    ("Last-Modified", "Mon, 01 Jan 2024 00:00:00 GMT".to_string()),
+                ],
+                b"",
+            ),
+            None => respond(&mut out, "404 Not Found", &[], b""),
+        },
+        ("GET", _) => {
+            let Some(data) = bucket.objects.get(&key) else {
+                return error(&mut out, "404 Not Found", "NoSuchKey");
+            };
+            let offset = request
+                .headers
+                .get("range")
+                .and_then(|r| r.strip_prefix("bytes="))
+                .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+            match offset {
+                Some(offset) if offset >= data.len() => {
+                    error(&mut out, "416 Range Not Satisfiable", "InvalidRange")
+                }
+                Some(offset) => {
+                    let range = format!("bytes {offset}-{}/{}", data.len() - 1, data.len());
+                    respond(
+                        &mut out,
+                        "206 Partial Content",
+                        &[("Content-Range", range)],
+                        &data[offset..],
+                    )
+                }
+                None => respond(&mut out, "200 OK", &[], data),
+            }
+        }
+        ("PUT", Some(upload_id)) => {
+            let number: u32 = request.query["partNumber"].parse().unwrap();
+            let Some(parts) = bucket.uploads.get_mut(&upload_id) else {
+                return error(&mut out, "404 Not Found", "NoSuchUpload");
+            };
+            parts.insert(number, request.body);
+            parts_uploaded.fetch_add(1, Ordering::SeqCst);
+            respond(
+                &mut out,
+                "200 OK",
+                &[("ETag", format!("\"etag-{number}\""))],
+                b"",
+            )
+        }
+        ("PUT", None) => {
+            if let Some(source) = request.headers.get("x-amz-copy-source") {
+                let source = decode(source);
+                let source_key = source
+                    .trim_start_matches('/')
+                    .strip_prefix(&format!("{BUCKET}/"))
+                    .unwrap_or_default();
+                let Some(data) = bucket.objects.get(source_key).cloned() else {
+                    return error(&mut out, "404 Not Found", "NoSuchKey");
+                };
+                bucket.objects.insert(key, data);
+                return respond(&mut out, "200 OK", &[], b"<CopyObjectResult/>");
+            }
+            bucket.objects.insert(key, request.body);
+            respond(&mut out, "200 OK", &[], b"")
+        }
+        ("POST", None) if request.query.contains_key("uploads") => {
+            let upload_id = format!("upload-{}", bucket.uploads.len() + 1);
+            bucket.uploads.insert(upload_id.clone(), BTreeMap::new());
+            let xml = format!(
+                "<InitiateMultipartUploadResult><Bucket>{BUCKET}</Bucket><Key>{}</Key>\
+                 <UploadId>{upload_id}</UploadId></InitiateMultipartUploadResult>",
+                xml_escape(&key)
+            );
+            respond(&mut out, "200 OK", &[], xml.as_bytes())
+        }
+        ("POST", Some(upload_id)) => {
+            let Some(parts) = bucket.uploads.remove(&upload_id) else {
+                return error(&mut out, "404 Not Found", "NoSuchUpload");
+            };
+            let listed = String::from_utf8_lossy(&request.body).into_owned();
+            let mut data = Vec::new();
+            for (i, (&number, part)) in parts.iter().enumerate() {
+                let last = i + 1 == parts.len();
+                if (!last && part.len() < MIN_PART)
+                    || !listed.contains(&format!("<PartNumber>{number}</PartNumber>"))
+                {
+                    // Real servers report this in the body of a 200.
+                    let xml = "<Error><Code>EntityTooSmall</Code><Message>part</Message></Error>";
+                    return respond(&mut out, "200 OK", &[], xml.as_bytes());
+                }
+                data.extend_from_slice(part);
+            }
+            bucket.objects.insert(key, data);
+            respond(&mut out, "200 OK", &[], b"<CompleteMultipartUploadResult/>")
+        }
+        ("DELETE", Some(upload_id)) => {
+            bucket.uploads.remove(&upload_id);
+            respond(&mut out, "204 No Content", &[], b"")
+        }
+        ("DELETE", None) => {
+            bucket.objects.remove(&key);
+            respond(&mut out, "204 No Content", &[], b"")
+        }
+        _ => error(&mut out, "405 Method Not Allowed", "MethodNotAllowed"),
+    }
+}
+
+fn names(fs: &dyn RemoteFs, path: &str) -> Vec<String> {
+    let mut names: Vec<String> = remote::read_directory(fs, path)
+        .expect("list")
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn s3_round_trip() {
+    let server = start_server(1000);
+    let session = server.connect();
+    assert_eq!(session.stats().banner.as_deref(), Some("test-s3"));
+
+    remote::write_file(&session, "/a b+c.txt", b"hello s3").expect("write");
+    session.mkdir("/dir").expect("mkdir");
+    assert_eq!(names(&session, "/"), ["a b+c.txt", "dir"]);
+    assert_eq!(server.object("dir/"), Some(Vec::new()));
+    let listing = remote::read_directory(&session, "/").unwrap();
+    let file = listing.iter().find(|e| e.name == "a b+c.txt").unwrap();
+    assert_eq!(file.size, Some(8));
+    assert_eq!(file.modified, Some(1_704_067_200));
+
+    session.rename("/a b+c.txt", "/dir/b.txt").expect("rename");
+    assert_eq!(server.object("a b+c.txt"), None);
+    let stat = session.stat("/dir/b.txt").expect("stat");
+    assert!(!stat.is_dir);
+    assert_eq!(stat.size, Some(8));
+    assert_eq!(stat.mtime, Some(1_704_067_200));
+    assert!(session.stat("/dir").unwrap().is_dir);
+    assert!(session.stat("/missing").is_err());
+    assert_eq!(
+        remote::read_file_full(&session, "/dir/b.txt", None).unwrap(),
+        b"hello s3"
+    );
+
+    // Listings below the root start with "..", and skip the dir marker.
+    let mut entries = Vec::new();
+    session
+        .list("/dir", &mut |batch| entries.extend(batch))
+        .unwrap();
+    let listed: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(listed, ["..", "b.txt"]);
+
+    remote::recursive_delete(&session, "/dir", true, None).expect("delete");
+    assert!(names(&session, "/").is_empty());
+    assert!(session.is_alive());
+}
+
+#[test]
+fn s3_listing_follows_continuation_tokens() {
+    let server = start_server(2);
+    {
+        let mut bucket = server.bucket.lock().unwrap();
+        for key in ["a", "b", "c/1", "c/2", "d/x/y", "e", "f"] {
+            bucket.objects.insert(key.to_string(), b"x".to_vec());
+        }
+    }
+    let session = server.connect();
+    let mut batches = Vec::new();
+    session.list("/", &mut |batch| batches.push(batch)).unwrap();
+    assert!(batches.len() >= 3);
+    let mut listed: Vec<(String, bool)> = batches
+        .into_iter()
+        .flatten()
+        .map(|e| (e.name, e.is_dir))
+        .collect();
+    listed.sort();
+    let expected = [
+        ("a", false),
+        ("b", false),
+        ("c", true),
+        ("d", true),
+        ("e", false),
+        ("f", false),
+    ];
+    assert_eq!(
+        listed,
+        expected.map(|(name, is_dir)| (name.to_string(), is_dir))
+    );
+    assert_eq!(names(&session, "/c"), ["1", "2"]);
+}
+
+#[test]
+fn s3_large_upload_goes_multipart() {
+    let server = start_server(1000);
+    let session = server.connect();
+    let contents: Vec<u8> = (0..17 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let local = std::env::temp_dir().join(format!("fileman-s3-{}.bin", std::process::id()));
+    std::fs::write(&local, &contents).unwrap();
+
+    let result = remote::copy_from_local(&session, &local, "/big.bin", None, None);
+    let _ = std::fs::remove_file(&local);
+    result.expect("upload");
+    assert_eq!(server.parts_uploaded.load(Ordering::SeqCst), 3);
+    assert!(server.object("big.bin") == Some(contents));
+    assert_eq!(
+        session.stat("/big.bin").unwrap().size,
+        Some(17 * 1024 * 1024)
+    );
+}
+
+#[test]
+fn s3_reader_seeks_with_ranges() {
+    let server = start_server(1000);
+    let contents: Vec<u8> = (0..50_000u32).map(|i| (i % 253) as u8).collect();
+    server
+        .bucket
+        .lock()
+        .unwrap()
+        .objects
+        .insert("data.bin".to_string(), contents.clone());
+    let session = server.connect();
+
+    let mut reader = session.open_read("/data.bin").expect("open");
+    let mut buf = [0u8; 16];
+    reader.seek(SeekFrom::Start(40_000)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], contents[40_000..40_016]);
+    reader.seek(SeekFrom::End(-16)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], contents[contents.len() - 16..]);
+    reader.seek(SeekFrom::Start(100)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], contents[100..116]);
+}
+
+#[test]
+fn s3_copy_within_bucket() {
+    let server = start_server(1000);
+    let session = server.connect();
+    session.mkdir("/src").unwrap();
+    remote::write_file(&session, "/src/one.txt", b"one").unwrap();
+    remote::write_file(&session, "/src/sub/two.txt", b"two").unwrap();
+
+    remote::recursive_copy(&session, "/src", "/", "dst").expect("copy");
+    assert_eq!(names(&session, "/dst"), ["one.txt", "sub"]);
+    assert_eq!(
+        server.object("dst/sub/two.txt").as_deref(),
+        Some(&b"two"[..])
+    );
+    assert_eq!(server.object("dst/"), Some(Vec::new()));
+    assert_eq!(server.object("src/one.txt").as_deref(), Some(&b"one"[..]));
+}
+
+#[test]
+fn s3_bad_credentials_point_at_aws_config() {
+    let server = start_server(1000);
+    let Err(message) = s3::connect_with(&server.host, server.profile("WRONG")) else {
+        panic!("connected with the wrong key");
+    };
+    assert!(message.contains("~/.aws/credentials"), "{message}");
+}
@@ -8,7 +8,7 @@
 
 use std::io::Read;
 
-use fileman::sftp;
+use fileman::{remote, sftp};
 
 fn connect_localhost() -> sftp::SftpSession {
     let config = sftp::load_ssh_config();
@@ -65,14 +65,14 @@ fn sftp_write_read_delete() {
     let contents = b"hello from fileman sftp test";
 
     // Write
-    sftp::write_file(&session.sftp, test_path, contents).expect("write file");
+    remote::write_file(&session, test_path, contents).expect("write file");
 
     // Read back
     let data = sftp::read_bytes_prefix(&session.sftp, test_path, 1024).expect("read file");
     assert_eq!(data, contents);
 
     // Delete
-    sftp::recursive_delete(&session.sftp, test_path, false, None).expect("delete file");
+    remote::recursive_delete(&session, test_path, false, None).expect("delete file");
 
     // Verify gone
     let result = sftp::read_bytes_prefix(&session.sftp, test_path, 1024);
@@ -86,14 +86,14 @@ fn sftp_mkdir_and_delete() {
     let dir_path = "/tmp/fileman_sftp_test_dir";
 
     // Clean up in case of prior failed run
-    let _ = sftp::recursive_delete(&session.sftp, dir_path, true, None);
+    let _ = remote::recursive_delete(&session, dir_path, true, None);
 
     // Create directory
     sftp::mkdir(&session.sftp, dir_path).expect("mkdir");
 
     // Write a file inside
     let file_path = format!("{dir_path}/nested.txt");
-    sftp::write_file(&session.sftp, &file_path, b"nested").expect("write nested");
+    remote::write_file(&session, &file_path, b"nested").expect("write nested");
 
     // List it
     let entries = sftp::read_directory(&session.sftp, "localhost", dir_path).expect("read dir");
@@ -104,7 +104,7 @@ fn sftp_mkdir_and_delete() {
     );
 
     // Recursive delete
-    sftp::recursive_delete(&session.sftp, dir_path, true, None).expect("recursive delete");
+    remote::recursive_delete(&session, dir_path, true, None).expect("recursive delete");
 
     // Verify gone
     let result = sftp::read_directory(&session.sftp, "localhost", dir_path);
@@ -119,10 +119,10 @@ fn sftp_rename() {
     let dst = "/tmp/fileman_sftp_test_rename_dst";
 
     // Clean up
-    let _ = sftp::recursive_delete(&session.sftp, src, false, None);
-    let _ = sftp::recursive_delete(&session.sftp, dst, false, None);
+    let _ = remote::recursive_delete(&session, src, false, None);
+    let _ = remote::recursive_delete(&session, dst, false, None);
 
-    sftp::write_file(&session.sftp, src, b"rename me").expect("write");
+    remote::write_file(&session, src, b"rename me").expect("write");
     sftp::rename(&session.sftp, src, dst).expect("rename");
 
     let data = sftp::read_bytes_prefix(&session.sftp, dst, 1024).expect("read renamed");
@@ -132,7 +132,7 @@ fn sftp_rename() {
     let result = sftp::read_bytes_prefix(&session.sftp, src, 1024);
     assert!(result.is_err(), "old path should not exist");
 
-    sftp::recursive_delete(&session.sftp, dst, false, None).expect("cleanup");
+    remote::recursive_delete(&session, dst, false, None).expect("cleanup");
 }
 
 #[test]
@@ -140,18 +140,18 @@ fn sftp_rename() {
 fn sftp_copy_remote_to_local() {
     let session = connect_localhost();
     let remote_path = "/tmp/fileman_sftp_test_r2l";
-    sftp::write_file(&session.sftp, remote_path, b"copy me locally").expect("write");
+    remote::write_file(&session, remote_path, b"copy me locally").expect("write");
 
     let local_dir = std::env::temp_dir().join("fileman_sftp_test_r2l_out");
     std::fs::create_dir_all(&local_dir).ok();
     let local_file = local_dir.join("copied.txt");
 
-    sftp::copy_remote_to_local(&session.sftp, remote_path, &local_file).expect("copy r2l");
+    remote::copy_to_local(&session, remote_path, &local_file, None, None).expect("copy r2l");
     let local_data = std::fs::read(&local_file).expect("read local");
     assert_eq!(local_data, b"copy me locally");
 
     // Cleanup
-    sftp::recursive_delete(&session.sftp, remote_path, false, None).ok();
+    remote::recursive_delete(&session, remote_path, false, None).ok();
     std::fs::remove_dir_all(&local_dir).ok();
 }
 
@@ -165,15 +165,15 @@ fn sftp_copy_local_to_remote() {
     std::fs::write(&local_file, b"upload me").expect("write local");
 
     let remote_path = "/tmp/fileman_sftp_test_l2r_uploaded";
-    let _ = sftp::recursive_delete(&session.sftp, remote_path, false, None);
+    let _ = remote::recursive_delete(&session, remote_path, false, None);
 
-    sftp::copy_local_to_remote(&session.sftp, &local_file, remote_path).expect("copy l2r");
+    remote::copy_from_local(&session, &local_file, remote_path, None, None).expect("copy l2r");
 
     let data = sftp::read_bytes_prefix(&session.sftp, remote_path, 1024).expect("read remote");
     assert_eq!(data, b"upload me");
 
     // Cleanup
-    sftp::recursive_delete(&session.sftp, remote_path, false, None).ok();
+    remote::recursive_delete(&session, remote_path, false, None).ok();
     std::fs::remove_dir_all(&local_dir).ok();
 }
 
@@ -205,14 +205,14 @@ fn sftp_open_remote_reader() {
     let session = connect_localhost();
     let remote_path = "/tmp/fileman_sftp_test_reader";
     let content = b"streaming read test data with more bytes";
-    sftp::write_file(&session.sftp, remote_path, content).expect("write");
+    remote::write_file(&session, remote_path, content).expect("write");
 
     let mut reader = sftp::open_remote_reader(&session.sftp, remote_path).expect("open reader");
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf).expect("read_to_end");
     assert_eq!(buf, content);
 
-    sftp::recursive_delete(&session.sftp, remote_path, false, None).ok();
+    remote::recursive_delete(&session, remote_path, false, None).ok();
 }
 
 #[test]
@@ -254,3 +254,12 @@ Host jump
     assert_eq!(jump.hostname.as_deref(), Some("jump.internal"));
     assert_eq!(jump.identity_files.len(), 2);
 }
+
+#[test]
+#[ignore]
+fn sftp_free_space() {
+    let session = connect_localhost();
+    let free = sftp::free_space(&session.sftp, "/tmp").expect("statvfs extension");
+    assert!(free > 0);
+    assert!(remote::free_space(&session, "/tmp").is_some());
+}
@@ -0,0 +1,450 @@
+//! Integration tests for the WebDAV backend against a minimal in-process
+//! HTTP server (std only) serving a temporary directory on 127.0.0.1.
+//!
+//! The server handles one request per connection and speaks just enough of
+//! RFC 4918 for the client: OPTIONS, PROPFIND with depth 0 and 1, GET with
+//! `Range`, chunked or sized PUT, DELETE, MKCOL, MOVE and COPY. It can
+//! ignore ranges, cut the first download short, or demand a login.
+
+#![cfg(feature = "webdav")]
+
+use std::{
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use fileman::{
+    remote::{self, RemoteFs},
+    webdav,
+};
+
+#[derive(Clone, Copy)]
+struct ServerOptions {
+    ranges: bool,
+    /// Close the first GET after this many body bytes.
+    cut_first_download_at: Option<usize>,
+    /// Answer every request with 401.
+    locked: bool,
+}
+
+const DEFAULT: ServerOptions = ServerOptions {
+    ranges: true,
+    cut_first_download_at: None,
+    locked: false,
+};
+
+struct TmpDir(PathBuf);
+
+impl TmpDir {
+    fn new(tag: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("fileman-dav-{tag}-{}-{n}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        TmpDir(path)
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Serve `root` on an ephemeral port; returns the `dav://` host key.
+fn start_server(root: &Path, options: ServerOptions) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let root = root.to_path_buf();
+    let cut_pending = Arc::new(AtomicBool::new(options.cut_first_download_at.is_some()));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let root = root.clone();
+            let cut_pending = cut_pending.clone();
+            std::thread::spawn(move || {
+                let _ = serve(stream, &root, options, &cut_pending);
+            });
+        }
+    });
+    format!("dav://127.0.0.1:{port}")
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn decode(s: &str) -> String {
+    let mut out = Vec::new();
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex: String = bytes.by_ref().take(2).map(char::from).collect();
+            out.push(u8::from_str_radix(&hex, 16).unwrap());
+        } else {
+            out.push(b);
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+fn encode(s: &str) -> String {
+    s.replace('%', "%25").replace(' ', "%20")
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> std::io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = decode(parts.next().unwrap_or("/"));
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    if let Some(len) = request.header("content-length") {
+        let mut body = vec![0; len.parse().unwrap()];
+        reader.read_exact(&mut body)?;
+        request.body = body;
+    } else if request
+        .header("transfer-encoding")
+        .is_some_and(|te| te.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size)?;
+            let size = usize::from_str_radix(size.trim(), 16).unwrap();
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk)?;
+            if size == 0 {
+                break;
+            }
+            request.body.extend_from_slice(&chunk[..size]);
+        }
+    }
+    Ok(request)
+}
+
+fn respond(
+    out: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> std::io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        body.len()
+    );
+    for &(name, ref value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    out.write_all(head.as_bytes())?;
+    out.write_all(body)
+}
+
+fn propfind_entry(href: &str, path: &Path) -> String {
+    let meta = std::fs::metadata(path).unwrap();
+    let props = if meta.is_dir() {
+        "<D:resourcetype><D:collection/></D:resourcetype>".to_string()
+    } else {
+        format!(
+            "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>",
+            meta.len()
+        )
+    };
+    format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>{props}\
+         <D:getlastmodified>Mon, 01 Jan 2024 00:00:00 GMT</D:getlastmodified>\
+         </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        encode(href)
+    )
+}
+
+fn copy_tree(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if src.is_dir() {
+        std::fs::create_dir(dst)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(src, dst).map(|_| ())
+    }
+}
+
+fn serve(
+    stream: TcpStream,
+    root: &Path,
+    options: ServerOptions,
+    cut_pending: &AtomicBool,
+) -> std::io::Result<()> {
+    let mut out = stream.try_clone()?;
+    let request = read_request(&mut BufReader::new(stream))?;
+    if options.locked {
+        let challenge = [("WWW-Authenticate", "Basic realm=\"dav\"".to_string())];
+        return respond(&mut out, "401 Unauthorized", &challenge, b"");
+    }
+    let local = root.join(request.path.trim_start_matches('/'));
+    let destination = request.header("destination").map(|url| {
+        let path = url.split_once("://").map_or(url, |(_, rest)| {
+            rest.find('/').map_or("/", |slash| &rest[slash..])
+        });
+        root.join(decode(path).trim_start_matches('/'))
+    });
+    match request.method.as_str() {
+        "OPTIONS" => respond(
+            &mut out,
+            "200 OK",
+            &[("DAV", "1".to_string()), ("Server", "test-dav".to_string())],
+            b"",
+        ),
+        "PROPFIND" => {
+            if !local.exists() {
+                return respond(&mut out, "404 Not Found", &[], b"");
+            }
+            let mut body = String::from(r#"<?xml version="1.0"?><D:multistatus xmlns:D="DAV:">"#);
+            body.push_str(&propfind_entry(&request.path, &local));
+            if request.header("depth") == Some("1") && local.is_dir() {
+                for entry in std::fs::read_dir(&local)? {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    let href = format!("{}/{name}", request.path.trim_end_matches('/'));
+                    body.push_str(&propfind_entry(&href, &entry.path()));
+                }
+            }
+            body.push_str("</D:multistatus>");
+            respond(&mut out, "207 Multi-Status", &[], body.as_bytes())
+        }
+        "GET" => {
+            let Ok(contents) = std::fs::read(&local) else {
+                return respond(&mut out, "404 Not Found", &[], b"");
+            };
+            let offset = request
+                .header("range")
+                .filter(|_| options.ranges)
+                .and_then(|r| r.strip_prefix("bytes="))
+                .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+            if let Some(cut) = options.cut_first_download_at
+                && cut_pending.swap(false, Ordering::SeqCst)
+            {
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+                    contents.len()
+                );
+                out.write_all(head.as_bytes())?;
+                return out.write_all(&contents[..cut]);
+            }
+            match offset {
+                Some(offset) if offset >= contents.len() => {
+                    respond(&mut out, "416 Range Not Satisfiable", &[], b"")
+                }
+                Some(offset) => {
+                    let range = format!("bytes {offset}-{}/{}", contents.len() - 1, contents.len());
+                    respond(
+                        &mut out,
+                        "206 Partial Content",
+                        &[("Content-Range", range)],
+                        &contents[offset..],
+                    )
+                }
+                None => respond(&mut out, "200 OK", &[], &contents),
+            }
+        }
+        "PUT" => {
+            std::fs::write(&local, &request.body)?;
+            respond(&mut out, "201 Created", &[], b"")
+        }
+        "DELETE" => {
+            let result = if local.is_dir() {
+                std::fs::remove_dir_all(&local)
+            } else {
+                std::fs::remove_file(&local)
+            };
+            match result {
+                Ok(()) => respond(&mut out, "204 No Content", &[], b""),
+                Err(_) => respond(&mut out, "404 Not Found", &[], b""),
+            }
+        }
+        "MKCOL" => match std::fs::create_dir(&local) {
+            Ok(()) => respond(&mut out, "201 Created", &[], b""),
+            Err(_) => respond(&mut out, "405 Method Not Allowed", &[], b""),
+        },
+        "MOVE" | "COPY" => {
+            let Some(dst) = destination else {
+                return respond(&mut out, "400 Bad Request", &[], b"");
+            };
+            let result = if request.method == "MOVE" {
+                std::fs::rename(&local, &dst)
+            } else {
+                copy_tree(&local, &dst)
+            };
+            match result {
+                Ok(()) => respond(&mut out, "201 Created", &[], b""),
+                Err(_) => respond(&mut out, "409 Conflict", &[], b""),
+            }
+        }
+        _ => respond(&mut out, "405 Method Not Allowed", &[], b""),
+    }
+}
+
+fn names(fs: &dyn RemoteFs, path: &str) -> Vec<String> {
+    let mut names: Vec<String> = remote::read_directory(fs, path)
+        .expect("list")
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn webdav_round_trip() {
+    let root = TmpDir::new("round-trip");
+    let session = webdav::connect(&start_server(&root.0, DEFAULT)).expect("connect");
+    assert_eq!(session.stats().banner.as_deref(), Some("test-dav"));
+
+    remote::write_file(&session, "/a b.txt", b"hello dav").expect("write");
+    session.mkdir("/dir").expect("mkdir");
+    assert_eq!(names(&session, "/"), ["a b.txt", "dir"]);
+    let listing = remote::read_directory(&session, "/").unwrap();
+    let file = listing.iter().find(|e| e.name == "a b.txt").unwrap();
+    assert_eq!(file.size, Some(9));
+    assert_eq!(file.modified, Some(1_704_067_200));
+
+    session.rename("/a b.txt", "/dir/b.txt").expect("rename");
+    let stat = session.stat("/dir/b.txt").expect("stat");
+    assert!(!stat.is_dir);
+    assert_eq!(stat.size, Some(9));
+    assert!(session.stat("/dir").unwrap().is_dir);
+    assert_eq!(
+        remote::read_file_full(&session, "/dir/b.txt", None).unwrap(),
+        b"hello dav"
+    );
+
+    // Listings below the root start with "..".
+    let mut batches = Vec::new();
+    session
+        .list("/dir", &mut |batch| batches.extend(batch))
+        .unwrap();
+    assert_eq!(batches[0].name, "..");
+    assert_eq!(batches.len(), 2);
+
+    remote::recursive_delete(&session, "/dir", true, None).expect("delete");
+    assert!(names(&session, "/").is_empty());
+    assert!(session.is_alive());
+}
+
+#[test]
+fn webdav_large_listing_arrives_in_batches() {
+    let root = TmpDir::new("batches");
+    let session = webdav::connect(&start_server(&root.0, DEFAULT)).expect("connect");
+    for i in 0..150 {
+        std::fs::write(root.0.join(format!("f{i:03}")), b"x").unwrap();
+    }
+    let mut batches = Vec::new();
+    session.list("/", &mut |batch| batches.push(batch)).unwrap();
+    assert!(batches.len() >= 3);
+    assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 150);
+}
+
+#[test]
+fn webdav_download_resumes_after_dropped_connection() {
+    let root = TmpDir::new("resume");
+    let options = ServerOptions {
+        cut_first_download_at: Some(1000),
+        ..DEFAULT
+    };
+    let session = webdav::connect(&start_server(&root.0, options)).expect("connect");
+    let contents: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(root.0.join("firmware.bin"), &contents).unwrap();
+
+    let local = TmpDir::new("resume-out");
+    let dst = local.0.join("firmware.bin");
+    remote::copy_to_local(&session, "/firmware.bin", &dst, None, None).expect("download");
+    assert_eq!(std::fs::read(&dst).unwrap(), contents);
+}
+
+#[test]
+fn webdav_reader_seeks_with_and_without_ranges() {
+    for ranges in [true, false] {
+        let root = TmpDir::new("seek");
+        let options = ServerOptions { ranges, ..DEFAULT };
+        let session = webdav::connect(&start_server(&root.0, options)).expect("connect");
+        let contents: Vec<u8> = (0..50_000u32).map(|i| (i % 253) as u8).collect();
+        std::fs::write(root.0.join("data.bin"), &contents).unwrap();
+
+        let mut reader = session.open_read("/data.bin").expect("open");
+        let mut buf = [0u8; 16];
+        reader.seek(SeekFrom::Start(40_000)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], contents[40_000..40_016]);
+        reader.seek(SeekFrom::End(-16)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], contents[contents.len() - 16..]);
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], contents[100..116]);
+    }
+}
+
+#[test]
+fn webdav_copy_within_server() {
+    let root = TmpDir::new("copy");
+    let session = webdav::connect(&start_server(&root.0, DEFAULT)).expect("connect");
+    std::fs::create_dir(root.0.join("src")).unwrap();
+    std::fs::write(root.0.join("src").join("one.txt"), b"one").unwrap();
+    std::fs::write(root.0.join("src").join("two.txt"), b"two").unwrap();
+
+    remote::recursive_copy(&session, "/src", "/", "dst").expect("copy");
+    assert_eq!(names(&session, "/dst"), ["one.txt", "two.txt"]);
+    assert_eq!(
+        std::fs::read(root.0.join("dst").join("two.txt")).unwrap(),
+        b"two"
+    );
+}
+
+#[test]
+fn webdav_login_failure_points_at_netrc() {
+    let root = TmpDir::new("locked");
+    let options = ServerOptions {
+        locked: true,
+        ..DEFAULT
+    };
+    let Err(message) = webdav::connect(&start_server(&root.0, options)) else {
+        panic!("connected without a login");
+    };
+    assert!(message.contains("~/.netrc"), "{message}");
+}